@@ -10,7 +10,6 @@
 //! ```
 
 use simple_agent::prelude::*;
-use simple_agent::{MessageRole, MessageContent};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -56,17 +55,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         max_steps: 10,
         max_tokens: 1024,
         temperature: Some(0.7),
+        ..Default::default()
     };
 
     // Create agent
     let agent = Agent::new(session, llm_client, registry, config);
 
     // Run the agent
-    let messages = agent.run("Hello! Can you help me with some questions?").await?;
+    let result = agent.run("Hello! Can you help me with some questions?").await?;
 
     // Print responses
     println!("\n=== Agent Response ===");
-    for message in messages {
+    for message in result.messages {
         match message.role {
             MessageRole::User => {
                 println!("\nUser: {}", content_to_text(&message.content));
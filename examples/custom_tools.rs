@@ -10,7 +10,6 @@
 //! ```
 
 use simple_agent::prelude::*;
-use simple_agent::{MessageRole, MessageContent, ToolError, ToolResult};
 use async_trait::async_trait;
 use serde_json::Value;
 use std::sync::Arc;
@@ -224,7 +223,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Get API key
     let api_key = std::env::var("OPENAI_API_KEY")
         .expect("Please set OPENAI_API_KEY environment variable");
-    let base_url: String = std::env::var("OPENAI_API_BASE_URL").expect("Please set OPENAI_API_BASE_URL environment variable").into();
+    let base_url = std::env::var("OPENAI_API_BASE_URL").expect("Please set OPENAI_API_BASE_URL environment variable");
 
     // Create OpenAI client
     let llm_client = LLMClientBuilder::new()
@@ -270,9 +269,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("Query: {}", query);
         println!("{}", "=".repeat(50));
 
-        let messages = agent.run(query).await?;
+        let result = agent.run(query).await?;
 
-        for message in messages {
+        for message in result.messages {
             match message.role {
                 MessageRole::User => {
                     println!("\nUser: {}", content_to_text(&message.content));
@@ -23,11 +23,9 @@
 use async_trait::async_trait;
 use clap::Parser;
 use serde_json::Value;
-use simple_agent::mcp::MCPClient;
 use simple_agent::prelude::*;
-use simple_agent::{MCToolInfo, MessageContent, MessageRole, ToolError, ToolResult};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 
 /// MCP Client Example
 #[derive(Parser, Debug)]
@@ -53,14 +51,14 @@ struct Args {
 /// A wrapper tool that calls MCP tools
 #[derive(Debug, Clone)]
 struct MCPWrappedTool {
-    client: Arc<Mutex<MCPClient>>,
+    client: Arc<RwLock<MCPClient>>,
     name: String,
     description: String,
     schema: Value,
 }
 
 impl MCPWrappedTool {
-    fn new(client: Arc<Mutex<MCPClient>>, tool_info: MCToolInfo) -> Self {
+    fn new(client: Arc<RwLock<MCPClient>>, tool_info: MCPToolInfo) -> Self {
         Self {
             client,
             name: tool_info.name,
@@ -85,13 +83,13 @@ impl Tool for MCPWrappedTool {
     }
 
     async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
-        let mut client = self.client.lock().await;
+        let client = self.client.read().await;
 
         let result = client
             .call_tool(&self.name, args)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
-        Ok(ToolResult::ok(result))
+        Ok(result.into())
     }
 }
 
@@ -105,9 +103,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Get API key
     let api_key =
         std::env::var("OPENAI_API_KEY").expect("Please set OPENAI_API_KEY environment variable");
-    let base_url: String = std::env::var("OPENAI_API_BASE_URL")
-        .expect("Please set OPENAI_API_BASE_URL environment variable")
-        .into();
+    let base_url = std::env::var("OPENAI_API_BASE_URL")
+        .expect("Please set OPENAI_API_BASE_URL environment variable");
 
     // Create OpenAI client
     let llm_client = LLMClientBuilder::new()
@@ -130,15 +127,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         // )
         .build()?;
 
-    // Wrap in Arc<Mutex> before connecting since MCPClient has mutable methods
-    let mcp_client_arc: Arc<Mutex<MCPClient>> = Arc::new(mcp_client.into());
+    // Wrap in Arc<RwLock> before connecting; calls only need a read lock, so they run concurrently
+    let mcp_client_arc: Arc<RwLock<MCPClient>> = Arc::new(mcp_client.into());
 
     println!("Connecting to MCP server...");
-    mcp_client_arc.lock().await.connect().await?;
+    mcp_client_arc.write().await.connect().await?;
     println!("Connected to MCP server!");
 
     // List available tools
-    let tools: Vec<MCToolInfo> = mcp_client_arc.lock().await.list_tools().await?;
+    let tools: Vec<MCPToolInfo> = mcp_client_arc.read().await.list_tools().await?;
     println!("\nAvailable MCP tools:");
     for tool in &tools {
         println!("  - {}: {}", tool.name, tool.description);
@@ -179,8 +176,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Create agent
     let registry = Arc::new(Mutex::new(registry));
-    let mut agent_config = AgentConfig::default();
-    agent_config.model = "MiniMax-M2.1".to_string();
+    let agent_config = AgentConfig {
+        model: "MiniMax-M2.1".to_string(),
+        ..Default::default()
+    };
 
     let agent = Agent::new(session, llm_client, registry, agent_config);
 
@@ -196,8 +195,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("{}", "=".repeat(60));
 
         match agent.run(query).await {
-            Ok(messages) => {
-                for message in messages {
+            Ok(result) => {
+                for message in result.messages {
                     match message.role {
                         MessageRole::User => {
                             println!("\nUser: {}", content_to_text(&message.content));
@@ -218,7 +217,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Cleanup
-    mcp_client_arc.lock().await.disconnect().await?;
+    mcp_client_arc.write().await.disconnect().await?;
 
     Ok(())
 }
@@ -0,0 +1,159 @@
+//! Procedural macros for `simple-agent`, re-exported from the main crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::{Expr, FnArg, ItemFn, Lit, Meta, Pat, Token, parse_macro_input};
+
+/// Turns an async function into a tool.
+///
+/// A unit struct named after the function in PascalCase implements `Tool`:
+/// its input schema is generated from the argument types, which must
+/// implement `Deserialize` and `JsonSchema`, and calls deserialize the
+/// arguments and await the function. The function must return a `Result`
+/// whose value converts into a `ToolResult` and whose error implements
+/// `Display`.
+///
+/// ```ignore
+/// /// Looks up the current weather.
+/// #[tool]
+/// async fn get_weather(location: String, unit: Option<Unit>) -> Result<String, ToolError> {
+///     // ...
+/// }
+///
+/// registry.register(Arc::new(GetWeather));
+/// ```
+///
+/// The tool's name defaults to the function name and its description to the
+/// function's doc comment; both can be set with
+/// `#[tool(name = "...", description = "...")]`.
+#[proc_macro_attribute]
+pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let options = parse_macro_input!(attr with Punctuated::<Meta, Token![,]>::parse_terminated);
+    let function = parse_macro_input!(item as ItemFn);
+    expand(options, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(options: Punctuated<Meta, Token![,]>, function: ItemFn) -> syn::Result<TokenStream2> {
+    let sig = &function.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(sig.fn_token, "#[tool] functions must be async"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&sig.generics, "#[tool] functions cannot be generic"));
+    }
+
+    let mut name = sig.ident.to_string();
+    let mut description = doc_comment(&function);
+    for option in options {
+        let Meta::NameValue(pair) = &option else {
+            return Err(syn::Error::new_spanned(option, "expected `name = \"...\"` or `description = \"...\"`"));
+        };
+        let Expr::Lit(syn::ExprLit { lit: Lit::Str(value), .. }) = &pair.value else {
+            return Err(syn::Error::new_spanned(&pair.value, "expected a string literal"));
+        };
+        if pair.path.is_ident("name") {
+            name = value.value();
+        } else if pair.path.is_ident("description") {
+            description = value.value();
+        } else {
+            return Err(syn::Error::new_spanned(&pair.path, "unknown #[tool] option"));
+        }
+    }
+
+    let mut fields = Vec::new();
+    let mut types = Vec::new();
+    for input in &sig.inputs {
+        match input {
+            FnArg::Typed(arg) => match &*arg.pat {
+                Pat::Ident(ident) => {
+                    fields.push(ident.ident.clone());
+                    types.push(arg.ty.clone());
+                }
+                pat => return Err(syn::Error::new_spanned(pat, "#[tool] arguments must be plain identifiers")),
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(receiver, "#[tool] functions cannot take `self`"));
+            }
+        }
+    }
+
+    let vis = &function.vis;
+    let function_ident = &sig.ident;
+    let tool_ident = format_ident!("{}", pascal_case(&function_ident.to_string()));
+    let struct_doc = format!("The `{}` tool, calling [`{}`].", name, function_ident);
+
+    Ok(quote! {
+        #function
+
+        #[doc = #struct_doc]
+        #[derive(Debug, Clone, Copy, Default)]
+        #vis struct #tool_ident;
+
+        const _: () = {
+            #[derive(::simple_agent::__private::serde::Deserialize, ::simple_agent::__private::schemars::JsonSchema)]
+            #[serde(crate = "::simple_agent::__private::serde")]
+            #[schemars(crate = "::simple_agent::__private::schemars")]
+            struct Arguments {
+                #(#fields: #types,)*
+            }
+
+            #[::simple_agent::__private::async_trait]
+            impl ::simple_agent::tool::Tool for #tool_ident {
+                fn name(&self) -> &str {
+                    #name
+                }
+
+                fn description(&self) -> &str {
+                    #description
+                }
+
+                fn parameters_schema(&self) -> ::simple_agent::__private::serde_json::Value {
+                    ::simple_agent::__private::schema_for::<Arguments>()
+                }
+
+                async fn execute(
+                    &self,
+                    args: ::simple_agent::__private::serde_json::Value,
+                ) -> ::std::result::Result<::simple_agent::tool::ToolResult, ::simple_agent::tool::ToolError> {
+                    let Arguments { #(#fields),* } = ::simple_agent::__private::parse_arguments(args)?;
+                    ::simple_agent::__private::into_result(#function_ident(#(#fields),*).await)
+                }
+            }
+        };
+    })
+}
+
+/// Joins the function's doc comment lines.
+fn doc_comment(function: &ItemFn) -> String {
+    let lines: Vec<String> = function
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(pair) => match &pair.value {
+                Expr::Lit(syn::ExprLit { lit: Lit::Str(line), .. }) => Some(line.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Converts a snake_case name to PascalCase.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
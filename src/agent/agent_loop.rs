@@ -1,19 +1,36 @@
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use futures::stream::{Stream, StreamExt};
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
-use crate::session::{Message, MessageContent, MessageRole, Session, SessionStatus};
-use crate::llm::{LLMClient, LLMInput, LLMEvent, FinishReason};
-use crate::tool::{ToolExecutor, ToolRegistry, ExecutionContext};
+use crate::session::{ContextStrategy, Message, MessageContent, MessageRole, Session, SessionProvenance, SessionStatus, SessionStore, StoreError, UsageReport};
+use crate::llm::{LLMClient, LLMError, LLMInput, LLMOutput, LLMEvent, FinishReason, PricingTable, RequestOptions, ToolChoice, Usage, TokenCounter, HeuristicTokenCounter};
+use crate::llm::tokens::context_window;
+use crate::mcp::{MCPClient, MCPLogLevel, MCPStatus, SamplingHandler};
+use crate::permission::PermissionManager;
+use crate::tool::{ToolExecutor, ToolRegistry, Vault, ToolCache, ExecutionContext, PendingToolCall, ToolExecutionEvent, TruncationPolicy, ToolFilter, ToolStats};
+use super::builder::ConfigDiagnostic;
+use super::bundle::BundleWriter;
+use super::context::{ContextProvider, SystemPromptProvider};
+use super::events::{EventBus, EventSubscription, TopicMask};
+use super::guardrail::{GuardrailPolicy, GuardrailState, GuardrailStep, GuardrailVerdict, OutputGuardrail};
+use super::output_validator::{OutputValidation, ValidationOutcome};
+use super::reflection::ReflectionConfig;
+use super::resume::{ResumeMismatch, ResumePolicy};
+use super::stop::{StepContext, StopCondition};
+use super::compaction::{self, CompactionConfig};
 
 /// Configuration for the agent.
 #[derive(Debug, Clone)]
 pub struct AgentConfig {
     /// The model to use
     pub model: String,
-    /// The system prompt
+    /// The system prompt; replaced by `Agent::with_system_prompt_provider`
     pub system_prompt: String,
     /// Maximum number of steps in the agent loop
     pub max_steps: usize,
@@ -21,6 +38,266 @@ pub struct AgentConfig {
     pub max_tokens: u32,
     /// Optional temperature
     pub temperature: Option<f32>,
+    /// How to react when an output guardrail trips
+    pub guardrail: GuardrailPolicy,
+    /// Context window size in tokens; looked up from the model name when `None`
+    pub context_window: Option<u32>,
+    /// Automatic compaction of older turns; disabled when `None`
+    pub compaction: Option<CompactionConfig>,
+    /// Per-request overrides (timeout, headers, body fields) sent with every LLM call
+    pub request_options: RequestOptions,
+    /// Validation of the final answer, with automatic re-asking; disabled when `None`
+    pub output_validator: Option<OutputValidation>,
+    /// How to react when the model stops without producing any output
+    pub empty_response: EmptyResponsePolicy,
+    /// Interval of `AgentEvent::Heartbeat` events while a stream waits on a
+    /// silent LLM response or tool execution; disabled when `None`
+    pub heartbeat_interval: Option<Duration>,
+    /// Resource limits of a single run
+    pub budget: RunBudget,
+    /// Review of final answers by a critic that can send the agent back;
+    /// disabled when `None`
+    pub reflection: Option<ReflectionConfig>,
+    /// Retries of LLM requests that failed with a transient error; disabled
+    /// when `None`
+    pub llm_retry: Option<RetryPolicy>,
+    /// Yield to the scheduler before every step, so runs whose LLM and
+    /// tools answer without waiting do not monopolize a worker thread
+    pub yield_between_steps: bool,
+    /// How long a tool call may run before it is aborted with an error
+    /// result; tools can override it with `Tool::timeout`. Disabled when `None`
+    pub tool_timeout: Option<Duration>,
+    /// Which messages of the session are sent with each request; all of
+    /// them when `None`. A session's own strategy takes precedence
+    pub context_strategy: Option<ContextStrategy>,
+}
+
+/// Limits on the resources a single run may consume. Unset limits are not
+/// enforced.
+///
+/// The limits are checked after every step, so a run may overshoot them by
+/// at most one LLM request and its tool calls.
+#[derive(Debug, Clone, Default)]
+pub struct RunBudget {
+    /// Maximum input plus output tokens across all LLM requests
+    pub max_tokens: Option<u64>,
+    /// Maximum estimated cost, from the agent's pricing table
+    pub max_cost: Option<f64>,
+    /// Maximum wall-clock duration
+    pub max_duration: Option<Duration>,
+}
+
+/// The budget limit a run exceeded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BudgetLimit {
+    /// Too many tokens were used
+    Tokens { used: u64, limit: u64 },
+    /// The estimated cost was too high
+    Cost { used: f64, limit: f64 },
+    /// The run took too long
+    Duration { elapsed: Duration, limit: Duration },
+}
+
+impl std::fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetLimit::Tokens { used, limit } => write!(f, "{} tokens used, limit is {}", used, limit),
+            BudgetLimit::Cost { used, limit } => write!(f, "cost {:.4} exceeds limit {:.4}", used, limit),
+            BudgetLimit::Duration { elapsed, limit } => {
+                write!(f, "ran for {:?}, limit is {:?}", elapsed, limit)
+            }
+        }
+    }
+}
+
+/// Tracks what a run has consumed against its budget.
+struct BudgetTracker {
+    started: Instant,
+    tokens: u64,
+    cost: f64,
+}
+
+impl BudgetTracker {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            tokens: 0,
+            cost: 0.0,
+        }
+    }
+
+    fn add(&mut self, usage: &Usage, cost: Option<f64>) {
+        self.tokens += u64::from(usage.input_tokens) + u64::from(usage.output_tokens);
+        self.cost += cost.unwrap_or(0.0);
+    }
+
+    /// Returns the first limit that has been exceeded.
+    fn check(&self, budget: &RunBudget) -> Option<BudgetLimit> {
+        if let Some(limit) = budget.max_tokens
+            && self.tokens > limit
+        {
+            return Some(BudgetLimit::Tokens { used: self.tokens, limit });
+        }
+        if let Some(limit) = budget.max_cost
+            && self.cost > limit
+        {
+            return Some(BudgetLimit::Cost { used: self.cost, limit });
+        }
+        let elapsed = self.started.elapsed();
+        match budget.max_duration {
+            Some(limit) if elapsed > limit => Some(BudgetLimit::Duration { elapsed, limit }),
+            _ => None,
+        }
+    }
+}
+
+/// Overrides applied to a single run by `Agent::run_with`.
+///
+/// Unset fields fall back to the agent's configuration, which is left
+/// untouched.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Model used for this run
+    pub model: Option<String>,
+    /// Sampling temperature used for this run
+    pub temperature: Option<f32>,
+    /// Maximum tokens generated per LLM request
+    pub max_tokens: Option<u32>,
+    /// Which tools the model may call
+    pub tool_choice: Option<ToolChoice>,
+    /// Appended to the system prompt for this run
+    pub extra_system_prompt: Option<String>,
+    /// Restricts the tools offered and run to a subset of the registry
+    pub tools: Option<ToolFilter>,
+    /// Permission rules layered over the agent's for this run
+    pub permissions: Option<PermissionManager>,
+}
+
+impl RunOptions {
+    /// Sets the model.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sets the temperature.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Sets the maximum tokens per request.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sets the tool choice.
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Sets text appended to the system prompt.
+    pub fn with_extra_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.extra_system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Restricts the run to the tools `filter` allows.
+    pub fn with_tools(mut self, filter: ToolFilter) -> Self {
+        self.tools = Some(filter);
+        self
+    }
+
+    /// Layers `permissions` over the agent's rules for this run; its rules
+    /// take priority.
+    pub fn with_permissions(mut self, permissions: PermissionManager) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// Applies the overrides to a request.
+    fn apply(&self, input: &mut LLMInput) {
+        if let Some(model) = &self.model {
+            input.model = model.clone();
+        }
+        if let Some(temperature) = self.temperature {
+            input.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            input.max_tokens = max_tokens;
+        }
+        if let Some(tool_choice) = &self.tool_choice {
+            input.request_options = std::mem::take(&mut input.request_options).with_tool_choice(tool_choice.clone());
+        }
+        if let Some(prompt) = &self.extra_system_prompt {
+            if !input.system_prompt.is_empty() {
+                input.system_prompt.push_str("\n\n");
+            }
+            input.system_prompt.push_str(prompt);
+        }
+    }
+}
+
+/// Exponential backoff for LLM requests that fail with a transient error
+/// (see `LLMError::is_transient`).
+///
+/// A streamed step that fails after producing output is retried from
+/// scratch; consumers should discard the partial message when they see
+/// `AgentEvent::Retry`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// How many times a failed request is retried
+    pub max_retries: usize,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay
+    pub max_backoff: Duration,
+    /// Factor the delay grows by after each retry
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the retry following `retries` earlier ones.
+    pub fn backoff(&self, retries: usize) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retries.min(i32::MAX as usize) as i32);
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+}
+
+/// Retry behavior for empty responses.
+///
+/// Some OpenAI-compatible backends occasionally finish with reason `stop`
+/// but no text and no tool calls. Instead of recording a blank assistant
+/// message, the agent nudges the model to answer.
+#[derive(Debug, Clone)]
+pub struct EmptyResponsePolicy {
+    /// How many times the model is nudged before the run fails
+    pub max_retries: usize,
+    /// The user message sent as a nudge
+    pub nudge: String,
+}
+
+impl Default for EmptyResponsePolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 1,
+            nudge: "Your previous response was empty. Please respond to the last message.".to_string(),
+        }
+    }
 }
 
 impl Default for AgentConfig {
@@ -31,12 +308,26 @@ impl Default for AgentConfig {
             max_steps: 100,
             max_tokens: 4096,
             temperature: None,
+            guardrail: GuardrailPolicy::default(),
+            context_window: None,
+            compaction: None,
+            request_options: RequestOptions::default(),
+            output_validator: None,
+            empty_response: EmptyResponsePolicy::default(),
+            heartbeat_interval: None,
+            budget: RunBudget::default(),
+            reflection: None,
+            llm_retry: None,
+            yield_between_steps: true,
+            tool_timeout: None,
+            context_strategy: None,
         }
     }
 }
 
 /// Events from the agent during execution.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum AgentEvent {
     /// A new message is starting
     MessageStart {
@@ -46,6 +337,10 @@ pub enum AgentEvent {
     Text {
         text: String,
     },
+    /// Reasoning ("thinking") content was received
+    Thinking {
+        text: String,
+    },
     /// A tool is being called
     ToolCall {
         name: String,
@@ -56,16 +351,272 @@ pub enum AgentEvent {
         name: String,
         result: String,
     },
+    /// A tool call was queued for execution
+    ToolQueued {
+        call_id: String,
+        name: String,
+    },
+    /// A tool call is paused until `Agent::approve` or `Agent::deny` is called
+    ApprovalRequired {
+        call_id: String,
+        tool: String,
+        args: serde_json::Value,
+    },
+    /// A queued tool call started executing
+    ToolStarted {
+        call_id: String,
+        name: String,
+        /// How long the call waited in the queue
+        queue_wait: Duration,
+    },
+    /// A running tool call reported progress
+    ToolProgress {
+        call_id: String,
+        name: String,
+        /// Fraction between 0 and 1, when known
+        progress: Option<f64>,
+        message: String,
+    },
+    /// A tool call finished executing
+    ToolCompleted {
+        call_id: String,
+        name: String,
+        duration: Duration,
+        is_error: bool,
+    },
     /// The message is complete
     MessageEnd {
         finish_reason: FinishReason,
     },
+    /// Older turns were summarized into a single message
+    Compacted {
+        /// Number of messages replaced by the summary
+        removed_messages: usize,
+    },
+    /// An output guardrail tripped and the streamed text was withdrawn
+    GuardrailTripped {
+        guardrail: String,
+        reason: String,
+        /// Whether the turn is being restarted with reinforced instructions
+        restarting: bool,
+    },
+    /// The final answer failed output validation
+    ValidationFailed {
+        reason: String,
+        /// Whether the model is being asked to correct its answer
+        retrying: bool,
+    },
+    /// The critic asked the agent to revise its final answer
+    Critique {
+        feedback: String,
+    },
+    /// The model stopped without producing text or tool calls
+    EmptyResponse {
+        /// Whether the model is being nudged to answer
+        retrying: bool,
+    },
+    /// The run exceeded its budget; no further events follow
+    BudgetExceeded {
+        limit: BudgetLimit,
+    },
+    /// An observer flagged the run for violating its policy
+    PolicyViolation {
+        reason: String,
+        /// Whether the observer halted the run
+        halted: bool,
+    },
+    /// The agent is still waiting on the LLM or a tool
+    Heartbeat {
+        /// How long the current wait has lasted
+        elapsed: Duration,
+    },
+    /// An LLM request failed with a transient error and is retried; any
+    /// partial output of the current message is discarded
+    Retry {
+        /// Number of the retry, starting at 1
+        attempt: usize,
+        error: String,
+    },
+    /// An MCP server became unreachable; its tools fail until it is back
+    MCPServerDown {
+        server: String,
+        error: String,
+    },
+    /// An MCP server sent a log message
+    MCPLog {
+        server: String,
+        level: MCPLogLevel,
+        logger: Option<String>,
+        message: String,
+    },
+    /// The run was cancelled; no further events follow
+    Cancelled,
     /// An error occurred
     Error {
         error: String,
     },
 }
 
+impl From<ToolExecutionEvent> for AgentEvent {
+    fn from(event: ToolExecutionEvent) -> Self {
+        match event {
+            ToolExecutionEvent::Queued { call_id, name } => AgentEvent::ToolQueued { call_id, name },
+            ToolExecutionEvent::ApprovalRequired { call_id, name, args } => {
+                AgentEvent::ApprovalRequired { call_id, tool: name, args }
+            }
+            ToolExecutionEvent::Started { call_id, name, queue_wait } => {
+                AgentEvent::ToolStarted { call_id, name, queue_wait }
+            }
+            ToolExecutionEvent::Progress { call_id, name, progress, message } => {
+                AgentEvent::ToolProgress { call_id, name, progress, message }
+            }
+            ToolExecutionEvent::Completed { call_id, name, duration, is_error } => {
+                AgentEvent::ToolCompleted { call_id, name, duration, is_error }
+            }
+        }
+    }
+}
+
+/// Predicted size and cost of the next LLM request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEstimate {
+    /// The model the request will be sent to
+    pub model: String,
+    /// Estimated prompt tokens (messages, system prompt and tool definitions)
+    pub input_tokens: usize,
+    /// Upper bound on generated tokens
+    pub max_output_tokens: u32,
+    /// Estimated cost of the prompt, if the model is priced
+    pub input_cost: Option<f64>,
+    /// Cost if the full output budget is used, if the model is priced
+    pub max_cost: Option<f64>,
+}
+
+/// Why a run ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// The model gave its final answer
+    Completed,
+    /// The final answer was cut off by the token limit
+    MaxTokens,
+    /// The provider withheld the final answer for moderation reasons
+    ContentFilter,
+    /// The final answer was withheld by an output guardrail
+    GuardrailTripped,
+    /// The final answer still failed validation after all retries
+    ValidationFailed,
+    /// The step limit was reached before a final answer
+    MaxSteps,
+    /// A stop condition ended the run after a step
+    StopCondition,
+}
+
+/// The outcome of one run of the agent.
+#[derive(Debug, Clone)]
+pub struct AgentRunResult {
+    /// Messages added to the session after the user message
+    pub messages: Vec<Message>,
+    /// Text of the final assistant message
+    pub text: String,
+    /// Tokens used across all LLM requests of the run
+    pub usage: Usage,
+    /// Number of LLM requests made
+    pub steps: usize,
+    /// Wall-clock duration of the run
+    pub elapsed: Duration,
+    /// Why the run ended
+    pub stop_reason: StopReason,
+}
+
+/// The outcome of a critic review.
+struct Critique {
+    /// Feedback to revise with, or `None` if the answer was approved
+    feedback: Option<String>,
+    usage: Usage,
+}
+
+/// Bookkeeping of a run, shared by the steps of `run` and `stream`.
+struct RunState {
+    step: usize,
+    usage: Usage,
+    budget: BudgetTracker,
+    guardrails: GuardrailState,
+    validation_retries: usize,
+    empty_retries: usize,
+    reflection_rounds: usize,
+    /// The task of the run, for the critic
+    task: String,
+}
+
+/// The LLM response of a step, whether completed or streamed.
+struct StepResponse {
+    content: Vec<MessageContent>,
+    finish_reason: FinishReason,
+    /// Token usage, when known
+    usage: Option<Usage>,
+    model: String,
+    latency: Duration,
+    /// The output guardrail that tripped on the text, and why
+    tripped: Option<(String, String)>,
+}
+
+/// How a run goes on after a step's response was handled.
+enum StepOutcome {
+    /// Send the next request
+    Continue,
+    /// The run is over
+    Stop(StopReason),
+    /// Execute the tool calls of the saved assistant message
+    ExecuteTools(ToolStep),
+}
+
+/// The tool calls of a step and the assistant message they belong to.
+struct ToolStep {
+    calls: Vec<MessageContent>,
+    message_id: String,
+    content: Vec<MessageContent>,
+}
+
+/// Bookkeeping of a finished agent loop.
+struct RunStats {
+    steps: usize,
+    usage: Usage,
+    stop_reason: StopReason,
+}
+
+/// Ticks at a fixed interval while a stream waits on a silent operation.
+struct Heartbeat {
+    started: std::time::Instant,
+    interval: Option<tokio::time::Interval>,
+}
+
+impl Heartbeat {
+    fn new(period: Option<Duration>) -> Self {
+        let interval = period.filter(|p| !p.is_zero()).map(|period| {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval
+        });
+        Self {
+            started: std::time::Instant::now(),
+            interval,
+        }
+    }
+
+    /// Waits for the next tick and returns how long the wait has lasted.
+    /// Never completes when heartbeats are disabled.
+    async fn tick(&mut self) -> Duration {
+        match &mut self.interval {
+            Some(interval) => {
+                interval.tick().await;
+                self.started.elapsed()
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
 /// A stream of agent events.
 pub type AgentStream = Pin<Box<dyn Stream<Item = AgentEvent> + Send>>;
 
@@ -81,6 +632,31 @@ pub enum AgentError {
     /// A tool error occurred
     #[error("Tool error: {0}")]
     ToolError(#[from] crate::tool::ToolError),
+    /// The prompt does not fit in the model's context window
+    #[error("Context window exceeded: {prompt_tokens} prompt tokens + {max_tokens} max tokens > {limit}")]
+    ContextWindowExceeded {
+        prompt_tokens: usize,
+        max_tokens: u32,
+        limit: u32,
+    },
+    /// The model kept returning empty responses
+    #[error("LLM returned an empty response")]
+    EmptyResponse,
+    /// The run exceeded its budget
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(BudgetLimit),
+    /// The run was cancelled
+    #[error("Run cancelled")]
+    Cancelled,
+    /// The configuration failed validation
+    #[error("Invalid agent configuration: {}", .0.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("; "))]
+    InvalidConfig(Vec<ConfigDiagnostic>),
+    /// A session store error occurred
+    #[error("Session store error: {0}")]
+    Store(#[from] StoreError),
+    /// A stored session does not match the agent resuming it
+    #[error("Cannot resume session: {}", .0.iter().map(|m| m.to_string()).collect::<Vec<_>>().join("; "))]
+    ResumeMismatch(Vec<ResumeMismatch>),
 }
 
 /// The agent that can run conversations with tools.
@@ -90,6 +666,17 @@ pub struct Agent {
     llm_client: Arc<dyn LLMClient>,
     tool_executor: Arc<ToolExecutor>,
     config: AgentConfig,
+    context_providers: Vec<Arc<dyn ContextProvider>>,
+    system_prompt_provider: Option<Arc<dyn SystemPromptProvider>>,
+    pricing: Arc<PricingTable>,
+    guardrails: Vec<Arc<dyn OutputGuardrail>>,
+    stop_conditions: Vec<Arc<dyn StopCondition>>,
+    token_counter: Arc<dyn TokenCounter>,
+    events: EventBus,
+    run_options: Arc<RunOptions>,
+    bundle_root: Option<PathBuf>,
+    store: Option<Arc<dyn SessionStore>>,
+    event_approvals: bool,
 }
 
 impl Agent {
@@ -100,12 +687,27 @@ impl Agent {
         registry: Arc<Mutex<ToolRegistry>>,
         config: AgentConfig,
     ) -> Self {
-        let tool_executor = Arc::new(ToolExecutor::new(registry));
+        let mut tool_executor = ToolExecutor::new(registry);
+        if let Some(timeout) = config.tool_timeout {
+            tool_executor = tool_executor.with_default_timeout(timeout);
+        }
+        let tool_executor = Arc::new(tool_executor);
         Self {
             session: Arc::new(Mutex::new(session)),
             llm_client,
             tool_executor,
             config,
+            context_providers: Vec::new(),
+            system_prompt_provider: None,
+            pricing: Arc::new(PricingTable::with_defaults()),
+            guardrails: Vec::new(),
+            stop_conditions: Vec::new(),
+            token_counter: Arc::new(HeuristicTokenCounter),
+            events: EventBus::new(),
+            run_options: Arc::default(),
+            bundle_root: None,
+            store: None,
+            event_approvals: false,
         }
     }
 
@@ -118,152 +720,1317 @@ impl Agent {
         Self::new(session, llm_client, registry, AgentConfig::default())
     }
 
-    /// Adds a user message to the session and runs the agent.
-    pub async fn run(&self, user_input: &str) -> Result<Vec<Message>, AgentError> {
-        let mut session = self.session.lock().await;
-        session.add_message(Message::new_user(user_input));
-        session.status = SessionStatus::Running;
-        drop(session);
+    /// Adds a context provider whose output is injected into every LLM call.
+    pub fn with_context_provider(mut self, provider: Arc<dyn ContextProvider>) -> Self {
+        self.context_providers.push(provider);
+        self
+    }
 
-        let messages = self.run_loop().await?;
+    /// Writes a run bundle for every run into a new directory under `root`.
+    ///
+    /// See `RunBundle` for the contents; bundles are reopened with
+    /// `RunBundle::load`.
+    pub fn with_run_bundles(mut self, root: impl Into<PathBuf>) -> Self {
+        self.bundle_root = Some(root.into());
+        self
+    }
 
-        let mut session = self.session.lock().await;
-        session.status = SessionStatus::Completed;
+    /// Saves the session to `store` after every step and when a run ends,
+    /// so conversations survive restarts; reopen them with `resume`. Save
+    /// failures are logged and do not fail the run.
+    pub fn with_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
 
-        Ok(messages)
+    /// Saves the session to the store, if the agent has one.
+    async fn autosave(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let session = self.session.lock().await.clone();
+        if let Err(e) = store.save(&session).await {
+            tracing::warn!(session_id = %session.id, error = %e, "Failed to save session");
+        }
     }
 
-    /// Runs the agent loop until completion.
-    async fn run_loop(&self) -> Result<Vec<Message>, AgentError> {
-        let mut step = 0;
+    /// Renders the system prompt with `provider` before every LLM call
+    /// instead of using `AgentConfig::system_prompt`.
+    pub fn with_system_prompt_provider(mut self, provider: Arc<dyn SystemPromptProvider>) -> Self {
+        self.system_prompt_provider = Some(provider);
+        self
+    }
 
-        while step < self.config.max_steps {
-            step += 1;
+    /// Sets the pricing table used to estimate request costs.
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = Arc::new(pricing);
+        self
+    }
 
-            // Get tool definitions from the registry
-            let tool_defs = self.tool_executor.get_tool_definitions().await;
-            debug!(count = tool_defs.len(), "Tool definitions loaded");
+    /// Adds an output guardrail checked against assistant text.
+    pub fn with_output_guardrail(mut self, guardrail: Arc<dyn OutputGuardrail>) -> Self {
+        self.guardrails.push(guardrail);
+        self
+    }
 
-            // Prepare LLM input
-            let session = self.session.lock().await;
-            let input = LLMInput {
-                model: self.config.model.clone(),
-                messages: session.messages.clone(),
-                system_prompt: self.config.system_prompt.clone(),
-                tools: tool_defs,
-                max_tokens: session.model.max_tokens,
-                temperature: self.config.temperature,
-            };
-            drop(session);
+    /// Adds a condition checked after every step that called tools; the run
+    /// ends with `StopReason::StopCondition` once any condition holds.
+    pub fn with_stop_condition(mut self, condition: Arc<dyn StopCondition>) -> Self {
+        self.stop_conditions.push(condition);
+        self
+    }
 
-            debug!(step, "Calling LLM");
+    /// Sets the vault whose secrets are resolved for tools and kept out of
+    /// prompts.
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.tool_executor = Arc::new((*self.tool_executor).clone().with_vault(vault));
+        self
+    }
 
-            // Call LLM
-            let response = self.llm_client.complete(input).await?;
+    /// Limits how many tool calls of one step run at the same time.
+    pub fn with_max_tool_concurrency(mut self, limit: usize) -> Self {
+        self.tool_executor = Arc::new((*self.tool_executor).clone().with_max_concurrency(limit));
+        self
+    }
 
-            // Create assistant message
-            let assistant_message = Message::new_assistant(response.content.clone());
-            let message_id = assistant_message.id.clone();
+    /// Shortens oversized tool results before they are added to the session.
+    pub fn with_tool_truncation(mut self, policy: TruncationPolicy) -> Self {
+        self.tool_executor = Arc::new((*self.tool_executor).clone().with_truncation(policy));
+        self
+    }
 
-            {
-                let mut session = self.session.lock().await;
-                session.add_message(assistant_message);
+    /// Sets the cache that serves repeated calls to deterministic tools,
+    /// across runs and sessions when it persists to disk.
+    pub fn with_tool_cache(mut self, cache: ToolCache) -> Self {
+        self.tool_executor = Arc::new((*self.tool_executor).clone().with_cache(cache));
+        self
+    }
+
+    /// Restricts the agent to the tools `filter` allows, e.g. to let agents
+    /// sharing one registry use different subsets of it.
+    pub fn with_tool_filter(mut self, filter: ToolFilter) -> Self {
+        self.tool_executor = Arc::new((*self.tool_executor).clone().with_filter(filter));
+        self
+    }
+
+    /// Returns the vault shared with tools.
+    pub fn vault(&self) -> &Vault {
+        self.tool_executor.vault()
+    }
+
+    /// Sets the permission rules consulted before every tool call. Calls
+    /// resolving to `Ask` are decided by the rules' approval handler, or
+    /// without one pause the run until approved or denied: streamed runs
+    /// report them as `AgentEvent::ApprovalRequired`, and `run` only with
+    /// `with_event_approvals`.
+    pub fn with_permissions(mut self, permissions: PermissionManager) -> Self {
+        self.tool_executor = Arc::new((*self.tool_executor).clone().with_permissions(permissions));
+        self
+    }
+
+    /// Approves a tool call awaiting approval, resuming the run. Returns
+    /// `false` if no such call is waiting.
+    pub fn approve(&self, call_id: &str) -> bool {
+        self.tool_executor.approve(call_id)
+    }
+
+    /// Denies a tool call awaiting approval; the model sees an error result.
+    /// Returns `false` if no such call is waiting.
+    pub fn deny(&self, call_id: &str) -> bool {
+        self.tool_executor.deny(call_id)
+    }
+
+    /// Denies tool calls left awaiting approval for longer than `timeout`.
+    pub fn with_approval_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_executor = Arc::new((*self.tool_executor).clone().with_approval_timeout(timeout));
+        self
+    }
+
+    /// Lets `run` ask subscribers to tool events for approvals, which they
+    /// answer with `approve` and `deny`. Without it, calls that need approval
+    /// and have no approval handler fail in `run`, since a subscriber may
+    /// only be watching.
+    pub fn with_event_approvals(mut self) -> Self {
+        self.event_approvals = true;
+        self
+    }
+
+    /// Sets the token counter used to size prompts before sending them.
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = counter;
+        self
+    }
+
+    /// Subscribes to the events of streamed runs on the given topics.
+    ///
+    /// Events of other topics are never cloned for the subscription, so a
+    /// consumer of tool events does not pay for text deltas. Runs started
+    /// with `run` publish the same step events as streamed runs, without
+    /// text, thinking or heartbeats; with `with_event_approvals`, subscribers
+    /// also hear of and answer approval requests.
+    pub fn events(&self, mask: TopicMask) -> EventSubscription {
+        self.events.subscribe(mask)
+    }
+
+    /// Publishes `AgentEvent::MCPServerDown` to event subscribers whenever
+    /// the client loses its connection, and `AgentEvent::MCPLog` for the
+    /// log messages the server sends. The task ends with the client.
+    pub fn monitor_mcp_server(&self, client: &MCPClient) -> tokio::task::JoinHandle<()> {
+        let server = client.name().to_string();
+        let mut status = client.subscribe_status();
+        let mut logs = client.subscribe_logs();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let mut was_connected = *status.borrow_and_update() == MCPStatus::Connected;
+            loop {
+                tokio::select! {
+                    changed = status.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                    }
+                    log = logs.recv() => {
+                        match log {
+                            Ok(log) => events.publish(&AgentEvent::MCPLog {
+                                message: log.text(),
+                                server: log.server,
+                                level: log.level,
+                                logger: log.logger,
+                            }),
+                            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        }
+                        continue;
+                    }
+                }
+                let current = status.borrow_and_update().clone();
+                let error = match &current {
+                    MCPStatus::Reconnecting { error, .. } | MCPStatus::Failed { error } => Some(error.clone()),
+                    _ => None,
+                };
+                if was_connected && let Some(error) = error {
+                    events.publish(&AgentEvent::MCPServerDown { server: server.clone(), error });
+                }
+                was_connected = current == MCPStatus::Connected;
             }
+        })
+    }
 
-            // Check for tool calls
-            let tool_calls: Vec<MessageContent> = response
-                .content
-                .iter()
-                .filter(|c| matches!(c, MessageContent::ToolCall { .. }))
-                .cloned()
-                .collect();
+    /// Creates a sibling agent that shares the LLM client, tool registry and
+    /// context providers, but starts with a new empty session and a config
+    /// tweaked by `overrides`.
+    pub async fn fork_with(&self, overrides: impl FnOnce(&mut AgentConfig)) -> Self {
+        let mut config = self.config.clone();
+        overrides(&mut config);
 
-            if tool_calls.is_empty() {
-                // No tool calls, loop ends
-                break;
+        let session = {
+            let session = self.session.lock().await;
+            Session::new(session.model.clone(), config.system_prompt.clone())
+        };
+
+        Self {
+            session: Arc::new(Mutex::new(session)),
+            llm_client: self.llm_client.clone(),
+            tool_executor: self.tool_executor.clone(),
+            config,
+            context_providers: self.context_providers.clone(),
+            system_prompt_provider: self.system_prompt_provider.clone(),
+            pricing: self.pricing.clone(),
+            guardrails: self.guardrails.clone(),
+            stop_conditions: self.stop_conditions.clone(),
+            token_counter: self.token_counter.clone(),
+            events: EventBus::new(),
+            run_options: Arc::default(),
+            bundle_root: self.bundle_root.clone(),
+            store: self.store.clone(),
+            event_approvals: self.event_approvals,
+        }
+    }
+
+    /// Replaces the agent's session with a stored one, checking that the
+    /// tools its history calls and the model still match.
+    ///
+    /// Returns the mismatches found. With `ResumePolicy::Adapt` a notice
+    /// describing them is added to the session; with `ResumePolicy::Strict`
+    /// any mismatch fails with `AgentError::ResumeMismatch` and the current
+    /// session is kept.
+    pub async fn resume(
+        &self,
+        store: &dyn SessionStore,
+        session_id: &str,
+        policy: ResumePolicy,
+    ) -> Result<Vec<ResumeMismatch>, AgentError> {
+        let mut session = store.load(session_id).await?;
+        let (tools, _) = self.tool_executor.versioned_tool_definitions().await;
+        let model = self.model();
+
+        let mismatches = ResumeMismatch::detect(&session, &tools, model);
+        if !mismatches.is_empty() {
+            if policy == ResumePolicy::Strict {
+                return Err(AgentError::ResumeMismatch(mismatches));
             }
+            tracing::warn!(session_id, count = mismatches.len(), "Resuming session with mismatches");
+            session.add_message(Message::new_user(ResumeMismatch::notice(&mismatches)));
+        }
+
+        *self.session.lock().await = session;
+        Ok(mismatches)
+    }
 
-            debug!(count = tool_calls.len(), "Executing tool calls");
+    /// Returns the agent configuration.
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
 
-            // Execute tool calls
-            let session_id = {
-                let session = self.session.lock().await;
-                session.id.clone()
-            };
+    /// Returns the model of the current run: the `RunOptions` override, or
+    /// the configured model.
+    fn model(&self) -> &str {
+        self.run_options.model.as_deref().unwrap_or(&self.config.model)
+    }
 
-            let ctx = ExecutionContext {
-                session_id,
-                message_id,
-            };
+    /// Runs the agent like `run`, with `options` overriding the configuration
+    /// for this run only.
+    pub async fn run_with(&self, user_input: &str, options: RunOptions) -> Result<AgentRunResult, AgentError> {
+        let mut agent = self.clone();
+        if let Some(filter) = &options.tools {
+            agent.tool_executor = Arc::new((*agent.tool_executor).clone().with_filter(filter.clone()));
+        }
+        if let Some(permissions) = &options.permissions {
+            agent.tool_executor = Arc::new((*agent.tool_executor).clone().with_permission_overlay(permissions.clone()));
+        }
+        agent.run_options = Arc::new(options);
+        agent.run(user_input).await
+    }
+
+    /// Adds a user message to the session and runs the agent.
+    ///
+    /// The session keeps the full history across calls, so each call continues
+    /// the conversation; the result holds only the messages produced for this
+    /// turn.
+    pub async fn run(&self, user_input: &str) -> Result<AgentRunResult, AgentError> {
+        let (agent, bundle) = self.open_bundle();
+        let started = Instant::now();
+        let user_message_id = agent.begin_turn(user_input).await;
+        let result = agent.run_loop().await;
+        let result = agent.finish_turn(&user_message_id, started, result).await;
+        agent.autosave().await;
+        agent.close_bundle(bundle, &result).await;
+        result
+    }
+
+    /// Adds a user message to the session and streams the agent's response;
+    /// the streaming counterpart of `run`.
+    pub async fn chat_stream(&self, user_input: &str) -> Result<AgentStream, AgentError> {
+        self.begin_turn(user_input).await;
+        self.stream().await
+    }
 
-            let results = self.tool_executor.execute_all(tool_calls, ctx).await;
+    /// Adds a user message to the session and runs the agent until it
+    /// finishes or `cancel` is triggered.
+    ///
+    /// Cancelling drops the in-flight LLM request and tool executions, closes
+    /// any unanswered tool calls and marks the session `Cancelled`.
+    pub async fn run_with_cancel(
+        &self,
+        user_input: &str,
+        cancel: CancellationToken,
+    ) -> Result<AgentRunResult, AgentError> {
+        let (agent, bundle) = self.open_bundle();
+        let started = Instant::now();
+        let user_message_id = agent.begin_turn(user_input).await;
 
-            // Save tool results
-            let tool_message = Message::new_tool_result(results);
-            {
-                let mut session = self.session.lock().await;
-                session.add_message(tool_message);
+        let result = tokio::select! {
+            result = agent.run_loop() => agent.finish_turn(&user_message_id, started, result).await,
+            _ = cancel.cancelled() => {
+                agent.mark_cancelled().await;
+                Err(AgentError::Cancelled)
+            }
+        };
+
+        agent.autosave().await;
+        agent.close_bundle(bundle, &result).await;
+        result
+    }
+
+    /// Returns the agent a run should use: when run bundles are enabled, a
+    /// copy whose LLM traffic is logged to a new bundle.
+    fn open_bundle(&self) -> (Self, Option<BundleWriter>) {
+        let Some(root) = &self.bundle_root else {
+            return (self.clone(), None);
+        };
+        match BundleWriter::create(root, self.llm_client.clone(), &self.config) {
+            Ok(bundle) => {
+                let agent = Self {
+                    llm_client: bundle.llm(),
+                    ..self.clone()
+                };
+                (agent, Some(bundle))
+            }
+            Err(e) => {
+                tracing::warn!(root = %root.display(), "Failed to create run bundle: {}", e);
+                (self.clone(), None)
             }
         }
+    }
 
+    /// Writes the transcript and profile of a finished run's bundle.
+    async fn close_bundle(&self, bundle: Option<BundleWriter>, result: &Result<AgentRunResult, AgentError>) {
+        let Some(bundle) = bundle else {
+            return;
+        };
         let session = self.session.lock().await;
-        Ok(session.messages.clone())
+        match result {
+            Ok(result) => bundle.finish(&session, result.steps, Some(result.stop_reason.clone()), None),
+            Err(e) => bundle.finish(&session, 0, None, Some(e.to_string())),
+        }
     }
 
-    /// Runs the agent with streaming output.
-    pub async fn stream(&self) -> Result<AgentStream, AgentError> {
-        let session = self.session.clone();
-        let llm_client = self.llm_client.clone();
-        let tool_executor = self.tool_executor.clone();
-        let config = self.config.clone();
+    /// Appends the user message and marks the session running. Returns the
+    /// message ID.
+    async fn begin_turn(&self, user_input: &str) -> String {
+        let user_message = Message::new_user(user_input);
+        let id = user_message.id.clone();
+        let mut session = self.session.lock().await;
+        session.add_message(user_message);
+        session.status = SessionStatus::Running;
+        id
+    }
 
-        let stream = async_stream::stream! {
-            let mut step = 0;
+    /// Records the final session status and collects the messages added
+    /// after the user message.
+    async fn finish_turn(
+        &self,
+        user_message_id: &str,
+        started: Instant,
+        result: Result<RunStats, AgentError>,
+    ) -> Result<AgentRunResult, AgentError> {
+        let mut session = self.session.lock().await;
+        session.status = match result {
+            Ok(_) => SessionStatus::Completed,
+            Err(_) => SessionStatus::Error,
+        };
+        let stats = result?;
 
-            while step < config.max_steps {
-                step += 1;
+        let start = session
+            .messages
+            .iter()
+            .position(|m| m.id == user_message_id)
+            .map_or(0, |i| i + 1);
+        let messages = session.messages[start..].to_vec();
+        let text = messages
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::Assistant)
+            .map(|m| Self::collect_text(&m.content))
+            .unwrap_or_default();
 
-                yield AgentEvent::MessageStart {
-                    role: MessageRole::Assistant
-                };
+        Ok(AgentRunResult {
+            messages,
+            text,
+            usage: stats.usage,
+            steps: stats.steps,
+            elapsed: started.elapsed(),
+            stop_reason: stats.stop_reason,
+        })
+    }
 
-                // Get tool definitions from the registry
-                let tool_defs = tool_executor.get_tool_definitions().await;
-
-                // Prepare LLM input
-                let session_guard = session.lock().await;
-                let input = LLMInput {
-                    model: config.model.clone(),
-                    messages: session_guard.messages.clone(),
-                    system_prompt: config.system_prompt.clone(),
-                    tools: tool_defs,
-                    max_tokens: session_guard.model.max_tokens,
-                    temperature: config.temperature,
-                };
-                drop(session_guard);
+    /// Streams the agent until it finishes or `cancel` is triggered, in which
+    /// case a terminal `AgentEvent::Cancelled` is yielded.
+    pub async fn stream_with_cancel(&self, cancel: CancellationToken) -> Result<AgentStream, AgentError> {
+        let mut inner = self.stream().await?;
+        let agent = self.clone();
 
-                // Stream LLM response
-                let mut llm_stream = match llm_client.stream(input).await {
-                    Ok(stream) => stream,
-                    Err(e) => {
-                        yield AgentEvent::Error {
-                            error: e.to_string()
-                        };
-                        return;
+        let stream = async_stream::stream! {
+            loop {
+                tokio::select! {
+                    // Check cancellation first so no further work starts once it is requested
+                    biased;
+                    _ = cancel.cancelled() => {
+                        // Dropping the inner stream aborts the LLM call and tools
+                        drop(inner);
+                        agent.mark_cancelled().await;
+                        agent.autosave().await;
+                        agent.events.publish(&AgentEvent::Cancelled);
+                        yield AgentEvent::Cancelled;
+                        break;
                     }
-                };
+                    event = inner.next() => match event {
+                        Some(event) => yield event,
+                        None => break,
+                    },
+                }
+            }
+        };
 
-                let mut content = Vec::new();
-                let mut tool_calls = Vec::new();
-                let _finish_reason = FinishReason::Stop;
-                let _current_tool_id: Option<String> = None;
+        Ok(Box::pin(stream))
+    }
 
-                while let Some(event_result) = llm_stream.next().await {
-                    match event_result {
+    /// Marks the session cancelled and answers tool calls that never ran, so
+    /// the conversation stays valid for providers.
+    async fn mark_cancelled(&self) {
+        let mut session = self.session.lock().await;
+        session.status = SessionStatus::Cancelled;
+
+        let answered: Vec<String> = session
+            .messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|c| match c {
+                MessageContent::ToolResult { tool_call_id, .. } => Some(tool_call_id.clone()),
+                _ => None,
+            })
+            .collect();
+        let Some(last_assistant) = session
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::Assistant)
+        else {
+            return;
+        };
+        let dangling: Vec<MessageContent> = last_assistant
+            .content
+            .iter()
+            .filter_map(|c| match c {
+                MessageContent::ToolCall { id, .. } if !answered.contains(id) => {
+                    Some(MessageContent::ToolResult {
+                        tool_call_id: id.clone(),
+                        result: "Tool call cancelled".to_string(),
+                        is_error: Some(true),
+                        blocks: Vec::new(),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        if !dangling.is_empty() {
+            session.add_message(Message::new_tool_result(dangling));
+        }
+    }
+
+    /// Runs the agent loop until completion.
+    async fn run_loop(&self) -> Result<RunStats, AgentError> {
+        let mut state = self.run_state().await;
+        let mut events = Vec::new();
+        let mut stop_reason = StopReason::MaxSteps;
+
+        while state.step < self.config.max_steps {
+            let input = self.begin_step(&mut state, &mut events).await;
+            self.publish_all(&mut events);
+            let input = input?;
+
+            debug!(step = state.step, "Calling LLM");
+
+            // Call LLM
+            let model = input.model.clone();
+            let requested = Instant::now();
+            let response = self.complete_with_retry(input).await?;
+            events.push(AgentEvent::MessageEnd {
+                finish_reason: response.finish_reason.clone(),
+            });
+            let tripped = self.check_guardrails(&Self::collect_text(&response.content));
+            let response = StepResponse {
+                content: response.content,
+                finish_reason: response.finish_reason,
+                usage: Some(response.usage),
+                model,
+                latency: requested.elapsed(),
+                tripped,
+            };
+            let outcome = self.finish_step(&mut state, response, &mut events).await;
+            self.publish_all(&mut events);
+            let tools = match outcome? {
+                StepOutcome::Continue => continue,
+                StepOutcome::Stop(reason) => {
+                    stop_reason = reason;
+                    break;
+                }
+                StepOutcome::ExecuteTools(tools) => tools,
+            };
+
+            debug!(count = tools.calls.len(), "Executing tool calls");
+
+            let ctx = ExecutionContext {
+                session_id: self.session_id().await,
+                message_id: tools.message_id.clone(),
+            };
+            let results = self.execute_tools(tools.calls.clone(), ctx).await;
+            let stop = self.finish_tools(&state, &tools, results, &mut events).await;
+            self.publish_all(&mut events);
+
+            if stop {
+                stop_reason = StopReason::StopCondition;
+                break;
+            }
+        }
+
+        Ok(RunStats {
+            steps: state.step,
+            usage: state.usage,
+            stop_reason,
+        })
+    }
+
+    /// Publishes the events of a `run` step, which has no stream to yield
+    /// them on.
+    fn publish_all(&self, events: &mut Vec<AgentEvent>) {
+        for event in events.drain(..) {
+            self.events.publish(&event);
+        }
+    }
+
+    /// Returns the terminal event a stream reports `error` with.
+    fn error_event(error: AgentError) -> AgentEvent {
+        match error {
+            AgentError::BudgetExceeded(limit) => AgentEvent::BudgetExceeded { limit },
+            error => AgentEvent::Error {
+                error: error.to_string(),
+            },
+        }
+    }
+
+    /// Starts the bookkeeping of a run of the current task.
+    async fn run_state(&self) -> RunState {
+        RunState {
+            step: 0,
+            usage: Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+            },
+            budget: BudgetTracker::new(),
+            guardrails: GuardrailState::default(),
+            validation_retries: 0,
+            empty_retries: 0,
+            reflection_rounds: 0,
+            task: self.current_task().await,
+        }
+    }
+
+    /// Opens a step of either loop: checks the budget, then builds the
+    /// input of the step's LLM request.
+    async fn begin_step(&self, state: &mut RunState, events: &mut Vec<AgentEvent>) -> Result<LLMInput, AgentError> {
+        if let Some(limit) = state.budget.check(&self.config.budget) {
+            return Err(AgentError::BudgetExceeded(limit));
+        }
+        state.step += 1;
+        if self.config.yield_between_steps {
+            tokio::task::yield_now().await;
+        }
+        events.push(AgentEvent::MessageStart {
+            role: MessageRole::Assistant,
+        });
+
+        let mut input = self.build_input().await;
+        if let Some(removed_messages) = self.maybe_compact(&input).await? {
+            events.push(AgentEvent::Compacted { removed_messages });
+            input = self.build_input().await;
+        }
+        if state.guardrails.reinforce {
+            self.reinforce(&mut input);
+        }
+
+        self.check_context_window(&input)?;
+        self.record_provenance(&input).await;
+        Ok(input)
+    }
+
+    /// Handles the LLM response of a step in either loop: records its usage,
+    /// applies the output guardrails, nudges after empty responses, saves the
+    /// assistant message, and validates and reviews a final answer.
+    async fn finish_step(
+        &self,
+        state: &mut RunState,
+        response: StepResponse,
+        events: &mut Vec<AgentEvent>,
+    ) -> Result<StepOutcome, AgentError> {
+        let StepResponse {
+            mut content,
+            finish_reason,
+            usage,
+            model,
+            latency,
+            tripped,
+        } = response;
+        if let Some(usage) = &usage {
+            let cost = self.record_usage(&model, usage).await;
+            state.budget.add(usage, cost);
+            state.usage.input_tokens += usage.input_tokens;
+            state.usage.output_tokens += usage.output_tokens;
+        }
+
+        // Check the answer against output guardrails
+        let guardrail_step = state.guardrails.step(&self.config.guardrail, tripped.is_some(), &mut content);
+        if let Some((guardrail, reason)) = tripped {
+            debug!(guardrail = %guardrail, reason = %reason, "Output guardrail tripped");
+            events.push(AgentEvent::GuardrailTripped {
+                guardrail,
+                reason,
+                restarting: guardrail_step == GuardrailStep::Restart,
+            });
+        }
+        if guardrail_step == GuardrailStep::Restart {
+            return Ok(StepOutcome::Continue);
+        }
+        let withheld = guardrail_step == GuardrailStep::Withhold;
+
+        if Self::is_empty_response(&content, &finish_reason) {
+            let nudge = self.nudge(&mut state.empty_retries);
+            events.push(AgentEvent::EmptyResponse {
+                retrying: nudge.is_some(),
+            });
+            let Some(nudge) = nudge else {
+                return Err(AgentError::EmptyResponse);
+            };
+            self.session.lock().await.add_message(Message::new_user(nudge));
+            return Ok(StepOutcome::Continue);
+        }
+
+        let calls: Vec<MessageContent> = content
+            .iter()
+            .filter(|c| matches!(c, MessageContent::ToolCall { .. }))
+            .cloned()
+            .collect();
+
+        // Only the final answer is validated
+        let invalid = if calls.is_empty() {
+            self.validate_output(&mut content)
+        } else {
+            None
+        };
+
+        let assistant_message =
+            Self::annotate_response(Message::new_assistant(content.clone()), &model, latency, usage);
+        let message_id = assistant_message.id.clone();
+        self.session.lock().await.add_message(assistant_message);
+
+        if !calls.is_empty() {
+            return Ok(StepOutcome::ExecuteTools(ToolStep {
+                calls,
+                message_id,
+                content,
+            }));
+        }
+
+        if let Some(reason) = &invalid {
+            let correction = self.correction(reason, &mut state.validation_retries);
+            events.push(AgentEvent::ValidationFailed {
+                reason: reason.clone(),
+                retrying: correction.is_some(),
+            });
+            if let Some(correction) = correction {
+                self.session.lock().await.add_message(Message::new_user(correction));
+                return Ok(StepOutcome::Continue);
+            }
+        } else if !withheld
+            && let Some(critique) = self
+                .reflect(&state.task, &content, &mut state.reflection_rounds, &mut state.budget)
+                .await?
+        {
+            state.usage.input_tokens += critique.usage.input_tokens;
+            state.usage.output_tokens += critique.usage.output_tokens;
+            if let Some(feedback) = critique.feedback {
+                events.push(AgentEvent::Critique {
+                    feedback: feedback.clone(),
+                });
+                let revision = Message::new_user(ReflectionConfig::revision(&feedback));
+                self.session.lock().await.add_message(revision);
+                return Ok(StepOutcome::Continue);
+            }
+        }
+
+        // No tool calls, loop ends
+        Ok(StepOutcome::Stop(if withheld {
+            StopReason::GuardrailTripped
+        } else if invalid.is_some() {
+            StopReason::ValidationFailed
+        } else {
+            match finish_reason {
+                FinishReason::MaxTokens => StopReason::MaxTokens,
+                FinishReason::ContentFilter => StopReason::ContentFilter,
+                _ => StopReason::Completed,
+            }
+        }))
+    }
+
+    /// Closes a step of either loop whose tool calls ran: saves their
+    /// results and returns whether a stop condition ends the run.
+    async fn finish_tools(
+        &self,
+        state: &RunState,
+        tools: &ToolStep,
+        results: Vec<MessageContent>,
+        events: &mut Vec<AgentEvent>,
+    ) -> bool {
+        for result in &results {
+            if let MessageContent::ToolResult {
+                tool_call_id,
+                result,
+                ..
+            } = result
+            {
+                events.push(AgentEvent::ToolResult {
+                    name: tool_call_id.clone(),
+                    result: result.clone(),
+                });
+            }
+        }
+
+        let stop = self.stop_condition_met(&StepContext {
+            step: state.step,
+            content: &tools.content,
+            tool_calls: &tools.calls,
+            tool_results: &results,
+            total_tokens: state.budget.tokens,
+        });
+
+        // Save tool results
+        let tool_message = self.tool_result_message(results, &tools.message_id);
+        self.session.lock().await.add_message(tool_message);
+        self.autosave().await;
+        stop
+    }
+
+    /// Executes the tool calls of a `run` step. With event approvals on and
+    /// anyone subscribed to tool events, the events are published, so
+    /// approval requests can be answered.
+    async fn execute_tools(&self, calls: Vec<MessageContent>, ctx: ExecutionContext) -> Vec<MessageContent> {
+        if !self.event_approvals || !self.events.has_subscribers(TopicMask::TOOL) {
+            return self.tool_executor.execute_all(calls, ctx).await;
+        }
+
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let execution = self.tool_executor.execute_all_with_events(calls, ctx, events_tx);
+        tokio::pin!(execution);
+        let results = loop {
+            tokio::select! {
+                results = &mut execution => break results,
+                Some(event) = events_rx.recv() => self.events.publish(&AgentEvent::from(event)),
+            }
+        };
+        while let Ok(event) = events_rx.try_recv() {
+            self.events.publish(&AgentEvent::from(event));
+        }
+        results
+    }
+
+    /// Builds the LLM input for the next step from the current session state.
+    async fn build_input(&self) -> LLMInput {
+        // Get tool definitions from the registry
+        let (tool_defs, tool_generation) = self.tool_executor.versioned_tool_definitions().await;
+        debug!(count = tool_defs.len(), "Tool definitions loaded");
+
+        let session = self.session.lock().await;
+
+        // Ephemeral context is appended to the system prompt for this call only
+        let mut system_prompt = match &self.system_prompt_provider {
+            Some(provider) => provider.render(&session).await,
+            None => self.config.system_prompt.clone(),
+        };
+        for provider in &self.context_providers {
+            if let Some(context) = provider.provide(&session).await {
+                if !system_prompt.is_empty() {
+                    system_prompt.push_str("\n\n");
+                }
+                system_prompt.push_str(&context);
+            }
+        }
+
+        // Stored secrets never reach the provider, only their references
+        let vault = self.tool_executor.vault();
+        let messages = if vault.is_empty() {
+            session.messages.clone()
+        } else {
+            session.messages.iter().map(|m| Self::redact_message(vault, m)).collect()
+        };
+
+        let mut input = LLMInput {
+            model: self.config.model.clone(),
+            messages,
+            system_prompt,
+            tools: tool_defs,
+            max_tokens: session.model.max_tokens,
+            temperature: self.config.temperature,
+            request_options: self.config.request_options.clone(),
+            // Filtered definitions differ from others of the same generation
+            tool_generation: self.tool_executor.filter().is_none().then_some(tool_generation),
+        };
+        self.run_options.apply(&mut input);
+
+        if let Some(strategy) = session.context_strategy.as_ref().or(self.config.context_strategy.as_ref()) {
+            // The messages get what the window leaves after everything else
+            let budget = self.context_limit(&input.model).map(|limit| {
+                let messages = std::mem::take(&mut input.messages);
+                let fixed = self.count_tokens(&input) + input.max_tokens as usize;
+                input.messages = messages;
+                (limit as usize).saturating_sub(fixed)
+            });
+            input.messages = strategy.apply(&input.messages, budget, self.token_counter.as_ref());
+        }
+
+        input
+    }
+
+    /// Records the model and tools of a request about to be sent, so a
+    /// resumed session can be checked against them.
+    async fn record_provenance(&self, input: &LLMInput) {
+        self.session.lock().await.provenance = Some(SessionProvenance {
+            model: input.model.clone(),
+            tools: input.tools.iter().map(|t| (t.name.clone(), t.fingerprint())).collect(),
+        });
+    }
+
+    /// Records the model, latency and token usage of the request that
+    /// produced an assistant message.
+    fn annotate_response(message: Message, model: &str, latency: Duration, usage: Option<Usage>) -> Message {
+        let message = message
+            .with_metadata("model", model)
+            .with_metadata("latency_ms", latency.as_millis() as u64);
+        match usage {
+            Some(usage) => message.with_usage(usage),
+            None => message,
+        }
+    }
+
+    /// Builds the message of a step's tool results, recording how long each
+    /// call of the assistant message `message_id` took.
+    fn tool_result_message(&self, results: Vec<MessageContent>, message_id: &str) -> Message {
+        let durations: serde_json::Map<String, serde_json::Value> = self
+            .tool_executor
+            .audit_log()
+            .into_iter()
+            .filter(|entry| entry.message_id == message_id)
+            .map(|entry| (entry.call_id, (entry.duration.as_millis() as u64).into()))
+            .collect();
+        Message::new_tool_result(results).with_metadata("tool_durations_ms", durations)
+    }
+
+    /// Replaces stored secrets in a message with their vault references.
+    fn redact_message(vault: &Vault, message: &Message) -> Message {
+        let mut message = message.clone();
+        for content in &mut message.content {
+            match content {
+                MessageContent::Text { text } => *text = vault.redact(text),
+                MessageContent::Thinking { thinking } => *thinking = vault.redact(thinking),
+                MessageContent::ToolCall { arguments, .. } => *arguments = vault.redact_value(arguments),
+                MessageContent::ToolResult { result, .. } => *result = vault.redact(result),
+                MessageContent::Image { .. } => {}
+            }
+        }
+        message
+    }
+
+    /// Counts the prompt tokens of the input.
+    pub fn count_tokens(&self, input: &LLMInput) -> usize {
+        self.token_counter.count_input(input)
+    }
+
+    /// Estimates the prompt tokens and cost of the next LLM request built
+    /// from the current session, without sending it.
+    pub async fn estimate_next_call(&self) -> CallEstimate {
+        let input = self.build_input().await;
+        let input_tokens = self.count_tokens(&input);
+        let input_usage = Usage {
+            input_tokens: u32::try_from(input_tokens).unwrap_or(u32::MAX),
+            output_tokens: 0,
+        };
+        let max_usage = Usage {
+            output_tokens: input.max_tokens,
+            ..input_usage.clone()
+        };
+
+        CallEstimate {
+            input_cost: self.pricing.cost(&input.model, &input_usage),
+            max_cost: self.pricing.cost(&input.model, &max_usage),
+            model: input.model,
+            input_tokens,
+            max_output_tokens: input.max_tokens,
+        }
+    }
+
+    /// Estimates the usage of a response cut off before the provider
+    /// reported it.
+    fn estimate_usage(&self, prompt_tokens: usize, output: &str) -> Usage {
+        Usage {
+            input_tokens: u32::try_from(prompt_tokens).unwrap_or(u32::MAX),
+            output_tokens: u32::try_from(self.token_counter.count_text(output)).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// Returns the context window size for the model, if known.
+    fn context_limit(&self, model: &str) -> Option<u32> {
+        self.config.context_window.or_else(|| context_window(model))
+    }
+
+    /// Ensures the prompt plus the generation budget fits in the model's
+    /// context window, when the window size is known.
+    fn check_context_window(&self, input: &LLMInput) -> Result<(), AgentError> {
+        let Some(limit) = self.context_limit(&input.model) else {
+            return Ok(());
+        };
+
+        let prompt_tokens = self.count_tokens(input);
+        if prompt_tokens + input.max_tokens as usize > limit as usize {
+            return Err(AgentError::ContextWindowExceeded {
+                prompt_tokens,
+                max_tokens: input.max_tokens,
+                limit,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Summarizes older turns into a single message when the prompt nears the
+    /// context limit. Returns the number of messages replaced, if any.
+    async fn maybe_compact(&self, input: &LLMInput) -> Result<Option<usize>, AgentError> {
+        let Some(config) = &self.config.compaction else {
+            return Ok(None);
+        };
+        let Some(limit) = self.context_limit(&input.model) else {
+            return Ok(None);
+        };
+
+        let used = self.count_tokens(input) + input.max_tokens as usize;
+        if (used as f64) < f64::from(limit) * f64::from(config.threshold) {
+            return Ok(None);
+        }
+
+        let (split, transcript) = {
+            let session = self.session.lock().await;
+            let Some(split) = compaction::split_point(&session.messages, config.keep_recent) else {
+                return Ok(None);
+            };
+            (split, compaction::render_transcript(&session.messages[..split]))
+        };
+
+        debug!(messages = split, "Compacting conversation history");
+
+        let model = config.model.clone().unwrap_or_else(|| input.model.clone());
+        let output = compaction::summarize(
+            self.llm_client.as_ref(),
+            config,
+            &model,
+            transcript,
+            input.request_options.clone(),
+        )
+        .await?;
+        self.record_usage(&model, &output.usage).await;
+
+        let summary = compaction::summary_message(&Self::collect_text(&output.content));
+
+        let mut session = self.session.lock().await;
+        session.messages.splice(..split, [summary]);
+
+        Ok(Some(split))
+    }
+
+    /// Returns whether any stop condition holds after a step.
+    fn stop_condition_met(&self, step: &StepContext<'_>) -> bool {
+        self.stop_conditions.iter().any(|condition| condition.should_stop(step))
+    }
+
+    /// Checks text against the output guardrails, returning the name of the
+    /// first guardrail that tripped and why.
+    fn check_guardrails(&self, text: &str) -> Option<(String, String)> {
+        self.guardrails.iter().find_map(|guardrail| match guardrail.check(text) {
+            GuardrailVerdict::Pass => None,
+            GuardrailVerdict::Trip { reason } => Some((guardrail.name().to_string(), reason)),
+        })
+    }
+
+    /// Appends the guardrail reinforcement instructions to the system prompt.
+    fn reinforce(&self, input: &mut LLMInput) {
+        if !input.system_prompt.is_empty() {
+            input.system_prompt.push_str("\n\n");
+        }
+        input.system_prompt.push_str(&self.config.guardrail.reinforcement);
+    }
+
+    /// Concatenates the text blocks of a message.
+    pub(crate) fn collect_text(content: &[MessageContent]) -> String {
+        content
+            .iter()
+            .filter_map(|c| match c {
+                MessageContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns whether a response finished normally with nothing in it.
+    fn is_empty_response(content: &[MessageContent], finish_reason: &FinishReason) -> bool {
+        matches!(finish_reason, FinishReason::Stop)
+            && content.iter().all(|c| match c {
+                MessageContent::Text { text } => text.trim().is_empty(),
+                MessageContent::Thinking { .. } => true,
+                _ => false,
+            })
+    }
+
+    /// Returns the nudge message for an empty response, or `None` once the
+    /// retry budget is spent.
+    fn nudge(&self, retries: &mut usize) -> Option<String> {
+        let policy = &self.config.empty_response;
+        if *retries >= policy.max_retries {
+            tracing::warn!("LLM returned an empty response; giving up");
+            return None;
+        }
+        *retries += 1;
+        debug!(attempt = *retries, "LLM returned an empty response; nudging");
+        Some(policy.nudge.clone())
+    }
+
+    /// Validates the final answer, applying repairs in place. Returns the
+    /// reason when the answer is invalid.
+    fn validate_output(&self, content: &mut Vec<MessageContent>) -> Option<String> {
+        let validation = self.config.output_validator.as_ref()?;
+        match validation.validator.validate(&Self::collect_text(content)) {
+            ValidationOutcome::Valid => None,
+            ValidationOutcome::Repaired(text) => {
+                content.retain(|c| !matches!(c, MessageContent::Text { .. }));
+                content.push(MessageContent::Text { text });
+                None
+            }
+            ValidationOutcome::Invalid(reason) => Some(reason),
+        }
+    }
+
+    /// Returns the correction message for an invalid answer, or `None` once
+    /// the retry budget is spent.
+    fn correction(&self, reason: &str, retries: &mut usize) -> Option<String> {
+        let validation = self.config.output_validator.as_ref()?;
+        if *retries >= validation.max_retries {
+            tracing::warn!(reason, "Final answer failed validation; giving up");
+            return None;
+        }
+        *retries += 1;
+        debug!(reason, attempt = *retries, "Final answer failed validation; re-asking");
+        Some(validation.correction(reason))
+    }
+
+    /// Asks the critic to review a final answer. Returns `None` when
+    /// reflection is disabled or its rounds for this run are spent.
+    async fn reflect(
+        &self,
+        task: &str,
+        content: &[MessageContent],
+        rounds: &mut usize,
+        budget: &mut BudgetTracker,
+    ) -> Result<Option<Critique>, crate::llm::LLMError> {
+        let Some(reflection) = &self.config.reflection else {
+            return Ok(None);
+        };
+        if *rounds >= reflection.max_rounds {
+            return Ok(None);
+        }
+
+        let llm = reflection.llm.as_ref().unwrap_or(&self.llm_client);
+        let model = reflection.model.clone().unwrap_or_else(|| self.model().to_string());
+        let output = llm
+            .complete(LLMInput {
+                model: model.clone(),
+                messages: vec![Message::new_user(ReflectionConfig::review_request(
+                    task,
+                    &Self::collect_text(content),
+                ))],
+                system_prompt: reflection.critic_prompt.clone(),
+                tools: Vec::new(),
+                max_tokens: reflection.max_tokens,
+                temperature: None,
+                request_options: self.config.request_options.clone(),
+                tool_generation: None,
+            })
+            .await?;
+        let cost = self.record_usage(&model, &output.usage).await;
+        budget.add(&output.usage, cost);
+
+        let feedback = ReflectionConfig::feedback(&Self::collect_text(&output.content));
+        if feedback.is_some() {
+            *rounds += 1;
+            debug!(round = *rounds, "Critic requested a revision");
+        }
+        Ok(Some(Critique {
+            feedback,
+            usage: output.usage,
+        }))
+    }
+
+    /// Returns the text of the latest user message, i.e. the task of the
+    /// current run.
+    async fn current_task(&self) -> String {
+        let session = self.session.lock().await;
+        session
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| Self::collect_text(&m.content))
+            .unwrap_or_default()
+    }
+
+    /// Records token usage and estimated cost for a request in the session,
+    /// returning the cost.
+    async fn record_usage(&self, model: &str, usage: &Usage) -> Option<f64> {
+        let cost = self.pricing.cost(model, usage);
+        let mut session = self.session.lock().await;
+        session.record_usage(model, usage, cost);
+        cost
+    }
+
+    /// Sends a request, retrying transient failures per `AgentConfig::llm_retry`
+    /// and publishing `AgentEvent::Retry` before each retry.
+    async fn complete_with_retry(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
+        if self.config.llm_retry.is_none() {
+            return self.llm_client.complete(input).await;
+        }
+        let mut retries = 0;
+        loop {
+            match self.llm_client.complete(input.clone()).await {
+                Ok(output) => return Ok(output),
+                Err(e) => {
+                    let Some(delay) = self.retry_delay(&e, retries) else {
+                        return Err(e);
+                    };
+                    retries += 1;
+                    tracing::warn!(attempt = retries, "LLM request failed, retrying in {:?}: {}", delay, e);
+                    self.events.publish(&AgentEvent::Retry {
+                        attempt: retries,
+                        error: e.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Returns the delay before retrying a failed request, or `None` if it
+    /// should not be retried.
+    fn retry_delay(&self, error: &LLMError, retries: usize) -> Option<Duration> {
+        let policy = self.config.llm_retry.as_ref()?;
+        (retries < policy.max_retries && error.is_transient()).then(|| policy.backoff(retries))
+    }
+
+    /// Runs the agent with streaming output.
+    pub async fn stream(&self) -> Result<AgentStream, AgentError> {
+        let (agent, bundle) = self.open_bundle();
+        let mut steps = agent.stream_steps();
+        let finisher = agent.clone();
+        let stream: AgentStream = Box::pin(async_stream::stream! {
+            // Like `finish_turn`, record how the run ended
+            let mut status = SessionStatus::Completed;
+            while let Some(event) = steps.next().await {
+                if matches!(event, AgentEvent::Error { .. } | AgentEvent::BudgetExceeded { .. }) {
+                    status = SessionStatus::Error;
+                }
+                yield event;
+            }
+            finisher.session.lock().await.status = status;
+            finisher.autosave().await;
+        });
+        let stream = match bundle {
+            Some(bundle) => bundle.record(stream, self.session.clone()),
+            None => stream,
+        };
+        Ok(self.events.tee(stream))
+    }
+
+    /// Builds the event stream of one run.
+    fn stream_steps(&self) -> AgentStream {
+        let agent = self.clone();
+        let config = self.config.clone();
+
+        let stream = async_stream::stream! {
+            let mut state = agent.run_state().await;
+            let mut events = Vec::new();
+
+            let mut llm_retries = 0;
+            'steps: while state.step < config.max_steps {
+                let input = agent.begin_step(&mut state, &mut events).await;
+                for event in events.drain(..) {
+                    yield event;
+                }
+                let input = match input {
+                    Ok(input) => input,
+                    Err(e) => {
+                        yield Self::error_event(e);
+                        return;
+                    }
+                };
+                let model = input.model.clone();
+                // A tripped stream ends before the provider reports its usage
+                let prompt_tokens = (!agent.guardrails.is_empty()).then(|| agent.count_tokens(&input));
+
+                // Stream LLM response
+                let mut heartbeat = Heartbeat::new(config.heartbeat_interval);
+                let requested = Instant::now();
+                let mut step_usage = None;
+                let request = agent.llm_client.stream(input);
+                tokio::pin!(request);
+                let response = loop {
+                    tokio::select! {
+                        response = &mut request => break response,
+                        elapsed = heartbeat.tick() => {
+                            yield AgentEvent::Heartbeat { elapsed };
+                        }
+                    }
+                };
+                let mut llm_stream = match response {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        if let Some(delay) = agent.retry_delay(&e, llm_retries) {
+                            llm_retries += 1;
+                            yield AgentEvent::Retry { attempt: llm_retries, error: e.to_string() };
+                            tokio::time::sleep(delay).await;
+                            state.step -= 1;
+                            continue 'steps;
+                        }
+                        yield AgentEvent::Error {
+                            error: e.to_string()
+                        };
+                        return;
+                    }
+                };
+
+                let mut content = Vec::new();
+                let mut tool_calls = Vec::new();
+                let mut streamed_text = String::new();
+                let mut finish_reason = FinishReason::Stop;
+                let mut tripped = None;
+
+                loop {
+                    let mut heartbeat = Heartbeat::new(config.heartbeat_interval);
+                    let next = loop {
+                        tokio::select! {
+                            next = llm_stream.next() => break next,
+                            elapsed = heartbeat.tick() => {
+                                yield AgentEvent::Heartbeat { elapsed };
+                            }
+                        }
+                    };
+                    let Some(event_result) = next else {
+                        break;
+                    };
+                    // Streams served from memory never wait, so spend the task's budget
+                    tokio::task::coop::consume_budget().await;
+                    match event_result {
                         Ok(LLMEvent::TextDelta { text }) => {
+                            streamed_text.push_str(&text);
+                            if let Some(trip) = agent.check_guardrails(&streamed_text) {
+                                tripped = Some(trip);
+                                break;
+                            }
                             yield AgentEvent::Text { text: text.clone() };
                             content.push(MessageContent::Text { text });
                         }
+                        Ok(LLMEvent::ThinkingDelta { text }) => {
+                            yield AgentEvent::Thinking { text: text.clone() };
+                            // Merge consecutive deltas into a single thinking block
+                            if let Some(MessageContent::Thinking { thinking }) = content.last_mut() {
+                                thinking.push_str(&text);
+                            } else {
+                                content.push(MessageContent::Thinking { thinking: text });
+                            }
+                        }
                         Ok(LLMEvent::ToolCallStart { id, name }) => {
                             content.push(MessageContent::ToolCall {
                                 id: id.clone(),
@@ -273,11 +2040,9 @@ impl Agent {
                         }
                         Ok(LLMEvent::ToolCallDelta { id: _, arguments }) => {
                             // Update the arguments in the last tool call
-                            if let Some(last) = content.last_mut() {
-                                if let MessageContent::ToolCall { arguments: args, .. } = last {
-                                    *args = serde_json::from_str(&arguments)
-                                        .unwrap_or(serde_json::json!({}));
-                                }
+                            if let Some(MessageContent::ToolCall { arguments: args, .. }) = content.last_mut() {
+                                *args = serde_json::from_str(&arguments)
+                                    .unwrap_or(serde_json::json!({}));
                             }
                         }
                         Ok(LLMEvent::ToolCallEnd { id }) => {
@@ -290,13 +2055,29 @@ impl Agent {
                                 }
                             }) {
                                 let call = content.remove(pos);
+                                if let MessageContent::ToolCall { name, arguments, .. } = &call {
+                                    yield AgentEvent::ToolCall {
+                                        name: name.clone(),
+                                        args: arguments.clone(),
+                                    };
+                                }
                                 tool_calls.push(call);
                             }
                         }
-                        Ok(LLMEvent::Finish { reason, .. }) => {
-                            yield AgentEvent::MessageEnd { finish_reason: reason.clone() };
+                        Ok(LLMEvent::Finish { reason, usage }) => {
+                            step_usage = Some(usage);
+                            finish_reason = reason.clone();
+                            yield AgentEvent::MessageEnd { finish_reason: reason };
                         }
                         Err(e) => {
+                            if let Some(delay) = agent.retry_delay(&e, llm_retries) {
+                                llm_retries += 1;
+                                yield AgentEvent::Retry { attempt: llm_retries, error: e.to_string() };
+                                drop(llm_stream);
+                                tokio::time::sleep(delay).await;
+                                state.step -= 1;
+                                continue 'steps;
+                            }
                             yield AgentEvent::Error {
                                 error: e.to_string()
                             };
@@ -306,57 +2087,79 @@ impl Agent {
                     }
                 }
 
-                // Save assistant message
-                let assistant_msg = Message::new_assistant(content);
-                let msg_id = assistant_msg.id.clone();
-                {
-                    let mut session_guard = session.lock().await;
-                    session_guard.add_message(assistant_msg);
-                }
+                // Stop the provider stream as soon as we are done with it
+                drop(llm_stream);
+                llm_retries = 0;
 
-                // No tool calls, loop ends
-                if tool_calls.is_empty() {
-                    break;
+                if tripped.is_some() && step_usage.is_none() {
+                    step_usage = Some(agent.estimate_usage(prompt_tokens.unwrap_or_default(), &streamed_text));
+                    yield AgentEvent::MessageEnd {
+                        finish_reason: FinishReason::ContentFilter,
+                    };
                 }
 
-                // Execute tools
-                let session_id = {
-                    let session_guard = session.lock().await;
-                    session_guard.id.clone()
+                // Completed tool calls follow the rest of the message
+                content.extend(tool_calls);
+                let response = StepResponse {
+                    content,
+                    finish_reason,
+                    usage: step_usage,
+                    model,
+                    latency: requested.elapsed(),
+                    tripped,
+                };
+                let outcome = agent.finish_step(&mut state, response, &mut events).await;
+                for event in events.drain(..) {
+                    yield event;
+                }
+                let tools = match outcome {
+                    Ok(StepOutcome::Continue) => continue,
+                    Ok(StepOutcome::Stop(_)) => break,
+                    Ok(StepOutcome::ExecuteTools(tools)) => tools,
+                    Err(e) => {
+                        yield Self::error_event(e);
+                        return;
+                    }
                 };
 
+                // Execute tools
                 let ctx = ExecutionContext {
-                    session_id,
-                    message_id: msg_id,
+                    session_id: agent.session_id().await,
+                    message_id: tools.message_id.clone(),
                 };
 
-                let results = tool_executor.execute_all(tool_calls, ctx).await;
-
-                // Output tool results
-                for result in &results {
-                    if let MessageContent::ToolResult {
-                        tool_call_id,
-                        result: res,
-                        ..
-                    } = result
-                    {
-                        yield AgentEvent::ToolResult {
-                            name: tool_call_id.clone(),
-                            result: res.clone(),
-                        };
+                // Execute while forwarding queue/progress events as they happen
+                let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+                let execution = agent.tool_executor.execute_all_with_events(tools.calls.clone(), ctx, events_tx);
+                tokio::pin!(execution);
+
+                let mut heartbeat = Heartbeat::new(config.heartbeat_interval);
+                let results = loop {
+                    tokio::select! {
+                        results = &mut execution => break results,
+                        Some(event) = events_rx.recv() => {
+                            yield AgentEvent::from(event);
+                        }
+                        elapsed = heartbeat.tick() => {
+                            yield AgentEvent::Heartbeat { elapsed };
+                        }
                     }
+                };
+                while let Ok(event) = events_rx.try_recv() {
+                    yield AgentEvent::from(event);
                 }
 
-                // Save tool results
-                let tool_msg = Message::new_tool_result(results);
-                {
-                    let mut session_guard = session.lock().await;
-                    session_guard.add_message(tool_msg);
+                let stop = agent.finish_tools(&state, &tools, results, &mut events).await;
+                for event in events.drain(..) {
+                    yield event;
+                }
+                if stop {
+                    break;
                 }
             }
         };
 
-        Ok(Box::pin(stream))
+        Box::pin(stream)
     }
 
     /// Gets the session ID.
@@ -365,9 +2168,748 @@ impl Agent {
         session.id.clone()
     }
 
+    /// Returns the accumulated token usage and estimated cost per model.
+    pub async fn usage_report(&self) -> UsageReport {
+        let session = self.session.lock().await;
+        session.usage_report()
+    }
+
+    /// Shuts down the tools initialized for the agent's session, releasing
+    /// the resources they hold. Tools set up again on their next call.
+    pub async fn shutdown(&self) {
+        let session_id = self.session.lock().await.id.clone();
+        self.tool_executor.shutdown_session(&session_id).await;
+    }
+
+    /// Returns a handler answering MCP servers' sampling requests with the
+    /// agent's LLM client and model, for `MCPClientBuilder::with_sampling`.
+    pub fn sampling_handler(&self) -> SamplingHandler {
+        SamplingHandler::new(self.llm_client.clone(), self.model().to_string())
+            .with_max_tokens(self.config.max_tokens)
+    }
+
+    /// Returns per-tool statistics of the calls the agent executed.
+    pub fn tool_stats(&self) -> Vec<ToolStats> {
+        self.tool_executor.stats()
+    }
+
+    /// Returns the tool calls that are currently queued or running.
+    pub fn pending_tool_calls(&self) -> Vec<PendingToolCall> {
+        self.tool_executor.pending_calls()
+    }
+
     /// Gets the current messages.
     pub async fn messages(&self) -> Vec<Message> {
         let session = self.session.lock().await;
         session.messages.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{JsonValidator, RegexGuardrail};
+    use crate::llm::{LLMOutput, ModelPricing};
+    use crate::testing::{EchoTool, MockLLMClient};
+    use crate::tool::ToolRegistry;
+
+    #[tokio::test]
+    async fn test_estimate_next_call() {
+        let mut session = Session::default();
+        session.model.max_tokens = 1_000;
+        session.add_message(Message::new_user("x".repeat(400)));
+        let agent = Agent::new(
+            session,
+            Arc::new(MockLLMClient::new()),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig {
+                model: "priced-model".to_string(),
+                ..Default::default()
+            },
+        )
+        .with_pricing(PricingTable::new().with_model("priced-model", ModelPricing::new(1.0, 2.0)));
+
+        let estimate = agent.estimate_next_call().await;
+        assert!(agent.session.lock().await.provenance.is_none());
+        assert_eq!(estimate.input_tokens, 104);
+        assert_eq!(estimate.max_output_tokens, 1_000);
+        assert_eq!(estimate.input_cost, Some(104.0 / 1_000_000.0));
+        assert_eq!(estimate.max_cost, Some(2_104.0 / 1_000_000.0));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_output_is_re_asked() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_text_response("Sure! Here it is.")
+                .with_text_response("```json\n{\"ok\": true,}\n```"),
+        );
+        let agent = Agent::new(
+            Session::default(),
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig {
+                output_validator: Some(OutputValidation::new(Arc::new(JsonValidator))),
+                ..Default::default()
+            },
+        );
+
+        let messages = agent.run("Answer in JSON").await.unwrap().messages;
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].role, MessageRole::User);
+        assert!(Agent::collect_text(&messages[1].content).starts_with("Your previous response was invalid"));
+        assert_eq!(Agent::collect_text(&messages[2].content), "{\"ok\": true}");
+        assert_eq!(llm.call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_empty_response_is_nudged() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_text_response("")
+                .with_text_response("Hello!"),
+        );
+        let mut session = Session::default();
+        session.add_message(Message::new_user("hi"));
+        let agent = Agent::new(
+            session,
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig::default(),
+        );
+
+        let events: Vec<_> = agent.stream().await.unwrap().collect().await;
+        assert!(events.iter().any(|e| matches!(e, AgentEvent::EmptyResponse { retrying: true })));
+
+        let messages = agent.messages().await;
+        assert_eq!(messages.len(), 3);
+        assert_eq!(Agent::collect_text(&messages[1].content), AgentConfig::default().empty_response.nudge);
+        assert_eq!(Agent::collect_text(&messages[2].content), "Hello!");
+        assert_eq!(llm.call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_withholds_tool_calls_and_records_usage() {
+        let leaked = vec![
+            MessageContent::ToolCall {
+                id: "call_1".to_string(),
+                name: "echo".to_string(),
+                arguments: serde_json::json!({}),
+            },
+            MessageContent::Text {
+                text: "Your key is sk-abcdefgh1234".to_string(),
+            },
+        ];
+        let output = LLMOutput {
+            content: leaked,
+            finish_reason: FinishReason::ToolCalls,
+            usage: Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+            },
+        };
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_response(output.clone())
+                .with_stream(output.into_events().into_iter().filter(|e| !matches!(e, LLMEvent::Finish { .. })).collect()),
+        );
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoTool));
+        let replacement = GuardrailPolicy::default().replacement;
+        let guardrail = Arc::new(RegexGuardrail::new("secrets", &[r"sk-[A-Za-z0-9]{8,}"]).unwrap());
+        let agent = Agent::with_defaults(Session::default(), llm, Arc::new(Mutex::new(registry)))
+            .with_output_guardrail(guardrail);
+
+        let result = agent.run("What's my key?").await.unwrap();
+        assert_eq!(result.stop_reason, StopReason::GuardrailTripped);
+        assert_eq!(result.messages.len(), 1);
+        assert_eq!(result.text, replacement);
+        assert_eq!(result.usage.output_tokens, 5);
+
+        // The stream trips before the provider reports usage, so it is estimated
+        agent.begin_turn("And again?").await;
+        let events: Vec<_> = agent.stream().await.unwrap().collect().await;
+        assert!(events.iter().any(|e| matches!(
+            e,
+            AgentEvent::MessageEnd {
+                finish_reason: FinishReason::ContentFilter
+            }
+        )));
+        assert!(matches!(events.last(), Some(AgentEvent::GuardrailTripped { restarting: false, .. })));
+        assert!(agent.usage_report().await.output_tokens > 5);
+
+        let messages = agent.messages().await;
+        assert_eq!(messages.len(), 4);
+        assert_eq!(Agent::collect_text(&messages[3].content), replacement);
+        assert!(messages.iter().flat_map(|m| &m.content).all(|c| !matches!(c, MessageContent::ToolCall { .. })));
+        assert!(agent.tool_stats().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_and_stream_handle_steps_alike() {
+        let script = || {
+            MockLLMClient::new()
+                .with_text_response("")
+                .with_tool_call_response("call_1", "missing", serde_json::json!({}))
+                .with_text_response("Done.")
+        };
+
+        let agent = Agent::with_defaults(
+            Session::default(),
+            Arc::new(script()),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+        );
+        let mut events = agent.events(TopicMask::REVIEW);
+        let run = agent.run("hi").await.unwrap().messages;
+        assert!(matches!(events.try_recv(), Some(AgentEvent::EmptyResponse { retrying: true })));
+
+        let agent = Agent::with_defaults(
+            Session::default(),
+            Arc::new(script()),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+        );
+        agent.begin_turn("hi").await;
+        agent.stream().await.unwrap().collect::<Vec<_>>().await;
+        let streamed = agent.messages().await.split_off(1);
+
+        let shape = |messages: &[Message]| {
+            messages
+                .iter()
+                .map(|m| (m.role.clone(), m.content.iter().filter(|c| matches!(c, MessageContent::ToolCall { .. })).count()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shape(&run), shape(&streamed));
+        assert_eq!(shape(&streamed)[1], (MessageRole::Assistant, 1));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_closes_pending_tool_calls() {
+        struct SlowTool;
+
+        #[async_trait::async_trait]
+        impl crate::tool::Tool for SlowTool {
+            fn name(&self) -> &str {
+                "slow"
+            }
+
+            fn description(&self) -> &str {
+                "Never finishes"
+            }
+
+            fn parameters_schema(&self) -> serde_json::Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            async fn execute(&self, _args: serde_json::Value) -> Result<crate::tool::ToolResult, crate::tool::ToolError> {
+                std::future::pending().await
+            }
+        }
+
+        let llm = Arc::new(MockLLMClient::new().with_tool_call_response("call_1", "slow", serde_json::json!({})));
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(SlowTool));
+        let agent = Agent::with_defaults(Session::default(), llm, Arc::new(Mutex::new(registry)));
+
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            trigger.cancel();
+        });
+
+        let result = agent.run_with_cancel("go", cancel).await;
+        assert!(matches!(result, Err(AgentError::Cancelled)));
+        assert!(agent.pending_tool_calls().is_empty());
+
+        let session = agent.session.lock().await;
+        assert_eq!(session.status, SessionStatus::Cancelled);
+        assert!(matches!(
+            &session.messages[2].content[0],
+            MessageContent::ToolResult { tool_call_id, is_error: Some(true), .. } if tool_call_id == "call_1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ask_rule_pauses_until_approved() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "echo", serde_json::json!({"text": "hi"}))
+                .with_tool_call_response("call_2", "echo", serde_json::json!({"text": "again"}))
+                .with_text_response("Done."),
+        );
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoTool));
+        let mut permissions = PermissionManager::new();
+        permissions.add_rule(crate::permission::Permission {
+            tool: "echo".to_string(),
+            action: crate::permission::PermissionAction::Ask,
+            patterns: None,
+            args: None,
+            quota: None,
+        });
+        let mut session = Session::default();
+        session.add_message(Message::new_user("echo twice"));
+        let agent = Agent::with_defaults(session, llm, Arc::new(Mutex::new(registry)))
+            .with_permissions(permissions);
+
+        let mut stream = agent.stream().await.unwrap();
+        let mut results = Vec::new();
+        while let Some(event) = stream.next().await {
+            match event {
+                AgentEvent::ApprovalRequired { call_id, tool, .. } => {
+                    assert_eq!(tool, "echo");
+                    if call_id == "call_1" {
+                        assert!(agent.approve(&call_id));
+                    } else {
+                        assert!(agent.deny(&call_id));
+                    }
+                }
+                AgentEvent::ToolResult { result, .. } => results.push(result),
+                _ => {}
+            }
+        }
+
+        assert_eq!(results[0], "hi");
+        assert!(results[1].contains("denied"));
+        assert!(!agent.approve("call_1"));
+    }
+
+    #[tokio::test]
+    async fn test_ask_rule_in_run_is_answered_through_events_or_fails() {
+        let agent = || {
+            let llm = MockLLMClient::new()
+                .with_tool_call_response("call_1", "echo", serde_json::json!({"text": "hi"}))
+                .with_text_response("Done.");
+            let mut registry = ToolRegistry::new();
+            registry.register(Arc::new(EchoTool));
+            let mut permissions = PermissionManager::new();
+            permissions.add_rule(crate::permission::Permission {
+                tool: "echo".to_string(),
+                action: crate::permission::PermissionAction::Ask,
+                patterns: None,
+                args: None,
+                quota: None,
+            });
+            Agent::with_defaults(Session::default(), Arc::new(llm), Arc::new(Mutex::new(registry)))
+                .with_permissions(permissions)
+        };
+        let result_of = |run: &AgentRunResult| {
+            run.messages
+                .iter()
+                .flat_map(|m| &m.content)
+                .find_map(|c| match c {
+                    MessageContent::ToolResult { result, .. } => Some(result.clone()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        // Nobody can answer: the call fails instead of waiting forever
+        let run = agent().run("echo").await.unwrap();
+        assert!(result_of(&run).contains("requires approval"));
+
+        // A subscriber that only watches is not asked
+        let watched = agent();
+        let _events = watched.events(TopicMask::TOOL);
+        let run = watched.run("echo").await.unwrap();
+        assert!(result_of(&run).contains("requires approval"));
+
+        let approving = agent().with_event_approvals();
+        let mut events = approving.events(TopicMask::TOOL);
+        let answerer = approving.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if let AgentEvent::ApprovalRequired { call_id, .. } = event {
+                    answerer.approve(&call_id);
+                }
+            }
+        });
+        assert_eq!(result_of(&approving.run("echo").await.unwrap()), "hi");
+
+        let ignored = agent().with_event_approvals().with_approval_timeout(Duration::from_millis(20));
+        let _events = ignored.events(TopicMask::TOOL);
+        let run = ignored.run("echo").await.unwrap();
+        assert!(result_of(&run).contains("not approved within"));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_while_tool_runs() {
+        struct NapTool;
+
+        #[async_trait::async_trait]
+        impl crate::tool::Tool for NapTool {
+            fn name(&self) -> &str {
+                "nap"
+            }
+
+            fn description(&self) -> &str {
+                "Sleeps for a while"
+            }
+
+            fn parameters_schema(&self) -> serde_json::Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            async fn execute(&self, _args: serde_json::Value) -> Result<crate::tool::ToolResult, crate::tool::ToolError> {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok(crate::tool::ToolResult::ok("rested"))
+            }
+        }
+
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "nap", serde_json::json!({}))
+                .with_text_response("Done."),
+        );
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(NapTool));
+        let mut session = Session::default();
+        session.add_message(Message::new_user("take a nap"));
+        let agent = Agent::new(
+            session,
+            llm,
+            Arc::new(Mutex::new(registry)),
+            AgentConfig {
+                heartbeat_interval: Some(Duration::from_millis(20)),
+                ..Default::default()
+            },
+        );
+
+        let events: Vec<_> = agent.stream().await.unwrap().collect().await;
+        let heartbeats: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::Heartbeat { elapsed } => Some(*elapsed),
+                _ => None,
+            })
+            .collect();
+        assert!(!heartbeats.is_empty());
+        assert!(heartbeats.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_run_keeps_history_and_returns_new_messages() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_text_response("Hi Ann!")
+                .with_text_response("Your name is Ann."),
+        );
+        let agent = Agent::with_defaults(Session::default(), llm.clone(), Arc::new(Mutex::new(ToolRegistry::new())));
+
+        let first = agent.run("I'm Ann").await.unwrap();
+        assert_eq!(first.messages.len(), 1);
+        assert_eq!(first.text, "Hi Ann!");
+
+        let second = agent.run("What's my name?").await.unwrap();
+        assert_eq!(second.text, "Your name is Ann.");
+        assert_eq!(llm.inputs()[1].messages.len(), 3);
+        assert_eq!(agent.messages().await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_when_token_budget_is_exceeded() {
+        let llm = Arc::new(MockLLMClient::new().with_response(LLMOutput {
+            content: vec![MessageContent::ToolCall {
+                id: "call_1".to_string(),
+                name: "lookup".to_string(),
+                arguments: serde_json::json!({}),
+            }],
+            finish_reason: FinishReason::ToolCalls,
+            usage: Usage {
+                input_tokens: 400,
+                output_tokens: 200,
+            },
+        }));
+        let agent = Agent::new(
+            Session::default(),
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig {
+                budget: RunBudget {
+                    max_tokens: Some(500),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        let error = agent.run("go").await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            AgentError::BudgetExceeded(BudgetLimit::Tokens { used: 600, limit: 500 })
+        ));
+        assert_eq!(llm.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_critic_sends_agent_back_once() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_text_response("4")
+                .with_text_response("REVISE: show your work")
+                .with_text_response("2 + 2 = 4")
+                .with_text_response("6")
+                .with_text_response("APPROVED"),
+        );
+        let agent = Agent::new(
+            Session::default(),
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig {
+                reflection: Some(ReflectionConfig::default()),
+                ..Default::default()
+            },
+        );
+
+        let result = agent.run("What is 2 + 2?").await.unwrap();
+
+        assert_eq!(result.text, "2 + 2 = 4");
+        assert!(Agent::collect_text(&result.messages[1].content).contains("show your work"));
+        let critic_input = &llm.inputs()[1];
+        assert!(Agent::collect_text(&critic_input.messages[0].content).starts_with("Task:\nWhat is 2 + 2?"));
+        assert_eq!(llm.call_count(), 3);
+
+        // The critic reviews with the model the run uses
+        let options = RunOptions::default().with_model("gpt-4o");
+        assert_eq!(agent.run_with("What is 3 + 3?", options).await.unwrap().text, "6");
+        assert_eq!(llm.inputs()[4].model, "gpt-4o");
+    }
+
+    #[tokio::test]
+    async fn test_messages_record_model_usage_and_tool_durations() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "echo", serde_json::json!({"text": "hi"}))
+                .with_text_response("Done."),
+        );
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoTool));
+        let agent = Agent::with_defaults(Session::default(), llm, Arc::new(Mutex::new(registry)));
+
+        let result = agent.run("echo").await.unwrap();
+        let (call, results, answer) = (&result.messages[0], &result.messages[1], &result.messages[2]);
+        assert_eq!(call.metadata["model"], agent.config().model);
+        assert!(call.metadata["latency_ms"].is_u64());
+        assert!(call.usage.is_some());
+        assert!(results.metadata["tool_durations_ms"]["call_1"].is_u64());
+        assert!(answer.usage.is_some());
+        assert!(result.messages.iter().all(|m| m.role != MessageRole::User));
+    }
+
+    #[tokio::test]
+    async fn test_store_saves_session_after_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(crate::session::FileSessionStore::new(dir.path()));
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_text_response("first")
+                .with_text_response("second")
+                .with_text_response("third"),
+        );
+        let agent = Agent::with_defaults(Session::default(), llm, Arc::new(Mutex::new(ToolRegistry::new())))
+            .with_store(store.clone());
+        let id = agent.session_id().await;
+
+        agent.run("Hi").await.unwrap();
+        let saved = store.load(&id).await.unwrap();
+        assert_eq!(saved.messages.len(), 2);
+        assert_eq!(saved.status, SessionStatus::Completed);
+
+        agent.session.lock().await.add_message(Message::new_user("Again"));
+        let mut stream = agent.stream().await.unwrap();
+        while stream.next().await.is_some() {}
+        assert_eq!(store.load(&id).await.unwrap().messages.len(), 4);
+
+        agent.run_with_cancel("More", CancellationToken::new()).await.unwrap();
+        assert_eq!(store.load(&id).await.unwrap().messages.len(), 6);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(matches!(agent.run_with_cancel("Stop", cancel).await, Err(AgentError::Cancelled)));
+        let saved = store.load(&id).await.unwrap();
+        assert_eq!(saved.messages.len(), 7);
+        assert_eq!(saved.status, SessionStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_turns_record_final_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(crate::session::FileSessionStore::new(dir.path()));
+        let llm = Arc::new(MockLLMClient::new().with_text_response("Hello!"));
+        let agent = Agent::with_defaults(Session::default(), llm, Arc::new(Mutex::new(ToolRegistry::new())))
+            .with_store(store.clone());
+        let id = agent.session_id().await;
+
+        agent.chat_stream("Hi").await.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(agent.session.lock().await.status, SessionStatus::Completed);
+        assert_eq!(store.load(&id).await.unwrap().status, SessionStatus::Completed);
+
+        // The script is exhausted, so the next request fails
+        let events: Vec<_> = agent.chat_stream("Again").await.unwrap().collect().await;
+        assert!(matches!(events.last(), Some(AgentEvent::Error { .. })));
+        assert_eq!(store.load(&id).await.unwrap().status, SessionStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_overrides_only_that_run() {
+        let llm = Arc::new(MockLLMClient::new().with_text_response("first").with_text_response("second"));
+        let agent = Agent::new(
+            Session::default(),
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig {
+                system_prompt: "Be brief.".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let options = RunOptions::default()
+            .with_model("gpt-4o")
+            .with_temperature(0.0)
+            .with_tool_choice(ToolChoice::None)
+            .with_extra_system_prompt("Answer in French.");
+        agent.run_with("Hi", options).await.unwrap();
+        agent.run("Hi again").await.unwrap();
+
+        let inputs = llm.inputs();
+        assert_eq!(inputs[0].model, "gpt-4o");
+        assert_eq!(inputs[0].temperature, Some(0.0));
+        assert_eq!(inputs[0].system_prompt, "Be brief.\n\nAnswer in French.");
+        assert_eq!(inputs[0].request_options.extra_body["tool_choice"], "none");
+        assert_eq!(inputs[1].model, agent.config().model);
+        assert_eq!(inputs[1].system_prompt, "Be brief.");
+        assert!(inputs[1].request_options.extra_body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_with_tool_filter_hides_other_groups() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "echo", serde_json::json!({"text": "hi"}))
+                .with_text_response("Done."),
+        );
+        let mut registry = ToolRegistry::new();
+        registry.register_in_group("debug", Arc::new(EchoTool));
+        let agent = Agent::with_defaults(Session::default(), llm.clone(), Arc::new(Mutex::new(registry)));
+
+        let options = RunOptions::default().with_tools(ToolFilter::new().with_group("fs"));
+        let result = agent.run_with("echo hi", options).await.unwrap();
+
+        let inputs = llm.inputs();
+        assert!(inputs[0].tools.is_empty());
+        assert!(inputs[0].tool_generation.is_none());
+        let output = result.messages.iter().flat_map(|m| &m.content).find_map(|c| match c {
+            MessageContent::ToolResult { result, .. } => Some(result.clone()),
+            _ => None,
+        });
+        assert_eq!(output.as_deref(), Some("Tool not found: echo"));
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_provider_renders_every_step() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "missing_tool", serde_json::json!({}))
+                .with_text_response("Done."),
+        );
+        let agent = Agent::new(
+            Session::default(),
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig {
+                system_prompt: "unused".to_string(),
+                ..Default::default()
+            },
+        )
+        .with_system_prompt_provider(Arc::new(|session: &Session| {
+            format!("{} messages so far.", session.messages.len())
+        }));
+
+        agent.run("Hi").await.unwrap();
+
+        let inputs = llm.inputs();
+        assert_eq!(inputs[0].system_prompt, "1 messages so far.");
+        assert_eq!(inputs[1].system_prompt, "3 messages so far.");
+    }
+
+    #[tokio::test]
+    async fn test_transient_llm_errors_are_retried() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_error(LLMError::RateLimitError("slow down".to_string()))
+                .with_text_response("Hello!")
+                .with_error(LLMError::ApiError("502".to_string()))
+                .with_text_response("Again!")
+                .with_error(LLMError::AuthError("bad key".to_string())),
+        );
+        let agent = Agent::new(
+            Session::default(),
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig {
+                llm_retry: Some(RetryPolicy {
+                    initial_backoff: Duration::from_millis(1),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let events: Vec<_> = agent.chat_stream("Hi").await.unwrap().collect().await;
+        assert!(matches!(&events[1], AgentEvent::Retry { attempt: 1, error } if error.contains("slow down")));
+        assert!(events.iter().any(|e| matches!(e, AgentEvent::Text { text } if text == "Hello!")));
+
+        let mut run_events = agent.events(TopicMask::RUN);
+        assert_eq!(agent.run("Hi again").await.unwrap().text, "Again!");
+        assert!(matches!(run_events.try_recv(), Some(AgentEvent::Retry { attempt: 1, error }) if error.contains("502")));
+        assert!(run_events.try_recv().is_none());
+        assert!(matches!(agent.run("Once more").await, Err(AgentError::LLMError(LLMError::AuthError(_)))));
+        assert_eq!(llm.call_count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_tool_heavy_run_lets_other_tasks_run_between_steps() {
+        const STEPS: usize = 50;
+
+        // On the single-threaded test runtime the probe only runs when the agent yields
+        async fn probe_ticks(yield_between_steps: bool) -> usize {
+            let mut llm = MockLLMClient::new();
+            for i in 0..STEPS {
+                llm = llm.with_tool_call_response(format!("call_{}", i), "missing_tool", serde_json::json!({}));
+            }
+            let agent = Agent::new(
+                Session::default(),
+                Arc::new(llm.with_text_response("Done.")),
+                Arc::new(Mutex::new(ToolRegistry::new())),
+                AgentConfig {
+                    yield_between_steps,
+                    ..Default::default()
+                },
+            );
+
+            let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+            let probe = tokio::spawn({
+                let done = done.clone();
+                async move {
+                    let mut ticks = 0;
+                    while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                        tokio::task::yield_now().await;
+                        ticks += 1;
+                    }
+                    ticks
+                }
+            });
+
+            agent.run("go").await.unwrap();
+            done.store(true, std::sync::atomic::Ordering::Relaxed);
+            probe.await.unwrap()
+        }
+
+        let yielding = probe_ticks(true).await;
+        let greedy = probe_ticks(false).await;
+        assert!(yielding >= STEPS, "probe ran {} times in {} steps", yielding, STEPS);
+        assert!(yielding > greedy + STEPS / 2, "probe ran {} times with yields, {} without", yielding, greedy);
+    }
+}
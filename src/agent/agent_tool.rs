@@ -0,0 +1,109 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::tool::{Tool, ToolError, ToolResult};
+use super::agent_loop::Agent;
+
+/// Exposes an agent as a tool, so a planner agent can delegate tasks to
+/// worker agents.
+///
+/// Every call runs the wrapped agent on a fresh session (sharing its LLM
+/// client, tools and config) and returns the text of its final answer.
+#[derive(Clone)]
+pub struct AgentTool {
+    agent: Agent,
+    name: String,
+    description: String,
+}
+
+impl AgentTool {
+    /// Wraps an agent under the given tool name and description.
+    pub fn new(agent: Agent, name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            agent,
+            name: name.into(),
+            description: description.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for AgentTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "task": {
+                    "type": "string",
+                    "description": "The task to delegate, with all the context needed to complete it"
+                }
+            },
+            "required": ["task"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let task = args["task"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidArguments("missing string field `task`".to_string()))?;
+
+        let worker = self.agent.fork_with(|_| {}).await;
+        let result = worker
+            .run(task)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult::ok(result.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentConfig;
+    use crate::session::{MessageContent, Session};
+    use crate::testing::MockLLMClient;
+    use crate::tool::ToolRegistry;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_planner_delegates_to_worker() {
+        let worker_llm = Arc::new(MockLLMClient::new().with_text_response("Paris"));
+        let worker = Agent::with_defaults(
+            Session::default(),
+            worker_llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+        );
+
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(AgentTool::new(worker, "researcher", "Looks up facts")));
+        let planner_llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "researcher", serde_json::json!({"task": "Capital of France?"}))
+                .with_text_response("The capital is Paris."),
+        );
+        let planner = Agent::new(
+            Session::default(),
+            planner_llm,
+            Arc::new(Mutex::new(registry)),
+            AgentConfig::default(),
+        );
+
+        let result = planner.run("Where is the Eiffel Tower?").await.unwrap();
+
+        assert_eq!(result.text, "The capital is Paris.");
+        assert!(matches!(
+            &result.messages[1].content[0],
+            MessageContent::ToolResult { result, .. } if result == "Paris"
+        ));
+        assert_eq!(worker_llm.inputs()[0].messages.len(), 1);
+    }
+}
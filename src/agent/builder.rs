@@ -0,0 +1,343 @@
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::llm::tokens::context_window;
+use crate::llm::{LLMClient, PricingTable, TokenCounter};
+use crate::session::{ModelConfig, Session};
+use crate::mcp::MCPManager;
+use crate::tool::ToolRegistry;
+use super::agent_loop::{Agent, AgentConfig, AgentError};
+use super::context::{ContextProvider, SystemPromptProvider};
+use super::guardrail::OutputGuardrail;
+use super::stop::StopCondition;
+
+/// How serious a configuration problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The agent will run, but probably not as intended
+    Warning,
+    /// The agent would fail or misbehave on the first request
+    Error,
+}
+
+/// A problem found while validating an agent configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiagnostic {
+    /// How serious the problem is
+    pub severity: Severity,
+    /// The configuration field the problem relates to
+    pub field: &'static str,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl ConfigDiagnostic {
+    fn warning(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            field,
+            message: message.into(),
+        }
+    }
+
+    fn error(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            field,
+            message: message.into(),
+        }
+    }
+
+    /// Returns whether this diagnostic is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", level, self.field, self.message)
+    }
+}
+
+impl AgentConfig {
+    /// Checks the configuration for problems that would otherwise only show
+    /// up on the first request.
+    pub fn validate(&self) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        if self.model.trim().is_empty() {
+            diagnostics.push(ConfigDiagnostic::error("model", "model name is empty"));
+        }
+
+        let limit = self.context_window.or_else(|| context_window(&self.model));
+        match limit {
+            Some(limit) if self.max_tokens >= limit => {
+                diagnostics.push(ConfigDiagnostic::error(
+                    "max_tokens",
+                    format!(
+                        "max_tokens ({}) leaves no room for the prompt in the {}-token context window of `{}`",
+                        self.max_tokens, limit, self.model
+                    ),
+                ));
+            }
+            Some(_) => {}
+            None if !self.model.trim().is_empty() => {
+                diagnostics.push(ConfigDiagnostic::warning(
+                    "model",
+                    format!(
+                        "unknown model `{}`; set `context_window` to enable context checks",
+                        self.model
+                    ),
+                ));
+            }
+            None => {}
+        }
+
+        if self.max_tokens == 0 {
+            diagnostics.push(ConfigDiagnostic::error("max_tokens", "max_tokens must be greater than 0"));
+        }
+
+        if self.max_steps == 0 {
+            diagnostics.push(ConfigDiagnostic::error("max_steps", "max_steps must be greater than 0"));
+        }
+
+        if let Some(temperature) = self.temperature
+            && !(0.0..=2.0).contains(&temperature)
+        {
+            diagnostics.push(ConfigDiagnostic::error(
+                "temperature",
+                format!("temperature {} is outside the supported range 0.0..=2.0", temperature),
+            ));
+        }
+
+        if let Some(compaction) = &self.compaction
+            && !(compaction.threshold > 0.0 && compaction.threshold <= 1.0)
+        {
+            diagnostics.push(ConfigDiagnostic::error(
+                "compaction.threshold",
+                format!("threshold {} must be in (0.0, 1.0]", compaction.threshold),
+            ));
+        }
+
+        diagnostics
+    }
+}
+
+/// A builder for agents that validates the configuration before building.
+pub struct AgentBuilder {
+    llm_client: Arc<dyn LLMClient>,
+    registry: Arc<Mutex<ToolRegistry>>,
+    config: AgentConfig,
+    session: Option<Session>,
+    context_providers: Vec<Arc<dyn ContextProvider>>,
+    system_prompt_provider: Option<Arc<dyn SystemPromptProvider>>,
+    guardrails: Vec<Arc<dyn OutputGuardrail>>,
+    stop_conditions: Vec<Arc<dyn StopCondition>>,
+    pricing: Option<PricingTable>,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    mcp_manager: Option<Arc<MCPManager>>,
+}
+
+impl AgentBuilder {
+    /// Creates a builder with an empty tool registry and default configuration.
+    pub fn new(llm_client: Arc<dyn LLMClient>) -> Self {
+        Self {
+            llm_client,
+            registry: Arc::new(Mutex::new(ToolRegistry::new())),
+            config: AgentConfig::default(),
+            session: None,
+            context_providers: Vec::new(),
+            system_prompt_provider: None,
+            guardrails: Vec::new(),
+            stop_conditions: Vec::new(),
+            pricing: None,
+            token_counter: None,
+            mcp_manager: None,
+        }
+    }
+
+    /// Sets the agent configuration.
+    pub fn with_config(mut self, config: AgentConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the tool registry.
+    pub fn with_registry(mut self, registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Sets the session; by default a new session is created from the config.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Adds a context provider.
+    pub fn with_context_provider(mut self, provider: Arc<dyn ContextProvider>) -> Self {
+        self.context_providers.push(provider);
+        self
+    }
+
+    /// Sets the provider rendering the system prompt before every call.
+    pub fn with_system_prompt_provider(mut self, provider: Arc<dyn SystemPromptProvider>) -> Self {
+        self.system_prompt_provider = Some(provider);
+        self
+    }
+
+    /// Adds an output guardrail.
+    pub fn with_output_guardrail(mut self, guardrail: Arc<dyn OutputGuardrail>) -> Self {
+        self.guardrails.push(guardrail);
+        self
+    }
+
+    /// Adds a stop condition.
+    pub fn with_stop_condition(mut self, condition: Arc<dyn StopCondition>) -> Self {
+        self.stop_conditions.push(condition);
+        self
+    }
+
+    /// Sets the pricing table.
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = Some(pricing);
+        self
+    }
+
+    /// Sets the token counter.
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = Some(counter);
+        self
+    }
+
+    /// Connects the manager's MCP servers when building and registers
+    /// their tools, named `{server}__{tool}`, in the agent's registry.
+    /// Servers failing to connect are logged and skipped.
+    pub fn with_mcp_manager(mut self, manager: Arc<MCPManager>) -> Self {
+        self.mcp_manager = Some(manager);
+        self
+    }
+
+    /// Returns all configuration warnings and errors without building.
+    pub async fn check(&self) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = self.config.validate();
+
+        let tool_count = self.registry.lock().await.len();
+        if tool_count > 0 && self.system_prompt_provider.is_none() && self.config.system_prompt.trim().is_empty() {
+            diagnostics.push(ConfigDiagnostic::warning(
+                "system_prompt",
+                format!(
+                    "{} tools are registered but the system prompt is empty; the model gets no guidance on when to use them",
+                    tool_count
+                ),
+            ));
+        }
+
+        if let Some(session) = &self.session
+            && session.model.max_tokens != self.config.max_tokens
+        {
+            diagnostics.push(ConfigDiagnostic::warning(
+                "max_tokens",
+                format!(
+                    "session max_tokens ({}) differs from config max_tokens ({}); the session value is sent",
+                    session.model.max_tokens, self.config.max_tokens
+                ),
+            ));
+        }
+
+        diagnostics
+    }
+
+    /// Validates the configuration and builds the agent, failing if any
+    /// error-level diagnostic is found. Warnings are logged.
+    pub async fn build(self) -> Result<Agent, AgentError> {
+        if let Some(manager) = &self.mcp_manager {
+            manager.connect_all().await;
+            manager.attach(self.registry.clone()).await;
+        }
+
+        let diagnostics = self.check().await;
+        for diagnostic in diagnostics.iter().filter(|d| !d.is_error()) {
+            tracing::warn!("{}", diagnostic);
+        }
+        let errors: Vec<_> = diagnostics.into_iter().filter(|d| d.is_error()).collect();
+        if !errors.is_empty() {
+            return Err(AgentError::InvalidConfig(errors));
+        }
+
+        let session = self.session.unwrap_or_else(|| {
+            Session::new(
+                ModelConfig {
+                    name: self.config.model.clone(),
+                    max_tokens: self.config.max_tokens,
+                    temperature: self.config.temperature,
+                    extra: None,
+                },
+                self.config.system_prompt.clone(),
+            )
+        });
+
+        let mut agent = Agent::new(session, self.llm_client, self.registry, self.config);
+        for provider in self.context_providers {
+            agent = agent.with_context_provider(provider);
+        }
+        if let Some(provider) = self.system_prompt_provider {
+            agent = agent.with_system_prompt_provider(provider);
+        }
+        for guardrail in self.guardrails {
+            agent = agent.with_output_guardrail(guardrail);
+        }
+        for condition in self.stop_conditions {
+            agent = agent.with_stop_condition(condition);
+        }
+        if let Some(pricing) = self.pricing {
+            agent = agent.with_pricing(pricing);
+        }
+        if let Some(counter) = self.token_counter {
+            agent = agent.with_token_counter(counter);
+        }
+        if let Some(manager) = &self.mcp_manager {
+            for name in manager.server_names() {
+                if let Some(client) = manager.client(name) {
+                    agent.monitor_mcp_server(&*client.read().await);
+                }
+            }
+        }
+        Ok(agent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLLMClient;
+
+    #[tokio::test]
+    async fn test_validate_reports_problems() {
+        let config = AgentConfig {
+            model: "gpt-4".to_string(),
+            max_tokens: 10_000,
+            temperature: Some(3.0),
+            ..Default::default()
+        };
+        let fields: Vec<_> = config.validate().iter().map(|d| d.field).collect();
+        assert_eq!(fields, vec!["max_tokens", "temperature"]);
+
+        let unknown = AgentConfig {
+            model: "my-local-model".to_string(),
+            ..Default::default()
+        };
+        let diagnostics = unknown.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+
+        let builder = AgentBuilder::new(Arc::new(MockLLMClient::new())).with_config(config);
+        assert!(matches!(builder.build().await, Err(AgentError::InvalidConfig(errors)) if errors.len() == 2));
+    }
+}
@@ -0,0 +1,307 @@
+use async_stream::stream;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+use crate::llm::{Cassette, LLMClient, LLMEvent, RecordedResponse, RecordingLLMClient, Usage};
+use crate::session::Session;
+use super::agent_loop::{AgentConfig, AgentEvent, AgentStream, StopReason};
+use super::recording::{EventRecorder, EventReplay, RecordedEvent};
+
+/// The session as it stood when the run ended
+pub const TRANSCRIPT_FILE: &str = "transcript.json";
+/// The agent events of a streamed run, as `RecordedEvent` lines
+pub const EVENTS_FILE: &str = "events.jsonl";
+/// Every LLM request and response of the run, as a `Cassette`
+pub const WIRE_FILE: &str = "wire.json";
+/// Timing and usage of the run
+pub const PROFILE_FILE: &str = "profile.json";
+/// The agent configuration the run started with
+pub const CONFIG_FILE: &str = "config.json";
+
+/// Timing and usage of a bundled run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunProfile {
+    /// Name of the bundle directory
+    pub run_id: String,
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+    /// Wall-clock duration of the run
+    pub elapsed_ms: u64,
+    /// Number of LLM requests that got a response
+    pub llm_calls: usize,
+    /// Tokens used across those requests
+    pub usage: Usage,
+    /// Number of steps taken
+    pub steps: usize,
+    /// Why the run ended, if it ended normally
+    pub stop_reason: Option<StopReason>,
+    /// The error the run failed with
+    pub error: Option<String>,
+}
+
+/// The agent configuration a bundled run started with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    /// The model
+    pub model: String,
+    /// The static system prompt
+    pub system_prompt: String,
+    /// Maximum number of steps
+    pub max_steps: usize,
+    /// Maximum tokens to generate
+    pub max_tokens: u32,
+    /// Temperature
+    pub temperature: Option<f32>,
+    /// Context window size
+    pub context_window: Option<u32>,
+    /// The full configuration in `Debug` form
+    pub debug: String,
+}
+
+impl ConfigSnapshot {
+    fn new(config: &AgentConfig) -> Self {
+        Self {
+            model: config.model.clone(),
+            system_prompt: config.system_prompt.clone(),
+            max_steps: config.max_steps,
+            max_tokens: config.max_tokens,
+            temperature: config.temperature,
+            context_window: config.context_window,
+            debug: format!("{:#?}", config),
+        }
+    }
+}
+
+/// A self-contained record of one run, as written by
+/// `Agent::with_run_bundles`.
+///
+/// Each run gets its own directory holding the transcript, the agent events,
+/// the raw LLM traffic, a profile and a config snapshot, so the directory can
+/// be attached to a bug report as is. The wire log can be served again with
+/// `ReplayLLMClient::new(bundle.wire)`.
+#[derive(Debug, Clone)]
+pub struct RunBundle {
+    /// The bundle directory
+    pub dir: PathBuf,
+    /// The session when the run ended
+    pub transcript: Session,
+    /// Agent events; empty for runs that were not streamed
+    pub events: Vec<RecordedEvent>,
+    /// LLM requests and responses
+    pub wire: Cassette,
+    /// Timing and usage
+    pub profile: RunProfile,
+    /// The configuration snapshot
+    pub config: ConfigSnapshot,
+}
+
+impl RunBundle {
+    /// Loads a bundle directory.
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            transcript: read_json(&dir.join(TRANSCRIPT_FILE))?,
+            events: EventReplay::load(dir.join(EVENTS_FILE))?.events().to_vec(),
+            wire: Cassette::load(dir.join(WIRE_FILE))?,
+            profile: read_json(&dir.join(PROFILE_FILE))?,
+            config: read_json(&dir.join(CONFIG_FILE))?,
+        })
+    }
+
+    /// Lists the bundle directories under `root`, oldest first.
+    pub fn list(root: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let mut dirs = Vec::new();
+        for entry in std::fs::read_dir(root)? {
+            let path = entry?.path();
+            if path.join(PROFILE_FILE).is_file() {
+                dirs.push(path);
+            }
+        }
+        dirs.sort();
+        Ok(dirs)
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let data = std::fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let data = serde_json::to_string_pretty(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    std::fs::write(path, data)
+}
+
+/// Writes the bundle of one run.
+pub(crate) struct BundleWriter {
+    dir: PathBuf,
+    run_id: String,
+    started: Instant,
+    started_at: DateTime<Utc>,
+    wire: Arc<RecordingLLMClient>,
+}
+
+impl BundleWriter {
+    /// Creates the run's directory under `root` and snapshots the config.
+    pub(crate) fn create(root: &Path, llm: Arc<dyn LLMClient>, config: &AgentConfig) -> io::Result<Self> {
+        let started_at = Utc::now();
+        let run_id = format!(
+            "{}-{}",
+            started_at.format("%Y%m%dT%H%M%S%.6fZ"),
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+        let dir = root.join(&run_id);
+        std::fs::create_dir_all(&dir)?;
+        write_json(&dir.join(CONFIG_FILE), &ConfigSnapshot::new(config))?;
+        write_json(&dir.join(WIRE_FILE), &Cassette::default())?;
+        std::fs::File::create(dir.join(EVENTS_FILE))?;
+
+        Ok(Self {
+            wire: Arc::new(RecordingLLMClient::new(llm, dir.join(WIRE_FILE))),
+            dir,
+            run_id,
+            started: Instant::now(),
+            started_at,
+        })
+    }
+
+    /// Returns the client that logs the run's LLM traffic.
+    pub(crate) fn llm(&self) -> Arc<dyn LLMClient> {
+        self.wire.clone()
+    }
+
+    /// Writes the transcript and profile.
+    pub(crate) fn finish(&self, session: &Session, steps: usize, stop_reason: Option<StopReason>, error: Option<String>) {
+        let wire = self.wire.cassette();
+        let mut usage = Usage { input_tokens: 0, output_tokens: 0 };
+        for interaction in &wire.interactions {
+            let call_usage = match &interaction.response {
+                RecordedResponse::Complete { output } => Some(&output.usage),
+                RecordedResponse::Stream { events } => events.iter().find_map(|e| match e {
+                    LLMEvent::Finish { usage, .. } => Some(usage),
+                    _ => None,
+                }),
+            };
+            if let Some(call_usage) = call_usage {
+                usage.input_tokens += call_usage.input_tokens;
+                usage.output_tokens += call_usage.output_tokens;
+            }
+        }
+
+        let profile = RunProfile {
+            run_id: self.run_id.clone(),
+            started_at: self.started_at,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            llm_calls: wire.interactions.len(),
+            usage,
+            steps,
+            stop_reason,
+            error,
+        };
+        let result = write_json(&self.dir.join(TRANSCRIPT_FILE), session)
+            .and_then(|_| write_json(&self.dir.join(PROFILE_FILE), &profile));
+        if let Err(e) = result {
+            tracing::warn!(dir = %self.dir.display(), "Failed to write run bundle: {}", e);
+        }
+    }
+
+    /// Records the events of a streamed run, finishing the bundle when the
+    /// stream ends or is dropped.
+    pub(crate) fn record(self, inner: AgentStream, session: Arc<Mutex<Session>>) -> AgentStream {
+        let recorder = match EventRecorder::create(self.dir.join(EVENTS_FILE)) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                tracing::warn!(dir = %self.dir.display(), "Failed to record run bundle events: {}", e);
+                None
+            }
+        };
+        let mut inner = match recorder {
+            Some(recorder) => recorder.record(inner),
+            None => inner,
+        };
+
+        let mut guard = StreamBundle {
+            writer: self,
+            session,
+            steps: 0,
+            error: None,
+        };
+        Box::pin(stream! {
+            while let Some(event) = inner.next().await {
+                guard.observe(&event);
+                yield event;
+            }
+        })
+    }
+}
+
+/// Finishes a streamed run's bundle once its stream is gone, including
+/// when a cancelled stream is dropped mid-run.
+struct StreamBundle {
+    writer: BundleWriter,
+    session: Arc<Mutex<Session>>,
+    steps: usize,
+    error: Option<String>,
+}
+
+impl StreamBundle {
+    fn observe(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::MessageEnd { .. } => self.steps += 1,
+            AgentEvent::Error { error } => self.error = Some(error.clone()),
+            _ => {}
+        }
+    }
+}
+
+impl Drop for StreamBundle {
+    fn drop(&mut self) {
+        match self.session.try_lock() {
+            Ok(session) => self.writer.finish(&session, self.steps, None, self.error.take()),
+            Err(_) => tracing::warn!(dir = %self.writer.dir.display(), "Session busy; run bundle transcript not written"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use crate::testing::MockLLMClient;
+    use crate::tool::ToolRegistry;
+
+    #[tokio::test]
+    async fn test_runs_write_loadable_bundles() {
+        let dir = tempfile::tempdir().unwrap();
+        let llm = Arc::new(MockLLMClient::new().with_text_response("Hello!").with_text_response("Bye!"));
+        let agent = Agent::new(
+            Session::default(),
+            llm,
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig::default(),
+        )
+        .with_run_bundles(dir.path());
+
+        agent.run("Hi").await.unwrap();
+        let events: Vec<_> = agent.chat_stream("Bye").await.unwrap().collect().await;
+
+        let bundles = RunBundle::list(dir.path()).unwrap();
+        assert_eq!(bundles.len(), 2);
+        let run = RunBundle::load(&bundles[0]).unwrap();
+        assert_eq!(run.profile.stop_reason, Some(StopReason::Completed));
+        assert_eq!(run.wire.interactions.len(), 1);
+        assert!(run.events.is_empty());
+        let streamed = RunBundle::load(&bundles[1]).unwrap();
+        assert_eq!(streamed.events.len(), events.len());
+        assert_eq!(streamed.profile.llm_calls, 1);
+        assert_eq!(streamed.transcript.messages.len(), 4);
+        assert_eq!(streamed.config.model, AgentConfig::default().model);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::llm::{LLMClient, LLMError, LLMInput, LLMOutput, RequestOptions};
+use crate::session::{Message, MessageContent, MessageRole};
+
+/// Settings for automatically summarizing older turns when the conversation
+/// approaches the model's context limit.
+#[derive(Debug, Clone)]
+pub struct CompactionConfig {
+    /// Fraction of the context window (0.0 to 1.0) at which compaction starts
+    pub threshold: f32,
+    /// Minimum number of recent messages kept verbatim
+    pub keep_recent: usize,
+    /// System prompt used to ask the model for a summary
+    pub summarizer_prompt: String,
+    /// Optional model used for summarization (defaults to the agent's model)
+    pub model: Option<String>,
+    /// Maximum tokens for the generated summary
+    pub max_summary_tokens: u32,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            keep_recent: 6,
+            summarizer_prompt: "You compress conversations. Summarize the transcript below so \
+                                the assistant can continue the task: keep the user's goals, \
+                                decisions made, important facts, tool results that are still \
+                                relevant and any open questions. Be concise."
+                .to_string(),
+            model: None,
+            max_summary_tokens: 1024,
+        }
+    }
+}
+
+/// Prefix of the synthetic message that replaces compacted turns.
+pub const SUMMARY_PREFIX: &str = "[Summary of earlier conversation]";
+
+/// Returns the index splitting `messages` into a compactable prefix and a
+/// kept suffix, or `None` when there is nothing to compact.
+///
+/// The kept suffix always starts at a user message so tool calls are never
+/// separated from their results.
+pub(crate) fn split_point(messages: &[Message], keep_recent: usize) -> Option<usize> {
+    let max = messages.len().checked_sub(keep_recent)?;
+    (1..=max)
+        .rev()
+        .find(|&i| i < messages.len() && messages[i].role == MessageRole::User)
+}
+
+/// Asks the model for a summary of the transcript.
+pub(crate) async fn summarize(
+    llm: &dyn LLMClient,
+    config: &CompactionConfig,
+    model: &str,
+    transcript: String,
+    request_options: RequestOptions,
+) -> Result<LLMOutput, LLMError> {
+    llm.complete(LLMInput {
+        model: model.to_string(),
+        messages: vec![Message::new_user(transcript)],
+        system_prompt: config.summarizer_prompt.clone(),
+        tools: Vec::new(),
+        max_tokens: config.max_summary_tokens,
+        temperature: None,
+        request_options,
+        tool_generation: None,
+    })
+    .await
+}
+
+/// Builds the message that replaces compacted turns.
+pub(crate) fn summary_message(summary: &str) -> Message {
+    Message::new_user(format!("{}\n{}", SUMMARY_PREFIX, summary))
+}
+
+/// Renders messages as a plain-text transcript for the summarizer.
+pub(crate) fn render_transcript(messages: &[Message]) -> String {
+    let mut transcript = String::new();
+    for message in messages {
+        for content in &message.content {
+            let line = match (&message.role, content) {
+                (MessageRole::User, MessageContent::Text { text }) => format!("User: {}", text),
+                (_, MessageContent::Text { text }) => format!("Assistant: {}", text),
+                (_, MessageContent::Thinking { .. }) => continue,
+                (_, MessageContent::ToolCall { name, arguments, .. }) => {
+                    format!("Assistant called tool `{}` with {}", name, arguments)
+                }
+                (_, MessageContent::ToolResult { result, .. }) => format!("Tool result: {}", result),
+                (_, MessageContent::Image { media_type, .. }) => format!("[{} image]", media_type),
+            };
+            transcript.push_str(&line);
+            transcript.push('\n');
+        }
+    }
+    transcript
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_point_keeps_tool_pairs_together() {
+        let messages = vec![
+            Message::new_user("first"),
+            Message::new_assistant(vec![MessageContent::ToolCall {
+                id: "1".to_string(),
+                name: "search".to_string(),
+                arguments: serde_json::json!({}),
+            }]),
+            Message::new_tool_result(vec![MessageContent::ToolResult {
+                tool_call_id: "1".to_string(),
+                result: "found".to_string(),
+                is_error: None,
+                blocks: Vec::new(),
+            }]),
+            Message::new_assistant(vec![MessageContent::Text { text: "done".to_string() }]),
+            Message::new_user("second"),
+            Message::new_assistant(vec![MessageContent::Text { text: "ok".to_string() }]),
+        ];
+
+        assert_eq!(split_point(&messages, 1), Some(4));
+        assert_eq!(split_point(&messages, 2), Some(4));
+        assert_eq!(split_point(&messages, 3), None);
+        assert_eq!(split_point(&messages, 10), None);
+    }
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Local, Utc};
+
+use crate::session::Session;
+
+/// Provides ephemeral context that is injected into every LLM call.
+///
+/// The returned text is appended to the system prompt for the current turn
+/// only; it is never stored in the session history.
+#[async_trait]
+pub trait ContextProvider: Send + Sync {
+    /// Returns the context for the current turn, or `None` to skip it.
+    async fn provide(&self, session: &Session) -> Option<String>;
+}
+
+/// Renders the system prompt afresh before every LLM call, replacing the
+/// static `AgentConfig::system_prompt`.
+///
+/// Context providers are still appended to the rendered prompt.
+#[async_trait]
+pub trait SystemPromptProvider: Send + Sync {
+    /// Returns the system prompt for the next call.
+    async fn render(&self, session: &Session) -> String;
+}
+
+#[async_trait]
+impl<F> SystemPromptProvider for F
+where
+    F: Fn(&Session) -> String + Send + Sync,
+{
+    async fn render(&self, session: &Session) -> String {
+        self(session)
+    }
+}
+
+/// A context provider that tells the model the current date and time in the
+/// user's timezone, along with locale formatting hints.
+#[derive(Debug, Clone)]
+pub struct DateTimeContextProvider {
+    offset: FixedOffset,
+    timezone: Option<String>,
+    locale: Option<String>,
+}
+
+impl DateTimeContextProvider {
+    /// Creates a provider using the system's local timezone.
+    pub fn local() -> Self {
+        Self::with_offset(*Local::now().offset())
+    }
+
+    /// Creates a provider for a fixed UTC offset.
+    pub fn with_offset(offset: FixedOffset) -> Self {
+        Self {
+            offset,
+            timezone: None,
+            locale: None,
+        }
+    }
+
+    /// Sets the timezone name shown to the model (e.g. "Asia/Shanghai").
+    pub fn with_timezone_name(mut self, name: impl Into<String>) -> Self {
+        self.timezone = Some(name.into());
+        self
+    }
+
+    /// Sets the user's locale (e.g. "en-US", "zh-CN").
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Renders the context for the given instant.
+    fn render(&self, now: DateTime<Utc>) -> String {
+        let local = now.with_timezone(&self.offset);
+        let zone = match &self.timezone {
+            Some(name) => format!("UTC{}, {}", self.offset, name),
+            None => format!("UTC{}", self.offset),
+        };
+
+        let mut text = format!(
+            "Current date and time: {} ({})",
+            local.format("%A, %Y-%m-%d %H:%M"),
+            zone
+        );
+
+        if let Some(locale) = &self.locale {
+            text.push_str(&format!(
+                "\nUser locale: {}. Format dates, times, numbers and currencies using {} conventions.",
+                locale, locale
+            ));
+        }
+
+        text
+    }
+}
+
+impl Default for DateTimeContextProvider {
+    fn default() -> Self {
+        Self::local()
+    }
+}
+
+#[async_trait]
+impl ContextProvider for DateTimeContextProvider {
+    async fn provide(&self, _session: &Session) -> Option<String> {
+        Some(self.render(Utc::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_datetime_context() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 31, 23, 30, 0).unwrap();
+        let provider = DateTimeContextProvider::with_offset(FixedOffset::east_opt(8 * 3600).unwrap())
+            .with_timezone_name("Asia/Shanghai")
+            .with_locale("zh-CN");
+
+        let text = provider.render(now);
+
+        assert!(text.starts_with("Current date and time: Saturday, 2025-02-01 07:30 (UTC+08:00, Asia/Shanghai)"));
+        assert!(text.contains("User locale: zh-CN"));
+    }
+}
@@ -0,0 +1,295 @@
+use async_stream::stream;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::ops::{BitOr, BitOrAssign};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+use super::agent_loop::{AgentEvent, AgentStream};
+
+/// A set of event topics a subscriber is interested in.
+///
+/// Masks combine with `|`, e.g. `TopicMask::TOOL | TopicMask::RUN`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TopicMask(u32);
+
+impl TopicMask {
+    /// Text deltas
+    pub const TEXT: Self = Self(1);
+    /// Reasoning deltas
+    pub const THINKING: Self = Self(1 << 1);
+    /// Tool calls, approvals, queueing, execution and results
+    pub const TOOL: Self = Self(1 << 2);
+    /// Message boundaries and compaction
+    pub const MESSAGE: Self = Self(1 << 3);
+    /// Guardrail, validation, critic, observer and empty-response reviews
+    pub const REVIEW: Self = Self(1 << 4);
+    /// Heartbeats while waiting
+    pub const HEARTBEAT: Self = Self(1 << 5);
+    /// Run lifecycle: retries, errors, cancellation, exceeded budgets
+    pub const RUN: Self = Self(1 << 6);
+    /// Every topic
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// Returns an empty mask.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns whether every topic of `other` is in the mask.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns whether the mask shares a topic with `other`.
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for TopicMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for TopicMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl AgentEvent {
+    /// Returns the topic the event is published under.
+    pub fn topic(&self) -> TopicMask {
+        match self {
+            AgentEvent::Text { .. } => TopicMask::TEXT,
+            AgentEvent::Thinking { .. } => TopicMask::THINKING,
+            AgentEvent::ToolCall { .. }
+            | AgentEvent::ToolResult { .. }
+            | AgentEvent::ToolQueued { .. }
+            | AgentEvent::ApprovalRequired { .. }
+            | AgentEvent::ToolStarted { .. }
+            | AgentEvent::ToolProgress { .. }
+            | AgentEvent::ToolCompleted { .. } => TopicMask::TOOL,
+            AgentEvent::MessageStart { .. } | AgentEvent::MessageEnd { .. } | AgentEvent::Compacted { .. } => {
+                TopicMask::MESSAGE
+            }
+            AgentEvent::GuardrailTripped { .. }
+            | AgentEvent::ValidationFailed { .. }
+            | AgentEvent::Critique { .. }
+            | AgentEvent::EmptyResponse { .. }
+            | AgentEvent::PolicyViolation { .. } => TopicMask::REVIEW,
+            AgentEvent::Heartbeat { .. } => TopicMask::HEARTBEAT,
+            AgentEvent::Retry { .. }
+            | AgentEvent::BudgetExceeded { .. }
+            | AgentEvent::MCPServerDown { .. }
+            | AgentEvent::MCPLog { .. }
+            | AgentEvent::Cancelled
+            | AgentEvent::Error { .. } => TopicMask::RUN,
+        }
+    }
+}
+
+struct Subscriber {
+    mask: TopicMask,
+    sender: mpsc::UnboundedSender<AgentEvent>,
+}
+
+/// Fans agent events out to subscribers, each filtered by a topic mask.
+///
+/// Events are only cloned for subscribers whose mask includes their topic,
+/// so a subscriber to tool events does not pay for every text delta.
+/// Clones share the same subscribers.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl EventBus {
+    /// Creates a bus without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribes to the events of the given topics.
+    pub fn subscribe(&self, mask: TopicMask) -> EventSubscription {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Subscriber { mask, sender });
+        }
+        EventSubscription { receiver }
+    }
+
+    /// Delivers the event to every interested subscriber, dropping
+    /// subscriptions that were closed.
+    pub fn publish(&self, event: &AgentEvent) {
+        let Ok(mut subscribers) = self.subscribers.lock() else {
+            return;
+        };
+        if subscribers.is_empty() {
+            return;
+        }
+        let topic = event.topic();
+        subscribers.retain(|subscriber| {
+            if subscriber.sender.is_closed() {
+                return false;
+            }
+            if subscriber.mask.intersects(topic) {
+                let _ = subscriber.sender.send(event.clone());
+            }
+            true
+        });
+    }
+
+    /// Returns the number of open subscriptions.
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers
+            .lock()
+            .map(|s| s.iter().filter(|s| !s.sender.is_closed()).count())
+            .unwrap_or(0)
+    }
+
+    /// Returns whether an open subscription wants events of `topic`.
+    pub(crate) fn has_subscribers(&self, topic: TopicMask) -> bool {
+        self.subscribers
+            .lock()
+            .is_ok_and(|s| s.iter().any(|s| !s.sender.is_closed() && s.mask.intersects(topic)))
+    }
+
+    /// Passes the stream through, publishing every event.
+    pub(crate) fn tee(&self, mut inner: AgentStream) -> AgentStream {
+        let bus = self.clone();
+        Box::pin(stream! {
+            while let Some(event) = inner.next().await {
+                bus.publish(&event);
+                yield event;
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("subscribers", &self.subscriber_count())
+            .finish()
+    }
+}
+
+/// A stream of the events matching a subscription's topics.
+#[derive(Debug)]
+pub struct EventSubscription {
+    receiver: mpsc::UnboundedReceiver<AgentEvent>,
+}
+
+impl EventSubscription {
+    /// Waits for the next event; `None` once the bus is gone.
+    pub async fn recv(&mut self) -> Option<AgentEvent> {
+        self.receiver.recv().await
+    }
+
+    /// Returns the next event if one is already queued.
+    pub fn try_recv(&mut self) -> Option<AgentEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Stream for EventSubscription {
+    type Item = AgentEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<AgentEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// An agent event stamped for forwarding to another process, e.g. as one
+/// WebSocket message or server-sent event.
+///
+/// The event's fields are flattened next to `seq` and `timestamp`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    /// Position of the event in its stream, starting at 0
+    pub seq: u64,
+    /// When the event was produced
+    pub timestamp: DateTime<Utc>,
+    /// The event
+    #[serde(flatten)]
+    pub event: AgentEvent,
+}
+
+impl EventEnvelope {
+    /// Formats the envelope as a server-sent event, with the sequence number
+    /// as its id and the event type as its name.
+    pub fn to_sse(&self) -> serde_json::Result<String> {
+        let data = serde_json::to_value(self)?;
+        let name = data["type"].as_str().unwrap_or("message").to_string();
+        Ok(format!("id: {}\nevent: {}\ndata: {}\n\n", self.seq, name, data))
+    }
+}
+
+/// A stream of stamped agent events.
+pub type EnvelopeStream = Pin<Box<dyn Stream<Item = EventEnvelope> + Send>>;
+
+/// Stamps every event of the stream with a sequence number and timestamp.
+pub fn sequenced(mut inner: AgentStream) -> EnvelopeStream {
+    Box::pin(stream! {
+        let mut seq = 0;
+        while let Some(event) = inner.next().await {
+            yield EventEnvelope { seq, timestamp: Utc::now(), event };
+            seq += 1;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::FinishReason;
+
+    #[test]
+    fn test_subscribers_only_receive_their_topics() {
+        let bus = EventBus::new();
+        let mut tools = bus.subscribe(TopicMask::TOOL | TopicMask::RUN);
+        let mut everything = bus.subscribe(TopicMask::ALL);
+        drop(bus.subscribe(TopicMask::TEXT));
+
+        bus.publish(&AgentEvent::Text { text: "hi".to_string() });
+        bus.publish(&AgentEvent::ToolCall {
+            name: "search".to_string(),
+            args: serde_json::json!({}),
+        });
+        bus.publish(&AgentEvent::MessageEnd { finish_reason: FinishReason::ToolCalls });
+        bus.publish(&AgentEvent::Cancelled);
+
+        assert!(matches!(tools.try_recv(), Some(AgentEvent::ToolCall { .. })));
+        assert!(matches!(tools.try_recv(), Some(AgentEvent::Cancelled)));
+        assert!(tools.try_recv().is_none());
+        assert_eq!(std::iter::from_fn(|| everything.try_recv()).count(), 4);
+        assert_eq!(bus.subscriber_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sequenced_events_round_trip_as_json() {
+        let inner: AgentStream = Box::pin(futures::stream::iter(vec![
+            AgentEvent::Text { text: "hi".to_string() },
+            AgentEvent::MessageEnd { finish_reason: FinishReason::Unknown("eos".to_string()) },
+        ]));
+        let envelopes: Vec<_> = sequenced(inner).collect().await;
+
+        let json = serde_json::to_value(&envelopes[1]).unwrap();
+        assert_eq!(json["seq"], 1);
+        assert_eq!(json["type"], "message_end");
+        let parsed: EventEnvelope = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            parsed.event,
+            AgentEvent::MessageEnd { finish_reason: FinishReason::Unknown(reason) } if reason == "eos"
+        ));
+        assert!(envelopes[0].to_sse().unwrap().starts_with("id: 0\nevent: text\ndata: {"));
+    }
+}
@@ -0,0 +1,174 @@
+use regex::Regex;
+
+use crate::session::MessageContent;
+
+/// The outcome of checking generated text against a guardrail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardrailVerdict {
+    /// The text is acceptable
+    Pass,
+    /// The text violates the guardrail
+    Trip {
+        /// Why the guardrail tripped
+        reason: String,
+    },
+}
+
+/// A guardrail that inspects assistant output as it is generated.
+///
+/// During streaming, `check` is called with the accumulated text of the
+/// current message after every delta, so implementations should be cheap.
+pub trait OutputGuardrail: Send + Sync {
+    /// Returns the name of the guardrail.
+    fn name(&self) -> &str;
+    /// Checks the text generated so far for the current message.
+    fn check(&self, text: &str) -> GuardrailVerdict;
+}
+
+/// A guardrail that trips when the output matches any of a set of regexes.
+#[derive(Debug, Clone)]
+pub struct RegexGuardrail {
+    name: String,
+    patterns: Vec<Regex>,
+}
+
+impl RegexGuardrail {
+    /// Creates a new regex guardrail.
+    pub fn new(name: impl Into<String>, patterns: &[&str]) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name: name.into(),
+            patterns: patterns
+                .iter()
+                .map(|p| Regex::new(p))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl OutputGuardrail for RegexGuardrail {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, text: &str) -> GuardrailVerdict {
+        match self.patterns.iter().find(|re| re.is_match(text)) {
+            Some(re) => GuardrailVerdict::Trip {
+                reason: format!("Output matched blocked pattern `{}`", re.as_str()),
+            },
+            None => GuardrailVerdict::Pass,
+        }
+    }
+}
+
+/// How the agent reacts when an output guardrail trips.
+#[derive(Debug, Clone)]
+pub struct GuardrailPolicy {
+    /// Text stored in place of the withheld partial output
+    pub replacement: String,
+    /// How many times a turn may be restarted after a trip
+    pub max_restarts: usize,
+    /// Instructions appended to the system prompt when restarting a turn
+    pub reinforcement: String,
+}
+
+impl Default for GuardrailPolicy {
+    fn default() -> Self {
+        Self {
+            replacement: "[response withheld by guardrail]".to_string(),
+            max_restarts: 0,
+            reinforcement: "Your previous response was withheld because it violated the \
+                            output policy. Answer again and strictly follow the policy."
+                .to_string(),
+        }
+    }
+}
+
+/// How a step continues after its response was checked against the output
+/// guardrails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GuardrailStep {
+    /// Nothing tripped
+    Pass,
+    /// The response is dropped and the turn restarted with reinforced instructions
+    Restart,
+    /// The whole response, tool calls included, was replaced by the replacement text
+    Withhold,
+}
+
+/// Tracks the output guardrail restarts of one run.
+#[derive(Debug, Default)]
+pub(crate) struct GuardrailState {
+    restarts: usize,
+    /// Whether the next request carries the reinforcement instructions
+    pub(crate) reinforce: bool,
+}
+
+impl GuardrailState {
+    /// Decides how a step continues after its response was checked,
+    /// withholding `content` once the restarts are spent.
+    pub(crate) fn step(
+        &mut self,
+        policy: &GuardrailPolicy,
+        tripped: bool,
+        content: &mut Vec<MessageContent>,
+    ) -> GuardrailStep {
+        if !tripped {
+            self.reinforce = false;
+            return GuardrailStep::Pass;
+        }
+        if self.restarts < policy.max_restarts {
+            self.restarts += 1;
+            self.reinforce = true;
+            return GuardrailStep::Restart;
+        }
+        self.reinforce = false;
+        *content = vec![MessageContent::Text {
+            text: policy.replacement.clone(),
+        }];
+        GuardrailStep::Withhold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_guardrail() {
+        let guardrail = RegexGuardrail::new("secrets", &[r"sk-[A-Za-z0-9]{8,}"]).unwrap();
+
+        assert_eq!(guardrail.check("Here is the answer"), GuardrailVerdict::Pass);
+        assert!(matches!(
+            guardrail.check("Your key is sk-abcdefgh1234"),
+            GuardrailVerdict::Trip { .. }
+        ));
+    }
+
+    #[test]
+    fn test_guardrail_state_restarts_then_withholds_whole_response() {
+        let policy = GuardrailPolicy {
+            max_restarts: 1,
+            ..GuardrailPolicy::default()
+        };
+        let mut state = GuardrailState::default();
+        let mut content = vec![
+            MessageContent::Text {
+                text: "sk-abcdefgh1234".to_string(),
+            },
+            MessageContent::ToolCall {
+                id: "call_1".to_string(),
+                name: "send".to_string(),
+                arguments: serde_json::json!({}),
+            },
+        ];
+
+        assert_eq!(state.step(&policy, true, &mut content.clone()), GuardrailStep::Restart);
+        assert!(state.reinforce);
+        assert_eq!(state.step(&policy, false, &mut content.clone()), GuardrailStep::Pass);
+        assert!(!state.reinforce);
+
+        assert_eq!(state.step(&policy, true, &mut content), GuardrailStep::Withhold);
+        assert!(!state.reinforce);
+        assert!(matches!(&content[..], [MessageContent::Text { text }] if *text == policy.replacement));
+    }
+}
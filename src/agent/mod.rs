@@ -1,3 +1,31 @@
 pub mod agent_loop;
+pub mod agent_tool;
+pub mod builder;
+pub mod bundle;
+pub mod compaction;
+pub mod context;
+pub mod events;
+pub mod guardrail;
+pub mod observer;
+pub mod output_validator;
+pub mod recording;
+pub mod reflection;
+pub mod resume;
+pub mod runtime;
+pub mod stop;
 
-pub use agent_loop::{Agent, AgentConfig, AgentEvent, AgentStream, AgentError};
+pub use agent_loop::{Agent, AgentConfig, AgentEvent, AgentStream, AgentError, AgentRunResult, BudgetLimit, CallEstimate, EmptyResponsePolicy, RetryPolicy, RunBudget, RunOptions, StopReason};
+pub use agent_tool::AgentTool;
+pub use builder::{AgentBuilder, ConfigDiagnostic, Severity};
+pub use bundle::{ConfigSnapshot, RunBundle, RunProfile};
+pub use context::{ContextProvider, DateTimeContextProvider, SystemPromptProvider};
+pub use events::{EnvelopeStream, EventBus, EventEnvelope, EventSubscription, TopicMask, sequenced};
+pub use guardrail::{GuardrailPolicy, GuardrailVerdict, OutputGuardrail, RegexGuardrail};
+pub use compaction::CompactionConfig;
+pub use observer::{Observer, ObserverAction, ObserverVerdict};
+pub use output_validator::{JsonValidator, OutputValidation, OutputValidator, ValidationOutcome};
+pub use reflection::ReflectionConfig;
+pub use resume::{ResumeMismatch, ResumePolicy};
+pub use recording::{EventRecorder, EventReplay, RecordedEvent, ReplaySpeed};
+pub use runtime::{AgentRuntime, RunPriority};
+pub use stop::{And, Not, Or, StepContext, StopCondition, StopConditionExt, TextContains, TokensExceed, ToolCalled};
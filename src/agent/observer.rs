@@ -0,0 +1,197 @@
+use async_stream::stream;
+use futures::StreamExt;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::llm::{LLMClient, LLMError, LLMInput};
+use crate::session::Message;
+use super::agent_loop::{Agent, AgentEvent, AgentStream};
+
+/// Instructions appended to the policy so verdicts can be parsed.
+const VERDICT_INSTRUCTIONS: &str = "You audit the run of another AI agent against the policy above. \
+    You are shown the transcript so far. Reply with `OK` if the agent complies, or with \
+    `VIOLATION: <reason>` if it violates the policy.";
+
+/// What an observer does when it detects a violation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ObserverAction {
+    /// Report the violation and let the run continue
+    #[default]
+    Flag,
+    /// Report the violation and cancel the run
+    Halt,
+}
+
+/// The outcome of a review.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObserverVerdict {
+    /// The run complies with the policy
+    Ok,
+    /// The run violates the policy
+    Violation {
+        /// Why the reviewer flagged the run
+        reason: String,
+    },
+}
+
+/// Audits another agent's run with a reviewing LLM and a policy prompt.
+///
+/// The transcript is reviewed every time the observed agent finishes a
+/// message, before its tool calls run. While a review is pending the
+/// observed stream is not polled, so a halted run never executes the tool
+/// calls that were flagged. Reviews that fail are logged and treated as
+/// passing.
+#[derive(Clone)]
+pub struct Observer {
+    llm: Arc<dyn LLMClient>,
+    model: String,
+    policy: String,
+    action: ObserverAction,
+    max_tokens: u32,
+}
+
+impl Observer {
+    /// Creates an observer that flags runs violating `policy`.
+    pub fn new(llm: Arc<dyn LLMClient>, model: impl Into<String>, policy: impl Into<String>) -> Self {
+        Self {
+            llm,
+            model: model.into(),
+            policy: policy.into(),
+            action: ObserverAction::default(),
+            max_tokens: 256,
+        }
+    }
+
+    /// Sets what happens when a violation is detected.
+    pub fn with_action(mut self, action: ObserverAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Reviews a transcript against the policy.
+    pub async fn review(&self, transcript: &str) -> Result<ObserverVerdict, LLMError> {
+        let output = self
+            .llm
+            .complete(LLMInput {
+                model: self.model.clone(),
+                messages: vec![Message::new_user(transcript)],
+                system_prompt: format!("{}\n\n{}", self.policy, VERDICT_INSTRUCTIONS),
+                tools: Vec::new(),
+                max_tokens: self.max_tokens,
+                temperature: Some(0.0),
+                request_options: Default::default(),
+                tool_generation: None,
+            })
+            .await?;
+        let reply = Agent::collect_text(&output.content);
+        let reply = reply.trim();
+
+        Ok(match reply.strip_prefix("VIOLATION") {
+            Some(reason) => ObserverVerdict::Violation {
+                reason: reason.trim_start_matches(':').trim().to_string(),
+            },
+            None => ObserverVerdict::Ok,
+        })
+    }
+
+    /// Passes the observed stream through, adding an
+    /// `AgentEvent::PolicyViolation` after each flagged message.
+    ///
+    /// To halt runs, `inner` must come from `Agent::stream_with_cancel` with
+    /// the same `cancel` token.
+    pub fn observe(&self, mut inner: AgentStream, cancel: CancellationToken) -> AgentStream {
+        let observer = self.clone();
+        Box::pin(stream! {
+            let mut transcript = String::new();
+            let mut text = String::new();
+
+            while let Some(event) = inner.next().await {
+                match &event {
+                    AgentEvent::Text { text: delta } => text.push_str(delta),
+                    AgentEvent::ToolCall { name, args } => {
+                        flush_text(&mut transcript, &mut text);
+                        transcript.push_str(&format!("Agent called tool `{}` with {}\n", name, args));
+                    }
+                    AgentEvent::ToolResult { result, .. } => {
+                        transcript.push_str(&format!("Tool result: {}\n", result));
+                    }
+                    _ => {}
+                }
+                let finished = matches!(event, AgentEvent::MessageEnd { .. });
+                yield event;
+                if !finished {
+                    continue;
+                }
+
+                flush_text(&mut transcript, &mut text);
+
+                match observer.review(&transcript).await {
+                    Ok(ObserverVerdict::Violation { reason }) => {
+                        let halted = observer.action == ObserverAction::Halt;
+                        if halted {
+                            cancel.cancel();
+                        }
+                        yield AgentEvent::PolicyViolation { reason, halted };
+                    }
+                    Ok(ObserverVerdict::Ok) => {}
+                    Err(e) => tracing::warn!("Observer review failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// Moves the text streamed so far into the transcript.
+fn flush_text(transcript: &mut String, text: &mut String) {
+    if !text.is_empty() {
+        transcript.push_str(&format!("Agent: {}\n", text));
+        text.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentConfig;
+    use crate::session::Session;
+    use crate::testing::MockLLMClient;
+    use crate::tool::ToolRegistry;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_observer_halts_violating_run_before_tools_run() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "delete_files", serde_json::json!({"path": "/"}))
+                .with_text_response("Done."),
+        );
+        let mut session = Session::default();
+        session.add_message(Message::new_user("free some disk space"));
+        let agent = Agent::new(
+            session,
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig::default(),
+        );
+        let reviewer = Arc::new(MockLLMClient::new().with_text_response("VIOLATION: deletes the root directory"));
+        let observer = Observer::new(reviewer.clone(), "gpt-4o-mini", "Never delete files outside /tmp.")
+            .with_action(ObserverAction::Halt);
+
+        let cancel = CancellationToken::new();
+        let inner = agent.stream_with_cancel(cancel.clone()).await.unwrap();
+        let events: Vec<_> = observer.observe(inner, cancel).collect().await;
+
+        assert!(matches!(
+            &events[events.len() - 2],
+            AgentEvent::PolicyViolation { reason, halted: true } if reason == "deletes the root directory"
+        ));
+        assert!(matches!(events.last(), Some(AgentEvent::Cancelled)));
+        assert!(!events.iter().any(|e| matches!(e, AgentEvent::ToolStarted { .. })));
+        assert!(reviewer.inputs()[0].messages[0].content.iter().any(|c| matches!(
+            c,
+            crate::session::MessageContent::Text { text } if text.contains("delete_files")
+        )));
+        assert_eq!(llm.call_count(), 1);
+        assert_eq!(agent.messages().await.len(), 1);
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+/// The result of validating the final assistant text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationOutcome {
+    /// The text is acceptable as is
+    Valid,
+    /// The text was fixed up; the repaired text replaces the answer
+    Repaired(String),
+    /// The text is unacceptable; the reason is sent back to the model
+    Invalid(String),
+}
+
+/// Inspects the final assistant text of a run, e.g. to enforce JSON or DSL
+/// output formats.
+pub trait OutputValidator: Send + Sync {
+    /// Validates (and optionally repairs) the answer.
+    fn validate(&self, text: &str) -> ValidationOutcome;
+}
+
+/// How the agent validates final answers and how often it re-asks.
+#[derive(Clone)]
+pub struct OutputValidation {
+    /// The validator applied to the final answer
+    pub validator: Arc<dyn OutputValidator>,
+    /// How many times the model is asked to correct an invalid answer
+    pub max_retries: usize,
+    /// Instruction appended after the validation error in the correction message
+    pub correction_prompt: String,
+}
+
+impl OutputValidation {
+    /// Creates a validation policy that re-asks up to twice.
+    pub fn new(validator: Arc<dyn OutputValidator>) -> Self {
+        Self {
+            validator,
+            max_retries: 2,
+            correction_prompt: "Reply again with only the corrected output.".to_string(),
+        }
+    }
+
+    /// Sets the number of correction attempts.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Builds the user message text asking the model to fix its answer.
+    pub(crate) fn correction(&self, reason: &str) -> String {
+        format!(
+            "Your previous response was invalid: {}\n{}",
+            reason, self.correction_prompt
+        )
+    }
+}
+
+impl std::fmt::Debug for OutputValidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputValidation")
+            .field("max_retries", &self.max_retries)
+            .field("correction_prompt", &self.correction_prompt)
+            .finish()
+    }
+}
+
+/// Requires the answer to be a single JSON value.
+///
+/// Common formatting slips are repaired without a round trip: Markdown code
+/// fences, prose around the JSON value and trailing commas.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonValidator;
+
+impl JsonValidator {
+    /// Extracts the outermost JSON object or array from the text.
+    fn extract(text: &str) -> Option<&str> {
+        let start = text.find(['{', '['])?;
+        let close = if text[start..].starts_with('{') { '}' } else { ']' };
+        let end = text.rfind(close)?;
+        (end > start).then(|| &text[start..=end])
+    }
+
+    /// Removes commas directly followed by a closing bracket, outside strings.
+    fn strip_trailing_commas(json: &str) -> String {
+        let chars: Vec<char> = json.chars().collect();
+        let mut out = String::with_capacity(json.len());
+        let mut in_string = false;
+        let mut escaped = false;
+
+        for (i, &c) in chars.iter().enumerate() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+            } else if c == '"' {
+                in_string = true;
+            } else if c == ',' {
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if matches!(next, Some('}') | Some(']')) {
+                    continue;
+                }
+            }
+            out.push(c);
+        }
+        out
+    }
+}
+
+impl OutputValidator for JsonValidator {
+    fn validate(&self, text: &str) -> ValidationOutcome {
+        let error = match serde_json::from_str::<serde_json::Value>(text.trim()) {
+            Ok(_) => return ValidationOutcome::Valid,
+            Err(e) => e,
+        };
+
+        if let Some(candidate) = Self::extract(text) {
+            for repaired in [candidate.to_string(), Self::strip_trailing_commas(candidate)] {
+                if serde_json::from_str::<serde_json::Value>(&repaired).is_ok() {
+                    return ValidationOutcome::Repaired(repaired);
+                }
+            }
+        }
+
+        ValidationOutcome::Invalid(format!("expected a single JSON value ({})", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_validator_repairs_common_slips() {
+        let validator = JsonValidator;
+        assert_eq!(validator.validate(" {\"a\": 1} "), ValidationOutcome::Valid);
+        assert_eq!(
+            validator.validate("Here you go:\n```json\n{\"a\": [1, 2,],}\n```"),
+            ValidationOutcome::Repaired("{\"a\": [1, 2]}".to_string())
+        );
+        assert_eq!(
+            validator.validate("{\"a\": \"x,}\",}"),
+            ValidationOutcome::Repaired("{\"a\": \"x,}\"}".to_string())
+        );
+        assert!(matches!(validator.validate("no json here"), ValidationOutcome::Invalid(_)));
+    }
+}
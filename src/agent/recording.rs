@@ -0,0 +1,162 @@
+use async_stream::stream;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::agent_loop::{AgentEvent, AgentStream};
+
+/// One line of an event recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Milliseconds since the recording started
+    pub offset_ms: u64,
+    /// The recorded event
+    pub event: AgentEvent,
+}
+
+/// Writes agent events to a JSONL file, one `RecordedEvent` per line.
+#[derive(Debug)]
+pub struct EventRecorder {
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+impl EventRecorder {
+    /// Creates (or truncates) the recording file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            started: Instant::now(),
+        })
+    }
+
+    /// Appends an event, timestamped relative to the start of the recording.
+    pub fn write(&mut self, event: &AgentEvent) -> io::Result<()> {
+        let line = serde_json::to_string(&RecordedEvent {
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            event: event.clone(),
+        })?;
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()
+    }
+
+    /// Passes the stream through unchanged while recording every event.
+    ///
+    /// Write failures are logged and do not interrupt the run.
+    pub fn record(mut self, mut inner: AgentStream) -> AgentStream {
+        Box::pin(stream! {
+            while let Some(event) = inner.next().await {
+                if let Err(e) = self.write(&event) {
+                    tracing::warn!("Failed to record agent event: {}", e);
+                }
+                yield event;
+            }
+        })
+    }
+}
+
+/// How recorded events are paced when replayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReplaySpeed {
+    /// Reproduce the original gaps between events
+    #[default]
+    Original,
+    /// Scale the original gaps, e.g. `2.0` replays twice as fast
+    Scaled(f64),
+    /// Emit all events immediately
+    FastForward,
+}
+
+/// Replays a recording as an `AgentStream`, so frontends can be built and
+/// tested without an LLM backend.
+#[derive(Debug, Clone)]
+pub struct EventReplay {
+    events: Vec<RecordedEvent>,
+}
+
+impl EventReplay {
+    /// Creates a replay from recorded events.
+    pub fn new(events: Vec<RecordedEvent>) -> Self {
+        Self { events }
+    }
+
+    /// Loads a JSONL recording, skipping blank lines.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut events = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            events.push(serde_json::from_str(&line)?);
+        }
+        Ok(Self::new(events))
+    }
+
+    /// Returns the recorded events.
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Streams the recorded events at the given speed.
+    pub fn stream(self, speed: ReplaySpeed) -> AgentStream {
+        Box::pin(stream! {
+            let started = tokio::time::Instant::now();
+            for recorded in self.events {
+                let offset = match speed {
+                    ReplaySpeed::Original => Some(Duration::from_millis(recorded.offset_ms)),
+                    ReplaySpeed::Scaled(factor) if factor > 0.0 => {
+                        Some(Duration::from_secs_f64(recorded.offset_ms as f64 / 1000.0 / factor))
+                    }
+                    _ => None,
+                };
+                if let Some(offset) = offset {
+                    tokio::time::sleep_until(started + offset).await;
+                }
+                yield recorded.event;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::FinishReason;
+    use crate::session::MessageRole;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+
+        let source: AgentStream = Box::pin(futures::stream::iter(vec![
+            AgentEvent::MessageStart { role: MessageRole::Assistant },
+            AgentEvent::Text { text: "hello".to_string() },
+            AgentEvent::MessageEnd { finish_reason: FinishReason::Stop },
+        ]));
+        let recorded: Vec<_> = EventRecorder::create(&path)
+            .unwrap()
+            .record(source)
+            .collect()
+            .await;
+        assert_eq!(recorded.len(), 3);
+
+        let replay = EventReplay::load(&path).unwrap();
+        assert_eq!(replay.events().len(), 3);
+        let replayed: Vec<_> = replay.stream(ReplaySpeed::FastForward).collect().await;
+        assert!(matches!(&replayed[1], AgentEvent::Text { text } if text == "hello"));
+        assert!(matches!(
+            replayed[2],
+            AgentEvent::MessageEnd { finish_reason: FinishReason::Stop }
+        ));
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use crate::llm::LLMClient;
+
+/// Self-correction of final answers: a critic reviews each final answer
+/// against the task and can send the agent back for another iteration.
+#[derive(Clone)]
+pub struct ReflectionConfig {
+    /// System prompt of the critic
+    pub critic_prompt: String,
+    /// Client used for the critic; defaults to the agent's client
+    pub llm: Option<Arc<dyn LLMClient>>,
+    /// Model used for the critic; defaults to the agent's model
+    pub model: Option<String>,
+    /// Maximum tokens of a critique
+    pub max_tokens: u32,
+    /// How many times the agent can be sent back per run
+    pub max_rounds: usize,
+}
+
+impl Default for ReflectionConfig {
+    fn default() -> Self {
+        Self {
+            critic_prompt: "You review an assistant's answer to a task. If the answer fully and \
+                            correctly addresses the task, reply with `APPROVED`. Otherwise reply \
+                            with `REVISE:` followed by specific, actionable feedback."
+                .to_string(),
+            llm: None,
+            model: None,
+            max_tokens: 512,
+            max_rounds: 1,
+        }
+    }
+}
+
+impl ReflectionConfig {
+    /// Uses a separate client and model for the critic.
+    pub fn with_critic(mut self, llm: Arc<dyn LLMClient>, model: impl Into<String>) -> Self {
+        self.llm = Some(llm);
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sets how many times the agent can be sent back per run.
+    pub fn with_max_rounds(mut self, max_rounds: usize) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    /// Builds the message asking the critic to review an answer.
+    pub(crate) fn review_request(task: &str, answer: &str) -> String {
+        format!("Task:\n{}\n\nAnswer:\n{}", task, answer)
+    }
+
+    /// Extracts the feedback from a critique, or `None` if the answer was
+    /// approved.
+    pub(crate) fn feedback(critique: &str) -> Option<String> {
+        let critique = critique.trim();
+        if critique.starts_with("APPROVED") {
+            return None;
+        }
+        let feedback = critique
+            .strip_prefix("REVISE")
+            .map(|rest| rest.trim_start_matches(':').trim())
+            .unwrap_or(critique);
+        Some(feedback.to_string())
+    }
+
+    /// Builds the user message sending the agent back with the feedback.
+    pub(crate) fn revision(feedback: &str) -> String {
+        format!(
+            "A reviewer found problems with your answer:\n{}\nPlease revise your answer.",
+            feedback
+        )
+    }
+}
+
+impl std::fmt::Debug for ReflectionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReflectionConfig")
+            .field("critic_prompt", &self.critic_prompt)
+            .field("model", &self.model)
+            .field("max_tokens", &self.max_tokens)
+            .field("max_rounds", &self.max_rounds)
+            .finish()
+    }
+}
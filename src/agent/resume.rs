@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::session::{MessageContent, Session};
+use crate::tool::ToolDefinition;
+
+/// How `Agent::resume` reacts when the agent differs from what produced the
+/// stored session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResumePolicy {
+    /// Resume and tell the model what changed with a notice message
+    #[default]
+    Adapt,
+    /// Refuse to resume with `AgentError::ResumeMismatch`
+    Strict,
+}
+
+/// A difference between a stored session and the agent resuming it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ResumeMismatch {
+    /// The history calls a tool the agent no longer has
+    MissingTool { name: String },
+    /// The input schema of a tool the history calls has changed
+    ChangedSchema { name: String },
+    /// The session last ran with another model
+    ModelChanged { from: String, to: String },
+}
+
+impl std::fmt::Display for ResumeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResumeMismatch::MissingTool { name } => write!(f, "the tool `{}` is no longer available", name),
+            ResumeMismatch::ChangedSchema { name } => write!(f, "the parameters of the tool `{}` have changed", name),
+            ResumeMismatch::ModelChanged { from, to } => write!(f, "the model changed from `{}` to `{}`", from, to),
+        }
+    }
+}
+
+impl ResumeMismatch {
+    /// Compares a stored session with the tools and model it is resumed with.
+    ///
+    /// Only tools the history calls are checked. Schema changes can only be
+    /// detected for sessions that recorded their provenance.
+    pub fn detect(session: &Session, tools: &[ToolDefinition], model: &str) -> Vec<Self> {
+        let called: BTreeSet<&str> = session
+            .messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|c| match c {
+                MessageContent::ToolCall { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        let mut mismatches = Vec::new();
+        for name in called {
+            let Some(tool) = tools.iter().find(|t| t.name == name) else {
+                mismatches.push(ResumeMismatch::MissingTool { name: name.to_string() });
+                continue;
+            };
+            if let Some(provenance) = &session.provenance
+                && let Some(fingerprint) = provenance.tools.get(name)
+                && *fingerprint != tool.fingerprint()
+            {
+                mismatches.push(ResumeMismatch::ChangedSchema { name: name.to_string() });
+            }
+        }
+
+        if let Some(provenance) = &session.provenance
+            && provenance.model != model
+        {
+            mismatches.push(ResumeMismatch::ModelChanged {
+                from: provenance.model.clone(),
+                to: model.to_string(),
+            });
+        }
+        mismatches
+    }
+
+    /// Builds the notice telling the model what changed.
+    pub(crate) fn notice(mismatches: &[Self]) -> String {
+        let changes: Vec<String> = mismatches.iter().map(|m| format!("- {}", m)).collect();
+        format!(
+            "[Notice] This conversation is being resumed and some things changed since it last ran:\n{}\n\
+             Do not call tools that are no longer available, and follow the current tool parameters \
+             rather than earlier calls.",
+            changes.join("\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentConfig, AgentError};
+    use crate::session::{FileSessionStore, Message, SessionProvenance, SessionStore};
+    use crate::testing::MockLLMClient;
+    use crate::tool::{Tool, ToolError, ToolRegistry, ToolResult};
+    use async_trait::async_trait;
+    use serde_json::{Value, json};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    struct FetchTool;
+
+    #[async_trait]
+    impl Tool for FetchTool {
+        fn name(&self) -> &str {
+            "fetch"
+        }
+        fn description(&self) -> &str {
+            "Fetches a URL"
+        }
+        fn parameters_schema(&self) -> Value {
+            json!({"type": "object", "properties": {"url": {"type": "string"}}})
+        }
+        async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+            Ok(ToolResult::ok(""))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_detects_missing_tools_and_changed_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileSessionStore::new(dir.path());
+        let mut stored = Session::default();
+        stored.add_message(Message::new_user("look this up"));
+        stored.add_message(Message::new_assistant(vec![
+            MessageContent::ToolCall { id: "1".into(), name: "search".into(), arguments: json!({}) },
+            MessageContent::ToolCall { id: "2".into(), name: "fetch".into(), arguments: json!({"href": "x"}) },
+        ]));
+        stored.provenance = Some(SessionProvenance {
+            model: "gpt-4o".to_string(),
+            tools: [("fetch".to_string(), "0000000000000000".to_string())].into(),
+        });
+        store.save(&stored).await.unwrap();
+
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(FetchTool));
+        let agent = Agent::new(
+            Session::default(),
+            Arc::new(MockLLMClient::new()),
+            Arc::new(Mutex::new(registry)),
+            AgentConfig { model: "gpt-4o".to_string(), ..Default::default() },
+        );
+
+        let strict = agent.resume(&store, &stored.id, ResumePolicy::Strict).await;
+        assert!(matches!(strict, Err(AgentError::ResumeMismatch(ref m)) if m.len() == 2));
+        assert!(agent.messages().await.is_empty());
+
+        let mismatches = agent.resume(&store, &stored.id, ResumePolicy::Adapt).await.unwrap();
+        assert_eq!(
+            mismatches,
+            [
+                ResumeMismatch::ChangedSchema { name: "fetch".to_string() },
+                ResumeMismatch::MissingTool { name: "search".to_string() },
+            ]
+        );
+        let messages = agent.messages().await;
+        assert_eq!(messages.len(), 3);
+        assert!(Agent::collect_text(&messages[2].content).contains("`search` is no longer available"));
+    }
+}
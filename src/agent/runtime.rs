@@ -0,0 +1,310 @@
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+use crate::llm::{LLMClient, LLMError, LLMInput, LLMOutput, LLMStream};
+use super::agent_loop::{Agent, AgentError, AgentRunResult, AgentStream};
+
+/// Scheduling priority of an agent run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RunPriority {
+    /// Batch and evaluation jobs that yield to interactive runs
+    Background,
+    /// User-facing chats
+    #[default]
+    Interactive,
+}
+
+tokio::task_local! {
+    static RUN_PRIORITY: RunPriority;
+}
+
+/// Returns the priority of the run the current task belongs to.
+///
+/// Code outside of `AgentRuntime::scope` is treated as interactive.
+pub fn current_priority() -> RunPriority {
+    RUN_PRIORITY.try_with(|p| *p).unwrap_or_default()
+}
+
+#[derive(Debug)]
+struct SchedulerState {
+    free_slots: usize,
+    tokens: f64,
+    last_refill: Instant,
+    waiting_interactive: usize,
+    waiting_background: usize,
+}
+
+/// Hands out LLM connection slots and rate-limiter tokens, preferring
+/// interactive runs whenever both are waiting.
+#[derive(Debug)]
+struct Scheduler {
+    max_slots: usize,
+    rate_limit: Option<(f64, Duration)>,
+    state: Mutex<SchedulerState>,
+    notify: Notify,
+}
+
+/// A held connection slot, returned to the scheduler on drop.
+struct SlotPermit {
+    scheduler: Arc<Scheduler>,
+}
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.scheduler.state.lock() {
+            state.free_slots += 1;
+        }
+        self.scheduler.notify.notify_waiters();
+    }
+}
+
+/// Keeps the waiter counts accurate even if an acquire is cancelled.
+struct WaitGuard<'a> {
+    scheduler: &'a Scheduler,
+    priority: RunPriority,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.scheduler.state.lock() {
+            match self.priority {
+                RunPriority::Interactive => state.waiting_interactive -= 1,
+                RunPriority::Background => state.waiting_background -= 1,
+            }
+        }
+        self.scheduler.notify.notify_waiters();
+    }
+}
+
+impl Scheduler {
+    fn new(max_slots: usize, rate_limit: Option<(f64, Duration)>) -> Self {
+        Self {
+            max_slots,
+            rate_limit,
+            state: Mutex::new(SchedulerState {
+                free_slots: max_slots,
+                tokens: rate_limit.map_or(0.0, |(capacity, _)| capacity),
+                last_refill: Instant::now(),
+                waiting_interactive: 0,
+                waiting_background: 0,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Refills rate-limiter tokens and returns how long until the next one.
+    fn refill(&self, state: &mut SchedulerState) -> Option<Duration> {
+        let (capacity, per) = self.rate_limit?;
+        let rate = capacity / per.as_secs_f64();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * rate).min(capacity);
+        state.last_refill = now;
+        if state.tokens >= 1.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - state.tokens) / rate))
+        }
+    }
+
+    async fn acquire(self: &Arc<Self>, priority: RunPriority) -> SlotPermit {
+        if let Ok(mut state) = self.state.lock() {
+            match priority {
+                RunPriority::Interactive => state.waiting_interactive += 1,
+                RunPriority::Background => state.waiting_background += 1,
+            }
+        }
+        let _guard = WaitGuard {
+            scheduler: self,
+            priority,
+        };
+
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let retry_after = {
+                // A poisoned scheduler stops limiting rather than stalling every run
+                let Ok(mut state) = self.state.lock() else {
+                    return SlotPermit {
+                        scheduler: self.clone(),
+                    };
+                };
+                let retry_after = self.refill(&mut state);
+                let preempted =
+                    priority == RunPriority::Background && state.waiting_interactive > 0;
+                if state.free_slots > 0 && retry_after.is_none() && !preempted {
+                    state.free_slots -= 1;
+                    if self.rate_limit.is_some() {
+                        state.tokens -= 1.0;
+                    }
+                    return SlotPermit {
+                        scheduler: self.clone(),
+                    };
+                }
+                retry_after
+            };
+
+            match retry_after {
+                Some(delay) => {
+                    tokio::select! {
+                        _ = notified => {}
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+}
+
+/// Schedules concurrent agent runs over a shared pool of LLM connection
+/// slots and an optional request rate limit.
+///
+/// LLM clients wrapped with `llm_client` acquire a slot for every request
+/// (held for the lifetime of a stream). When slots or rate-limiter tokens are
+/// scarce, interactive runs are served before background runs.
+#[derive(Debug, Clone)]
+pub struct AgentRuntime {
+    scheduler: Arc<Scheduler>,
+}
+
+impl AgentRuntime {
+    /// Creates a runtime allowing at most `max_concurrent_requests` LLM
+    /// requests in flight.
+    pub fn new(max_concurrent_requests: usize) -> Self {
+        Self {
+            scheduler: Arc::new(Scheduler::new(max_concurrent_requests.max(1), None)),
+        }
+    }
+
+    /// Additionally limits requests to `requests` per `per`, with bursts up
+    /// to `requests`.
+    pub fn with_rate_limit(self, requests: u32, per: Duration) -> Self {
+        Self {
+            scheduler: Arc::new(Scheduler::new(
+                self.scheduler.max_slots,
+                Some((f64::from(requests.max(1)), per)),
+            )),
+        }
+    }
+
+    /// Wraps an LLM client so its requests are scheduled by this runtime.
+    pub fn llm_client(&self, inner: Arc<dyn LLMClient>) -> Arc<dyn LLMClient> {
+        Arc::new(ScheduledLLMClient {
+            inner,
+            scheduler: self.scheduler.clone(),
+        })
+    }
+
+    /// Runs a future with the given priority applied to its LLM requests.
+    pub async fn scope<F: Future>(&self, priority: RunPriority, future: F) -> F::Output {
+        RUN_PRIORITY.scope(priority, future).await
+    }
+
+    /// Runs the agent on `input` with the given priority.
+    pub async fn run(
+        &self,
+        agent: &Agent,
+        input: &str,
+        priority: RunPriority,
+    ) -> Result<AgentRunResult, AgentError> {
+        self.scope(priority, agent.run(input)).await
+    }
+
+    /// Streams the agent with the given priority.
+    pub async fn stream(&self, agent: &Agent, priority: RunPriority) -> Result<AgentStream, AgentError> {
+        let mut inner = self.scope(priority, agent.stream()).await?;
+        Ok(Box::pin(stream! {
+            while let Some(event) = RUN_PRIORITY.scope(priority, inner.next()).await {
+                yield event;
+            }
+        }))
+    }
+
+    /// Returns the number of LLM requests currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.scheduler
+            .state
+            .lock()
+            .map_or(0, |state| self.scheduler.max_slots - state.free_slots)
+    }
+
+    /// Returns the number of interactive and background requests waiting.
+    pub fn waiting(&self) -> (usize, usize) {
+        self.scheduler
+            .state
+            .lock()
+            .map_or((0, 0), |state| (state.waiting_interactive, state.waiting_background))
+    }
+}
+
+/// An LLM client that acquires a runtime slot for each request.
+struct ScheduledLLMClient {
+    inner: Arc<dyn LLMClient>,
+    scheduler: Arc<Scheduler>,
+}
+
+#[async_trait]
+impl LLMClient for ScheduledLLMClient {
+    async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
+        let permit = self.scheduler.acquire(current_priority()).await;
+        let mut inner = self.inner.stream(input).await?;
+        Ok(Box::pin(stream! {
+            let _permit = permit;
+            while let Some(event) = inner.next().await {
+                yield event;
+            }
+        }))
+    }
+
+    async fn complete(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
+        let _permit = self.scheduler.acquire(current_priority()).await;
+        self.inner.complete(input).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_interactive_preempts_background() {
+        let runtime = AgentRuntime::new(1);
+        let scheduler = runtime.scheduler.clone();
+        let held = scheduler.acquire(RunPriority::Background).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for priority in [RunPriority::Background, RunPriority::Interactive] {
+            let scheduler = scheduler.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire(priority).await;
+                order.lock().unwrap().push(priority);
+            }));
+            // Make sure the background waiter is queued first
+            while runtime.waiting().0 + runtime.waiting().1 < handles.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        assert_eq!(runtime.in_flight(), 1);
+        drop(held);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec![RunPriority::Interactive, RunPriority::Background]
+        );
+        assert_eq!(runtime.in_flight(), 0);
+    }
+}
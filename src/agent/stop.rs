@@ -0,0 +1,186 @@
+use crate::session::MessageContent;
+
+/// What a stop condition sees after a step that called tools.
+#[derive(Debug, Clone, Copy)]
+pub struct StepContext<'a> {
+    /// Number of the step, starting at 1
+    pub step: usize,
+    /// Content of the step's assistant message
+    pub content: &'a [MessageContent],
+    /// Tool calls made in the step
+    pub tool_calls: &'a [MessageContent],
+    /// Results of those tool calls
+    pub tool_results: &'a [MessageContent],
+    /// Input plus output tokens used by the run so far
+    pub total_tokens: u64,
+}
+
+impl StepContext<'_> {
+    /// Returns the text of the step's assistant message.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|c| match c {
+                MessageContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns whether a tool with the given name was called in the step.
+    pub fn called(&self, tool: &str) -> bool {
+        self.tool_calls
+            .iter()
+            .any(|c| matches!(c, MessageContent::ToolCall { name, .. } if name == tool))
+    }
+}
+
+/// Decides whether a run ends after a step, beyond the model answering
+/// without tool calls.
+///
+/// Conditions are checked after the tool calls of each step have run and
+/// their results have been saved; a run that stops this way ends with
+/// `StopReason::StopCondition`. Closures taking a `&StepContext` are
+/// conditions too.
+pub trait StopCondition: Send + Sync {
+    /// Returns whether the run should stop.
+    fn should_stop(&self, step: &StepContext<'_>) -> bool;
+}
+
+impl<F> StopCondition for F
+where
+    F: Fn(&StepContext<'_>) -> bool + Send + Sync,
+{
+    fn should_stop(&self, step: &StepContext<'_>) -> bool {
+        self(step)
+    }
+}
+
+/// Stops once a tool with the given name was called, e.g. `submit_answer`.
+#[derive(Debug, Clone)]
+pub struct ToolCalled(pub String);
+
+impl ToolCalled {
+    /// Creates the condition.
+    pub fn new(tool: impl Into<String>) -> Self {
+        Self(tool.into())
+    }
+}
+
+impl StopCondition for ToolCalled {
+    fn should_stop(&self, step: &StepContext<'_>) -> bool {
+        step.called(&self.0)
+    }
+}
+
+/// Stops once the assistant's text contains a marker, e.g. `DONE`.
+#[derive(Debug, Clone)]
+pub struct TextContains(pub String);
+
+impl TextContains {
+    /// Creates the condition.
+    pub fn new(marker: impl Into<String>) -> Self {
+        Self(marker.into())
+    }
+}
+
+impl StopCondition for TextContains {
+    fn should_stop(&self, step: &StepContext<'_>) -> bool {
+        step.text().contains(&self.0)
+    }
+}
+
+/// Stops once the run has used more than the given number of tokens.
+#[derive(Debug, Clone, Copy)]
+pub struct TokensExceed(pub u64);
+
+impl StopCondition for TokensExceed {
+    fn should_stop(&self, step: &StepContext<'_>) -> bool {
+        step.total_tokens > self.0
+    }
+}
+
+/// Stops when both conditions hold.
+#[derive(Debug, Clone)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: StopCondition, B: StopCondition> StopCondition for And<A, B> {
+    fn should_stop(&self, step: &StepContext<'_>) -> bool {
+        self.0.should_stop(step) && self.1.should_stop(step)
+    }
+}
+
+/// Stops when either condition holds.
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: StopCondition, B: StopCondition> StopCondition for Or<A, B> {
+    fn should_stop(&self, step: &StepContext<'_>) -> bool {
+        self.0.should_stop(step) || self.1.should_stop(step)
+    }
+}
+
+/// Stops when the condition does not hold.
+#[derive(Debug, Clone)]
+pub struct Not<A>(pub A);
+
+impl<A: StopCondition> StopCondition for Not<A> {
+    fn should_stop(&self, step: &StepContext<'_>) -> bool {
+        !self.0.should_stop(step)
+    }
+}
+
+/// Combinators for stop conditions.
+pub trait StopConditionExt: StopCondition + Sized {
+    /// Stops when both conditions hold.
+    fn and<B: StopCondition>(self, other: B) -> And<Self, B> {
+        And(self, other)
+    }
+
+    /// Stops when either condition holds.
+    fn or<B: StopCondition>(self, other: B) -> Or<Self, B> {
+        Or(self, other)
+    }
+
+    /// Stops when the condition does not hold.
+    fn not(self) -> Not<Self> {
+        Not(self)
+    }
+}
+
+impl<T: StopCondition> StopConditionExt for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentConfig, StopReason};
+    use crate::session::Session;
+    use crate::testing::MockLLMClient;
+    use crate::tool::ToolRegistry;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_run_stops_once_a_condition_holds() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "search", serde_json::json!({}))
+                .with_tool_call_response("call_2", "submit_answer", serde_json::json!({"answer": 42}))
+                .with_text_response("Unreachable."),
+        );
+        let condition = ToolCalled::new("submit_answer").or(TextContains::new("DONE").and(TokensExceed(0).not()));
+        let agent = Agent::new(
+            Session::default(),
+            llm.clone(),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig::default(),
+        )
+        .with_stop_condition(Arc::new(condition));
+
+        let result = agent.run("Answer").await.unwrap();
+
+        assert_eq!(result.stop_reason, StopReason::StopCondition);
+        assert_eq!(result.steps, 2);
+        assert_eq!(llm.inputs().len(), 2);
+    }
+}
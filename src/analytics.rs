@@ -0,0 +1,332 @@
+//! Per-session and cross-session statistics computed from stored sessions.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::session::{MessageContent, MessageRole, ModelUsage, Session, SessionStatus};
+
+/// Statistics for a single session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    /// The session ID
+    pub session_id: String,
+    /// The session status
+    pub status: SessionStatus,
+    /// Number of user turns
+    pub turns: usize,
+    /// Total number of messages
+    pub messages: usize,
+    /// Number of tool calls made by the assistant
+    pub tool_calls: usize,
+    /// Number of tool results flagged as errors
+    pub tool_errors: usize,
+    /// Average time from a user or tool message to the next assistant message
+    pub avg_response_latency_ms: Option<f64>,
+    /// Total input tokens
+    pub input_tokens: u64,
+    /// Total output tokens
+    pub output_tokens: u64,
+    /// Estimated cost in US dollars
+    pub cost: f64,
+}
+
+/// Usage statistics for a single tool across sessions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolStats {
+    /// The tool name
+    pub name: String,
+    /// Number of calls
+    pub calls: usize,
+    /// Number of calls whose result was an error
+    pub errors: usize,
+    /// Fraction of calls that failed
+    pub error_rate: f64,
+}
+
+/// Aggregated statistics over a set of sessions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsReport {
+    /// Statistics per session, in input order
+    pub sessions: Vec<SessionStats>,
+    /// Tool usage, most used first
+    pub tools: Vec<ToolStats>,
+    /// Token spend per model across sessions
+    pub by_model: HashMap<String, ModelUsage>,
+    /// Average user turns per session
+    pub avg_turns_per_session: f64,
+    /// Fraction of sessions that ended in an error
+    pub session_error_rate: f64,
+    /// Fraction of tool calls that failed
+    pub tool_error_rate: f64,
+    /// Average response latency across all sessions
+    pub avg_response_latency_ms: Option<f64>,
+}
+
+impl SessionStats {
+    /// Computes statistics for a session.
+    pub fn from_session(session: &Session) -> Self {
+        let mut tool_calls = 0;
+        let mut tool_errors = 0;
+        let mut latencies = Vec::new();
+
+        for (index, message) in session.messages.iter().enumerate() {
+            for content in &message.content {
+                match content {
+                    MessageContent::ToolCall { .. } => tool_calls += 1,
+                    MessageContent::ToolResult { is_error: Some(true), .. } => tool_errors += 1,
+                    _ => {}
+                }
+            }
+            if message.role == MessageRole::Assistant
+                && let Some(previous) = index.checked_sub(1).map(|i| &session.messages[i])
+                && previous.role != MessageRole::Assistant
+            {
+                let elapsed = message.created_at - previous.created_at;
+                latencies.push(elapsed.num_milliseconds() as f64);
+            }
+        }
+
+        let report = session.usage_report();
+        Self {
+            session_id: session.id.clone(),
+            status: session.status.clone(),
+            turns: session
+                .messages
+                .iter()
+                .filter(|m| m.role == MessageRole::User)
+                .count(),
+            messages: session.messages.len(),
+            tool_calls,
+            tool_errors,
+            avg_response_latency_ms: average(&latencies),
+            input_tokens: report.input_tokens,
+            output_tokens: report.output_tokens,
+            cost: report.cost,
+        }
+    }
+}
+
+impl AnalyticsReport {
+    /// Computes statistics over the given sessions.
+    pub fn from_sessions<'a>(sessions: impl IntoIterator<Item = &'a Session>) -> Self {
+        let mut report = Self::default();
+        let mut tools: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut latencies = Vec::new();
+        let mut errored_sessions = 0;
+
+        for session in sessions {
+            // Map call IDs to tool names so errors can be attributed
+            let mut call_names = HashMap::new();
+            for message in &session.messages {
+                for content in &message.content {
+                    match content {
+                        MessageContent::ToolCall { id, name, .. } => {
+                            call_names.insert(id.clone(), name.clone());
+                            tools.entry(name.clone()).or_default().0 += 1;
+                        }
+                        MessageContent::ToolResult { tool_call_id, is_error: Some(true), .. } => {
+                            if let Some(name) = call_names.get(tool_call_id) {
+                                tools.entry(name.clone()).or_default().1 += 1;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            for (model, usage) in &session.usage {
+                let total = report.by_model.entry(model.clone()).or_default();
+                total.requests += usage.requests;
+                total.input_tokens += usage.input_tokens;
+                total.output_tokens += usage.output_tokens;
+                total.cost += usage.cost;
+            }
+
+            if session.status == SessionStatus::Error {
+                errored_sessions += 1;
+            }
+
+            let stats = SessionStats::from_session(session);
+            if let Some(latency) = stats.avg_response_latency_ms {
+                latencies.push(latency);
+            }
+            report.sessions.push(stats);
+        }
+
+        let session_count = report.sessions.len();
+        if session_count > 0 {
+            let turns: usize = report.sessions.iter().map(|s| s.turns).sum();
+            report.avg_turns_per_session = turns as f64 / session_count as f64;
+            report.session_error_rate = errored_sessions as f64 / session_count as f64;
+        }
+
+        let (calls, errors) = tools
+            .values()
+            .fold((0, 0), |(calls, errors), (c, e)| (calls + c, errors + e));
+        if calls > 0 {
+            report.tool_error_rate = errors as f64 / calls as f64;
+        }
+
+        report.tools = tools
+            .into_iter()
+            .map(|(name, (calls, errors))| ToolStats {
+                name,
+                calls,
+                errors,
+                error_rate: errors as f64 / calls as f64,
+            })
+            .collect();
+        report
+            .tools
+            .sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+        report.avg_response_latency_ms = average(&latencies);
+        report
+    }
+
+    /// Serializes the full report as pretty-printed JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Renders the per-session statistics as CSV.
+    pub fn sessions_csv(&self) -> String {
+        let mut csv = String::from(
+            "session_id,status,turns,messages,tool_calls,tool_errors,avg_response_latency_ms,input_tokens,output_tokens,cost\n",
+        );
+        for s in &self.sessions {
+            let status = serde_json::to_value(&s.status)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            let row = [
+                csv_field(&s.session_id),
+                status,
+                s.turns.to_string(),
+                s.messages.to_string(),
+                s.tool_calls.to_string(),
+                s.tool_errors.to_string(),
+                s.avg_response_latency_ms.map(|l| format!("{:.1}", l)).unwrap_or_default(),
+                s.input_tokens.to_string(),
+                s.output_tokens.to_string(),
+                format!("{:.6}", s.cost),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Renders the tool usage statistics as CSV.
+    pub fn tools_csv(&self) -> String {
+        let mut csv = String::from("name,calls,errors,error_rate\n");
+        for t in &self.tools {
+            csv.push_str(&format!(
+                "{},{},{},{:.4}\n",
+                csv_field(&t.name),
+                t.calls,
+                t.errors,
+                t.error_rate
+            ));
+        }
+        csv
+    }
+
+    /// Renders the token spend per model as CSV, sorted by model name.
+    pub fn models_csv(&self) -> String {
+        let mut models: Vec<_> = self.by_model.iter().collect();
+        models.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut csv = String::from("model,requests,input_tokens,output_tokens,cost\n");
+        for (model, usage) in models {
+            csv.push_str(&format!(
+                "{},{},{},{},{:.6}\n",
+                csv_field(model),
+                usage.requests,
+                usage.input_tokens,
+                usage.output_tokens,
+                usage.cost
+            ));
+        }
+        csv
+    }
+}
+
+fn average(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Quotes a CSV field when it contains separators, quotes or newlines.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Usage;
+    use crate::session::Message;
+
+    #[test]
+    fn test_report_aggregates_sessions() {
+        let mut first = Session::default();
+        first.add_message(Message::new_user("search twice"));
+        first.add_message(Message::new_assistant(vec![
+            MessageContent::ToolCall {
+                id: "1".to_string(),
+                name: "search".to_string(),
+                arguments: serde_json::json!({}),
+            },
+            MessageContent::ToolCall {
+                id: "2".to_string(),
+                name: "search".to_string(),
+                arguments: serde_json::json!({}),
+            },
+        ]));
+        first.add_message(Message::new_tool_result(vec![
+            MessageContent::ToolResult {
+                tool_call_id: "1".to_string(),
+                result: "ok".to_string(),
+                is_error: None,
+                blocks: Vec::new(),
+            },
+            MessageContent::ToolResult {
+                tool_call_id: "2".to_string(),
+                result: "boom".to_string(),
+                is_error: Some(true),
+                blocks: Vec::new(),
+            },
+        ]));
+        first.record_usage("gpt-4o", &Usage { input_tokens: 100, output_tokens: 10 }, Some(0.5));
+
+        let mut second = Session {
+            status: SessionStatus::Error,
+            ..Default::default()
+        };
+        second.add_message(Message::new_user("hi"));
+        second.add_message(Message::new_user("again"));
+        second.record_usage("gpt-4o", &Usage { input_tokens: 50, output_tokens: 5 }, Some(0.25));
+
+        let report = AnalyticsReport::from_sessions([&first, &second]);
+
+        assert_eq!(report.avg_turns_per_session, 1.5);
+        assert_eq!(report.session_error_rate, 0.5);
+        assert_eq!(report.tool_error_rate, 0.5);
+        assert_eq!(report.tools[0].name, "search");
+        assert_eq!(report.tools[0].errors, 1);
+        assert_eq!(report.by_model["gpt-4o"].input_tokens, 150);
+        assert_eq!(report.sessions[0].tool_calls, 2);
+        assert!(report.sessions[0].avg_response_latency_ms.is_some());
+        assert!(report.tools_csv().starts_with("name,calls,errors,error_rate\nsearch,2,1,0.5000\n"));
+        assert!(report.models_csv().contains("gpt-4o,2,150,15,0.750000"));
+        assert_eq!(report.sessions_csv().lines().count(), 3);
+        assert!(report.to_json().unwrap().contains("\"tool_error_rate\": 0.5"));
+    }
+}
@@ -0,0 +1,10 @@
+//! Deprecated names kept for one release window.
+//!
+//! When a public item is renamed, its old name stays here as a
+//! `#[deprecated]` alias for the next release and is removed in the one
+//! after. Old paths keep resolving in the meantime, so upgrading only
+//! produces warnings pointing at the new name.
+
+/// Renamed to [`MCPToolInfo`](crate::mcp::MCPToolInfo).
+#[deprecated(since = "0.2.0", note = "renamed to `MCPToolInfo`")]
+pub type MCToolInfo = crate::mcp::MCPToolInfo;
@@ -12,8 +12,10 @@
 //!
 //! ## Quick Start
 //!
-//! ```rust
+//! ```rust,no_run
 //! use simple_agent::prelude::*;
+//! use std::sync::Arc;
+//! use tokio::sync::Mutex;
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -35,38 +37,60 @@
 //!
 //!     // Create and run agent
 //!     let agent = Agent::with_defaults(session, llm_client, registry);
-//!     let messages = agent.run("Hello!").await?;
-//!
-//!     for message in messages {
-//!         println!("{:?}", message);
-//!     }
+//!     let result = agent.run("Hello!").await?;
+//!     println!("{}", result.text);
 //!
 //!     Ok(())
 //! }
 //! ```
 //!
 
+extern crate self as simple_agent;
+
 pub mod agent;
+pub mod analytics;
+pub mod compat;
 pub mod llm;
 pub mod session;
 pub mod tool;
 pub mod mcp;
+pub mod net;
 pub mod permission;
+pub mod testing;
+pub mod workflow;
 
 // Re-exports for convenient usage
-pub use agent::{Agent, AgentConfig, AgentEvent};
-pub use llm::{LLMClient, LLMInput, LLMOutput, LLMEvent, OpenAIClient};
+pub use agent::{Agent, AgentBuilder, AgentTool, AgentConfig, ConfigDiagnostic, AgentEvent, AgentRunResult, RunBundle, BudgetLimit, CallEstimate, RetryPolicy, RunBudget, RunOptions, StopReason, StopCondition, StopConditionExt, ContextProvider, DateTimeContextProvider, SystemPromptProvider, EventEnvelope, EventSubscription, TopicMask, OutputGuardrail, GuardrailPolicy, Observer, ObserverAction, ReflectionConfig, ResumePolicy, CompactionConfig, AgentRuntime, RunPriority};
+pub use llm::{LLMClient, LLMInput, RequestOptions, ToolChoice, LLMOutput, LLMEvent, OpenAIClient, FallbackLLMClient, CircuitBreakerLLMClient, RecordingLLMClient, ReplayLLMClient, ModelPricing, PricingTable, ProviderProfile, ProviderAdapter, RequestSigner};
 pub use llm::client::LLMClientBuilder;
-pub use session::{Session, Message, MessageContent, MessageRole, ModelConfig};
-pub use tool::{Tool, ToolRegistry, ToolExecutor, ToolDefinition, ToolResult, ToolError, DynTool};
-pub use mcp::{MCPClient, MCPClientBuilder, MCPConfig, MCPTransport, MCToolInfo};
-pub use permission::{PermissionManager, Permission, PermissionAction, PermissionResult};
+pub use session::{Session, Message, MessageContent, MessageRole, ToolResultBlock, ModelConfig, ModelUsage, UsageReport, SessionStore, FileSessionStore, SessionMaintenance, ContextStrategy};
+pub use tool::{Tool, TypedTool, ToolContext, RateLimit, TruncationPolicy, ToolRegistry, ToolFilter, ConflictPolicy, ToolExecutor, ToolDefinition, ToolResult, ToolError, DynTool, ToolCache, Vault};
+pub use mcp::{MCPClient, MCPClientBuilder, MCPConfig, MCPTransport, MCPToolInfo, MCPManager, MCPServer, MCPStatus, SamplingHandler};
+pub use net::EndpointResolution;
+pub use tokio_util::sync::CancellationToken;
+pub use permission::{PermissionManager, Permission, PermissionAction, PermissionResult, ApprovalHandler};
+#[allow(deprecated)]
+pub use compat::MCToolInfo;
+pub use simple_agent_macros::tool;
 
 /// Prelude module with commonly used types.
 pub mod prelude {
-    pub use crate::agent::{Agent, AgentConfig};
+    pub use crate::agent::{Agent, AgentBuilder, AgentConfig, AgentError, AgentEvent, AgentRunResult, StopReason};
     pub use crate::llm::{LLMClient, OpenAIClient};
-    pub use crate::session::{Session, Message, ModelConfig};
-    pub use crate::tool::{Tool, ToolRegistry, ToolResult, ToolError, DynTool};
+    pub use crate::mcp::{MCPClient, MCPClientBuilder, MCPConfig, MCPError, MCPToolInfo, MCPTransport};
+    pub use crate::permission::{Permission, PermissionAction, PermissionManager};
+    pub use crate::session::{Session, Message, MessageContent, MessageRole, ModelConfig};
+    pub use crate::tool::{Tool, TypedTool, ToolContext, ToolRegistry, ToolDefinition, ToolResult, ToolError, DynTool};
     pub use crate::LLMClientBuilder;
+    pub use crate::tool;
+}
+
+/// Items used by macro-generated code; not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+    pub use schemars;
+    pub use serde;
+    pub use serde_json;
+    pub use crate::tool::macro_support::{into_result, parse_arguments, schema_for};
 }
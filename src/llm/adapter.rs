@@ -0,0 +1,809 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::profile::ProviderProfile;
+use super::{LLMInput, LLMOutput, LLMEvent, FinishReason, Usage, LLMError};
+use crate::session::{MessageContent, MessageRole, ToolResultBlock};
+use crate::tool::ToolDefinition;
+
+/// Translates between the SDK's types and a provider's wire format.
+///
+/// Every method defaults to the OpenAI chat completions format, so an
+/// adapter for an OpenAI-compatible gateway only overrides what differs and
+/// can call the `OpenAIAdapter` functions for the rest.
+pub trait ProviderAdapter: Send + Sync {
+    /// Builds the JSON body of a chat request, with `tools` as built by
+    /// `build_tools`.
+    fn build_request_body(&self, profile: &ProviderProfile, input: &LLMInput, tools: &Value, stream: bool) -> Value {
+        OpenAIAdapter::request_body_with_tools(profile, input, tools, stream)
+    }
+
+    /// Builds the tool array of a chat request. Clients cache the result
+    /// per tool registry generation, so this should only depend on `tools`.
+    fn build_tools(&self, tools: &[ToolDefinition]) -> Value {
+        OpenAIAdapter::tools(tools)
+    }
+
+    /// Parses the body of a non-streaming response.
+    fn parse_response(&self, body: &str) -> Result<LLMOutput, LLMError> {
+        OpenAIAdapter::parse_response(body)
+    }
+
+    /// Parses the `data:` payload of one server-sent event.
+    fn parse_stream_event(&self, data: &str, state: &mut StreamState) -> Result<Vec<LLMEvent>, LLMError> {
+        OpenAIAdapter::parse_stream_event(data, state)
+    }
+}
+
+/// State carried across the events of one streamed response.
+#[derive(Debug, Clone, Default)]
+pub struct StreamState {
+    /// Whether usage arrives in a trailing chunk after the finish reason
+    pub include_usage: bool,
+    /// A finish reason held back until the usage chunk arrives
+    pub pending_finish: Option<FinishReason>,
+}
+
+impl StreamState {
+    /// Creates the state for a new response.
+    pub fn new(include_usage: bool) -> Self {
+        Self {
+            include_usage,
+            pending_finish: None,
+        }
+    }
+
+    /// Returns the finish event still held back when the stream ends.
+    pub fn finish(&mut self) -> Option<LLMEvent> {
+        self.pending_finish.take().map(|reason| LLMEvent::Finish {
+            reason,
+            usage: Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+            },
+        })
+    }
+}
+
+/// The OpenAI chat completions wire format.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenAIAdapter;
+
+impl ProviderAdapter for OpenAIAdapter {}
+
+/// OpenAI API response for chat completions.
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: UsageInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: MessageResponse,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct MessageResponse {
+    content: Option<String>,
+    reasoning_content: Option<String>,
+    tool_calls: Option<Vec<ToolCall>>,
+    /// Whether `content` came after `tool_calls` in the response object
+    text_after_tool_calls: bool,
+}
+
+// Providers that return both text and tool calls in one message only convey
+// their order through the order of the keys, so it is recorded while parsing.
+impl<'de> Deserialize<'de> for MessageResponse {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MessageVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for MessageVisitor {
+            type Value = MessageResponse;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a chat completion message")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<MessageResponse, A::Error> {
+                let mut message = MessageResponse::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "content" => {
+                            message.content = map.next_value()?;
+                            message.text_after_tool_calls = message.tool_calls.is_some();
+                        }
+                        "reasoning_content" => message.reasoning_content = map.next_value()?,
+                        "tool_calls" => message.tool_calls = map.next_value()?,
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(message)
+            }
+        }
+
+        deserializer.deserialize_map(MessageVisitor)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    #[serde(default)]
+    id: String,
+    function: FunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionCall {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UsageInfo {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+/// Streaming response chunk.
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    usage: Option<UsageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    delta: Delta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Delta {
+    content: Option<String>,
+    #[serde(default)]
+    reasoning_content: Option<String>,
+    tool_calls: Option<Vec<ChunkToolCall>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkToolCall {
+    #[serde(default)]
+    id: String,
+    function: ChunkFunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkFunctionCall {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+impl OpenAIAdapter {
+    /// Builds the JSON request body, adjusted to the provider's capabilities.
+    pub fn request_body(profile: &ProviderProfile, input: &LLMInput, stream: bool) -> Value {
+        Self::request_body_with_tools(profile, input, &Self::tools(&input.tools), stream)
+    }
+
+    /// Builds the JSON request body around an already formatted tool array.
+    pub fn request_body_with_tools(profile: &ProviderProfile, input: &LLMInput, tools: &Value, stream: bool) -> Value {
+        let mut body = serde_json::Map::new();
+        body.insert("model".to_string(), Value::from(input.model.clone()));
+        body.insert("messages".to_string(), Value::Array(Self::build_messages(input, profile)));
+        body.insert(
+            profile.max_tokens_field.as_str().to_string(),
+            Value::from(input.max_tokens),
+        );
+        if let Some(temperature) = input.temperature {
+            body.insert("temperature".to_string(), Value::from(temperature));
+        }
+        body.insert("stream".to_string(), Value::Bool(stream));
+
+        if profile.tools && !input.tools.is_empty() {
+            body.insert("tools".to_string(), tools.clone());
+            if profile.parallel_tool_calls {
+                body.insert("parallel_tool_calls".to_string(), Value::Bool(true));
+            }
+        }
+
+        if stream && profile.stream_options {
+            body.insert(
+                "stream_options".to_string(),
+                serde_json::json!({ "include_usage": true }),
+            );
+        }
+
+        for (name, value) in &input.request_options.extra_body {
+            body.insert(name.clone(), value.clone());
+        }
+
+        Value::Object(body)
+    }
+
+    /// Formats tool definitions as chat completions functions.
+    pub fn tools(tools: &[ToolDefinition]) -> Value {
+        tools
+            .iter()
+            .map(|tool| {
+                serde_json::json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Converts the system prompt and conversation to chat messages.
+    ///
+    /// Tool call ids the provider would reject are mapped through the
+    /// profile's id format.
+    pub fn build_messages(input: &LLMInput, profile: &ProviderProfile) -> Vec<Value> {
+        let ids = &profile.id_format;
+        let mut messages = Vec::new();
+
+        // Add system prompt
+        if !input.system_prompt.is_empty() {
+            messages.push(serde_json::json!({
+                "role": "system",
+                "content": input.system_prompt
+            }));
+        }
+
+        // Add conversation messages
+        for msg in &input.messages {
+            match msg.role {
+                MessageRole::User => {
+                    messages.push(serde_json::json!({
+                        "role": "user",
+                        "content": Self::user_content(&msg.content)
+                    }));
+                }
+                MessageRole::Assistant => {
+                    let tool_calls = msg.content.iter().filter_map(|c| {
+                        if let MessageContent::ToolCall { id, name, arguments } = c {
+                            Some(serde_json::json!({
+                                "id": ids.map(id),
+                                "type": "function",
+                                "function": {
+                                    "name": name,
+                                    "arguments": arguments.to_string()
+                                }
+                            }))
+                        } else {
+                            None
+                        }
+                    }).collect::<Vec<_>>();
+
+                    if !tool_calls.is_empty() {
+                        // Text written before the calls is kept; providers expect null otherwise
+                        let text = Self::content_to_string(&msg.content);
+                        messages.push(serde_json::json!({
+                            "role": "assistant",
+                            "content": if text.is_empty() { Value::Null } else { Value::String(text) },
+                            "tool_calls": tool_calls
+                        }));
+                    } else {
+                        messages.push(serde_json::json!({
+                            "role": "assistant",
+                            "content": Self::content_to_string(&msg.content)
+                        }));
+                    }
+                }
+                MessageRole::Tool => {
+                    for content in &msg.content {
+                        if let MessageContent::ToolResult {
+                            tool_call_id,
+                            result,
+                            blocks,
+                            ..
+                        } = content
+                        {
+                            let content = if profile.multipart_tool_results && !blocks.is_empty() {
+                                Self::tool_result_parts(blocks)
+                            } else {
+                                Value::String(result.clone())
+                            };
+                            messages.push(serde_json::json!({
+                                "role": "tool",
+                                "tool_call_id": ids.map(tool_call_id),
+                                "content": content
+                            }));
+                        }
+                    }
+
+                    // Tool messages cannot carry images, so they follow as a user message
+                    let images: Vec<_> = msg
+                        .content
+                        .iter()
+                        .flat_map(|c| match c {
+                            MessageContent::Image { .. } => vec![c.clone()],
+                            MessageContent::ToolResult { blocks, .. } => blocks
+                                .iter()
+                                .filter_map(|b| match b {
+                                    ToolResultBlock::Image { media_type, data } => Some(MessageContent::Image {
+                                        media_type: media_type.clone(),
+                                        data: data.clone(),
+                                    }),
+                                    _ => None,
+                                })
+                                .collect(),
+                            _ => Vec::new(),
+                        })
+                        .collect();
+                    if !images.is_empty() {
+                        messages.push(serde_json::json!({
+                            "role": "user",
+                            "content": Self::user_content(&images)
+                        }));
+                    }
+                }
+            }
+        }
+
+        messages
+    }
+
+    /// Parses a non-streaming chat completion response body.
+    pub fn parse_response(response_text: &str) -> Result<LLMOutput, LLMError> {
+        let response: ChatCompletionResponse = serde_json::from_str(response_text)
+            .map_err(|e| LLMError::InvalidResponse(format!("{}: {}", e, response_text)))?;
+
+        let Some(choice) = response.choices.into_iter().next() else {
+            return Err(LLMError::InvalidResponse(
+                format!("No choices in response. Response: {}", response_text)
+            ));
+        };
+
+        let mut content = Vec::new();
+
+        if let Some(ref reasoning) = choice.message.reasoning_content
+            && !reasoning.is_empty()
+        {
+            content.push(MessageContent::Thinking {
+                thinking: reasoning.clone(),
+            });
+        }
+
+        // Text and tool calls keep the order the provider sent them in
+        let text = choice
+            .message
+            .content
+            .filter(|text| !text.is_empty())
+            .map(|text| MessageContent::Text { text });
+        let tool_calls = choice.message.tool_calls.unwrap_or_default().into_iter().map(|tool_call| {
+            let arguments: Value = if tool_call.function.arguments.is_empty() {
+                serde_json::json!({})
+            } else {
+                serde_json::from_str(&tool_call.function.arguments)
+                    .unwrap_or(serde_json::json!({}))
+            };
+
+            MessageContent::ToolCall {
+                id: tool_call.id,
+                name: tool_call.function.name,
+                arguments,
+            }
+        });
+
+        if choice.message.text_after_tool_calls {
+            content.extend(tool_calls);
+            content.extend(text);
+        } else {
+            content.extend(text);
+            content.extend(tool_calls);
+        }
+
+        let finish_reason = choice
+            .finish_reason
+            .as_deref()
+            .map(FinishReason::from_provider)
+            .unwrap_or_else(|| FinishReason::Unknown(String::new()));
+
+        Ok(LLMOutput {
+            content,
+            finish_reason,
+            usage: Usage {
+                input_tokens: response.usage.prompt_tokens,
+                output_tokens: response.usage.completion_tokens,
+            },
+        })
+    }
+
+    /// Parses the `data:` payload of one server-sent event into stream
+    /// events. Unparseable chunks are skipped.
+    pub fn parse_stream_event(data: &str, state: &mut StreamState) -> Result<Vec<LLMEvent>, LLMError> {
+        let chunk = match serde_json::from_str::<ChatCompletionChunk>(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tracing::debug!("Failed to parse chunk: {:?}", e);
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut events = Vec::new();
+        if let Some(ref usage) = chunk.usage
+            && let Some(reason) = state.pending_finish.take()
+        {
+            events.push(LLMEvent::Finish {
+                reason,
+                usage: Usage {
+                    input_tokens: usage.prompt_tokens,
+                    output_tokens: usage.completion_tokens,
+                },
+            });
+        }
+
+        for choice in chunk.choices {
+            if let Some(reasoning) = choice.delta.reasoning_content
+                && !reasoning.is_empty()
+            {
+                events.push(LLMEvent::ThinkingDelta { text: reasoning });
+            }
+
+            if let Some(text) = choice.delta.content {
+                events.push(LLMEvent::TextDelta { text });
+            }
+
+            for tool_call in choice.delta.tool_calls.unwrap_or_default() {
+                if let Some(name) = tool_call.function.name {
+                    events.push(LLMEvent::ToolCallStart {
+                        id: tool_call.id.clone(),
+                        name,
+                    });
+                }
+                if let Some(arguments) = tool_call.function.arguments {
+                    events.push(LLMEvent::ToolCallDelta {
+                        id: tool_call.id,
+                        arguments,
+                    });
+                }
+            }
+
+            if let Some(ref reason) = choice.finish_reason {
+                let reason = FinishReason::from_provider(reason);
+                if state.include_usage {
+                    state.pending_finish = Some(reason);
+                } else {
+                    events.push(LLMEvent::Finish {
+                        reason,
+                        usage: Usage {
+                            input_tokens: 0,
+                            output_tokens: 0,
+                        },
+                    });
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Converts user content to a plain string, or to content parts when it
+    /// contains images.
+    fn user_content(content: &[MessageContent]) -> Value {
+        if !content.iter().any(|c| matches!(c, MessageContent::Image { .. })) {
+            return Value::String(Self::content_to_string(content));
+        }
+
+        let parts = content
+            .iter()
+            .filter_map(|c| match c {
+                MessageContent::Text { text } => Some(serde_json::json!({
+                    "type": "text",
+                    "text": text
+                })),
+                MessageContent::Image { media_type, data } => Some(serde_json::json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", media_type, data) }
+                })),
+                _ => None,
+            })
+            .collect();
+        Value::Array(parts)
+    }
+
+    /// Converts structured tool result blocks to text content parts. Images
+    /// are left out since tool messages cannot carry them.
+    fn tool_result_parts(blocks: &[ToolResultBlock]) -> Value {
+        let parts = blocks
+            .iter()
+            .filter(|b| !matches!(b, ToolResultBlock::Image { .. }))
+            .map(|b| {
+                let text = match b {
+                    ToolResultBlock::Json { value } => value.to_string(),
+                    other => other.render(),
+                };
+                serde_json::json!({ "type": "text", "text": text })
+            })
+            .collect();
+        Value::Array(parts)
+    }
+
+    /// Converts message content to a string.
+    fn content_to_string(content: &[MessageContent]) -> String {
+        content
+            .iter()
+            .filter_map(|c| {
+                if let MessageContent::Text { text } = c {
+                    Some(text.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RequestOptions;
+    use crate::llm::profile::IdFormat;
+    use crate::session::Message;
+
+    #[test]
+    fn test_parse_response_with_reasoning() {
+        let body = r#"{
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "reasoning_content": "The user greets me.",
+                    "content": "Hello!"
+                },
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }"#;
+
+        let output = OpenAIAdapter::parse_response(body).unwrap();
+
+        assert_eq!(output.content.len(), 2);
+        assert!(matches!(
+            &output.content[0],
+            MessageContent::Thinking { thinking } if thinking == "The user greets me."
+        ));
+        assert!(matches!(
+            &output.content[1],
+            MessageContent::Text { text } if text == "Hello!"
+        ));
+        assert_eq!(output.usage.input_tokens, 10);
+    }
+
+    #[test]
+    fn test_parse_response_keeps_mixed_content_order() {
+        fn kinds(body: &str) -> Vec<&'static str> {
+            OpenAIAdapter::parse_response(body)
+                .unwrap()
+                .content
+                .iter()
+                .map(|c| match c {
+                    MessageContent::Thinking { .. } => "thinking",
+                    MessageContent::Text { .. } => "text",
+                    MessageContent::ToolCall { .. } => "tool_call",
+                    _ => "other",
+                })
+                .collect()
+        }
+
+        assert_eq!(kinds(include_str!("fixtures/deepseek_text_then_tool.json")), ["text", "tool_call"]);
+        assert_eq!(kinds(include_str!("fixtures/groq_tool_then_text.json")), ["tool_call", "text"]);
+        assert_eq!(
+            kinds(include_str!("fixtures/minimax_reasoning_text_tool.json")),
+            ["thinking", "text", "tool_call"]
+        );
+    }
+
+    #[test]
+    fn test_parse_response_keeps_content_filter_reason() {
+        let body = r#"{"choices": [{"message": {"content": ""}, "finish_reason": "content_filter"}]}"#;
+        let output = OpenAIAdapter::parse_response(body).unwrap();
+        assert!(matches!(output.finish_reason, FinishReason::ContentFilter));
+
+        let body = r#"{"choices": [{"message": {"content": "hi"}, "finish_reason": "eos"}]}"#;
+        let output = OpenAIAdapter::parse_response(body).unwrap();
+        assert!(matches!(output.finish_reason, FinishReason::Unknown(reason) if reason == "eos"));
+    }
+
+    #[test]
+    fn test_stream_holds_finish_until_usage() {
+        let mut state = StreamState::new(true);
+        let events = OpenAIAdapter::parse_stream_event(
+            r#"{"choices": [{"delta": {"content": "Hi"}, "finish_reason": "stop"}]}"#,
+            &mut state,
+        )
+        .unwrap();
+        assert!(matches!(&events[..], [LLMEvent::TextDelta { text }] if text == "Hi"));
+
+        let events = OpenAIAdapter::parse_stream_event(
+            r#"{"choices": [], "usage": {"prompt_tokens": 3, "completion_tokens": 1}}"#,
+            &mut state,
+        )
+        .unwrap();
+        assert!(matches!(&events[..], [LLMEvent::Finish { usage, .. }] if usage.input_tokens == 3));
+        assert!(state.finish().is_none());
+    }
+
+    #[test]
+    fn test_request_body_follows_profile() {
+        let input = LLMInput {
+            model: "gpt-4o".to_string(),
+            messages: vec![],
+            system_prompt: String::new(),
+            tools: vec![crate::tool::ToolDefinition {
+                name: "search".to_string(),
+                description: "Searches".to_string(),
+                input_schema: serde_json::json!({"type": "object"}),
+            }],
+            max_tokens: 256,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        };
+
+        let body = OpenAIAdapter::request_body(&ProviderProfile::openai(), &input, true);
+        assert_eq!(body["max_completion_tokens"], 256);
+        assert_eq!(body["tools"][0]["function"]["name"], "search");
+        assert_eq!(body["parallel_tool_calls"], true);
+        assert_eq!(body["stream_options"]["include_usage"], true);
+
+        let mut input = input;
+        input.request_options = RequestOptions::default().with_body_field("top_k", serde_json::json!(20));
+        let body = OpenAIAdapter::request_body(&ProviderProfile::minimax(), &input, true);
+        assert_eq!(body["top_k"], 20);
+        assert_eq!(body["max_tokens"], 256);
+        assert!(body.get("tools").is_none());
+        assert!(body.get("stream_options").is_none());
+        assert!(body.get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_tool_images_follow_as_user_message() {
+        let input = LLMInput {
+            model: "gpt-4o".to_string(),
+            messages: vec![Message::new_tool_result(vec![
+                MessageContent::ToolResult {
+                    tool_call_id: "call_1".to_string(),
+                    result: "Captured".to_string(),
+                    is_error: None,
+                    blocks: Vec::new(),
+                },
+                MessageContent::image("image/png", b"png"),
+            ])],
+            system_prompt: String::new(),
+            tools: vec![],
+            max_tokens: 256,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        };
+
+        let messages = OpenAIAdapter::build_messages(&input, &ProviderProfile::generic());
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["role"], "tool");
+        assert_eq!(messages[1]["role"], "user");
+        assert_eq!(messages[1]["content"][0]["image_url"]["url"], "data:image/png;base64,cG5n");
+    }
+
+    #[test]
+    fn test_structured_tool_results_become_content_parts() {
+        let result = crate::tool::ToolResult::ok("Found 1 file")
+            .with_json(serde_json::json!({"path": "src/lib.rs"}))
+            .with_resource("file:///src/lib.rs", Some("lib.rs".to_string()))
+            .with_block(ToolResultBlock::Image {
+                media_type: "image/png".to_string(),
+                data: "cG5n".to_string(),
+            });
+        let (text, blocks) = result.content();
+        let input = LLMInput {
+            model: "gpt-4o".to_string(),
+            messages: vec![Message::new_tool_result(vec![MessageContent::ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: text,
+                is_error: None,
+                blocks,
+            }])],
+            system_prompt: String::new(),
+            tools: vec![],
+            max_tokens: 256,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        };
+
+        let messages = OpenAIAdapter::build_messages(&input, &ProviderProfile::openai());
+        let parts = messages[0]["content"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1]["text"], r#"{"path":"src/lib.rs"}"#);
+        assert_eq!(parts[2]["text"], "[resource: lib.rs](file:///src/lib.rs)");
+        assert_eq!(messages[1]["content"][0]["image_url"]["url"], "data:image/png;base64,cG5n");
+
+        let messages = OpenAIAdapter::build_messages(&input, &ProviderProfile::generic());
+        let text = messages[0]["content"].as_str().unwrap();
+        assert!(text.starts_with("Found 1 file\n{\n  \"path\""));
+        assert!(text.ends_with("[image: image/png]"));
+    }
+
+    #[test]
+    fn test_unsafe_tool_call_ids_are_mapped_consistently() {
+        let long_id = "toolu_01A09q90qw90lq917835lq9.extra-long-identifier";
+        let input = LLMInput {
+            model: "mistral-large".to_string(),
+            messages: vec![
+                Message::new_assistant(vec![MessageContent::ToolCall {
+                    id: long_id.to_string(),
+                    name: "search".to_string(),
+                    arguments: serde_json::json!({}),
+                }]),
+                Message::new_tool_result(vec![MessageContent::ToolResult {
+                    tool_call_id: long_id.to_string(),
+                    result: "found".to_string(),
+                    is_error: None,
+                    blocks: Vec::new(),
+                }]),
+            ],
+            system_prompt: String::new(),
+            tools: vec![],
+            max_tokens: 256,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        };
+
+        let profile = ProviderProfile::mistral();
+        let ids = &profile.id_format;
+        let messages = OpenAIAdapter::build_messages(&input, &profile);
+        let call_id = messages[0]["tool_calls"][0]["id"].as_str().unwrap();
+        assert_eq!(call_id.len(), 9);
+        assert!(ids.accepts(call_id));
+        assert_eq!(messages[1]["tool_call_id"], call_id);
+        assert_eq!(IdFormat::default().map("call_abc123"), "call_abc123");
+    }
+
+    #[test]
+    fn test_assistant_text_is_sent_with_tool_calls() {
+        let call = |id: &str| MessageContent::ToolCall {
+            id: id.to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({}),
+        };
+        let input = LLMInput {
+            model: "gpt-4o".to_string(),
+            messages: vec![
+                Message::new_assistant(vec![
+                    MessageContent::Text {
+                        text: "Let me look that up.".to_string(),
+                    },
+                    call("call_1"),
+                ]),
+                Message::new_assistant(vec![call("call_2")]),
+            ],
+            system_prompt: String::new(),
+            tools: vec![],
+            max_tokens: 256,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        };
+
+        let messages = OpenAIAdapter::build_messages(&input, &ProviderProfile::openai());
+        assert_eq!(messages[0]["content"], "Let me look that up.");
+        assert_eq!(messages[0]["tool_calls"][0]["id"], "call_1");
+        assert!(messages[1]["content"].is_null());
+        assert_eq!(messages[1]["tool_calls"][0]["id"], "call_2");
+    }
+}
@@ -0,0 +1,284 @@
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::{LLMClient, LLMError, LLMEvent, LLMInput, LLMOutput, LLMStream};
+
+/// A recorded sequence of LLM request/response pairs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Interactions in the order they happened
+    pub interactions: Vec<Interaction>,
+}
+
+/// A single recorded request and its response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request sent to the provider
+    pub request: LLMInput,
+    /// The response the provider returned
+    pub response: RecordedResponse,
+}
+
+/// A recorded response, either complete or streamed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedResponse {
+    /// Response returned by `LLMClient::complete`
+    Complete { output: LLMOutput },
+    /// Events returned by `LLMClient::stream`
+    Stream { events: Vec<LLMEvent> },
+}
+
+impl Cassette {
+    /// Loads a cassette from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        serde_json::from_str(&data)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes the cassette to a JSON file, creating parent directories.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, data)
+    }
+}
+
+/// Wraps an `LLMClient` and records every successful request/response pair
+/// to a cassette file.
+///
+/// The file is rewritten after each interaction so a crashed run still
+/// leaves a usable cassette. Failed requests are passed through and not
+/// recorded.
+pub struct RecordingLLMClient {
+    inner: Arc<dyn LLMClient>,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl RecordingLLMClient {
+    /// Creates a recorder writing to `path`.
+    pub fn new(inner: Arc<dyn LLMClient>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+        }
+    }
+
+    /// Returns a copy of everything recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    fn record(cassette: &Mutex<Cassette>, path: &Path, interaction: Interaction) {
+        let mut cassette = cassette.lock().unwrap();
+        cassette.interactions.push(interaction);
+        if let Err(e) = cassette.save(path) {
+            tracing::warn!(path = %path.display(), "Failed to write LLM cassette: {}", e);
+        }
+    }
+}
+
+impl std::fmt::Debug for RecordingLLMClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingLLMClient")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl LLMClient for RecordingLLMClient {
+    async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
+        let mut inner = self.inner.stream(input.clone()).await?;
+        let cassette = self.cassette.clone();
+        let path = self.path.clone();
+
+        Ok(Box::pin(stream! {
+            let mut events = Vec::new();
+            while let Some(event) = inner.next().await {
+                match event {
+                    Ok(event) => {
+                        events.push(event.clone());
+                        yield Ok(event);
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+            Self::record(&cassette, &path, Interaction {
+                request: input,
+                response: RecordedResponse::Stream { events },
+            });
+        }))
+    }
+
+    async fn complete(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
+        let output = self.inner.complete(input.clone()).await?;
+        Self::record(
+            &self.cassette,
+            &self.path,
+            Interaction {
+                request: input,
+                response: RecordedResponse::Complete {
+                    output: output.clone(),
+                },
+            },
+        );
+        Ok(output)
+    }
+}
+
+/// Serves responses from a cassette in the order they were recorded.
+///
+/// Recorded streams can be served through `complete` and vice versa. With
+/// strict matching enabled, each request must match the recorded one
+/// (model, system prompt and message contents; ids and timestamps are
+/// ignored).
+#[derive(Debug)]
+pub struct ReplayLLMClient {
+    interactions: Mutex<VecDeque<Interaction>>,
+    strict: bool,
+}
+
+impl ReplayLLMClient {
+    /// Creates a replayer from an in-memory cassette.
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            interactions: Mutex::new(cassette.interactions.into()),
+            strict: false,
+        }
+    }
+
+    /// Creates a replayer from a cassette file.
+    pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    /// Requires each request to match the recorded one.
+    pub fn with_strict_matching(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Returns the number of interactions not yet served.
+    pub fn remaining(&self) -> usize {
+        self.interactions.lock().unwrap().len()
+    }
+
+    fn next(&self, input: &LLMInput) -> Result<RecordedResponse, LLMError> {
+        let interaction = self.interactions.lock().unwrap().pop_front().ok_or_else(|| {
+            LLMError::InvalidResponse("Cassette has no recorded interactions left".to_string())
+        })?;
+
+        if self.strict && request_key(&interaction.request) != request_key(input) {
+            return Err(LLMError::InvalidResponse(format!(
+                "Request does not match cassette (expected model `{}` with {} messages)",
+                interaction.request.model,
+                interaction.request.messages.len()
+            )));
+        }
+
+        Ok(interaction.response)
+    }
+}
+
+/// The parts of a request compared in strict mode.
+fn request_key(input: &LLMInput) -> serde_json::Value {
+    let messages: Vec<_> = input
+        .messages
+        .iter()
+        .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+        .collect();
+    serde_json::json!({
+        "model": input.model,
+        "system_prompt": input.system_prompt,
+        "messages": messages,
+    })
+}
+
+#[async_trait]
+impl LLMClient for ReplayLLMClient {
+    async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
+        let events = match self.next(&input)? {
+            RecordedResponse::Stream { events } => events,
+            RecordedResponse::Complete { output } => output.into_events(),
+        };
+        Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+    }
+
+    async fn complete(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
+        match self.next(&input)? {
+            RecordedResponse::Complete { output } => Ok(output),
+            RecordedResponse::Stream { events } => Ok(LLMOutput::from_events(&events)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{Message, MessageContent};
+    use crate::testing::MockLLMClient;
+
+    fn input(text: &str) -> LLMInput {
+        LLMInput {
+            model: "test-model".to_string(),
+            messages: vec![Message::new_user(text)],
+            system_prompt: String::new(),
+            tools: vec![],
+            max_tokens: 100,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = std::env::temp_dir().join(format!("cassette-{}.json", uuid::Uuid::new_v4()));
+        let mock = MockLLMClient::new()
+            .with_text_response("first")
+            .with_tool_call_response("call_1", "search", serde_json::json!({"q": "rust"}));
+        let recorder = RecordingLLMClient::new(Arc::new(mock), &path);
+
+        recorder.complete(input("one")).await.unwrap();
+        let events: Vec<_> = recorder.stream(input("two")).await.unwrap().collect().await;
+        assert_eq!(events.len(), 4);
+        assert_eq!(recorder.cassette().interactions.len(), 2);
+
+        let replay = ReplayLLMClient::from_file(&path).unwrap().with_strict_matching();
+        let first = replay.complete(input("one")).await.unwrap();
+        assert!(matches!(&first.content[0], MessageContent::Text { text } if text == "first"));
+
+        // A recorded stream can be replayed as a complete response
+        let second = replay.complete(input("two")).await.unwrap();
+        assert!(matches!(
+            &second.content[0],
+            MessageContent::ToolCall { name, arguments, .. }
+                if name == "search" && arguments["q"] == "rust"
+        ));
+        assert_eq!(replay.remaining(), 0);
+        assert!(replay.complete(input("three")).await.is_err());
+
+        let strict = ReplayLLMClient::from_file(&path).unwrap().with_strict_matching();
+        assert!(strict.complete(input("different")).await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
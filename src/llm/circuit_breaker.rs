@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use super::{LLMClient, LLMError, LLMInput, LLMOutput, LLMStream};
+
+/// The state of a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests are rejected (or sent to the fallback) until the cooldown ends
+    Open,
+    /// A single probe request is allowed through to test the endpoint
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+/// An LLM client that stops calling an endpoint after repeated failures.
+///
+/// After `failure_threshold` consecutive transient failures the circuit
+/// opens: requests fail fast with `LLMError::CircuitOpen`, or go to the
+/// fallback client when one is set. Once `cooldown` has elapsed a single
+/// probe request is let through; success closes the circuit, failure opens
+/// it again. For streams only errors returned when opening the stream count.
+pub struct CircuitBreakerLLMClient {
+    inner: Arc<dyn LLMClient>,
+    fallback: Option<Arc<dyn LLMClient>>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    breaker: Mutex<Breaker>,
+}
+
+/// Whether a request may be sent to the inner client, and if it is a probe.
+enum Admission {
+    Allowed { probe: bool },
+    Rejected,
+}
+
+impl CircuitBreakerLLMClient {
+    /// Creates a breaker that opens after `failure_threshold` consecutive
+    /// failures and probes again after `cooldown`.
+    pub fn new(inner: Arc<dyn LLMClient>, failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            inner,
+            fallback: None,
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            breaker: Mutex::new(Breaker {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_in_flight: false,
+            }),
+        }
+    }
+
+    /// Sends requests to `fallback` while the circuit is open.
+    pub fn with_fallback(mut self, fallback: Arc<dyn LLMClient>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Returns the current state, moving to half-open if the cooldown elapsed.
+    pub fn state(&self) -> CircuitState {
+        let mut breaker = self.breaker.lock().unwrap();
+        self.refresh(&mut breaker);
+        breaker.state
+    }
+
+    fn refresh(&self, breaker: &mut Breaker) {
+        if breaker.state == CircuitState::Open
+            && breaker.opened_at.is_some_and(|at| at.elapsed() >= self.cooldown)
+        {
+            breaker.state = CircuitState::HalfOpen;
+            breaker.probe_in_flight = false;
+        }
+    }
+
+    fn admit(&self) -> Admission {
+        let mut breaker = self.breaker.lock().unwrap();
+        self.refresh(&mut breaker);
+        match breaker.state {
+            CircuitState::Closed => Admission::Allowed { probe: false },
+            CircuitState::HalfOpen if !breaker.probe_in_flight => {
+                breaker.probe_in_flight = true;
+                Admission::Allowed { probe: true }
+            }
+            CircuitState::HalfOpen | CircuitState::Open => Admission::Rejected,
+        }
+    }
+
+    fn on_success(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.state = CircuitState::Closed;
+        breaker.consecutive_failures = 0;
+        breaker.opened_at = None;
+        breaker.probe_in_flight = false;
+    }
+
+    fn on_failure(&self, error: &LLMError, probe: bool) {
+        let mut breaker = self.breaker.lock().unwrap();
+        if probe {
+            breaker.probe_in_flight = false;
+        }
+        if !error.is_transient() {
+            // The endpoint answered; the request itself was bad
+            if probe {
+                breaker.state = CircuitState::Closed;
+                breaker.consecutive_failures = 0;
+            }
+            return;
+        }
+
+        breaker.consecutive_failures += 1;
+        if probe || breaker.consecutive_failures >= self.failure_threshold {
+            if breaker.state != CircuitState::Open {
+                warn!(failures = breaker.consecutive_failures, error = %error, "Opening LLM circuit breaker");
+            }
+            breaker.state = CircuitState::Open;
+            breaker.opened_at = Some(Instant::now());
+        }
+    }
+
+    fn rejected(&self) -> LLMError {
+        LLMError::CircuitOpen(format!(
+            "endpoint failed {} times in a row; retrying after {:?}",
+            self.failure_threshold, self.cooldown
+        ))
+    }
+}
+
+impl std::fmt::Debug for CircuitBreakerLLMClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreakerLLMClient")
+            .field("failure_threshold", &self.failure_threshold)
+            .field("cooldown", &self.cooldown)
+            .field("has_fallback", &self.fallback.is_some())
+            .field("breaker", &self.breaker)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl LLMClient for CircuitBreakerLLMClient {
+    async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
+        let probe = match self.admit() {
+            Admission::Allowed { probe } => probe,
+            Admission::Rejected => {
+                return match &self.fallback {
+                    Some(fallback) => fallback.stream(input).await,
+                    None => Err(self.rejected()),
+                };
+            }
+        };
+
+        match self.inner.stream(input).await {
+            Ok(stream) => {
+                self.on_success();
+                Ok(stream)
+            }
+            Err(e) => {
+                self.on_failure(&e, probe);
+                Err(e)
+            }
+        }
+    }
+
+    async fn complete(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
+        let probe = match self.admit() {
+            Admission::Allowed { probe } => probe,
+            Admission::Rejected => {
+                return match &self.fallback {
+                    Some(fallback) => fallback.complete(input).await,
+                    None => Err(self.rejected()),
+                };
+            }
+        };
+
+        match self.inner.complete(input).await {
+            Ok(output) => {
+                self.on_success();
+                Ok(output)
+            }
+            Err(e) => {
+                self.on_failure(&e, probe);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Message;
+    use crate::testing::MockLLMClient;
+
+    fn input() -> LLMInput {
+        LLMInput {
+            model: "test".to_string(),
+            messages: vec![Message::new_user("hi")],
+            system_prompt: String::new(),
+            tools: vec![],
+            max_tokens: 16,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        }
+    }
+
+    fn api_error() -> LLMError {
+        LLMError::ApiError("503".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold_and_uses_fallback() {
+        let inner = Arc::new(MockLLMClient::new().with_error(api_error()).with_error(api_error()));
+        let fallback = Arc::new(MockLLMClient::new().with_text_response("fallback"));
+        let client = CircuitBreakerLLMClient::new(inner.clone(), 2, Duration::from_secs(3600));
+
+        assert!(client.complete(input()).await.is_err());
+        assert_eq!(client.state(), CircuitState::Closed);
+        assert!(client.complete(input()).await.is_err());
+        assert_eq!(client.state(), CircuitState::Open);
+
+        assert!(matches!(client.complete(input()).await, Err(LLMError::CircuitOpen(_))));
+        assert_eq!(inner.call_count(), 2);
+
+        let client = client.with_fallback(fallback.clone());
+        assert!(client.complete(input()).await.is_ok());
+        assert_eq!(fallback.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_closes_circuit() {
+        let inner = Arc::new(
+            MockLLMClient::new()
+                .with_error(api_error())
+                .with_error(api_error())
+                .with_text_response("recovered"),
+        );
+        let client = CircuitBreakerLLMClient::new(inner.clone(), 1, Duration::ZERO);
+
+        assert!(client.complete(input()).await.is_err());
+        assert_eq!(client.state(), CircuitState::HalfOpen);
+
+        // A failed probe reopens the circuit
+        assert!(client.complete(input()).await.is_err());
+        assert!(client.complete(input()).await.is_ok());
+        assert_eq!(client.state(), CircuitState::Closed);
+        assert_eq!(inner.call_count(), 3);
+    }
+}
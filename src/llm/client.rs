@@ -1,14 +1,18 @@
 use async_trait::async_trait;
 use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
-use crate::session::Message;
+use crate::net::EndpointResolution;
+use crate::session::{Message, MessageContent};
 use crate::tool::ToolDefinition;
 use super::openai::OpenAIClient;
+use super::profile::ProviderProfile;
+use super::signing::RequestSigner;
 
 /// Input for an LLM request.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMInput {
     /// The model to use
     pub model: String,
@@ -22,13 +26,88 @@ pub struct LLMInput {
     pub max_tokens: u32,
     /// Optional temperature (0.0 to 1.0)
     pub temperature: Option<f32>,
+    /// Per-request overrides of client-level settings
+    #[serde(default)]
+    pub request_options: RequestOptions,
+    /// Registry generation `tools` were built from, letting clients reuse
+    /// the provider-formatted tool array while it is unchanged
+    #[serde(skip)]
+    pub tool_generation: Option<u64>,
+}
+
+/// Per-request overrides applied on top of the client configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestOptions {
+    /// Overrides the client-level timeout for this request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<std::time::Duration>,
+    /// Additional HTTP headers sent with this request
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra_headers: HashMap<String, String>,
+    /// Additional top-level body fields; these win over generated fields
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra_body: serde_json::Map<String, serde_json::Value>,
+}
+
+impl RequestOptions {
+    /// Sets the timeout.
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Adds an HTTP header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds a body field.
+    pub fn with_body_field(mut self, name: impl Into<String>, value: serde_json::Value) -> Self {
+        self.extra_body.insert(name.into(), value);
+        self
+    }
+
+    /// Constrains which tools the model may call.
+    pub fn with_tool_choice(self, choice: ToolChoice) -> Self {
+        self.with_body_field("tool_choice", choice.to_value())
+    }
+}
+
+/// Which tools the model may call in a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools
+    Auto,
+    /// The model must not call tools
+    None,
+    /// The model must call at least one tool
+    Required,
+    /// The model must call the named tool
+    Tool(String),
+}
+
+impl ToolChoice {
+    /// Returns the OpenAI `tool_choice` value.
+    pub fn to_value(&self) -> serde_json::Value {
+        match self {
+            ToolChoice::Auto => "auto".into(),
+            ToolChoice::None => "none".into(),
+            ToolChoice::Required => "required".into(),
+            ToolChoice::Tool(name) => serde_json::json!({
+                "type": "function",
+                "function": { "name": name },
+            }),
+        }
+    }
 }
 
 /// Output from an LLM response.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMOutput {
     /// The content of the response
-    pub content: Vec<super::super::session::MessageContent>,
+    pub content: Vec<MessageContent>,
     /// The reason the response finished
     pub finish_reason: FinishReason,
     /// Token usage statistics
@@ -47,6 +126,25 @@ pub enum FinishReason {
     MaxTokens,
     /// Stopped due to an error
     Error,
+    /// The provider withheld or cut the output for moderation reasons
+    ContentFilter,
+    /// A reason the client does not recognize, as reported by the provider
+    Unknown(String),
+}
+
+impl FinishReason {
+    /// Maps an OpenAI-style `finish_reason` string, keeping unrecognized
+    /// values in `Unknown`.
+    pub fn from_provider(reason: &str) -> Self {
+        match reason {
+            "stop" | "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "tool_calls" | "function_call" | "tool_use" => FinishReason::ToolCalls,
+            "length" | "max_tokens" => FinishReason::MaxTokens,
+            "content_filter" | "refusal" => FinishReason::ContentFilter,
+            "error" => FinishReason::Error,
+            other => FinishReason::Unknown(other.to_string()),
+        }
+    }
 }
 
 /// Token usage statistics.
@@ -59,12 +157,17 @@ pub struct Usage {
 }
 
 /// Events from a streaming LLM response.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum LLMEvent {
     /// A text chunk was received
     TextDelta {
         text: String,
     },
+    /// A reasoning ("thinking") chunk was received
+    ThinkingDelta {
+        text: String,
+    },
     /// A tool call has started
     ToolCallStart {
         id: String,
@@ -90,6 +193,99 @@ pub enum LLMEvent {
     },
 }
 
+impl LLMOutput {
+    /// Converts the output into the equivalent sequence of stream events.
+    pub fn into_events(self) -> Vec<LLMEvent> {
+        let mut events = Vec::new();
+        for content in self.content {
+            match content {
+                MessageContent::Text { text } => events.push(LLMEvent::TextDelta { text }),
+                MessageContent::Thinking { thinking } => {
+                    events.push(LLMEvent::ThinkingDelta { text: thinking })
+                }
+                MessageContent::ToolCall { id, name, arguments } => {
+                    events.push(LLMEvent::ToolCallStart { id: id.clone(), name });
+                    events.push(LLMEvent::ToolCallDelta {
+                        id: id.clone(),
+                        arguments: arguments.to_string(),
+                    });
+                    events.push(LLMEvent::ToolCallEnd { id });
+                }
+                MessageContent::ToolResult { .. } | MessageContent::Image { .. } => {}
+            }
+        }
+        events.push(LLMEvent::Finish {
+            reason: self.finish_reason,
+            usage: self.usage,
+        });
+        events
+    }
+
+    /// Folds a sequence of stream events back into a complete output.
+    ///
+    /// Consecutive text and thinking deltas are merged, and tool call
+    /// argument chunks are concatenated before being parsed as JSON.
+    pub fn from_events(events: &[LLMEvent]) -> Self {
+        let mut content: Vec<MessageContent> = Vec::new();
+        let mut arguments: Vec<(String, String)> = Vec::new();
+        let mut finish_reason = FinishReason::Stop;
+        let mut usage = Usage {
+            input_tokens: 0,
+            output_tokens: 0,
+        };
+
+        for event in events {
+            match event {
+                LLMEvent::TextDelta { text } => match content.last_mut() {
+                    Some(MessageContent::Text { text: existing }) => existing.push_str(text),
+                    _ => content.push(MessageContent::Text { text: text.clone() }),
+                },
+                LLMEvent::ThinkingDelta { text } => match content.last_mut() {
+                    Some(MessageContent::Thinking { thinking }) => thinking.push_str(text),
+                    _ => content.push(MessageContent::Thinking {
+                        thinking: text.clone(),
+                    }),
+                },
+                LLMEvent::ToolCallStart { id, name } => {
+                    content.push(MessageContent::ToolCall {
+                        id: id.clone(),
+                        name: name.clone(),
+                        arguments: serde_json::Value::Null,
+                    });
+                    arguments.push((id.clone(), String::new()));
+                }
+                LLMEvent::ToolCallDelta { id, arguments: chunk } => {
+                    if let Some((_, buffer)) = arguments.iter_mut().find(|(call_id, _)| call_id == id) {
+                        buffer.push_str(chunk);
+                    }
+                }
+                LLMEvent::ToolCallEnd { .. } | LLMEvent::Error { .. } => {}
+                LLMEvent::Finish {
+                    reason,
+                    usage: final_usage,
+                } => {
+                    finish_reason = reason.clone();
+                    usage = final_usage.clone();
+                }
+            }
+        }
+
+        for item in &mut content {
+            if let MessageContent::ToolCall { id, arguments: value, .. } = item
+                && let Some((_, buffer)) = arguments.iter().find(|(call_id, _)| call_id == id)
+            {
+                *value = serde_json::from_str(buffer).unwrap_or(serde_json::Value::Null);
+            }
+        }
+
+        Self {
+            content,
+            finish_reason,
+            usage,
+        }
+    }
+}
+
 /// A stream of LLM events.
 pub type LLMStream = Pin<Box<dyn Stream<Item = Result<LLMEvent, LLMError>> + Send>>;
 
@@ -111,6 +307,22 @@ pub enum LLMError {
     /// Rate limit exceeded
     #[error("Rate limit exceeded: {0}")]
     RateLimitError(String),
+    /// The endpoint's circuit breaker is open and the request was not sent
+    #[error("Circuit open: {0}")]
+    CircuitOpen(String),
+}
+
+impl LLMError {
+    /// Returns whether the error is transient (API errors, rate limits,
+    /// timeouts and open circuits) and the request may succeed if retried or
+    /// sent elsewhere.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LLMError::ApiError(_) | LLMError::RateLimitError(_) | LLMError::CircuitOpen(_) => true,
+            LLMError::NetworkError(e) => e.is_timeout(),
+            LLMError::InvalidResponse(_) | LLMError::AuthError(_) => false,
+        }
+    }
 }
 
 /// Trait for LLM clients.
@@ -123,11 +335,26 @@ pub trait LLMClient: Send + Sync {
 }
 
 /// A builder for creating LLM clients.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct LLMClientBuilder {
     api_key: Option<String>,
     base_url: Option<String>,
     timeout: Option<std::time::Duration>,
+    profile: Option<ProviderProfile>,
+    signers: Vec<Arc<dyn RequestSigner>>,
+    resolution: EndpointResolution,
+}
+
+impl std::fmt::Debug for LLMClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LLMClientBuilder")
+            .field("base_url", &self.base_url)
+            .field("timeout", &self.timeout)
+            .field("profile", &self.profile)
+            .field("signers", &self.signers.len())
+            .field("resolution", &self.resolution)
+            .finish()
+    }
 }
 
 impl LLMClientBuilder {
@@ -154,14 +381,40 @@ impl LLMClientBuilder {
         self
     }
 
+    /// Sets the provider profile; detected from the base URL by default.
+    pub fn with_profile(mut self, profile: ProviderProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Adds a hook that mutates or signs every outgoing request.
+    pub fn with_signer(mut self, signer: Arc<dyn RequestSigner>) -> Self {
+        self.signers.push(signer);
+        self
+    }
+
+    /// Overrides host name resolution for the HTTP client.
+    pub fn with_endpoint_resolution(mut self, resolution: EndpointResolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
     /// Creates an OpenAI client.
     pub fn build_openai(self) -> Result<Arc<dyn LLMClient>, LLMError> {
-        Ok(Arc::new(OpenAIClient::new(
+        let mut client = OpenAIClient::with_endpoint_resolution(
             self.api_key
                 .or_else(|| std::env::var("OPENAI_API_KEY").ok())
                 .ok_or(LLMError::AuthError("OpenAI API key not provided".to_string()))?,
             self.base_url,
             self.timeout,
-        )))
+            &self.resolution,
+        );
+        if let Some(profile) = self.profile {
+            client = client.with_profile(profile);
+        }
+        for signer in self.signers {
+            client = client.with_signer(signer);
+        }
+        Ok(Arc::new(client))
     }
 }
@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+use super::{LLMClient, LLMError, LLMInput, LLMOutput, LLMStream};
+
+/// An LLM client that wraps an ordered list of clients and fails over to the
+/// next one when a request fails with a transient error.
+///
+/// Only API errors, rate limits and timeouts trigger a fallback; other errors
+/// (e.g. authentication failures) are returned immediately.
+#[derive(Clone)]
+pub struct FallbackLLMClient {
+    clients: Vec<Arc<dyn LLMClient>>,
+}
+
+impl FallbackLLMClient {
+    /// Creates a new fallback client. The first client is the primary one.
+    pub fn new(clients: Vec<Arc<dyn LLMClient>>) -> Self {
+        Self { clients }
+    }
+
+    /// Appends a client to the end of the fallback chain.
+    pub fn with_fallback(mut self, client: Arc<dyn LLMClient>) -> Self {
+        self.clients.push(client);
+        self
+    }
+
+    /// Returns the number of clients in the chain.
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Returns whether the chain is empty.
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+}
+
+impl std::fmt::Debug for FallbackLLMClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FallbackLLMClient")
+            .field("clients_count", &self.clients.len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl LLMClient for FallbackLLMClient {
+    async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
+        let mut last_error = None;
+
+        for (index, client) in self.clients.iter().enumerate() {
+            match client.stream(input.clone()).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) if e.is_transient() && index + 1 < self.clients.len() => {
+                    warn!(index, error = %e, "LLM client failed, falling back to next client");
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            LLMError::ApiError("No LLM clients configured".to_string())
+        }))
+    }
+
+    async fn complete(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
+        let mut last_error = None;
+
+        for (index, client) in self.clients.iter().enumerate() {
+            match client.complete(input.clone()).await {
+                Ok(output) => return Ok(output),
+                Err(e) if e.is_transient() && index + 1 < self.clients.len() => {
+                    warn!(index, error = %e, "LLM client failed, falling back to next client");
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            LLMError::ApiError("No LLM clients configured".to_string())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{FinishReason, Usage};
+    use crate::session::MessageContent;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct StubClient {
+        error: Option<fn() -> LLMError>,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMClient for StubClient {
+        async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
+            let output = self.complete(input).await?;
+            Ok(Box::pin(futures::stream::iter(output.into_events().into_iter().map(Ok))))
+        }
+
+        async fn complete(&self, _input: LLMInput) -> Result<LLMOutput, LLMError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.error {
+                Some(error) => Err(error()),
+                None => Ok(LLMOutput {
+                    content: vec![MessageContent::Text { text: "ok".to_string() }],
+                    finish_reason: FinishReason::Stop,
+                    usage: Usage { input_tokens: 0, output_tokens: 0 },
+                }),
+            }
+        }
+    }
+
+    fn input() -> LLMInput {
+        LLMInput {
+            model: "test".to_string(),
+            messages: Vec::new(),
+            system_prompt: String::new(),
+            tools: Vec::new(),
+            max_tokens: 16,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_transient_error() {
+        let primary = Arc::new(StubClient {
+            error: Some(|| LLMError::RateLimitError("slow down".to_string())),
+            calls: AtomicUsize::new(0),
+        });
+        let secondary = Arc::new(StubClient { error: None, calls: AtomicUsize::new(0) });
+        let client = FallbackLLMClient::new(vec![primary.clone(), secondary.clone()]);
+
+        let output = client.complete(input()).await.unwrap();
+
+        assert!(matches!(output.finish_reason, FinishReason::Stop));
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_fall_back_on_auth_error() {
+        let primary = Arc::new(StubClient {
+            error: Some(|| LLMError::AuthError("bad key".to_string())),
+            calls: AtomicUsize::new(0),
+        });
+        let secondary = Arc::new(StubClient { error: None, calls: AtomicUsize::new(0) });
+        let client = FallbackLLMClient::new(vec![primary, secondary.clone()]);
+
+        let result = client.complete(input()).await;
+
+        assert!(matches!(result, Err(LLMError::AuthError(_))));
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 0);
+    }
+}
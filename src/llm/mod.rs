@@ -1,5 +1,23 @@
+pub mod adapter;
+pub mod cassette;
+pub mod circuit_breaker;
 pub mod client;
+pub mod fallback;
 pub mod openai;
+pub mod pricing;
+pub mod profile;
+pub mod signing;
+pub mod tokens;
 
-pub use client::{LLMClient, LLMInput, LLMOutput, LLMEvent, LLMStream, FinishReason, Usage, LLMError};
+pub use adapter::{OpenAIAdapter, ProviderAdapter, StreamState};
+pub use cassette::{Cassette, Interaction, RecordedResponse, RecordingLLMClient, ReplayLLMClient};
+pub use circuit_breaker::{CircuitBreakerLLMClient, CircuitState};
+pub use client::{LLMClient, LLMInput, RequestOptions, ToolChoice, LLMOutput, LLMEvent, LLMStream, FinishReason, Usage, LLMError};
+pub use fallback::FallbackLLMClient;
 pub use openai::OpenAIClient;
+pub use pricing::{ModelPricing, PricingTable};
+pub use profile::{IdFormat, MaxTokensField, ProviderProfile};
+pub use signing::{RequestSigner, StaticHeaders};
+pub use tokens::{counter_for_model, TokenCounter, HeuristicTokenCounter};
+#[cfg(feature = "tiktoken")]
+pub use tokens::TiktokenCounter;
@@ -1,125 +1,41 @@
 use async_trait::async_trait;
 use async_stream::stream;
 use futures::stream::StreamExt;
-use reqwest::{Client, RequestBuilder};
-use serde::{Deserialize, Serialize};
+use reqwest::Client;
 use serde_json::Value;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::debug;
 
-use super::{LLMClient, LLMInput, LLMOutput, LLMStream, LLMEvent, FinishReason, Usage, LLMError};
-use crate::session::{MessageContent, MessageRole};
-
-/// OpenAI API response for chat completions.
-#[derive(Debug, Deserialize)]
-struct ChatCompletionResponse {
-    #[serde(default)]
-    id: String,
-    #[serde(default)]
-    object: String,
-    #[serde(default)]
-    created: u64,
-    #[serde(default)]
-    model: String,
-    #[serde(default)]
-    choices: Vec<Choice>,
-    #[serde(default)]
-    usage: UsageInfo,
-}
-
-#[derive(Debug, Deserialize)]
-struct Choice {
-    #[serde(default)]
-    index: u32,
-    message: MessageResponse,
-    finish_reason: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct MessageResponse {
-    #[serde(default)]
-    role: String,
-    content: Option<String>,
-    tool_calls: Option<Vec<ToolCall>>,
-}
+use super::adapter::{OpenAIAdapter, ProviderAdapter, StreamState};
+use super::profile::ProviderProfile;
+use super::signing::RequestSigner;
+use super::{LLMClient, LLMInput, LLMOutput, LLMStream, LLMError};
+use crate::net::EndpointResolution;
 
-#[derive(Debug, Deserialize)]
-struct ToolCall {
-    #[serde(default)]
-    id: String,
-    #[serde(rename = "type", default)]
-    call_type: String,
-    function: FunctionCall,
-}
-
-#[derive(Debug, Deserialize)]
-struct FunctionCall {
-    #[serde(default)]
-    name: String,
-    #[serde(default)]
-    arguments: String,
-}
-
-#[derive(Debug, Default, Deserialize)]
-struct UsageInfo {
-    #[serde(default)]
-    prompt_tokens: u32,
-    #[serde(default)]
-    completion_tokens: u32,
-    #[serde(default)]
-    total_tokens: u32,
-}
-
-/// Streaming response chunk.
-#[derive(Debug, Deserialize)]
-struct ChatCompletionChunk {
-    #[serde(default)]
-    id: String,
-    #[serde(default)]
-    object: String,
-    #[serde(default)]
-    created: u64,
-    #[serde(default)]
-    model: String,
-    choices: Vec<ChunkChoice>,
-}
-
-#[derive(Debug, Deserialize)]
-struct ChunkChoice {
-    #[serde(default)]
-    index: u32,
-    delta: Delta,
-    finish_reason: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct Delta {
-    #[serde(default)]
-    role: Option<String>,
-    content: Option<String>,
-    tool_calls: Option<Vec<ChunkToolCall>>,
-}
-
-#[derive(Debug, Deserialize)]
-struct ChunkToolCall {
-    #[serde(default)]
-    id: String,
-    #[serde(rename = "type", default)]
-    call_type: String,
-    function: ChunkFunctionCall,
-}
-
-#[derive(Debug, Deserialize)]
-struct ChunkFunctionCall {
-    name: Option<String>,
-    arguments: Option<String>,
-}
+/// A formatted tool array and the registry generation it was built from.
+type CachedTools = Mutex<Option<(u64, Arc<Value>)>>;
 
 /// An LLM client for OpenAI's API.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct OpenAIClient {
     client: Client,
     base_url: String,
+    profile: ProviderProfile,
+    signers: Vec<Arc<dyn RequestSigner>>,
+    adapter: Arc<dyn ProviderAdapter>,
+    /// Formatted tool array of the last registry generation seen
+    tool_cache: Arc<CachedTools>,
+}
+
+impl std::fmt::Debug for OpenAIClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenAIClient")
+            .field("base_url", &self.base_url)
+            .field("profile", &self.profile)
+            .field("signers", &self.signers.len())
+            .finish()
+    }
 }
 
 impl OpenAIClient {
@@ -128,6 +44,17 @@ impl OpenAIClient {
         api_key: String,
         base_url: Option<String>,
         timeout: Option<Duration>,
+    ) -> Self {
+        Self::with_endpoint_resolution(api_key, base_url, timeout, &EndpointResolution::default())
+    }
+
+    /// Creates a new OpenAI client whose host names are resolved through
+    /// `resolution` instead of system DNS.
+    pub fn with_endpoint_resolution(
+        api_key: String,
+        base_url: Option<String>,
+        timeout: Option<Duration>,
+        resolution: &EndpointResolution,
     ) -> Self {
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert(
@@ -140,9 +67,11 @@ impl OpenAIClient {
             reqwest::header::HeaderValue::from_static("application/json"),
         );
 
-        let mut client_builder = reqwest::Client::builder()
-            .default_headers(headers)
-            .http1_title_case_headers();
+        let mut client_builder = resolution.apply(
+            reqwest::Client::builder()
+                .default_headers(headers)
+                .http1_title_case_headers(),
+        );
 
         if let Some(timeout) = timeout {
             client_builder = client_builder.timeout(timeout);
@@ -150,157 +79,99 @@ impl OpenAIClient {
 
         let client = client_builder.build().expect("Failed to build HTTP client");
 
+        let base_url = base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+        let profile = ProviderProfile::detect(&base_url);
+
         Self {
             client,
-            base_url: base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
+            base_url,
+            profile,
+            signers: Vec::new(),
+            adapter: Arc::new(OpenAIAdapter),
+            tool_cache: Arc::new(Mutex::new(None)),
         }
     }
 
-    /// Creates a request builder for chat completions.
-    fn chat_completions_request(&self, input: &LLMInput) -> RequestBuilder {
-        // Note: MiniMax API does not support the OpenAI tool format
-        // Tools will be skipped for now - this can be extended for APIs that support tools
-        let tools: Vec<Value> = Vec::new();
-
-        let body = ChatRequest {
-            model: input.model.clone(),
-            messages: Self::build_messages(input),
-            tools: if tools.is_empty() { None } else { Some(tools) },
-            max_tokens: Some(input.max_tokens),
-            temperature: input.temperature,
-            stream: false,
-        };
+    /// Replaces the wire format adapter, e.g. for a gateway that deviates
+    /// from the OpenAI format.
+    pub fn with_adapter(mut self, adapter: Arc<dyn ProviderAdapter>) -> Self {
+        self.adapter = adapter;
+        self.tool_cache = Arc::new(Mutex::new(None));
+        self
+    }
 
-        debug!(model = %input.model, "Sending request to OpenAI");
+    /// Adds a hook that mutates or signs every outgoing request.
+    pub fn with_signer(mut self, signer: Arc<dyn RequestSigner>) -> Self {
+        self.signers.push(signer);
+        self
+    }
 
-        self.client
-            .post(&format!("{}/chat/completions", self.base_url))
-            .json(&body)
+    /// Overrides the provider profile detected from the base URL.
+    pub fn with_profile(mut self, profile: ProviderProfile) -> Self {
+        self.profile = profile;
+        self
     }
 
-    /// Builds messages for the API request.
-    fn build_messages(input: &LLMInput) -> Vec<Value> {
-        let mut messages = Vec::new();
+    /// Returns the provider profile used to serialize requests.
+    pub fn profile(&self) -> &ProviderProfile {
+        &self.profile
+    }
 
-        // Add system prompt
-        if !input.system_prompt.is_empty() {
-            messages.push(serde_json::json!({
-                "role": "system",
-                "content": input.system_prompt
-            }));
+    /// Returns the formatted tool array, reusing the cached one while the
+    /// registry generation is unchanged.
+    fn tools_json(&self, input: &LLMInput) -> Arc<Value> {
+        let Some(generation) = input.tool_generation else {
+            return Arc::new(self.adapter.build_tools(&input.tools));
+        };
+        let mut cache = self.tool_cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((cached, tools)) = &*cache
+            && *cached == generation
+        {
+            return tools.clone();
         }
+        let tools = Arc::new(self.adapter.build_tools(&input.tools));
+        *cache = Some((generation, tools.clone()));
+        tools
+    }
 
-        // Add conversation messages
-        for msg in &input.messages {
-            match msg.role {
-                MessageRole::User => {
-                    messages.push(serde_json::json!({
-                        "role": "user",
-                        "content": Self::content_to_string(&msg.content)
-                    }));
-                }
-                MessageRole::Assistant => {
-                    let tool_calls = msg.content.iter().filter_map(|c| {
-                        if let MessageContent::ToolCall { id, name, arguments } = c {
-                            Some(serde_json::json!({
-                                "id": id,
-                                "type": "function",
-                                "function": {
-                                    "name": name,
-                                    "arguments": arguments.to_string()
-                                }
-                            }))
-                        } else {
-                            None
-                        }
-                    }).collect::<Vec<_>>();
-
-                    if !tool_calls.is_empty() {
-                        messages.push(serde_json::json!({
-                            "role": "assistant",
-                            "content": null,
-                            "tool_calls": tool_calls
-                        }));
-                    } else {
-                        messages.push(serde_json::json!({
-                            "role": "assistant",
-                            "content": Self::content_to_string(&msg.content)
-                        }));
-                    }
-                }
-                MessageRole::Tool => {
-                    for content in &msg.content {
-                        if let MessageContent::ToolResult {
-                            tool_call_id,
-                            result,
-                            is_error: _,
-                        } = content
-                        {
-                            messages.push(serde_json::json!({
-                                "role": "tool",
-                                "tool_call_id": tool_call_id,
-                                "content": result
-                            }));
-                        }
-                    }
-                }
-            }
+    /// Builds, signs and sends a chat completions request.
+    async fn send_chat_completions(
+        &self,
+        input: &LLMInput,
+        stream: bool,
+    ) -> Result<reqwest::Response, LLMError> {
+        debug!(model = %input.model, provider = %self.profile.name, stream, "Sending request to OpenAI");
+
+        let options = &input.request_options;
+        let mut builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&self.adapter.build_request_body(&self.profile, input, &self.tools_json(input), stream));
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
         }
+        for (name, value) in &options.extra_headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let mut request = builder.build().map_err(LLMError::NetworkError)?;
 
-        messages
-    }
+        for signer in &self.signers {
+            signer.sign(&mut request).await?;
+        }
 
-    /// Converts message content to a string.
-    fn content_to_string(content: &[MessageContent]) -> String {
-        content
-            .iter()
-            .filter_map(|c| {
-                if let MessageContent::Text { text } = c {
-                    Some(text.clone())
-                } else {
-                    None
-                }
-            })
-            .collect()
+        self.client
+            .execute(request)
+            .await
+            .map_err(LLMError::NetworkError)
     }
 }
 
-#[derive(Serialize)]
-struct ChatRequest {
-    model: String,
-    messages: Vec<Value>,
-    tools: Option<Vec<Value>>,
-    max_tokens: Option<u32>,
-    temperature: Option<f32>,
-    stream: bool,
-}
-
 #[async_trait]
 impl LLMClient for OpenAIClient {
     async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
-        let client = self.client.clone();
-        let base_url = self.base_url.clone();
-
-        // Note: MiniMax API does not support the OpenAI tool format
-        let tools: Vec<Value> = Vec::new();
-
-        let body = ChatRequest {
-            model: input.model.clone(),
-            messages: Self::build_messages(&input),
-            tools: if tools.is_empty() { None } else { Some(tools) },
-            max_tokens: Some(input.max_tokens),
-            temperature: input.temperature,
-            stream: true,
-        };
+        let include_usage = self.profile.stream_options;
 
-        debug!(model = %input.model, "Starting streaming request to OpenAI");
-
-        let response = client
-            .post(&format!("{}/chat/completions", base_url))
-            .json(&body)
-            .send()
-            .await
-            .map_err(LLMError::NetworkError)?;
+        let response = self.send_chat_completions(&input, true).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.map_err(LLMError::NetworkError)?;
@@ -309,10 +180,10 @@ impl LLMClient for OpenAIClient {
 
         let mut stream = response.bytes_stream();
 
+        let adapter = self.adapter.clone();
+
         let s = stream! {
-            let mut buffer = String::new();
-            let mut current_tool_id: Option<String> = None;
-            let mut current_tool_name: Option<String> = None;
+            let mut state = StreamState::new(include_usage);
 
             while let Some(chunk) = stream.next().await {
                 let chunk = match chunk {
@@ -325,146 +196,43 @@ impl LLMClient for OpenAIClient {
 
                 let line = String::from_utf8_lossy(&chunk);
 
-                if line.starts_with("data: ") {
-                    let data = &line[6..];
+                if let Some(data) = line.strip_prefix("data: ") {
                     if data == "[DONE]" {
                         break;
                     }
 
-                    match serde_json::from_str::<ChatCompletionChunk>(data) {
-                        Ok(chunk) => {
-                            for choice in chunk.choices {
-                                if let Some(ref delta) = choice.delta.content {
-                                    yield Ok(LLMEvent::TextDelta {
-                                        text: delta.clone()
-                                    });
-                                    buffer.clear();
-                                }
-
-                                if let Some(ref tool_calls) = choice.delta.tool_calls {
-                                    for tool_call in tool_calls {
-                                        if let Some(ref name) = tool_call.function.name {
-                                            current_tool_id = Some(tool_call.id.clone());
-                                            current_tool_name = Some(name.clone());
-                                            yield Ok(LLMEvent::ToolCallStart {
-                                                id: tool_call.id.clone(),
-                                                name: name.clone(),
-                                            });
-                                        }
-
-                                        if let Some(ref args) = tool_call.function.arguments {
-                                            buffer.push_str(args);
-                                            yield Ok(LLMEvent::ToolCallDelta {
-                                                id: tool_call.id.clone(),
-                                                arguments: args.clone(),
-                                            });
-                                        }
-                                    }
-                                }
-
-                                if let Some(ref reason) = choice.finish_reason {
-                                    if reason == "tool_calls" {
-                                        if let (Some(_id), Some(_name)) = (current_tool_id.take(), current_tool_name.take()) {
-                                            // Tool call ended
-                                        }
-                                    }
-
-                                    let finish_reason = match reason.as_str() {
-                                        "stop" => FinishReason::Stop,
-                                        "tool_calls" => FinishReason::ToolCalls,
-                                        "length" => FinishReason::MaxTokens,
-                                        _ => FinishReason::Error,
-                                    };
-
-                                    yield Ok(LLMEvent::Finish {
-                                        reason: finish_reason,
-                                        usage: Usage {
-                                            input_tokens: 0,
-                                            output_tokens: 0,
-                                        },
-                                    });
-                                }
+                    match adapter.parse_stream_event(data, &mut state) {
+                        Ok(events) => {
+                            for event in events {
+                                yield Ok(event);
                             }
                         }
                         Err(e) => {
-                            tracing::debug!("Failed to parse chunk: {:?}", e);
+                            yield Err(e);
+                            return;
                         }
                     }
                 }
             }
+
+            if let Some(event) = state.finish() {
+                yield Ok(event);
+            }
         };
 
         Ok(Box::pin(s))
     }
 
     async fn complete(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
-        let request = self.chat_completions_request(&input);
-
-        let response_text = request
-            .send()
-            .await
-            .map_err(LLMError::NetworkError)?
+        let response_text = self
+            .send_chat_completions(&input, false)
+            .await?
             .text()
             .await
             .map_err(|e| LLMError::InvalidResponse(e.to_string()))?;
 
         tracing::debug!("LLM response: {}", response_text);
 
-        let response: ChatCompletionResponse = serde_json::from_str(&response_text)
-            .map_err(|e| LLMError::InvalidResponse(format!("{}: {}", e, response_text)))?;
-
-        if response.choices.is_empty() {
-            return Err(LLMError::InvalidResponse(
-                format!("No choices in response. Response: {}", response_text)
-            ));
-        }
-
-        let mut content = Vec::new();
-
-        for choice in response.choices {
-            if let Some(ref tool_calls) = choice.message.tool_calls {
-                for tool_call in tool_calls {
-                    let arguments: Value = if tool_call.function.arguments.is_empty() {
-                        serde_json::json!({})
-                    } else {
-                        serde_json::from_str(&tool_call.function.arguments)
-                            .unwrap_or(serde_json::json!({}))
-                    };
-
-                    content.push(MessageContent::ToolCall {
-                        id: tool_call.id.clone(),
-                        name: tool_call.function.name.clone(),
-                        arguments,
-                    });
-                }
-            }
-
-            if let Some(ref text) = choice.message.content {
-                if !text.is_empty() {
-                    content.push(MessageContent::Text {
-                        text: text.clone(),
-                    });
-                }
-            }
-
-            let finish_reason = match choice.finish_reason.as_deref() {
-                Some("stop") => FinishReason::Stop,
-                Some("tool_calls") => FinishReason::ToolCalls,
-                Some("length") => FinishReason::MaxTokens,
-                _ => FinishReason::Error,
-            };
-
-            return Ok(LLMOutput {
-                content,
-                finish_reason,
-                usage: Usage {
-                    input_tokens: response.usage.prompt_tokens,
-                    output_tokens: response.usage.completion_tokens,
-                },
-            });
-        }
-
-        // If we get here without returning, something went wrong
-        Err(LLMError::InvalidResponse("No choices in response".to_string()))
+        self.adapter.parse_response(&response_text)
     }
 }
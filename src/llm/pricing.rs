@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::Usage;
+
+/// Price of a model in US dollars per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Price per million input (prompt) tokens
+    pub input_per_million: f64,
+    /// Price per million output (completion) tokens
+    pub output_per_million: f64,
+}
+
+impl ModelPricing {
+    /// Creates a new pricing entry.
+    pub fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+
+    /// Returns the estimated cost of the given usage in US dollars.
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input_per_million
+            + usage.output_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// A table of model prices used to estimate the cost of requests.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PricingTable {
+    models: HashMap<String, ModelPricing>,
+}
+
+impl PricingTable {
+    /// Creates an empty pricing table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a table pre-populated with list prices for common models.
+    pub fn with_defaults() -> Self {
+        Self::new()
+            .with_model("gpt-4o", ModelPricing::new(2.50, 10.00))
+            .with_model("gpt-4o-mini", ModelPricing::new(0.15, 0.60))
+            .with_model("gpt-4.1", ModelPricing::new(2.00, 8.00))
+            .with_model("gpt-4.1-mini", ModelPricing::new(0.40, 1.60))
+            .with_model("gpt-4.1-nano", ModelPricing::new(0.10, 0.40))
+            .with_model("o3-mini", ModelPricing::new(1.10, 4.40))
+            .with_model("deepseek-chat", ModelPricing::new(0.27, 1.10))
+            .with_model("deepseek-reasoner", ModelPricing::new(0.55, 2.19))
+    }
+
+    /// Adds or replaces the pricing for a model.
+    pub fn with_model(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.set(model, pricing);
+        self
+    }
+
+    /// Adds or replaces the pricing for a model.
+    pub fn set(&mut self, model: impl Into<String>, pricing: ModelPricing) {
+        self.models.insert(model.into(), pricing);
+    }
+
+    /// Looks up the pricing for a model.
+    ///
+    /// Falls back to the longest configured name that prefixes `model`, so
+    /// dated snapshots like `gpt-4o-2024-08-06` resolve to `gpt-4o`.
+    pub fn get(&self, model: &str) -> Option<&ModelPricing> {
+        self.models.get(model).or_else(|| {
+            self.models
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, pricing)| pricing)
+        })
+    }
+
+    /// Returns the estimated cost of the usage for a model, if it is priced.
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.get(model).map(|pricing| pricing.cost(usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_uses_longest_prefix() {
+        let table = PricingTable::with_defaults();
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 500_000,
+        };
+
+        assert_eq!(table.cost("gpt-4o-2024-08-06", &usage), Some(7.5));
+        assert_eq!(table.cost("gpt-4o-mini-2024-07-18", &usage), Some(0.45));
+        assert_eq!(table.cost("unknown-model", &usage), None);
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The request field used to cap generated tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaxTokensField {
+    /// The classic `max_tokens` field
+    MaxTokens,
+    /// OpenAI's newer `max_completion_tokens` field
+    MaxCompletionTokens,
+}
+
+impl MaxTokensField {
+    /// Returns the JSON field name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaxTokensField::MaxTokens => "max_tokens",
+            MaxTokensField::MaxCompletionTokens => "max_completion_tokens",
+        }
+    }
+}
+
+/// Constraints a provider places on the tool call ids it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdFormat {
+    /// Maximum id length in bytes
+    pub max_len: usize,
+    /// Whether only ASCII letters and digits are allowed (otherwise `-` and
+    /// `_` are accepted as well)
+    pub alphanumeric_only: bool,
+}
+
+impl Default for IdFormat {
+    fn default() -> Self {
+        Self {
+            max_len: 40,
+            alphanumeric_only: false,
+        }
+    }
+}
+
+impl IdFormat {
+    /// Returns whether the provider accepts the id as is.
+    pub fn accepts(&self, id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= self.max_len
+            && id.chars().all(|c| {
+                c.is_ascii_alphanumeric() || (!self.alphanumeric_only && (c == '-' || c == '_'))
+            })
+    }
+
+    /// Maps an id to one the provider accepts.
+    ///
+    /// Accepted ids pass through unchanged; others are replaced by a hash of
+    /// the original, so the same id always maps to the same provider id and
+    /// tool calls stay paired with their results across requests.
+    pub(crate) fn map<'a>(&self, id: &'a str) -> Cow<'a, str> {
+        if self.accepts(id) {
+            return Cow::Borrowed(id);
+        }
+
+        // FNV-1a, which unlike the std hasher is stable across releases
+        let hash = id.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        let prefix = if self.alphanumeric_only { "" } else { "call_" };
+        let mut mapped = format!("{}{:016x}", prefix, hash);
+        mapped.truncate(self.max_len);
+        Cow::Owned(mapped)
+    }
+}
+
+/// Capabilities of an OpenAI-compatible backend, used to adjust how chat
+/// completion requests are serialized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderProfile {
+    /// Human-readable provider name, used in logs
+    pub name: String,
+    /// Whether tool definitions can be sent
+    pub tools: bool,
+    /// Whether the `parallel_tool_calls` flag is accepted
+    pub parallel_tool_calls: bool,
+    /// Whether `stream_options.include_usage` is accepted when streaming
+    pub stream_options: bool,
+    /// Which field carries the token limit
+    pub max_tokens_field: MaxTokensField,
+    /// Which tool call ids are accepted
+    #[serde(default)]
+    pub id_format: IdFormat,
+    /// Whether tool messages accept an array of text content parts, used for
+    /// structured tool results
+    #[serde(default)]
+    pub multipart_tool_results: bool,
+}
+
+impl ProviderProfile {
+    /// A conservative profile for unknown OpenAI-compatible servers.
+    pub fn generic() -> Self {
+        Self {
+            name: "generic".to_string(),
+            tools: true,
+            parallel_tool_calls: false,
+            stream_options: false,
+            max_tokens_field: MaxTokensField::MaxTokens,
+            id_format: IdFormat::default(),
+            multipart_tool_results: false,
+        }
+    }
+
+    /// OpenAI's own API.
+    pub fn openai() -> Self {
+        Self {
+            name: "openai".to_string(),
+            tools: true,
+            parallel_tool_calls: true,
+            stream_options: true,
+            max_tokens_field: MaxTokensField::MaxCompletionTokens,
+            id_format: IdFormat::default(),
+            multipart_tool_results: true,
+        }
+    }
+
+    /// MiniMax, which does not accept OpenAI-format tool definitions.
+    pub fn minimax() -> Self {
+        Self {
+            name: "minimax".to_string(),
+            tools: false,
+            ..Self::generic()
+        }
+    }
+
+    /// Groq.
+    pub fn groq() -> Self {
+        Self {
+            name: "groq".to_string(),
+            parallel_tool_calls: true,
+            ..Self::generic()
+        }
+    }
+
+    /// Together AI.
+    pub fn together() -> Self {
+        Self {
+            name: "together".to_string(),
+            ..Self::generic()
+        }
+    }
+
+    /// OpenRouter.
+    pub fn openrouter() -> Self {
+        Self {
+            name: "openrouter".to_string(),
+            parallel_tool_calls: true,
+            stream_options: true,
+            ..Self::generic()
+        }
+    }
+
+    /// Mistral, which requires 9-character alphanumeric tool call ids.
+    pub fn mistral() -> Self {
+        Self {
+            name: "mistral".to_string(),
+            id_format: IdFormat {
+                max_len: 9,
+                alphanumeric_only: true,
+            },
+            ..Self::generic()
+        }
+    }
+
+    /// A vLLM server.
+    pub fn vllm() -> Self {
+        Self {
+            name: "vllm".to_string(),
+            stream_options: true,
+            ..Self::generic()
+        }
+    }
+
+    /// Picks a profile from the API base URL, falling back to `generic`.
+    pub fn detect(base_url: &str) -> Self {
+        let url = base_url.to_ascii_lowercase();
+        if url.contains("api.openai.com") {
+            Self::openai()
+        } else if url.contains("minimax") {
+            Self::minimax()
+        } else if url.contains("groq.com") {
+            Self::groq()
+        } else if url.contains("together") {
+            Self::together()
+        } else if url.contains("openrouter.ai") {
+            Self::openrouter()
+        } else if url.contains("mistral.ai") {
+            Self::mistral()
+        } else {
+            Self::generic()
+        }
+    }
+}
+
+impl Default for ProviderProfile {
+    fn default() -> Self {
+        Self::generic()
+    }
+}
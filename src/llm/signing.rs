@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use reqwest::header::{HeaderName, HeaderValue};
+
+use super::LLMError;
+
+/// A hook that can mutate or sign outgoing provider HTTP requests before
+/// they are sent.
+///
+/// Signers run in the order they were added, after the body and default
+/// headers are set, so they can hash the final body (for example HMAC
+/// gateway headers or AWS SigV4 for proxies). Closures of the form
+/// `Fn(&mut reqwest::Request) -> Result<(), LLMError>` implement this trait.
+#[async_trait]
+pub trait RequestSigner: Send + Sync {
+    /// Mutates the request, typically by adding authentication headers.
+    async fn sign(&self, request: &mut reqwest::Request) -> Result<(), LLMError>;
+}
+
+#[async_trait]
+impl<F> RequestSigner for F
+where
+    F: Fn(&mut reqwest::Request) -> Result<(), LLMError> + Send + Sync,
+{
+    async fn sign(&self, request: &mut reqwest::Request) -> Result<(), LLMError> {
+        self(request)
+    }
+}
+
+/// A signer that adds fixed headers to every request.
+#[derive(Debug, Clone, Default)]
+pub struct StaticHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl StaticHeaders {
+    /// Creates an empty header set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a header, failing if the name or value is not valid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self, LLMError> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| LLMError::AuthError(format!("Invalid header name `{}`: {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| LLMError::AuthError(format!("Invalid value for header `{}`: {}", name, e)))?;
+        self.headers.push((name, value));
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl RequestSigner for StaticHeaders {
+    async fn sign(&self, request: &mut reqwest::Request) -> Result<(), LLMError> {
+        for (name, value) in &self.headers {
+            request.headers_mut().insert(name.clone(), value.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signers_see_final_body() {
+        let mut request = reqwest::Client::new()
+            .post("http://localhost/v1/chat/completions")
+            .body("{\"model\":\"m\"}")
+            .build()
+            .unwrap();
+
+        let body_length = |request: &mut reqwest::Request| -> Result<(), LLMError> {
+            let length = request.body().and_then(|b| b.as_bytes()).map_or(0, |b| b.len());
+            request
+                .headers_mut()
+                .insert("x-body-length", HeaderValue::from(length));
+            Ok(())
+        };
+        body_length.sign(&mut request).await.unwrap();
+        StaticHeaders::new()
+            .with_header("x-gateway", "internal")
+            .unwrap()
+            .sign(&mut request)
+            .await
+            .unwrap();
+
+        assert_eq!(request.headers()["x-body-length"], "13");
+        assert_eq!(request.headers()["x-gateway"], "internal");
+    }
+}
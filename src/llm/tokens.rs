@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::session::{Message, MessageContent};
+
+use super::LLMInput;
+
+/// Approximate per-message token overhead of chat formatting.
+const MESSAGE_OVERHEAD: usize = 4;
+/// Rough prompt cost of one image.
+const IMAGE_TOKENS: usize = 765;
+
+/// Counts tokens so prompt sizes can be checked before calling the LLM.
+pub trait TokenCounter: Send + Sync {
+    /// Counts the tokens in a piece of text.
+    fn count_text(&self, text: &str) -> usize;
+
+    /// Counts the tokens of a single message.
+    fn count_message(&self, message: &Message) -> usize {
+        let content: usize = message
+            .content
+            .iter()
+            .map(|c| match c {
+                MessageContent::Text { text } => self.count_text(text),
+                // Reasoning is not sent back to the provider
+                MessageContent::Thinking { .. } => 0,
+                MessageContent::ToolCall { name, arguments, .. } => {
+                    self.count_text(name) + self.count_text(&arguments.to_string())
+                }
+                MessageContent::ToolResult { result, .. } => self.count_text(result),
+                // Providers bill images by resolution; use a typical tile cost
+                MessageContent::Image { .. } => IMAGE_TOKENS,
+            })
+            .sum();
+        content + MESSAGE_OVERHEAD
+    }
+
+    /// Counts the prompt tokens of a full LLM request.
+    fn count_input(&self, input: &LLMInput) -> usize {
+        let system = if input.system_prompt.is_empty() {
+            0
+        } else {
+            self.count_text(&input.system_prompt) + MESSAGE_OVERHEAD
+        };
+        let messages: usize = input.messages.iter().map(|m| self.count_message(m)).sum();
+        let tools: usize = input
+            .tools
+            .iter()
+            .map(|t| {
+                self.count_text(&t.name)
+                    + self.count_text(&t.description)
+                    + self.count_text(&t.input_schema.to_string())
+            })
+            .sum();
+        system + messages + tools
+    }
+}
+
+/// A dependency-free counter that estimates roughly four characters per token.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenCounter;
+
+impl TokenCounter for HeuristicTokenCounter {
+    fn count_text(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// An exact counter backed by OpenAI's BPE tokenizers.
+#[cfg(feature = "tiktoken")]
+pub struct TiktokenCounter {
+    bpe: tiktoken_rs::CoreBPE,
+}
+
+#[cfg(feature = "tiktoken")]
+impl TiktokenCounter {
+    /// Creates a counter for the given model, falling back to `o200k_base`
+    /// for models tiktoken does not know about. Returns `None` if the
+    /// tokenizer cannot be loaded.
+    pub fn for_model(model: &str) -> Option<Self> {
+        let bpe = tiktoken_rs::get_bpe_from_model(model)
+            .or_else(|_| tiktoken_rs::o200k_base())
+            .ok()?;
+        Some(Self { bpe })
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+impl TokenCounter for TiktokenCounter {
+    fn count_text(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+/// Returns the most accurate counter available for the model: tiktoken's
+/// when the `tiktoken` feature is enabled and its tokenizer loads, the
+/// heuristic counter otherwise.
+pub fn counter_for_model(model: &str) -> Arc<dyn TokenCounter> {
+    #[cfg(feature = "tiktoken")]
+    match TiktokenCounter::for_model(model) {
+        Some(counter) => return Arc::new(counter),
+        None => tracing::warn!(model, "Failed to load tokenizer; estimating tokens instead"),
+    }
+    #[cfg(not(feature = "tiktoken"))]
+    let _ = model;
+    Arc::new(HeuristicTokenCounter)
+}
+
+/// Returns the context window size of well-known models, in tokens.
+pub fn context_window(model: &str) -> Option<u32> {
+    const WINDOWS: &[(&str, u32)] = &[
+        ("gpt-4.1", 1_047_576),
+        ("gpt-4o", 128_000),
+        ("gpt-4-turbo", 128_000),
+        ("gpt-4", 8_192),
+        ("gpt-3.5-turbo", 16_385),
+        ("o1", 200_000),
+        ("o3", 200_000),
+        ("o4-mini", 200_000),
+        ("deepseek", 64_000),
+        ("MiniMax-M2", 204_800),
+    ];
+
+    WINDOWS
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, window)| *window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heuristic_counter_and_context_window() {
+        let counter = HeuristicTokenCounter;
+        assert_eq!(counter.count_text(""), 0);
+        assert_eq!(counter.count_text("abcdefgh"), 2);
+        assert_eq!(counter.count_text("abcdefghi"), 3);
+
+        assert_eq!(context_window("gpt-4o-mini"), Some(128_000));
+        assert_eq!(context_window("gpt-4-0613"), Some(8_192));
+        assert_eq!(context_window("my-local-model"), None);
+    }
+
+    #[test]
+    fn test_counter_for_model() {
+        let counter = counter_for_model("gpt-4o");
+        assert_eq!(counter.count_text(""), 0);
+        #[cfg(not(feature = "tiktoken"))]
+        assert_eq!(counter.count_text("abcdefgh"), 2);
+        #[cfg(feature = "tiktoken")]
+        assert_eq!(counter.count_text("hello"), 1);
+    }
+}
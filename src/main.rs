@@ -1,3 +1,101 @@
-fn main() {
-    println!("Hello, world!");
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand};
+use simple_agent::agent::CompactionConfig;
+use simple_agent::LLMClientBuilder;
+use simple_agent::session::{FileSessionStore, MaintenanceReport, SessionMaintenance};
+
+#[derive(Parser)]
+#[command(name = "simple-agent", about = "Simple Agent SDK command line tools")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Shrinks a session store by purging old tool outputs and summarizing
+    /// older turns
+    Maintain {
+        /// Directory of the file session store
+        #[arg(long)]
+        store: PathBuf,
+        /// Replace tool outputs older than this many days with a placeholder
+        #[arg(long, value_name = "DAYS")]
+        purge_tool_outputs: Option<i64>,
+        /// Summarize older turns of every session
+        #[arg(long)]
+        compact: bool,
+        /// Summarize existing summaries again
+        #[arg(long)]
+        resummarize: bool,
+        /// Messages kept verbatim when compacting
+        #[arg(long, default_value_t = 6)]
+        keep_recent: usize,
+        /// Model used for summaries
+        #[arg(long, default_value = "gpt-4o-mini")]
+        model: String,
+        /// Base URL of the OpenAI-compatible API; the key is read from `OPENAI_API_KEY`
+        #[arg(long)]
+        base_url: Option<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    match Cli::parse().command {
+        Command::Maintain {
+            store,
+            purge_tool_outputs,
+            compact,
+            resummarize,
+            keep_recent,
+            model,
+            base_url,
+        } => {
+            let maintenance = SessionMaintenance::new(Arc::new(FileSessionStore::new(store)));
+
+            if let Some(days) = purge_tool_outputs {
+                let report = maintenance.purge_tool_outputs(chrono::Duration::days(days)).await?;
+                print_report("purge", &report);
+            }
+
+            if compact || resummarize {
+                let mut builder = LLMClientBuilder::new();
+                if let Some(base_url) = base_url {
+                    builder = builder.with_base_url(base_url);
+                }
+                let llm = builder.build_openai()?;
+                let config = CompactionConfig {
+                    keep_recent,
+                    ..Default::default()
+                };
+
+                if compact {
+                    let report = maintenance.compact(llm.as_ref(), &config, &model).await?;
+                    print_report("compact", &report);
+                }
+                if resummarize {
+                    let report = maintenance.resummarize(llm.as_ref(), &config, &model).await?;
+                    print_report("resummarize", &report);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_report(operation: &str, report: &MaintenanceReport) {
+    println!(
+        "{}: {} sessions scanned, {} changed, {} messages compacted, {} tool outputs purged",
+        operation,
+        report.sessions_scanned,
+        report.sessions_changed,
+        report.messages_compacted,
+        report.tool_outputs_purged
+    );
 }
@@ -1,32 +1,91 @@
 use std::sync::Arc;
 use async_trait::async_trait;
 use serde_json::Value;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
 
-use crate::tool::{Tool, ToolDefinition, ToolResult, ToolError};
-use crate::mcp::client::MCPClient;
+use crate::tool::{Tool, ToolContext, ToolDefinition, ToolRegistry, ToolResult, ToolError};
+use crate::mcp::client::{MCPClient, MCPError};
+use crate::mcp::manifest::ToolManifest;
 
 /// Adapter that wraps an MCP client tool as a local Tool.
+///
+/// Calls only take a read lock on the client, so parallel calls to the same
+/// server are in flight together; connecting needs the write lock.
 #[derive(Debug, Clone)]
 pub struct MCPToolAdapter {
-    client: Arc<Mutex<MCPClient>>,
+    client: Arc<RwLock<MCPClient>>,
     definition: ToolDefinition,
+    name: String,
+    lazy: Option<LazyConnect>,
+}
+
+/// Connecting on first use, and where to cache the tools found then.
+#[derive(Debug, Clone)]
+struct LazyConnect {
+    manifest: Option<Arc<ToolManifest>>,
 }
 
 impl MCPToolAdapter {
     /// Creates a new MCP tool adapter.
-    pub fn new(client: Arc<Mutex<MCPClient>>, definition: ToolDefinition) -> Self {
+    pub fn new(client: Arc<RwLock<MCPClient>>, definition: ToolDefinition) -> Self {
         Self {
             client,
+            name: definition.name.clone(),
             definition,
+            lazy: None,
         }
     }
+
+    /// Connects the client on the first call if it is not connected, e.g.
+    /// for tools registered from a cached manifest. The server's current
+    /// tools are then stored in `manifest`.
+    pub fn with_lazy_connect(mut self, manifest: Option<Arc<ToolManifest>>) -> Self {
+        self.lazy = Some(LazyConnect { manifest });
+        self
+    }
+
+    /// Connects the client if lazy connecting is enabled and it is not
+    /// connected yet.
+    async fn ensure_connected(&self) -> Result<(), ToolError> {
+        let Some(lazy) = &self.lazy else {
+            return Ok(());
+        };
+        if self.client.read().await.is_connected() {
+            return Ok(());
+        }
+        let mut client = self.client.write().await;
+        // Another call may have connected while we waited for the lock
+        if client.is_connected() {
+            return Ok(());
+        }
+        tracing::debug!(server = %client.name(), "Connecting to MCP server on first use");
+        client.connect().await.map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        if let Some(manifest) = &lazy.manifest {
+            match client.list_tools().await {
+                Ok(tools) => {
+                    if let Err(e) = manifest.store(client.name(), tools) {
+                        tracing::warn!(error = %e, "Failed to update the MCP tool manifest");
+                    }
+                }
+                Err(e) => tracing::warn!(server = %client.name(), error = %e, "Failed to list MCP tools"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Exposes the tool as `{prefix}__{name}`, e.g. to tell apart tools of
+    /// different servers. The server is still called with its own name.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.name = format!("{}__{}", prefix, self.definition.name);
+        self
+    }
 }
 
 #[async_trait]
 impl Tool for MCPToolAdapter {
     fn name(&self) -> &str {
-        &self.definition.name
+        &self.name
     }
 
     fn description(&self) -> &str {
@@ -38,23 +97,39 @@ impl Tool for MCPToolAdapter {
     }
 
     async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
-        let mut client = self.client.lock().await;
-        let output = client
+        self.ensure_connected().await?;
+        let client = self.client.read().await;
+        let result = client
             .call_tool(&self.definition.name, args)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
 
-        Ok(ToolResult {
-            output,
-            metadata: None,
-            error: None,
-        })
+        Ok(result.into())
+    }
+
+    /// Calls the tool, reporting the progress the server sends as
+    /// `AgentEvent::ToolProgress`.
+    async fn execute_with_ctx(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        self.ensure_connected().await?;
+        let client = self.client.read().await;
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let call = client.call_tool_with_progress(&self.definition.name, args, progress_tx);
+        // Ends once the call completes and drops its sender
+        let forward = async {
+            while let Some(progress) = progress_rx.recv().await {
+                let message = progress.message.clone().unwrap_or_default();
+                ctx.report_progress(progress.fraction(), message);
+            }
+        };
+        let (result, ()) = futures::join!(call, forward);
+
+        Ok(result.map_err(|e| ToolError::ExecutionFailed(e.to_string()))?.into())
     }
 }
 
 /// Converts a list of MCP tool definitions to local tools.
 pub fn adapt_mcp_tools(
-    client: Arc<Mutex<MCPClient>>,
+    client: Arc<RwLock<MCPClient>>,
     tools: Vec<ToolDefinition>,
 ) -> Vec<Arc<dyn Tool>> {
     tools
@@ -64,3 +139,79 @@ pub fn adapt_mcp_tools(
         })
         .collect()
 }
+
+/// Registers the server's current tools in `registry`, in a group named
+/// after the server, replacing the tools registered from it before.
+/// Returns the number of tools registered.
+pub async fn sync_mcp_tools(
+    client: &Arc<RwLock<MCPClient>>,
+    registry: &Mutex<ToolRegistry>,
+) -> Result<usize, MCPError> {
+    sync_tools(client, registry, false).await
+}
+
+/// Syncs the server's tools, prefixing their names with the server name
+/// when `prefixed`.
+pub(crate) async fn sync_tools(
+    client: &Arc<RwLock<MCPClient>>,
+    registry: &Mutex<ToolRegistry>,
+    prefixed: bool,
+) -> Result<usize, MCPError> {
+    let (server, tools) = {
+        let client = client.read().await;
+        (client.name().to_string(), client.list_tools().await?)
+    };
+    let definitions: Vec<ToolDefinition> = tools.into_iter().map(ToolDefinition::from).collect();
+    let count = definitions.len();
+
+    let mut registry = registry.lock().await;
+    let stale: Vec<String> = registry.tools_in_group(&server).into_iter().map(String::from).collect();
+    for name in stale {
+        registry.unregister(&name);
+    }
+    for definition in definitions {
+        let mut tool = MCPToolAdapter::new(client.clone(), definition);
+        if prefixed {
+            tool = tool.with_prefix(&server);
+        }
+        registry.register_in_group(server.clone(), Arc::new(tool));
+    }
+    tracing::debug!(server = %server, count, "Synced MCP tools");
+    Ok(count)
+}
+
+/// Registers the server's tools like [`sync_mcp_tools`], then keeps them in
+/// sync whenever the server reports its tool list changed, so long-lived
+/// agents pick up new tools without a restart.
+///
+/// The returned task runs until aborted or the client is dropped.
+pub async fn watch_mcp_tools(
+    client: Arc<RwLock<MCPClient>>,
+    registry: Arc<Mutex<ToolRegistry>>,
+) -> Result<JoinHandle<()>, MCPError> {
+    watch_tools(client, registry, false).await
+}
+
+/// Watches the server's tools, prefixing their names with the server name
+/// when `prefixed`.
+pub(crate) async fn watch_tools(
+    client: Arc<RwLock<MCPClient>>,
+    registry: Arc<Mutex<ToolRegistry>>,
+    prefixed: bool,
+) -> Result<JoinHandle<()>, MCPError> {
+    // Subscribe first so changes during the initial sync are not missed
+    let mut changed = client.read().await.subscribe_tools_changed();
+    sync_tools(&client, &registry, prefixed).await?;
+
+    let client = Arc::downgrade(&client);
+    Ok(tokio::spawn(async move {
+        while changed.changed().await.is_ok() {
+            let Some(client) = client.upgrade() else {
+                break;
+            };
+            if let Err(e) = sync_tools(&client, &registry, prefixed).await {
+                tracing::warn!(error = %e, "Failed to reload MCP tools");
+            }
+        }
+    }))
+}
@@ -0,0 +1,392 @@
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::client::MCPError;
+
+/// An OAuth access token, with the refresh token to renew it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuthToken {
+    /// The bearer token sent to the server
+    pub access_token: String,
+    /// The token used to get a new access token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// When the access token expires, if the server said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl OAuthToken {
+    /// Returns whether the token expired or expires within a minute.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now() + chrono::Duration::seconds(60))
+    }
+}
+
+/// Persists OAuth tokens between runs, by server name.
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    /// Returns the token saved for a server.
+    async fn load(&self, server: &str) -> Option<OAuthToken>;
+
+    /// Saves a server's token, replacing the previous one.
+    async fn save(&self, server: &str, token: &OAuthToken);
+}
+
+/// Keeps tokens in memory only.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore(std::sync::Mutex<HashMap<String, OAuthToken>>);
+
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn load(&self, server: &str) -> Option<OAuthToken> {
+        self.0.lock().ok().and_then(|tokens| tokens.get(server).cloned())
+    }
+
+    async fn save(&self, server: &str, token: &OAuthToken) {
+        if let Ok(mut tokens) = self.0.lock() {
+            tokens.insert(server.to_string(), token.clone());
+        }
+    }
+}
+
+/// Keeps tokens in a JSON file mapping server names to tokens, readable
+/// by the owner only.
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    /// Creates a store backed by the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    async fn read(&self) -> HashMap<String, OAuthToken> {
+        tokio::fs::read_to_string(&self.path)
+            .await
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl TokenStore for FileTokenStore {
+    async fn load(&self, server: &str) -> Option<OAuthToken> {
+        self.read().await.remove(server)
+    }
+
+    async fn save(&self, server: &str, token: &OAuthToken) {
+        let mut tokens = self.read().await;
+        tokens.insert(server.to_string(), token.clone());
+        let Ok(text) = serde_json::to_string_pretty(&tokens) else {
+            return;
+        };
+        if let Err(e) = tokio::fs::write(&self.path, text).await {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to save OAuth token");
+            return;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = tokio::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600)).await;
+        }
+    }
+}
+
+/// The client registration and endpoints of an authorization server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthConfig {
+    /// The client ID registered with the authorization server
+    pub client_id: String,
+    /// The client secret, for confidential clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    /// Where the user is sent to authorize the client
+    pub authorization_endpoint: String,
+    /// Where codes and refresh tokens are exchanged for access tokens
+    pub token_endpoint: String,
+    /// Where the authorization server redirects the user with the code
+    pub redirect_uri: String,
+    /// The scopes requested
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// Authorization server metadata (RFC 8414), as far as it is used.
+#[derive(Debug, Deserialize)]
+struct ServerMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+impl OAuthConfig {
+    /// Creates a config for a public client.
+    pub fn new(
+        client_id: impl Into<String>,
+        authorization_endpoint: impl Into<String>,
+        token_endpoint: impl Into<String>,
+        redirect_uri: impl Into<String>,
+    ) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: None,
+            authorization_endpoint: authorization_endpoint.into(),
+            token_endpoint: token_endpoint.into(),
+            redirect_uri: redirect_uri.into(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Reads the endpoints from the metadata MCP servers publish at
+    /// `/.well-known/oauth-authorization-server`, falling back to the
+    /// default `/authorize` and `/token` paths when there is none.
+    pub async fn discover(
+        server_url: &str,
+        client_id: impl Into<String>,
+        redirect_uri: impl Into<String>,
+    ) -> Result<Self, MCPError> {
+        let url = reqwest::Url::parse(server_url).map_err(|e| MCPError::AuthError(e.to_string()))?;
+        let origin = url.origin().ascii_serialization();
+        let response = reqwest::get(format!("{}/.well-known/oauth-authorization-server", origin))
+            .await
+            .map_err(|e| MCPError::HttpError(e.to_string()))?;
+        let (authorization_endpoint, token_endpoint) = if response.status().is_success() {
+            let metadata: ServerMetadata = response.json().await.map_err(|e| MCPError::AuthError(e.to_string()))?;
+            (metadata.authorization_endpoint, metadata.token_endpoint)
+        } else {
+            (format!("{}/authorize", origin), format!("{}/token", origin))
+        };
+        Ok(Self::new(client_id, authorization_endpoint, token_endpoint, redirect_uri))
+    }
+
+    /// Sets the client secret.
+    pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret = Some(secret.into());
+        self
+    }
+
+    /// Adds a requested scope.
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+}
+
+/// An authorization in progress: send the user to `url`, then pass the
+/// `code` and `state` of the redirect to `OAuthClient::exchange_code`.
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    /// The URL the user opens to authorize the client
+    pub url: String,
+    /// The value the redirect must carry back
+    pub state: String,
+    code_verifier: String,
+}
+
+/// The token endpoint's response.
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+}
+
+/// Runs the OAuth 2.1 authorization-code flow with PKCE for one MCP server
+/// and keeps its access token fresh, persisting it in a `TokenStore`.
+pub struct OAuthClient {
+    server: String,
+    config: OAuthConfig,
+    store: Arc<dyn TokenStore>,
+    http: reqwest::Client,
+    token: Mutex<Option<OAuthToken>>,
+}
+
+impl OAuthClient {
+    /// Creates a client for the server named `server`, the key its token is
+    /// stored under.
+    pub fn new(server: impl Into<String>, config: OAuthConfig, store: Arc<dyn TokenStore>) -> Self {
+        Self {
+            server: server.into(),
+            config,
+            store,
+            http: reqwest::Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Starts an authorization, returning the URL to send the user to.
+    pub fn authorization_request(&self) -> AuthorizationRequest {
+        let code_verifier = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+        let state = uuid::Uuid::new_v4().simple().to_string();
+        let mut params = vec![
+            ("response_type", "code".to_string()),
+            ("client_id", self.config.client_id.clone()),
+            ("redirect_uri", self.config.redirect_uri.clone()),
+            ("code_challenge", challenge),
+            ("code_challenge_method", "S256".to_string()),
+            ("state", state.clone()),
+        ];
+        if !self.config.scopes.is_empty() {
+            params.push(("scope", self.config.scopes.join(" ")));
+        }
+        let url = reqwest::Url::parse_with_params(&self.config.authorization_endpoint, &params)
+            .map(String::from)
+            .unwrap_or_else(|_| self.config.authorization_endpoint.clone());
+        AuthorizationRequest { url, state, code_verifier }
+    }
+
+    /// Exchanges the code of the redirect for a token, and saves it.
+    pub async fn exchange_code(
+        &self,
+        request: &AuthorizationRequest,
+        code: &str,
+        state: &str,
+    ) -> Result<OAuthToken, MCPError> {
+        if state != request.state {
+            return Err(MCPError::AuthError("Authorization state does not match".to_string()));
+        }
+        self.request_token(vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code.to_string()),
+            ("redirect_uri", self.config.redirect_uri.clone()),
+            ("code_verifier", request.code_verifier.clone()),
+        ], None)
+        .await
+    }
+
+    /// Returns a valid access token, loading it from the store and
+    /// refreshing it when it expired.
+    pub async fn access_token(&self) -> Result<String, MCPError> {
+        let mut cached = self.token.lock().await;
+        if cached.is_none() {
+            *cached = self.store.load(&self.server).await;
+        }
+        match cached.clone() {
+            Some(token) if !token.is_expired() => Ok(token.access_token),
+            Some(_) => {
+                drop(cached);
+                self.refresh().await.map(|token| token.access_token)
+            }
+            None => Err(MCPError::AuthError(format!(
+                "Not authorized with `{}`; run the authorization flow first",
+                self.server
+            ))),
+        }
+    }
+
+    /// Gets a new access token with the refresh token, and saves it.
+    pub async fn refresh(&self) -> Result<OAuthToken, MCPError> {
+        let current = match self.token.lock().await.clone() {
+            Some(token) => Some(token),
+            None => self.store.load(&self.server).await,
+        };
+        let refresh_token = current
+            .and_then(|token| token.refresh_token)
+            .ok_or_else(|| MCPError::AuthError(format!("No refresh token for `{}`; authorize again", self.server)))?;
+        self.request_token(vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.clone()),
+        ], Some(refresh_token))
+        .await
+    }
+
+    /// Posts to the token endpoint. Servers may omit the refresh token when
+    /// refreshing; `refresh_token` is kept then.
+    async fn request_token(
+        &self,
+        mut params: Vec<(&str, String)>,
+        refresh_token: Option<String>,
+    ) -> Result<OAuthToken, MCPError> {
+        params.push(("client_id", self.config.client_id.clone()));
+        if let Some(secret) = &self.config.client_secret {
+            params.push(("client_secret", secret.clone()));
+        }
+        let response = self
+            .http
+            .post(&self.config.token_endpoint)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| MCPError::HttpError(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(MCPError::AuthError(format!(
+                "Token request failed: {} - {}",
+                response.status(),
+                response.text().await.unwrap_or_default()
+            )));
+        }
+        let response: TokenResponse = response.json().await.map_err(|e| MCPError::AuthError(e.to_string()))?;
+        let token = OAuthToken {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token.or(refresh_token),
+            expires_at: response.expires_in.map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+        };
+        self.store.save(&self.server, &token).await;
+        *self.token.lock().await = Some(token.clone());
+        Ok(token)
+    }
+}
+
+impl std::fmt::Debug for OAuthClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuthClient")
+            .field("server", &self.server)
+            .field("token_endpoint", &self.config.token_endpoint)
+            .finish()
+    }
+}
+
+/// How requests to a remote server are authenticated: extra headers, a
+/// static `Authorization` value, or OAuth.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Credentials {
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) auth: Option<String>,
+    pub(crate) oauth: Option<Arc<OAuthClient>>,
+}
+
+impl Credentials {
+    async fn apply(&self, mut request: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder, MCPError> {
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(oauth) = &self.oauth {
+            request = request.bearer_auth(oauth.access_token().await?);
+        } else if let Some(auth) = &self.auth {
+            request = request.header(reqwest::header::AUTHORIZATION, auth);
+        }
+        Ok(request)
+    }
+
+    /// Sends a request built by `build`. When the server rejects an OAuth
+    /// token, refreshes it and sends the request once more.
+    pub(crate) async fn send(
+        &self,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, MCPError> {
+        let http_error = |e: reqwest::Error| MCPError::HttpError(e.to_string());
+        let response = self.apply(build()).await?.send().await.map_err(http_error)?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED
+            && let Some(oauth) = &self.oauth
+        {
+            tracing::debug!(server = %oauth.server, "MCP server rejected the access token; refreshing it");
+            oauth.refresh().await?;
+            return self.apply(build()).await?.send().await.map_err(http_error);
+        }
+        Ok(response)
+    }
+}
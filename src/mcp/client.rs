@@ -1,12 +1,20 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::process::{Command, Stdio, Child};
-use std::io::{BufReader, Write, BufRead};
-use std::sync::{atomic::AtomicU64, Mutex, Arc};
+use std::sync::{atomic::AtomicU64, Arc};
 use std::time::Duration;
 use tracing::debug;
-use tokio::task;
+
+use crate::net::EndpointResolution;
+use crate::session::ToolResultBlock;
+use crate::tool::ToolResult;
+use super::auth::{Credentials, OAuthClient};
+use super::framing::StdioFraming;
+use super::rpc::{RpcError, ServerHandlers};
+use super::sampling::SamplingHandler;
+use super::sse::SseTransport;
+use super::stdio::StdioTransport;
+use super::streamable::StreamableHttpTransport;
 
 /// Configuration for connecting to an MCP server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,15 +23,22 @@ pub struct MCPConfig {
     pub name: String,
     /// Transport type and configuration
     pub transport: MCPTransport,
-    /// Connection timeout
+    /// Timeout of connecting and of each request
     #[serde(default = "default_timeout")]
     pub timeout: Duration,
+    /// Headers sent with every request over HTTP, SSE and Streamable HTTP
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
 }
 
-fn default_timeout() -> Duration {
+pub(crate) fn default_timeout() -> Duration {
     Duration::from_secs(30)
 }
 
+/// Protocol versions the client speaks, newest first. The newest is
+/// proposed; servers answer with the version they pick.
+pub(crate) const PROTOCOL_VERSIONS: [&str; 2] = ["2025-03-26", "2024-11-05"];
+
 /// Transport type for MCP connection.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -37,17 +52,31 @@ pub enum MCPTransport {
         /// Environment variables
         #[serde(skip_serializing_if = "Option::is_none")]
         env: Option<std::collections::HashMap<String, String>>,
+        /// How messages are delimited; detected from the server by default
+        #[serde(default)]
+        framing: StdioFraming,
     },
     /// Connect via HTTP
     Http {
         /// The URL of the MCP server
         url: String,
     },
-    /// Connect via Server-Sent Events
+    /// Connect via Streamable HTTP: every message is posted to one endpoint,
+    /// which answers with JSON or an event stream
+    #[serde(rename = "streamable-http")]
+    StreamableHttp {
+        /// The URL of the MCP endpoint
+        url: String,
+        /// Optional `Authorization` header value
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auth: Option<String>,
+    },
+    /// Connect via Server-Sent Events: the server streams messages over a
+    /// long-lived `GET` and announces where the client posts its own
     Sse {
         /// The URL of the SSE endpoint
         url: String,
-        /// Optional authentication header
+        /// Optional `Authorization` header value
         #[serde(skip_serializing_if = "Option::is_none")]
         auth: Option<String>,
     },
@@ -74,6 +103,39 @@ pub enum MCPError {
     /// HTTP error
     #[error("HTTP error: {0}")]
     HttpError(String),
+    /// Authentication failed or is missing
+    #[error("Auth error: {0}")]
+    AuthError(String),
+    /// Invalid server configuration
+    #[error("Config error: {0}")]
+    ConfigError(String),
+    /// A server's sampling request was rejected by the policy
+    #[error("Sampling rejected: {0}")]
+    SamplingRejected(String),
+    /// The server did not declare the capability a request needs
+    #[error("Unsupported by server: {0}")]
+    Unsupported(String),
+}
+
+/// The connection state of an MCP client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MCPStatus {
+    /// Not connected, or disconnected on purpose
+    Disconnected,
+    /// Connected and initialized
+    Connected,
+    /// The connection was lost and is being re-established
+    Reconnecting {
+        /// Number of the reconnection attempt, starting at 1
+        attempt: u32,
+        /// Why the connection was considered lost
+        error: String,
+    },
+    /// Connecting failed, and no more attempts are made
+    Failed {
+        /// The last error
+        error: String,
+    },
 }
 
 /// Builder for MCP client.
@@ -82,6 +144,10 @@ pub struct MCPClientBuilder {
     name: Option<String>,
     transport: Option<MCPTransport>,
     timeout: Option<Duration>,
+    headers: HashMap<String, String>,
+    oauth: Option<Arc<OAuthClient>>,
+    resolution: EndpointResolution,
+    sampling: Option<SamplingHandler>,
 }
 
 impl MCPClientBuilder {
@@ -106,10 +172,25 @@ impl MCPClientBuilder {
             command: command.into(),
             args,
             env: None,
+            framing: StdioFraming::Auto,
         });
         self
     }
 
+    /// Sets the transport configuration directly.
+    pub fn with_transport(mut self, transport: MCPTransport) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Sets the message framing of the stdio transport.
+    pub fn with_stdio_framing(mut self, framing: StdioFraming) -> Self {
+        if let Some(MCPTransport::Stdio { framing: current, .. }) = &mut self.transport {
+            *current = framing;
+        }
+        self
+    }
+
     /// Configures HTTP transport.
     pub fn with_http_transport(mut self, url: impl Into<String>) -> Self {
         self.transport = Some(MCPTransport::Http {
@@ -118,6 +199,15 @@ impl MCPClientBuilder {
         self
     }
 
+    /// Configures Streamable HTTP transport.
+    pub fn with_streamable_http_transport(mut self, url: impl Into<String>) -> Self {
+        self.transport = Some(MCPTransport::StreamableHttp {
+            url: url.into(),
+            auth: None,
+        });
+        self
+    }
+
     /// Configures SSE transport.
     pub fn with_sse_transport(mut self, url: impl Into<String>) -> Self {
         self.transport = Some(MCPTransport::Sse {
@@ -133,6 +223,38 @@ impl MCPClientBuilder {
         self
     }
 
+    /// Adds a header sent with every request to a remote server.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Authenticates to a remote server with a static bearer token.
+    pub fn with_bearer_token(self, token: impl AsRef<str>) -> Self {
+        let value = format!("Bearer {}", token.as_ref());
+        self.with_header("Authorization", value)
+    }
+
+    /// Authenticates to a remote server with OAuth; the access token is
+    /// refreshed when it expires or the server rejects it.
+    pub fn with_oauth(mut self, oauth: Arc<OAuthClient>) -> Self {
+        self.oauth = Some(oauth);
+        self
+    }
+
+    /// Overrides host name resolution for HTTP and SSE transports.
+    pub fn with_endpoint_resolution(mut self, resolution: EndpointResolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Answers the server's `sampling/createMessage` requests with the
+    /// handler's LLM, advertising the `sampling` capability.
+    pub fn with_sampling(mut self, handler: SamplingHandler) -> Self {
+        self.sampling = Some(handler);
+        self
+    }
+
     /// Builds the MCP client.
     pub fn build(self) -> Result<MCPClient, MCPError> {
         let name = self.name.ok_or_else(|| MCPError::ConnectionError(
@@ -144,12 +266,21 @@ impl MCPClientBuilder {
         let timeout = self.timeout.unwrap_or_else(default_timeout);
 
         Ok(MCPClient {
-            config: MCPConfig { name, transport, timeout },
-            process: None,
-            stdin: None,
-            stdout_reader: None,
+            config: MCPConfig { name, transport, timeout, headers: self.headers },
+            oauth: self.oauth,
+            stdio: None,
             http_client: None,
-            sse_url: None,
+            sse: None,
+            streamable: None,
+            resolution: self.resolution,
+            sampling: self.sampling,
+            notifications: NotificationSinks {
+                tools_changed: tokio::sync::watch::channel(0).0,
+                progress: Default::default(),
+                logs: tokio::sync::broadcast::channel(64).0,
+            },
+            status: tokio::sync::watch::channel(MCPStatus::Disconnected).0,
+            handshake: None,
             message_id: AtomicU64::new(0),
         })
     }
@@ -160,12 +291,17 @@ impl MCPClientBuilder {
 pub struct MCPClient {
     config: MCPConfig,
     // Stdio transport fields
-    process: Option<Child>,
-    stdin: Option<std::process::ChildStdin>,
-    stdout_reader: Option<Arc<Mutex<BufReader<std::process::ChildStdout>>>>,
+    stdio: Option<StdioTransport>,
     // HTTP/SSE transport fields
     http_client: Option<reqwest::Client>,
-    sse_url: Option<String>,
+    sse: Option<SseTransport>,
+    streamable: Option<StreamableHttpTransport>,
+    oauth: Option<Arc<OAuthClient>>,
+    resolution: EndpointResolution,
+    sampling: Option<SamplingHandler>,
+    notifications: NotificationSinks,
+    status: tokio::sync::watch::Sender<MCPStatus>,
+    handshake: Option<Handshake>,
     // Message ID counter for JSON-RPC
     message_id: AtomicU64,
 }
@@ -178,62 +314,156 @@ impl MCPClient {
 
     /// Connects to the MCP server.
     pub async fn connect(&mut self) -> Result<(), MCPError> {
+        let result = self.open().await;
+        match &result {
+            Ok(()) => self.set_status(MCPStatus::Connected),
+            // While reconnecting, the supervisor decides when to give up
+            Err(e) if !matches!(self.status(), MCPStatus::Reconnecting { .. }) => {
+                self.set_status(MCPStatus::Failed { error: e.to_string() })
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Returns the connection state.
+    pub fn status(&self) -> MCPStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Returns a receiver that changes with the connection state.
+    pub fn subscribe_status(&self) -> tokio::sync::watch::Receiver<MCPStatus> {
+        self.status.subscribe()
+    }
+
+    pub(crate) fn set_status(&self, status: MCPStatus) {
+        self.status.send_replace(status);
+    }
+
+    /// Returns an unconnected client with the same configuration, sharing
+    /// this client's status and notification subscribers, so a replacement
+    /// connection can be opened without holding this one.
+    pub(crate) fn detached(&self) -> MCPClient {
+        MCPClient {
+            config: self.config.clone(),
+            stdio: None,
+            http_client: None,
+            sse: None,
+            streamable: None,
+            oauth: self.oauth.clone(),
+            resolution: self.resolution.clone(),
+            sampling: self.sampling.clone(),
+            notifications: self.notifications.clone(),
+            status: self.status.clone(),
+            handshake: None,
+            message_id: AtomicU64::new(self.message_id.load(std::sync::atomic::Ordering::SeqCst)),
+        }
+    }
+
+    /// Tells tool list subscribers to reload, as after a reconnection the
+    /// server's tools may have changed.
+    pub(crate) fn notify_tools_changed(&self) {
+        self.notifications.tools_changed.send_modify(|generation| *generation += 1);
+    }
+
+    /// Sends a keepalive `ping`, failing if the server does not answer
+    /// within the configured timeout.
+    pub async fn ping(&self) -> Result<(), MCPError> {
+        self.request("ping", serde_json::json!({})).await.map(|_| ())
+    }
+
+    /// Opens the configured transport.
+    async fn open(&mut self) -> Result<(), MCPError> {
         // Clone the transport config so we can use it while keeping self borrowed
         let transport = self.config.transport.clone();
 
         match transport {
-            MCPTransport::Stdio { command, args, env } => {
-                self.connect_stdio(&command, &args, &env).await
+            MCPTransport::Stdio { command, args, env, framing } => {
+                self.connect_stdio(&command, &args, &env, framing).await
             }
             MCPTransport::Http { url } => {
                 self.connect_http(&url).await
             }
-            MCPTransport::Sse { url, .. } => {
-                self.connect_sse(&url).await
+            MCPTransport::Sse { url, auth } => {
+                self.connect_sse(&url, auth).await
+            }
+            MCPTransport::StreamableHttp { url, auth } => {
+                self.connect_streamable_http(&url, auth).await
             }
         }
     }
 
-    /// Connects via stdio.
+    /// Returns the server name.
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Returns whether a transport is open.
+    pub fn is_connected(&self) -> bool {
+        self.stdio.is_some() || self.http_client.is_some() || self.sse.is_some() || self.streamable.is_some()
+    }
+
+    /// Returns a receiver that changes whenever the server reports its
+    /// tool list changed (`notifications/tools/list_changed`).
+    pub fn subscribe_tools_changed(&self) -> tokio::sync::watch::Receiver<u64> {
+        self.notifications.tools_changed.subscribe()
+    }
+
+    /// Returns a receiver of the log messages the server sends
+    /// (`notifications/message`). Messages are also emitted via `tracing`.
+    pub fn subscribe_logs(&self) -> tokio::sync::broadcast::Receiver<MCPLogMessage> {
+        self.notifications.logs.subscribe()
+    }
+
+    /// Returns the protocol version agreed with the server, once connected.
+    pub fn protocol_version(&self) -> Option<&str> {
+        self.handshake.as_ref()?.protocol_version.as_deref()
+    }
+
+    /// Returns the capabilities the server declared, once connected.
+    pub fn server_capabilities(&self) -> Option<&ServerCapabilities> {
+        self.handshake.as_ref()?.capabilities.as_ref()
+    }
+
+    /// Returns the name and version the server reported, once connected.
+    pub fn server_info(&self) -> Option<&MCPServerInfo> {
+        self.handshake.as_ref()?.server_info.as_ref()
+    }
+
+    /// Fails with `Unsupported` when the server declared its capabilities
+    /// without `capability`. Servers declaring none are not gated.
+    fn require(&self, capability: &str, declared: impl Fn(&ServerCapabilities) -> bool) -> Result<(), MCPError> {
+        match self.server_capabilities() {
+            Some(capabilities) if !declared(capabilities) => Err(MCPError::Unsupported(format!(
+                "`{}` does not support {}",
+                self.config.name, capability
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the session the server assigned over Streamable HTTP.
+    pub fn session_id(&self) -> Option<String> {
+        self.streamable.as_ref().and_then(StreamableHttpTransport::session_id)
+    }
+
+    /// Connects via stdio and performs the initialize handshake.
     async fn connect_stdio(
         &mut self,
         command: &str,
         args: &[String],
         env: &Option<HashMap<String, String>>,
+        framing: StdioFraming,
     ) -> Result<(), MCPError> {
         debug!("Starting MCP server: {} {:?}", command, args);
 
-        let mut cmd = Command::new(command);
-        cmd.args(args);
-        cmd.stdin(Stdio::piped());
-        cmd.stdout(Stdio::piped());
+        self.stdio = Some(StdioTransport::spawn(&self.config.name, command, args, env, framing, self.server_handlers())?);
 
-        if let Some(env_vars) = env {
-            for (key, value) in env_vars {
-                cmd.env(key, value);
-            }
+        if let Err(e) = self.initialize().await {
+            self.stdio = None;
+            return Err(e);
         }
 
-        let mut process = cmd.spawn().map_err(|e| {
-            MCPError::ConnectionError(format!("Failed to start MCP server: {}", e))
-        })?;
-
-        let stdin = process.stdin.take().ok_or_else(|| {
-            MCPError::ConnectionError("Failed to get stdin".to_string())
-        })?;
-
-        let stdout = process.stdout.take().ok_or_else(|| {
-            MCPError::ConnectionError("Failed to get stdout".to_string())
-        })?;
-
-        self.process = Some(process);
-        self.stdin = Some(stdin);
-        self.stdout_reader = Some(Arc::new(Mutex::new(BufReader::new(stdout))));
-
-        // Send initialize message and read response
-        self.send_initialize().await?;
-        let _init_response = self.read_json_response().await?;
-
         debug!("MCP server initialized successfully");
 
         Ok(())
@@ -243,19 +473,23 @@ impl MCPClient {
     async fn connect_http(&mut self, url: &str) -> Result<(), MCPError> {
         debug!("Connecting to MCP server via HTTP: {}", url);
 
-        let client = reqwest::Client::builder()
-            .timeout(self.config.timeout)
+        let client = self
+            .resolution
+            .apply(reqwest::Client::builder().timeout(self.config.timeout))
             .build()
             .map_err(|e| MCPError::ConnectionError(e.to_string()))?;
 
         // Verify the connection by sending an initialize request
-        let response = client
-            .post(&format!("{}/rpc", url))
-            .header("Content-Type", "application/json")
-            .json(&self.create_initialize_request())
-            .send()
-            .await
-            .map_err(|e| MCPError::HttpError(e.to_string()))?;
+        let initialize = self.create_initialize_request();
+        let response = self
+            .credentials(None)
+            .send(|| {
+                client
+                    .post(format!("{}/rpc", url))
+                    .header("Content-Type", "application/json")
+                    .json(&initialize)
+            })
+            .await?;
 
         if !response.status().is_success() {
             return Err(MCPError::HttpError(format!(
@@ -264,134 +498,236 @@ impl MCPClient {
                 response.text().await.unwrap_or_default()
             )));
         }
+        if let Ok(response) = response.json::<Value>().await
+            && let Ok(result) = Self::into_result(response)
+        {
+            self.handshake = Some(Handshake::accept(result)?);
+        }
 
         self.http_client = Some(client);
         debug!("Successfully connected to MCP server via HTTP");
         Ok(())
     }
 
-    /// Connects via SSE.
-    async fn connect_sse(&mut self, url: &str) -> Result<(), MCPError> {
+    /// Connects via SSE and performs the initialize handshake.
+    async fn connect_sse(&mut self, url: &str, auth: Option<String>) -> Result<(), MCPError> {
         debug!("Connecting to MCP server via SSE: {}", url);
 
-        let client = reqwest::Client::builder()
-            .timeout(self.config.timeout)
+        // The event stream stays open, so only connecting is timed out
+        let client = self
+            .resolution
+            .apply(reqwest::Client::builder().connect_timeout(self.config.timeout))
             .build()
             .map_err(|e| MCPError::ConnectionError(e.to_string()))?;
 
-        self.http_client = Some(client);
-        self.sse_url = Some(url.to_string());
+        self.sse = Some(SseTransport::connect(client, url, self.credentials(auth), self.config.timeout, self.server_handlers()).await?);
+
+        if let Err(e) = self.initialize().await {
+            self.sse = None;
+            return Err(e);
+        }
 
         debug!("Successfully connected to MCP server via SSE");
         Ok(())
     }
 
+    /// Connects via Streamable HTTP and performs the initialize handshake,
+    /// which opens the session.
+    async fn connect_streamable_http(&mut self, url: &str, auth: Option<String>) -> Result<(), MCPError> {
+        debug!("Connecting to MCP server via Streamable HTTP: {}", url);
+
+        // Responses may stream for long, so only connecting is timed out
+        let client = self
+            .resolution
+            .apply(reqwest::Client::builder().connect_timeout(self.config.timeout))
+            .build()
+            .map_err(|e| MCPError::ConnectionError(e.to_string()))?;
+
+        self.streamable = Some(StreamableHttpTransport::new(client, url, self.credentials(auth), self.server_handlers()));
+
+        if let Err(e) = self.initialize().await {
+            self.streamable = None;
+            return Err(e);
+        }
+
+        debug!(session_id = ?self.session_id(), "Successfully connected to MCP server via Streamable HTTP");
+        Ok(())
+    }
+
+    /// Performs the initialize handshake over the open transport: agrees on
+    /// the protocol version, records the server's capabilities and confirms
+    /// with `notifications/initialized`.
+    async fn initialize(&mut self) -> Result<(), MCPError> {
+        let result = self.request("initialize", self.initialize_params()).await?;
+        let handshake = Handshake::accept(result)?;
+        debug!(server = %self.config.name, version = ?handshake.protocol_version, "Negotiated MCP protocol version");
+        self.handshake = Some(handshake);
+        self.send_message(serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await
+    }
+
+    /// Returns the parameters of the initialize request.
+    fn initialize_params(&self) -> Value {
+        let mut capabilities = serde_json::Map::new();
+        if self.sampling.is_some() {
+            capabilities.insert("sampling".to_string(), serde_json::json!({}));
+        }
+        serde_json::json!({
+            "protocolVersion": PROTOCOL_VERSIONS[0],
+            "capabilities": capabilities,
+            "clientInfo": {
+                "name": "simple-agent",
+                "version": "0.1.0"
+            }
+        })
+    }
+
     /// Creates the initialize request.
     fn create_initialize_request(&self) -> Value {
         serde_json::json!({
             "jsonrpc": "2.0",
             "id": 1,
             "method": "initialize",
-            "params": {
-                "protocolVersion": "2024-11-05",
-                "capabilities": {},
-                "clientInfo": {
-                    "name": "simple-agent",
-                    "version": "0.1.0"
-                }
-            }
+            "params": self.initialize_params()
         })
     }
 
-    /// Sends the initialize message.
-    async fn send_initialize(&mut self) -> Result<(), MCPError> {
-        let message = self.create_initialize_request();
-        self.send_message(message).await
-    }
-
     /// Sends a JSON-RPC message.
-    async fn send_message(&mut self, message: Value) -> Result<(), MCPError> {
+    async fn send_message(&self, message: Value) -> Result<(), MCPError> {
         match &self.config.transport {
             MCPTransport::Stdio { .. } => {
-                self.send_message_stdio(message).await
+                let stdio = self.stdio.as_ref().ok_or_else(|| {
+                    MCPError::ConnectionError("Not connected".to_string())
+                })?;
+                stdio.notify(message).await
             }
             MCPTransport::Http { url } => {
                 self.send_message_http(message, url).await
             }
-            MCPTransport::Sse { url, .. } => {
-                // For SSE, we use the same HTTP endpoint for requests
-                self.send_message_http(message, url).await
+            MCPTransport::Sse { .. } => {
+                let sse = self.sse.as_ref().ok_or_else(|| {
+                    MCPError::ConnectionError("Not connected".to_string())
+                })?;
+                sse.notify(message).await
+            }
+            MCPTransport::StreamableHttp { .. } => {
+                let streamable = self.streamable.as_ref().ok_or_else(|| {
+                    MCPError::ConnectionError("Not connected".to_string())
+                })?;
+                streamable.notify(message).await
             }
         }
     }
 
-    /// Sends a message via stdio.
-    async fn send_message_stdio(&mut self, message: Value) -> Result<(), MCPError> {
-        let message_str = serde_json::to_string(&message)
-            .map_err(|e| MCPError::ProtocolError(e.to_string()))?;
-
-        let stdin = self.stdin.as_mut().ok_or_else(|| {
-            MCPError::ConnectionError("Not connected".to_string())
-        })?;
-
-        stdin.write_all(message_str.as_bytes()).map_err(|e| {
-            MCPError::ConnectionError(format!("Failed to write to stdin: {}", e))
-        })?;
-        stdin.write_all(b"\n").map_err(|e| {
-            MCPError::ConnectionError(format!("Failed to write newline: {}", e))
-        })?;
-
-        Ok(())
+    /// Returns how requests to a remote server are authenticated, given the
+    /// transport's static `Authorization` value.
+    fn credentials(&self, auth: Option<String>) -> Credentials {
+        Credentials {
+            headers: self.config.headers.clone(),
+            auth,
+            oauth: self.oauth.clone(),
+        }
     }
 
-    /// Reads a JSON-RPC response line from stdout, skipping non-JSON lines.
-    async fn read_json_response(&self) -> Result<Value, MCPError> {
-        let reader_arc = self.stdout_reader.as_ref().ok_or_else(|| {
-            MCPError::ConnectionError("Not connected".to_string())
-        })?;
-
-        // Keep reading until we get valid JSON
-        loop {
-            // Clone the Arc for each iteration
-            let reader_arc_clone = reader_arc.clone();
-
-            let line = task::spawn_blocking(move || {
-                let mut reader = reader_arc_clone.lock().map_err(|e| {
-                    MCPError::ProtocolError(format!("Failed to lock reader: {}", e))
-                })?;
+    /// Returns the handlers of messages the server initiates.
+    fn server_handlers(&self) -> ServerHandlers {
+        let server = self.config.name.clone();
+        let sampling = self.sampling.clone();
+        ServerHandlers {
+            on_notification: Arc::new({
+                let (server, sinks) = (server.clone(), self.notifications.clone());
+                move |notification: &Value| Self::dispatch_notification(&server, &sinks, notification)
+            }),
+            on_request: Arc::new(move |method, params| {
+                let (server, sampling) = (server.clone(), sampling.clone());
+                Box::pin(async move { Self::answer_request(&server, sampling.as_ref(), &method, params).await })
+            }),
+        }
+    }
 
-                let mut line = String::new();
-                reader.read_line(&mut line).map_err(|e| {
-                    MCPError::ProtocolError(format!("Failed to read response: {}", e))
+    /// Answers a request sent by `server`.
+    async fn answer_request(
+        server: &str,
+        sampling: Option<&SamplingHandler>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, RpcError> {
+        match (method, sampling) {
+            ("ping", _) => Ok(serde_json::json!({})),
+            ("sampling/createMessage", Some(sampling)) => {
+                let request = serde_json::from_value(params)
+                    .map_err(|e| RpcError::new(-32602, format!("Invalid params: {}", e)))?;
+                let result = sampling.create_message(server, request).await.map_err(|e| match e {
+                    // The code the spec uses for requests the user declined
+                    MCPError::SamplingRejected(message) => RpcError::new(-1, message),
+                    e => RpcError::new(-32603, e.to_string()),
                 })?;
+                serde_json::to_value(result).map_err(|e| RpcError::new(-32603, e.to_string()))
+            }
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
 
-                // Trim the line
-                let trimmed = line.trim();
-
-                // Skip empty lines
-                if trimmed.is_empty() {
-                    return Ok::<Option<String>, MCPError>(None);
-                }
+    /// Handles a notification pushed by `server`.
+    fn dispatch_notification(server: &str, sinks: &NotificationSinks, notification: &Value) {
+        let method = notification.get("method").and_then(Value::as_str).unwrap_or_default();
+        let params = notification.get("params").cloned().unwrap_or(Value::Null);
 
-                // Try to parse as JSON
-                match serde_json::from_str::<Value>(trimmed) {
-                    Ok(json) => Ok(Some(serde_json::to_string(&json).unwrap_or(trimmed.to_string()))),
-                    Err(_) => {
-                        // Not JSON, might be a log message - print it for debugging
-                        tracing::debug!("Skipping non-JSON line: {}", trimmed);
-                        Ok(None)
+        match method {
+            "notifications/message" => {
+                let message = Self::log_server_message(server, &params);
+                // Nobody listening is fine
+                let _ = sinks.logs.send(message);
+            }
+            "notifications/progress" => {
+                let token = match params.get("progressToken") {
+                    Some(Value::String(token)) => token.clone(),
+                    Some(other) => other.to_string(),
+                    None => return,
+                };
+                let listener = sinks.progress.lock().ok().and_then(|listeners| listeners.get(&token).cloned());
+                match (listener, serde_json::from_value::<MCPProgress>(params)) {
+                    (Some(listener), Ok(progress)) => {
+                        let _ = listener.send(progress);
                     }
+                    _ => debug!(server, %token, "Ignoring MCP progress of an unknown call"),
                 }
-            }).await.map_err(|e| {
-                MCPError::ProtocolError(format!("Task error: {}", e))
-            })?;
-
-            if let Ok(Some(json_line)) = line {
-                return serde_json::from_str(&json_line)
-                    .map_err(|e| MCPError::ProtocolError(format!("Failed to parse response: {}", e)));
             }
-            // If line was None (empty or non-JSON), continue the loop
+            "notifications/tools/list_changed" => {
+                debug!(server, "MCP tool list changed");
+                sinks.tools_changed.send_modify(|generation| *generation += 1);
+            }
+            _ => debug!(server, method, "Ignoring MCP notification"),
+        }
+    }
+
+    /// Emits a server log entry through `tracing` and returns it.
+    fn log_server_message(server: &str, params: &Value) -> MCPLogMessage {
+        let level = params
+            .get("level")
+            .cloned()
+            .and_then(|l| serde_json::from_value(l).ok())
+            .unwrap_or(MCPLogLevel::Info);
+        let logger = params.get("logger").and_then(Value::as_str).map(String::from);
+        let data = params.get("data").cloned().unwrap_or(Value::Null);
+        let message = MCPLogMessage {
+            server: server.to_string(),
+            level,
+            logger,
+            data,
+        };
+        let (logger, data) = (message.logger.as_deref().unwrap_or_default(), message.text());
+
+        match level {
+            MCPLogLevel::Debug => tracing::debug!(server, logger, "{}", data),
+            MCPLogLevel::Info | MCPLogLevel::Notice => tracing::info!(server, logger, "{}", data),
+            MCPLogLevel::Warning => tracing::warn!(server, logger, "{}", data),
+            MCPLogLevel::Error
+            | MCPLogLevel::Critical
+            | MCPLogLevel::Alert
+            | MCPLogLevel::Emergency => tracing::error!(server, logger, "{}", data),
         }
+        message
     }
 
     /// Sends a message via HTTP.
@@ -400,13 +736,15 @@ impl MCPClient {
             MCPError::ConnectionError("Not connected".to_string())
         })?;
 
-        let response = client
-            .post(&format!("{}/rpc", url))
-            .header("Content-Type", "application/json")
-            .json(&message)
-            .send()
-            .await
-            .map_err(|e| MCPError::HttpError(e.to_string()))?;
+        let response = self
+            .credentials(None)
+            .send(|| {
+                client
+                    .post(format!("{}/rpc", url))
+                    .header("Content-Type", "application/json")
+                    .json(&message)
+            })
+            .await?;
 
         if !response.status().is_success() {
             return Err(MCPError::HttpError(format!(
@@ -421,110 +759,167 @@ impl MCPClient {
 
     /// Disconnects from the MCP server.
     pub async fn disconnect(&mut self) -> Result<(), MCPError> {
+        if !matches!(self.status(), MCPStatus::Reconnecting { .. }) {
+            self.set_status(MCPStatus::Disconnected);
+        }
         // Clean up stdio transport
-        if let Some(mut process) = self.process.take() {
-            process.wait().map_err(|e| {
-                MCPError::ConnectionError(format!("Failed to wait for process: {}", e))
-            })?;
+        if let Some(stdio) = self.stdio.take() {
+            stdio.shutdown().await?;
         }
 
-        self.stdin = None;
-        self.stdout_reader = None;
+        self.handshake = None;
 
         // Clean up HTTP/SSE transport
         self.http_client = None;
-        self.sse_url = None;
+        self.sse = None;
+        if let Some(streamable) = self.streamable.take() {
+            streamable.close().await;
+        }
 
         Ok(())
     }
 
     /// Lists available tools from the MCP server.
-    pub async fn list_tools(&mut self) -> Result<Vec<MCToolInfo>, MCPError> {
-        let request = self.create_json_rpc_request("tools/list", Value::Object(serde_json::Map::new()));
-
-        match &self.config.transport {
-            MCPTransport::Stdio { .. } => {
-                // Send the tools/list request
-                self.send_message_stdio(request).await?;
-
-                // Read the JSON response (skips non-JSON lines)
-                let response = self.read_json_response().await?;
-
-                // Check for JSON-RPC error
-                if let Some(error) = response.get("error") {
-                    return Err(MCPError::ExecutionError(
-                        error.to_string()
-                    ));
-                }
-
-                // Extract tools from result
-                let result = response.get("result")
-                    .ok_or_else(|| MCPError::ProtocolError("No result in response".to_string()))?;
+    pub async fn list_tools(&self) -> Result<Vec<MCPToolInfo>, MCPError> {
+        // Servers without tools have none to list
+        if self.require("tools", |c| c.tools.is_some()).is_err() {
+            return Ok(Vec::new());
+        }
+        let result = self
+            .request("tools/list", Value::Object(serde_json::Map::new()))
+            .await?;
 
-                let tools: Vec<MCToolInfo> = serde_json::from_value(result.get("tools")
-                    .cloned()
-                    .unwrap_or_else(|| serde_json::json!([])))
-                    .map_err(|e| MCPError::ProtocolError(e.to_string()))?;
+        let response: ToolsListResponse = serde_json::from_value(result)
+            .map_err(|e| MCPError::ProtocolError(e.to_string()))?;
 
-                Ok(tools)
-            }
-            MCPTransport::Http { url } => {
-                let response: ToolsListResponse = self.call_json_rpc_method(request, url).await?;
-                Ok(response.tools)
-            }
-            MCPTransport::Sse { url, .. } => {
-                let response: ToolsListResponse = self.call_json_rpc_method(request, url).await?;
-                Ok(response.tools)
-            }
-        }
+        Ok(response.tools)
     }
 
     /// Calls a tool on the MCP server.
     pub async fn call_tool(
-        &mut self,
+        &self,
         name: &str,
         arguments: Value,
-    ) -> Result<String, MCPError> {
+    ) -> Result<MCPToolResult, MCPError> {
         let params = serde_json::json!({
             "name": name,
             "arguments": arguments
         });
 
-        let request = self.create_json_rpc_request("tools/call", params);
+        let result = self.request("tools/call", params).await?;
 
-        match &self.config.transport {
-            MCPTransport::Stdio { .. } => {
-                // Send the tool call request
-                self.send_message_stdio(request).await?;
+        serde_json::from_value(result).map_err(|e| MCPError::ProtocolError(e.to_string()))
+    }
 
-                // Read the JSON response (skips non-JSON lines)
-                let response = self.read_json_response().await?;
+    /// Calls a tool, sending the progress the server reports for the call
+    /// (`notifications/progress`) to `progress` until the call completes.
+    pub async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress: tokio::sync::mpsc::UnboundedSender<MCPProgress>,
+    ) -> Result<MCPToolResult, MCPError> {
+        let token = uuid::Uuid::new_v4().to_string();
+        let params = serde_json::json!({
+            "name": name,
+            "arguments": arguments,
+            "_meta": {"progressToken": token}
+        });
 
-                // Check for JSON-RPC error
-                if let Some(error) = response.get("error") {
-                    return Err(MCPError::ExecutionError(
-                        error.to_string()
-                    ));
-                }
+        let listeners = self.notifications.progress.clone();
+        if let Ok(mut listeners) = listeners.lock() {
+            listeners.insert(token.clone(), progress);
+        }
+        let result = self.request("tools/call", params).await;
+        if let Ok(mut listeners) = listeners.lock() {
+            listeners.remove(&token);
+        }
 
-                // Extract result
-                let result = response.get("result")
-                    .ok_or_else(|| MCPError::ProtocolError("No result in response".to_string()))?;
+        serde_json::from_value(result?).map_err(|e| MCPError::ProtocolError(e.to_string()))
+    }
 
-                // Format the tool result
-                self.extract_tool_result(result.clone())
+    /// Requests completion suggestions for a prompt or resource argument.
+    pub async fn complete_argument(
+        &self,
+        reference: CompletionReference,
+        argument_name: &str,
+        argument_value: &str,
+    ) -> Result<CompletionResult, MCPError> {
+        // Before `completions` was declared, servers with prompts or
+        // resources completed their arguments
+        self.require("completions", |c| c.completions.is_some() || c.prompts.is_some() || c.resources.is_some())?;
+        let params = serde_json::json!({
+            "ref": reference,
+            "argument": {
+                "name": argument_name,
+                "value": argument_value
             }
-            MCPTransport::Http { url } => {
-                let response: Value = self.call_json_rpc_method(request, url).await?;
-                self.extract_tool_result(response)
+        });
+
+        let result = self.request("completion/complete", params).await?;
+
+        let response: CompletionResponse = serde_json::from_value(result)
+            .map_err(|e| MCPError::ProtocolError(e.to_string()))?;
+
+        Ok(response.completion)
+    }
+
+    /// Sets the minimum level of log messages the server should send.
+    pub async fn set_log_level(&self, level: MCPLogLevel) -> Result<(), MCPError> {
+        self.require("logging", |c| c.logging.is_some())?;
+        self.request("logging/setLevel", serde_json::json!({ "level": level }))
+            .await?;
+        Ok(())
+    }
+
+    /// Sends a JSON-RPC request and returns its result.
+    async fn request(&self, method: &str, params: Value) -> Result<Value, MCPError> {
+        let request = self.create_json_rpc_request(method, params);
+
+        match &self.config.transport {
+            MCPTransport::Stdio { .. } => {
+                let stdio = self.stdio.as_ref().ok_or_else(|| {
+                    MCPError::ConnectionError("Not connected".to_string())
+                })?;
+                let response = stdio.request(request, self.config.timeout).await?;
+                Self::into_result(response)
+            }
+            MCPTransport::Sse { .. } => {
+                let sse = self.sse.as_ref().ok_or_else(|| {
+                    MCPError::ConnectionError("Not connected".to_string())
+                })?;
+                let response = sse.request(request, self.config.timeout).await?;
+                Self::into_result(response)
+            }
+            MCPTransport::StreamableHttp { .. } => {
+                let streamable = self.streamable.as_ref().ok_or_else(|| {
+                    MCPError::ConnectionError("Not connected".to_string())
+                })?;
+                let response = streamable.request(request, self.config.timeout).await?;
+                Self::into_result(response)
             }
-            MCPTransport::Sse { url, .. } => {
-                let response: Value = self.call_json_rpc_method(request, url).await?;
-                self.extract_tool_result(response)
+            MCPTransport::Http { url } => {
+                let url = url.clone();
+                self.call_json_rpc_method(request, &url).await
             }
         }
     }
 
+    /// Returns the result of a JSON-RPC response, or its error.
+    fn into_result(response: Value) -> Result<Value, MCPError> {
+        // Check for JSON-RPC error
+        if let Some(error) = response.get("error") {
+            return Err(MCPError::ExecutionError(
+                error.to_string()
+            ));
+        }
+
+        // Extract result
+        response.get("result")
+            .cloned()
+            .ok_or_else(|| MCPError::ProtocolError("No result in response".to_string()))
+    }
+
     /// Creates a JSON-RPC request.
     fn create_json_rpc_request(&self, method: &str, params: Value) -> Value {
         let id = self.message_id.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
@@ -546,13 +941,15 @@ impl MCPClient {
             MCPError::ConnectionError("Not connected".to_string())
         })?;
 
-        let response = client
-            .post(&format!("{}/rpc", url))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| MCPError::HttpError(e.to_string()))?;
+        let response = self
+            .credentials(None)
+            .send(|| {
+                client
+                    .post(format!("{}/rpc", url))
+                    .header("Content-Type", "application/json")
+                    .json(&request)
+            })
+            .await?;
 
         if !response.status().is_success() {
             return Err(MCPError::HttpError(format!(
@@ -582,34 +979,976 @@ impl MCPClient {
         serde_json::from_value(result)
             .map_err(|e| MCPError::ProtocolError(e.to_string()))
     }
-
-    /// Extracts the tool result from a JSON-RPC response.
-    fn extract_tool_result(&self, response: Value) -> Result<String, MCPError> {
-        // Handle the MCP tool call response format
-        // The response may contain structured content
-        Ok(response.to_string())
-    }
 }
 
 /// Information about a tool from the MCP server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MCToolInfo {
+pub struct MCPToolInfo {
     pub name: String,
-    pub description: String,
+    /// A human-readable name for display
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(default)]
+    pub description: String,
+    /// JSON Schema of the arguments; an empty object schema when the
+    /// server sends none
+    #[serde(rename = "inputSchema", alias = "input_schema", default = "empty_object_schema")]
     pub input_schema: Value,
+    /// Hints about the tool's behavior
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<MCPToolAnnotations>,
+}
+
+fn empty_object_schema() -> Value {
+    serde_json::json!({"type": "object", "properties": {}})
+}
+
+impl From<MCPToolInfo> for crate::tool::ToolDefinition {
+    /// Describes the tool by its title when it has no description.
+    fn from(tool: MCPToolInfo) -> Self {
+        let description = match (tool.description.is_empty(), tool.title.or(tool.annotations.and_then(|a| a.title))) {
+            (true, Some(title)) => title,
+            _ => tool.description,
+        };
+        Self {
+            name: tool.name,
+            description,
+            input_schema: tool.input_schema,
+        }
+    }
+}
+
+/// Hints a server gives about a tool's behavior. They are not guaranteed
+/// to be accurate, so untrusted servers' hints should not relax checks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MCPToolAnnotations {
+    /// A human-readable name for display
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify its environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// The tool may perform destructive updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// Repeating a call with the same arguments has no further effect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// The tool interacts with external entities
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+/// The result of a `tools/call` request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MCPToolResult {
+    /// The content blocks returned by the tool
+    #[serde(default)]
+    pub content: Vec<MCPContent>,
+    /// Structured output matching the tool's output schema, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    /// Whether the tool reported a failure
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// A content block of a tool result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MCPContent {
+    /// Plain text
+    Text {
+        text: String,
+    },
+    /// A base64-encoded image
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    /// The contents of a resource, embedded in the result
+    Resource {
+        resource: MCPResourceContents,
+    },
+    /// A link to a resource the client can read
+    ResourceLink {
+        uri: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+    },
+    /// A content type this client does not know, e.g. audio
+    #[serde(other)]
+    Unsupported,
+}
+
+/// The contents of an embedded resource: `text` or base64 `blob`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MCPResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+impl From<MCPToolResult> for ToolResult {
+    /// Joins the text blocks into the output and keeps images, embedded
+    /// resources and links as structured blocks. Failed calls keep their
+    /// text as the output so the model sees why.
+    fn from(result: MCPToolResult) -> Self {
+        let mut texts = Vec::new();
+        let mut blocks = Vec::new();
+        for content in result.content {
+            match content {
+                MCPContent::Text { text } => texts.push(text),
+                MCPContent::Image { data, mime_type } => blocks.push(ToolResultBlock::Image { media_type: mime_type, data }),
+                MCPContent::Resource { resource } => match (resource.text, resource.blob) {
+                    (Some(text), _) => blocks.push(ToolResultBlock::Text { text }),
+                    (None, Some(data)) if resource.mime_type.as_deref().is_some_and(|m| m.starts_with("image/")) => {
+                        blocks.push(ToolResultBlock::Image { media_type: resource.mime_type.unwrap_or_default(), data })
+                    }
+                    _ => blocks.push(ToolResultBlock::Resource {
+                        uri: resource.uri,
+                        name: None,
+                        mime_type: resource.mime_type,
+                    }),
+                },
+                MCPContent::ResourceLink { uri, name, mime_type } => {
+                    blocks.push(ToolResultBlock::Resource { uri, name, mime_type })
+                }
+                MCPContent::Unsupported => {}
+            }
+        }
+        // Structured output normally repeats the text; only show it when alone
+        if texts.is_empty()
+            && blocks.is_empty()
+            && let Some(value) = result.structured_content
+        {
+            blocks.push(ToolResultBlock::Json { value });
+        }
+
+        let output = texts.join("\n");
+        let mut converted = ToolResult::ok(output.clone());
+        if result.is_error {
+            converted.error = Some(output);
+        }
+        blocks.into_iter().fold(converted, ToolResult::with_block)
+    }
+}
+
+/// The optional features a server declared in the initialize handshake.
+/// Each is present, with its settings, when supported.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completions: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<Value>,
+}
+
+/// The name and version a server reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MCPServerInfo {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// The result of the initialize handshake.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Handshake {
+    #[serde(default)]
+    protocol_version: Option<String>,
+    #[serde(default)]
+    capabilities: Option<ServerCapabilities>,
+    #[serde(default)]
+    server_info: Option<MCPServerInfo>,
+}
+
+impl Handshake {
+    /// Parses an initialize result, failing when the server picked a
+    /// protocol version the client does not speak.
+    fn accept(result: Value) -> Result<Self, MCPError> {
+        let handshake: Self = serde_json::from_value(result).map_err(|e| MCPError::ProtocolError(e.to_string()))?;
+        if let Some(version) = &handshake.protocol_version
+            && !PROTOCOL_VERSIONS.contains(&version.as_str())
+        {
+            return Err(MCPError::ProtocolError(format!(
+                "Unsupported protocol version {} (supported: {})",
+                version,
+                PROTOCOL_VERSIONS.join(", ")
+            )));
+        }
+        Ok(handshake)
+    }
+}
+
+/// Progress a server reported for a running request.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MCPProgress {
+    /// Progress so far, increasing with each notification
+    pub progress: f64,
+    /// The total to reach, when known
+    #[serde(default)]
+    pub total: Option<f64>,
+    /// A description of the current step
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl MCPProgress {
+    /// Returns the progress as a fraction between 0 and 1, when the total
+    /// is known.
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0.0)
+            .map(|total| (self.progress / total).clamp(0.0, 1.0))
+    }
+}
+
+/// A log message sent by a server (`notifications/message`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MCPLogMessage {
+    /// The server that sent it
+    pub server: String,
+    /// Its severity
+    pub level: MCPLogLevel,
+    /// The logger that emitted it, if named
+    pub logger: Option<String>,
+    /// The logged data, usually a string
+    pub data: Value,
+}
+
+impl MCPLogMessage {
+    /// Returns the data as text: strings as is, other values as JSON.
+    pub fn text(&self) -> String {
+        match &self.data {
+            Value::String(text) => text.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// Where the notifications of a server are forwarded.
+#[derive(Debug, Clone)]
+struct NotificationSinks {
+    /// Bumped whenever the server reports its tool list changed
+    tools_changed: tokio::sync::watch::Sender<u64>,
+    /// Receivers of the progress of running tool calls, by progress token
+    progress: Arc<std::sync::Mutex<HashMap<String, tokio::sync::mpsc::UnboundedSender<MCPProgress>>>>,
+    logs: tokio::sync::broadcast::Sender<MCPLogMessage>,
+}
+
+/// Severity of MCP server log messages (RFC 5424 levels).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MCPLogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+/// Reference to the prompt or resource whose argument is being completed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CompletionReference {
+    /// A prompt, identified by name
+    #[serde(rename = "ref/prompt")]
+    Prompt {
+        name: String,
+    },
+    /// A resource or resource template, identified by URI
+    #[serde(rename = "ref/resource")]
+    Resource {
+        uri: String,
+    },
+}
+
+/// Completion suggestions returned by the MCP server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionResult {
+    /// Suggested values
+    pub values: Vec<String>,
+    /// Total number of available values, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    /// Whether more values exist beyond those returned
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// Response from completion/complete method.
+#[derive(Debug, Clone, Deserialize)]
+struct CompletionResponse {
+    completion: CompletionResult,
 }
 
 /// Response from tools/list method.
 #[derive(Debug, Clone, Deserialize)]
 pub struct ToolsListResponse {
-    pub tools: Vec<MCToolInfo>,
+    pub tools: Vec<MCPToolInfo>,
 }
 
-impl Drop for MCPClient {
-    fn drop(&mut self) {
-        if let Some(mut process) = self.process.take() {
-            let _ = process.kill();
+#[cfg(test)]
+impl MCPClient {
+    /// Returns a client talking to a server over in-memory streams, with
+    /// the server's ends of them.
+    pub(crate) fn over_io(builder: MCPClientBuilder, name: &str) -> (Self, tokio::io::DuplexStream) {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (output, input) = tokio::io::split(client_io);
+        let mut client = builder
+            .with_name(name)
+            .with_stdio_transport("unused", Vec::new())
+            .build()
+            .unwrap();
+        client.stdio = Some(StdioTransport::from_io(output, input, StdioFraming::Newline, client.server_handlers()));
+        client.set_status(MCPStatus::Connected);
+        (client, server_io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_wire_format() {
+        let reference = CompletionReference::Prompt { name: "code_review".to_string() };
+        assert_eq!(
+            serde_json::to_value(&reference).unwrap(),
+            serde_json::json!({"type": "ref/prompt", "name": "code_review"})
+        );
+
+        let response: CompletionResponse = serde_json::from_value(serde_json::json!({
+            "completion": {"values": ["python", "pytorch"], "total": 10, "hasMore": true}
+        }))
+        .unwrap();
+        assert_eq!(response.completion.values, vec!["python", "pytorch"]);
+        assert_eq!(response.completion.total, Some(10));
+        assert!(response.completion.has_more);
+    }
+
+    #[test]
+    fn test_tool_lists_of_real_servers_keep_schemas_and_annotations() {
+        let parse = |fixture: &str| {
+            let response: Value = serde_json::from_str(fixture).unwrap();
+            serde_json::from_value::<ToolsListResponse>(response["result"].clone()).unwrap().tools
+        };
+
+        let tools = parse(include_str!("fixtures/filesystem_tools_list.json"));
+        assert_eq!(tools[0].input_schema["required"], serde_json::json!(["path"]));
+        assert_eq!(tools[0].title.as_deref(), Some("Read Text File"));
+        assert_eq!(tools[0].annotations.as_ref().unwrap().read_only_hint, Some(true));
+        assert_eq!(tools[1].annotations.as_ref().unwrap().destructive_hint, Some(false));
+
+        let tools = parse(include_str!("fixtures/everything_tools_list.json"));
+        assert_eq!(tools[0].input_schema["properties"]["message"]["type"], "string");
+        let definition = crate::tool::ToolDefinition::from(tools[1].clone());
+        assert_eq!(definition.description, "Annotated Message");
+        assert_eq!(tools[2].input_schema, serde_json::json!({"type": "object", "properties": {}}));
+
+        // Cached manifests written before keep loading
+        let legacy: MCPToolInfo =
+            serde_json::from_value(serde_json::json!({"name": "a", "description": "", "input_schema": {"type": "object"}})).unwrap();
+        assert_eq!(legacy.input_schema, serde_json::json!({"type": "object"}));
+    }
+
+    #[tokio::test]
+    async fn test_initialize_negotiates_version_and_gates_on_capabilities() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        for (version, supported) in [("2024-11-05", true), ("1999-01-01", false)] {
+            let (mut client, server_io) = MCPClient::over_io(MCPClient::builder(), "io-test");
+            let server = tokio::spawn(async move {
+                let (output, mut input) = tokio::io::split(server_io);
+                let mut lines = BufReader::new(output).lines();
+                let request: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+                assert_eq!(request["params"]["protocolVersion"], PROTOCOL_VERSIONS[0]);
+                let result = serde_json::json!({
+                    "protocolVersion": version,
+                    "capabilities": {"tools": {"listChanged": true}},
+                    "serverInfo": {"name": "fake", "version": "1.2.0"}
+                });
+                let response = serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                input.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+                // Only the initialized notification may follow
+                let mut methods = Vec::new();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    methods.push(serde_json::from_str::<Value>(&line).unwrap()["method"].clone());
+                }
+                methods
+            });
+
+            let result = client.initialize().await;
+            if !supported {
+                assert!(matches!(result, Err(MCPError::ProtocolError(_))));
+                continue;
+            }
+            result.unwrap();
+            assert_eq!(client.protocol_version(), Some("2024-11-05"));
+            assert_eq!(client.server_info().unwrap().name, "fake");
+            assert!(client.server_capabilities().unwrap().resources.is_none());
+            assert!(matches!(client.set_log_level(MCPLogLevel::Debug).await, Err(MCPError::Unsupported(_))));
+            drop(client);
+            assert_eq!(server.await.unwrap(), vec![Value::from("notifications/initialized")]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampling_requests_are_answered_by_the_llm() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let llm = Arc::new(crate::testing::MockLLMClient::new().with_text_response("Paris"));
+        let sampling = SamplingHandler::new(llm.clone(), "test-model")
+            .with_max_tokens(50)
+            .with_policy(|_, request| request.system_prompt.is_none());
+        let (_client, server_io) = MCPClient::over_io(MCPClient::builder().with_sampling(sampling), "io-test");
+
+        let (output, mut input) = tokio::io::split(server_io);
+        let mut lines = BufReader::new(output).lines();
+        let ask = |id: u64, system_prompt: Option<&str>| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "sampling/createMessage",
+                "params": {
+                    "messages": [{"role": "user", "content": {"type": "text", "text": "Capital of France?"}}],
+                    "systemPrompt": system_prompt,
+                    "maxTokens": 100
+                }
+            })
+        };
+        for request in [ask(1, None), ask(2, Some("Be verbose"))] {
+            input.write_all(format!("{}\n", request).as_bytes()).await.unwrap();
+        }
+
+        let mut replies = HashMap::new();
+        for _ in 0..2 {
+            let reply: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            replies.insert(reply["id"].as_u64().unwrap(), reply);
+        }
+        assert_eq!(replies[&1]["result"]["content"], serde_json::json!({"type": "text", "text": "Paris"}));
+        assert_eq!(replies[&1]["result"]["model"], "test-model");
+        assert_eq!(replies[&1]["result"]["stopReason"], "endTurn");
+        assert_eq!(replies[&2]["error"]["code"], -1);
+
+        let inputs = llm.inputs();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].max_tokens, 50);
+        assert!(matches!(&inputs[0].messages[0].content[0], crate::session::MessageContent::Text { text } if text == "Capital of France?"));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_tool_calls_are_in_flight_together() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (client, server_io) = MCPClient::over_io(MCPClient::builder(), "io-test");
+        let client = Arc::new(tokio::sync::RwLock::new(client));
+        let tools = crate::mcp::adapt_mcp_tools(
+            client,
+            vec![crate::tool::ToolDefinition {
+                name: "lookup".to_string(),
+                description: "Looks up a key".to_string(),
+                input_schema: serde_json::json!({"type": "object"}),
+            }],
+        );
+
+        // Only answers once both calls arrived, last one first
+        tokio::spawn(async move {
+            let (output, mut input) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(output).lines();
+            let mut requests = Vec::new();
+            while requests.len() < 2 {
+                let line = lines.next_line().await.unwrap().unwrap();
+                requests.push(serde_json::from_str::<Value>(&line).unwrap());
+            }
+            for request in requests.iter().rev() {
+                let response = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": {"content": [{"type": "text", "text": request["params"]["arguments"]["key"]}]}
+                });
+                input.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+            }
+        });
+
+        let (a, b) = tokio::time::timeout(
+            Duration::from_secs(5),
+            futures::future::join(
+                tools[0].execute(serde_json::json!({"key": "a"})),
+                tools[0].execute(serde_json::json!({"key": "b"})),
+            ),
+        )
+        .await
+        .expect("calls were serialized");
+        assert_eq!(a.unwrap().output, "a");
+        assert_eq!(b.unwrap().output, "b");
+    }
+
+    #[tokio::test]
+    async fn test_progress_and_logs_of_tool_calls_are_forwarded() {
+        use crate::tool::{Tool, ToolContext, ToolExecutionEvent};
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (client, server_io) = MCPClient::over_io(MCPClient::builder(), "io-test");
+        let mut logs = client.subscribe_logs();
+        let client = Arc::new(tokio::sync::RwLock::new(client));
+        let tool = crate::mcp::MCPToolAdapter::new(
+            client,
+            crate::tool::ToolDefinition {
+                name: "index".to_string(),
+                description: "Indexes files".to_string(),
+                input_schema: serde_json::json!({"type": "object"}),
+            },
+        );
+
+        tokio::spawn(async move {
+            let (output, mut input) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(output).lines();
+            let request: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            let token = request["params"]["_meta"]["progressToken"].clone();
+            let messages = [
+                serde_json::json!({"jsonrpc": "2.0", "method": "notifications/message", "params": {"level": "warning", "logger": "indexer", "data": "slow disk"}}),
+                serde_json::json!({"jsonrpc": "2.0", "method": "notifications/progress", "params": {"progressToken": token, "progress": 1, "total": 4, "message": "a.rs"}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": {"content": [{"type": "text", "text": "done"}]}}),
+            ];
+            for message in messages {
+                input.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+            }
+        });
+
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let ctx = ToolContext {
+            session_id: "s".to_string(),
+            message_id: "m".to_string(),
+            call_id: "call-1".to_string(),
+            tool_name: "index".to_string(),
+            cancellation: Default::default(),
+            permissions: None,
+            progress: Some(progress_tx),
+        };
+        let result = tool.execute_with_ctx(serde_json::json!({}), &ctx).await.unwrap();
+        assert_eq!(result.output, "done");
+
+        match progress_rx.try_recv() {
+            Ok(ToolExecutionEvent::Progress { call_id, progress, message, .. }) => {
+                assert_eq!(call_id, "call-1");
+                assert_eq!(progress, Some(0.25));
+                assert_eq!(message, "a.rs");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        let log = logs.recv().await.unwrap();
+        assert_eq!((log.level, log.logger.as_deref(), log.text().as_str()), (MCPLogLevel::Warning, Some("indexer"), "slow disk"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_list_changes_reload_the_registry() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (client, server_io) = MCPClient::over_io(MCPClient::builder(), "io-test");
+        let client = Arc::new(tokio::sync::RwLock::new(client));
+        let registry = Arc::new(tokio::sync::Mutex::new(crate::tool::ToolRegistry::new()));
+
+        // Serves one tool, then announces a second one replacing it
+        let server = tokio::spawn(async move {
+            let (output, mut input) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(output).lines();
+            for (i, tools) in [vec!["search"], vec!["search_v2", "fetch"]].into_iter().enumerate() {
+                if i > 0 {
+                    let notification = serde_json::json!({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"});
+                    input.write_all(format!("{}\n", notification).as_bytes()).await.unwrap();
+                }
+                let request: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+                assert_eq!(request["method"], "tools/list");
+                let tools: Vec<_> = tools
+                    .iter()
+                    .map(|name| serde_json::json!({"name": name, "description": "", "input_schema": {}}))
+                    .collect();
+                let response = serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": {"tools": tools}});
+                input.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+            }
+        });
+
+        let watcher = crate::mcp::watch_mcp_tools(client, registry.clone()).await.unwrap();
+        assert!(registry.lock().await.get("search").is_some());
+
+        server.await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while registry.lock().await.get("fetch").is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("tools were not reloaded");
+        let registry = registry.lock().await;
+        assert!(registry.get("search").is_none());
+        assert_eq!(registry.tools_in_group("io-test").len(), 2);
+        watcher.abort();
+    }
+
+    /// A request read by the fake servers: request line, headers with
+    /// lowercase names, and body.
+    type FakeRequest = (String, HashMap<String, String>, String);
+
+    /// Reads one HTTP request from a keep-alive connection.
+    async fn read_request<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Option<FakeRequest> {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await.ok()? == 0 {
+            return None;
+        }
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.ok()?;
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+        let length = headers.get("content-length").map_or(Some(0), |l| l.parse().ok())?;
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await.ok()?;
+        Some((request_line, headers, String::from_utf8(body).ok()?))
+    }
+
+    #[tokio::test]
+    async fn test_sse_transport_discovers_endpoint_and_correlates_responses() {
+        use tokio::io::{AsyncWriteExt, BufReader};
+        use tokio::sync::mpsc;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sse", listener.local_addr().unwrap());
+        let (events_tx, events_rx) = mpsc::unbounded_channel::<String>();
+        let events_rx = Arc::new(tokio::sync::Mutex::new(Some(events_rx)));
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let (events_tx, events_rx) = (events_tx.clone(), events_rx.clone());
+                tokio::spawn(async move {
+                    let (reader, mut writer) = socket.into_split();
+                    let mut reader = BufReader::new(reader);
+                    while let Some((request_line, _, body)) = read_request(&mut reader).await {
+                        if request_line.starts_with("GET /sse") {
+                            let mut events = events_rx.lock().await.take().unwrap();
+                            writer
+                                .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\n")
+                                .await
+                                .unwrap();
+                            writer.write_all(b"event: endpoint\ndata: /messages?session=1\n\n").await.unwrap();
+                            while let Some(event) = events.recv().await {
+                                writer.write_all(event.as_bytes()).await.unwrap();
+                            }
+                            return;
+                        }
+                        assert!(request_line.starts_with("POST /messages?session=1"));
+                        writer
+                            .write_all(b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\n\r\n")
+                            .await
+                            .unwrap();
+                        let message: Value = serde_json::from_str(&body).unwrap();
+                        let Some(id) = message.get("id").cloned() else {
+                            continue;
+                        };
+                        let result = match message["method"].as_str() {
+                            Some("initialize") => serde_json::json!({"protocolVersion": "2024-11-05"}),
+                            Some("tools/list") => serde_json::json!({
+                                "tools": [{"name": "search", "description": "Searches"}]
+                            }),
+                            _ => continue,
+                        };
+                        let log = serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "method": "notifications/message",
+                            "params": {"level": "info", "data": "working"}
+                        });
+                        let response = serde_json::json!({"jsonrpc": "2.0", "id": id, "result": result});
+                        // Split across writes to exercise the decoder
+                        let response = format!("event: message\ndata: {}\n\n", response);
+                        let (head, tail) = response.split_at(10);
+                        events_tx.send(format!("data: {}\n\n", log)).unwrap();
+                        events_tx.send(head.to_string()).unwrap();
+                        events_tx.send(tail.to_string()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let mut client = MCPClient::builder()
+            .with_name("sse-test")
+            .with_sse_transport(url)
+            .with_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        client.connect().await.unwrap();
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "search");
+        client.disconnect().await.unwrap();
+        assert!(matches!(client.list_tools().await, Err(MCPError::ConnectionError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_keeps_session_and_resumes_streams() {
+        use tokio::io::{AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let server_seen = seen.clone();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let seen = server_seen.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = socket.into_split();
+                    let mut reader = BufReader::new(reader);
+                    while let Some((request_line, headers, body)) = read_request(&mut reader).await {
+                        let method = request_line.split(' ').next().unwrap_or_default().to_string();
+                        let session = headers.get("mcp-session-id").cloned();
+                        let message: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+                        seen.lock().unwrap().push((method.clone(), session, headers.get("last-event-id").cloned()));
+                        let response = match (method.as_str(), message["method"].as_str()) {
+                            ("POST", Some("initialize")) => {
+                                let body = serde_json::json!({
+                                    "jsonrpc": "2.0",
+                                    "id": message["id"],
+                                    "result": {"protocolVersion": "2025-03-26"}
+                                })
+                                .to_string();
+                                format!(
+                                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nmcp-session-id: abc\r\ncontent-length: {}\r\n\r\n{}",
+                                    body.len(),
+                                    body
+                                )
+                            }
+                            // The stream breaks before the response
+                            ("POST", Some("tools/list")) => {
+                                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\nid: 1\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n".to_string()
+                            }
+                            ("GET", _) => {
+                                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\nid: 2\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"tools\":[]}}\n\n".to_string()
+                            }
+                            _ => "HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\n\r\n".to_string(),
+                        };
+                        writer.write_all(response.as_bytes()).await.unwrap();
+                        if response.contains("connection: close") {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut client = MCPClient::builder()
+            .with_name("streamable-test")
+            .with_streamable_http_transport(url)
+            .with_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        client.connect().await.unwrap();
+        assert_eq!(client.session_id().as_deref(), Some("abc"));
+
+        assert!(client.list_tools().await.unwrap().is_empty());
+        client.disconnect().await.unwrap();
+
+        let seen = seen.lock().unwrap().clone();
+        let abc = Some("abc".to_string());
+        assert_eq!(
+            seen,
+            vec![
+                ("POST".to_string(), None, None),
+                ("POST".to_string(), abc.clone(), None),
+                ("POST".to_string(), abc.clone(), None),
+                ("GET".to_string(), abc.clone(), Some("1".to_string())),
+                ("DELETE".to_string(), abc, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejected_oauth_token_is_refreshed_and_retried() {
+        use crate::mcp::auth::{MemoryTokenStore, OAuthConfig, OAuthToken, TokenStore};
+        use tokio::io::{AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let authorizations = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = authorizations.clone();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let seen = seen.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = socket.into_split();
+                    let mut reader = BufReader::new(reader);
+                    while let Some((request_line, headers, body)) = read_request(&mut reader).await {
+                        let authorization = headers.get("authorization").cloned().unwrap_or_default();
+                        let (status, body) = if request_line.contains("/token") {
+                            assert!(body.contains("grant_type=refresh_token") && body.contains("refresh_token=r1"));
+                            ("200 OK", serde_json::json!({"access_token": "new", "expires_in": 3600}).to_string())
+                        } else if authorization != "Bearer new" {
+                            ("401 Unauthorized", String::new())
+                        } else {
+                            seen.lock().unwrap().push(authorization);
+                            let message: Value = serde_json::from_str(&body).unwrap();
+                            match message.get("id") {
+                                Some(id) => ("200 OK", serde_json::json!({"jsonrpc": "2.0", "id": id, "result": {}}).to_string()),
+                                None => ("202 Accepted", String::new()),
+                            }
+                        };
+                        let response = format!(
+                            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                            status,
+                            body.len(),
+                            body
+                        );
+                        writer.write_all(response.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let store = Arc::new(MemoryTokenStore::default());
+        let stale = OAuthToken { access_token: "old".to_string(), refresh_token: Some("r1".to_string()), expires_at: None };
+        store.save("remote", &stale).await;
+        let config = OAuthConfig::new("client", format!("{}/authorize", base), format!("{}/token", base), "http://localhost/callback");
+        let oauth = Arc::new(OAuthClient::new("remote", config, store.clone()));
+
+        let mut client = MCPClient::builder()
+            .with_name("remote")
+            .with_streamable_http_transport(format!("{}/mcp", base))
+            .with_oauth(oauth.clone())
+            .build()
+            .unwrap();
+        client.connect().await.unwrap();
+
+        assert_eq!(authorizations.lock().unwrap().len(), 2);
+        let saved = store.load("remote").await.unwrap();
+        assert_eq!(saved.access_token, "new");
+        assert_eq!(saved.refresh_token.as_deref(), Some("r1"));
+
+        let request = oauth.authorization_request();
+        assert!(request.url.contains("code_challenge_method=S256") && request.url.contains(&request.state));
+    }
+
+    #[test]
+    fn test_tool_result_content_blocks_are_parsed() {
+        let result: MCPToolResult = serde_json::from_value(serde_json::json!({
+            "content": [
+                {"type": "text", "text": "Found 2 files"},
+                {"type": "image", "data": "aGk=", "mimeType": "image/png"},
+                {"type": "resource", "resource": {"uri": "file:///a.txt", "mimeType": "text/plain", "text": "hello"}},
+                {"type": "resource_link", "uri": "file:///b.bin", "name": "b.bin"},
+                {"type": "audio", "data": "", "mimeType": "audio/wav"}
+            ],
+            "isError": true
+        }))
+        .unwrap();
+
+        let result = ToolResult::from(result);
+        assert_eq!(result.output, "Found 2 files");
+        assert_eq!(result.error.as_deref(), Some("Found 2 files"));
+        assert_eq!(
+            result.blocks,
+            vec![
+                ToolResultBlock::Image { media_type: "image/png".to_string(), data: "aGk=".to_string() },
+                ToolResultBlock::Text { text: "hello".to_string() },
+                ToolResultBlock::Resource { uri: "file:///b.bin".to_string(), name: Some("b.bin".to_string()), mime_type: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_server_logs_map_to_tracing_levels() {
+        #[derive(Clone, Default)]
+        struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let dispatch = tracing::Dispatch::new(
+            tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::DEBUG)
+                .with_ansi(false)
+                .without_time()
+                .with_writer(move || writer.clone())
+                .finish(),
+        );
+        let sinks = NotificationSinks {
+            tools_changed: tokio::sync::watch::channel(0).0,
+            progress: Default::default(),
+            logs: tokio::sync::broadcast::channel(8).0,
+        };
+        let mut logs = sinks.logs.subscribe();
+
+        let cases = [
+            ("debug", "DEBUG"),
+            ("notice", "INFO"),
+            ("warning", "WARN"),
+            ("critical", "ERROR"),
+            ("bogus", "INFO"),
+        ];
+        for (level, expected) in cases {
+            captured.0.lock().unwrap().clear();
+            tracing::dispatcher::with_default(&dispatch, || {
+                MCPClient::dispatch_notification(
+                    "fs",
+                    &sinks,
+                    &serde_json::json!({
+                        "method": "notifications/message",
+                        "params": {"level": level, "logger": "storage", "data": "disk almost full"}
+                    }),
+                );
+            });
+
+            let line = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+            assert!(line.trim_start().starts_with(expected), "{level}: {line}");
+            assert!(line.contains("disk almost full"), "{line}");
+            assert!(line.contains(r#"server="fs""#) && line.contains(r#"logger="storage""#), "{line}");
+
+            let message = logs.try_recv().unwrap();
+            assert_eq!(message.server, "fs");
+            assert_eq!(message.logger.as_deref(), Some("storage"));
         }
     }
 }
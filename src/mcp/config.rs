@@ -0,0 +1,151 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use super::client::{default_timeout, MCPConfig, MCPError, MCPTransport};
+use super::framing::StdioFraming;
+
+/// A config file in the `mcpServers` format shared by desktop MCP hosts.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigFile {
+    mcp_servers: BTreeMap<String, ServerEntry>,
+}
+
+/// One server: a command to spawn, or the URL of a remote server.
+#[derive(Debug, Deserialize)]
+struct ServerEntry {
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    env: Option<HashMap<String, String>>,
+    url: Option<String>,
+    /// `sse` for the SSE transport; remote servers default to Streamable HTTP
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+/// Reads the servers of a config file, skipping disabled ones.
+pub(crate) fn load(path: &Path) -> Result<Vec<MCPConfig>, MCPError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| MCPError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+    parse(&text)
+}
+
+fn parse(text: &str) -> Result<Vec<MCPConfig>, MCPError> {
+    let config: ConfigFile = serde_json::from_str(text).map_err(|e| MCPError::ConfigError(e.to_string()))?;
+    config
+        .mcp_servers
+        .into_iter()
+        .filter(|(_, entry)| !entry.disabled)
+        .map(|(name, entry)| {
+            let headers = entry
+                .headers
+                .iter()
+                .map(|(header, value)| Ok((header.clone(), expand_env(value)?)))
+                .collect::<Result<_, MCPError>>()?;
+            let transport = transport(&name, entry)?;
+            Ok(MCPConfig {
+                name,
+                transport,
+                timeout: default_timeout(),
+                headers,
+            })
+        })
+        .collect()
+}
+
+fn transport(name: &str, entry: ServerEntry) -> Result<MCPTransport, MCPError> {
+    if let Some(command) = entry.command {
+        let env = entry
+            .env
+            .map(|env| {
+                env.into_iter()
+                    .map(|(key, value)| Ok((key, expand_env(&value)?)))
+                    .collect::<Result<HashMap<_, _>, MCPError>>()
+            })
+            .transpose()?;
+        return Ok(MCPTransport::Stdio {
+            command: expand_env(&command)?,
+            args: entry.args.iter().map(|arg| expand_env(arg)).collect::<Result<_, _>>()?,
+            env,
+            framing: StdioFraming::Auto,
+        });
+    }
+
+    let url = entry
+        .url
+        .ok_or_else(|| MCPError::ConfigError(format!("MCP server `{}` has neither a command nor a url", name)))?;
+    let url = expand_env(&url)?;
+    // Headers, `Authorization` included, are sent from `MCPConfig::headers`
+    Ok(match entry.kind.as_deref() {
+        Some("sse") => MCPTransport::Sse { url, auth: None },
+        _ => MCPTransport::StreamableHttp { url, auth: None },
+    })
+}
+
+/// Replaces `${VAR}` (or `${env:VAR}`) with the value of environment
+/// variable `VAR`, failing when it is not set.
+fn expand_env(value: &str) -> Result<String, MCPError> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        let var = &rest[start + 2..start + end];
+        let var = var.strip_prefix("env:").unwrap_or(var);
+        let value = std::env::var(var)
+            .map_err(|_| MCPError::ConfigError(format!("Environment variable `{}` is not set", var)))?;
+        expanded.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_servers_and_expands_env() {
+        let path = std::env::var("PATH").unwrap();
+        let servers = parse(
+            r#"{
+                "mcpServers": {
+                    "fs": {
+                        "command": "npx",
+                        "args": ["-y", "server-filesystem", "${env:PATH}"],
+                        "env": {"TOKEN": "t-${PATH}"}
+                    },
+                    "remote": {"url": "https://example.com/mcp", "headers": {"Authorization": "Bearer ${PATH}"}},
+                    "legacy": {"url": "https://example.com/sse", "type": "sse"},
+                    "off": {"command": "unused", "disabled": true}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let names: Vec<_> = servers.iter().map(|server| server.name.as_str()).collect();
+        assert_eq!(names, vec!["fs", "legacy", "remote"]);
+        match &servers[0].transport {
+            MCPTransport::Stdio { args, env, .. } => {
+                assert_eq!(args[2], path);
+                assert_eq!(env.as_ref().unwrap()["TOKEN"], format!("t-{}", path));
+            }
+            other => panic!("unexpected transport {:?}", other),
+        }
+        assert!(matches!(&servers[1].transport, MCPTransport::Sse { .. }));
+        assert!(matches!(&servers[2].transport, MCPTransport::StreamableHttp { .. }));
+        assert_eq!(servers[2].headers["Authorization"], format!("Bearer {}", path));
+
+        let missing = parse(r#"{"mcpServers": {"x": {"command": "${MCP_CONFIG_TEST_MISSING}"}}}"#);
+        assert!(matches!(missing, Err(MCPError::ConfigError(_))));
+    }
+}
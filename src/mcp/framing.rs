@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The largest `Content-Length` body accepted from a server.
+const MAX_CONTENT_LENGTH: usize = 8 * 1024 * 1024;
+
+/// How JSON-RPC messages are delimited on a stdio transport.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StdioFraming {
+    /// Read both framings and answer in the framing the server uses.
+    ///
+    /// Messages are newline-delimited until the server's first message
+    /// reveals its framing, so servers that only accept `Content-Length`
+    /// input must be configured explicitly.
+    #[default]
+    Auto,
+    /// One JSON message per line
+    Newline,
+    /// LSP-style `Content-Length` headers followed by the JSON body
+    ContentLength,
+}
+
+/// Writes one message in the given framing. `Auto` writes a line.
+pub async fn write_message(
+    writer: &mut (impl AsyncWrite + Unpin),
+    message: &str,
+    framing: StdioFraming,
+) -> io::Result<()> {
+    let framed = match framing {
+        StdioFraming::ContentLength => format!("Content-Length: {}\r\n\r\n{}", message.len(), message),
+        StdioFraming::Auto | StdioFraming::Newline => format!("{}\n", message),
+    };
+    // One write per message keeps concurrent writers from interleaving
+    writer.write_all(framed.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// Reads the next JSON message in either framing, returning its body and
+/// the framing it arrived in.
+///
+/// Lines that are neither headers nor JSON (e.g. server logs) are skipped.
+/// Returns `UnexpectedEof` when the server closes its output, and
+/// `InvalidData` for bodies over 8 MiB.
+pub async fn read_message(reader: &mut (impl AsyncBufRead + Unpin)) -> io::Result<(String, StdioFraming)> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "MCP server closed its output"));
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some(length) = content_length(trimmed) {
+            // The header is untrusted, so never allocate whatever it claims
+            if length > MAX_CONTENT_LENGTH {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("MCP message of {} bytes exceeds the {} byte limit", length, MAX_CONTENT_LENGTH),
+                ));
+            }
+            // Skip any further headers up to the blank separator line
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).await? == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "MCP server closed its output"));
+                }
+                if header.trim().is_empty() {
+                    break;
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            let body = String::from_utf8(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            return Ok((body, StdioFraming::ContentLength));
+        }
+
+        if serde_json::from_str::<serde_json::Value>(trimmed).is_ok() {
+            return Ok((trimmed.to_string(), StdioFraming::Newline));
+        }
+        tracing::debug!("Skipping non-JSON line: {}", trimmed);
+    }
+}
+
+/// Parses a `Content-Length` header line.
+fn content_length(line: &str) -> Option<usize> {
+    let (name, value) = line.split_once(':')?;
+    if !name.trim().eq_ignore_ascii_case("content-length") {
+        return None;
+    }
+    value.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reads_both_framings() {
+        let mut output = Vec::new();
+        write_message(&mut output, r#"{"id":1}"#, StdioFraming::ContentLength).await.unwrap();
+        output.extend_from_slice(b"server starting...\n");
+        write_message(&mut output, r#"{"id":2}"#, StdioFraming::Newline).await.unwrap();
+        output.extend_from_slice(b"Content-Length: 9\r\nContent-Type: application/json\r\n\r\n{\n\"id\":3}");
+
+        let mut reader = output.as_slice();
+        assert_eq!(read_message(&mut reader).await.unwrap(), (r#"{"id":1}"#.to_string(), StdioFraming::ContentLength));
+        assert_eq!(read_message(&mut reader).await.unwrap(), (r#"{"id":2}"#.to_string(), StdioFraming::Newline));
+        assert_eq!(read_message(&mut reader).await.unwrap(), ("{\n\"id\":3}".to_string(), StdioFraming::ContentLength));
+        assert_eq!(read_message(&mut reader).await.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_content_length() {
+        for length in [MAX_CONTENT_LENGTH + 1, usize::MAX] {
+            let input = format!("Content-Length: {}\r\n\r\n{{}}", length);
+            let error = read_message(&mut input.as_bytes()).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
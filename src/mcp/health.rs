@@ -0,0 +1,215 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use super::client::{MCPClient, MCPStatus};
+
+/// Keepalive and reconnection settings.
+#[derive(Debug, Clone)]
+pub struct HealthCheck {
+    /// Time between keepalive pings
+    pub interval: Duration,
+    /// Delay before the second reconnection attempt; doubled after each
+    /// failed attempt
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between attempts
+    pub max_backoff: Duration,
+    /// Attempts before giving up; unlimited when `None`
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_attempts: None,
+        }
+    }
+}
+
+impl HealthCheck {
+    /// Sets the time between keepalive pings.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the initial and maximum delay between reconnection attempts.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Gives up reconnecting after `attempts` attempts.
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+}
+
+/// Pings the server every `check.interval` and, when a ping fails because
+/// the process died or the session dropped, reconnects with exponential
+/// backoff. Progress shows in `MCPClient::status`.
+///
+/// The task stops when the client is dropped, when it gives up, or when
+/// aborted. Clients disconnected on purpose are not pinged.
+pub fn spawn_health_check(client: Arc<RwLock<MCPClient>>, check: HealthCheck) -> JoinHandle<()> {
+    let client = Arc::downgrade(&client);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(check.interval).await;
+            let Some(client) = client.upgrade() else {
+                break;
+            };
+            let error = {
+                let client = client.read().await;
+                if client.status() != MCPStatus::Connected {
+                    continue;
+                }
+                match client.ping().await {
+                    Ok(()) => continue,
+                    Err(e) => {
+                        tracing::warn!(server = %client.name(), error = %e, "MCP server is unreachable");
+                        e.to_string()
+                    }
+                }
+            };
+            if !reconnect(&client, &check, error).await {
+                break;
+            }
+        }
+    })
+}
+
+/// Reconnects until it succeeds or the attempts run out, returning whether
+/// it succeeded.
+///
+/// Each attempt connects a detached copy of the client, so readers are not
+/// blocked meanwhile, then swaps it in and has tool watchers reload.
+async fn reconnect(client: &RwLock<MCPClient>, check: &HealthCheck, mut error: String) -> bool {
+    let (server, mut lost) = {
+        let mut client = client.write().await;
+        let detached = client.detached();
+        (client.name().to_string(), std::mem::replace(&mut *client, detached))
+    };
+    lost.set_status(MCPStatus::Reconnecting { attempt: 1, error: error.clone() });
+    if let Err(e) = lost.disconnect().await {
+        tracing::debug!(server = %server, error = %e, "Error closing the lost MCP connection");
+    }
+
+    let mut backoff = check.initial_backoff;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let mut fresh = {
+            let client = client.read().await;
+            client.set_status(MCPStatus::Reconnecting { attempt, error: error.clone() });
+            client.detached()
+        };
+        match fresh.connect().await {
+            Ok(()) => {
+                *client.write().await = fresh;
+                client.read().await.notify_tools_changed();
+                tracing::info!(server = %server, attempt, "Reconnected to MCP server");
+                return true;
+            }
+            Err(e) => error = e.to_string(),
+        }
+        if check.max_attempts.is_some_and(|max| attempt >= max) {
+            tracing::error!(server = %server, %error, "Giving up reconnecting to MCP server");
+            client.read().await.set_status(MCPStatus::Failed { error });
+            return false;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(check.max_backoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentEvent, TopicMask};
+    use crate::session::Session;
+    use crate::testing::MockLLMClient;
+    use crate::tool::ToolRegistry;
+
+    #[tokio::test]
+    async fn test_lost_server_is_reported_and_retried() {
+        let (client, server_io) = MCPClient::over_io(MCPClient::builder(), "io-test");
+        let client = Arc::new(RwLock::new(client));
+        let agent = Agent::with_defaults(
+            Session::default(),
+            Arc::new(MockLLMClient::new()),
+            Arc::new(tokio::sync::Mutex::new(ToolRegistry::new())),
+        );
+        let mut events = agent.events(TopicMask::RUN);
+        agent.monitor_mcp_server(&*client.read().await);
+
+        // The server goes away; reconnecting fails as there is no `unused` command
+        drop(server_io);
+        let check = HealthCheck::default()
+            .with_interval(Duration::from_millis(10))
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .with_max_attempts(2);
+        tokio::time::timeout(Duration::from_secs(5), spawn_health_check(client.clone(), check))
+            .await
+            .expect("health check did not give up")
+            .unwrap();
+
+        assert!(matches!(client.read().await.status(), MCPStatus::Failed { .. }));
+        match events.recv().await {
+            Some(AgentEvent::MCPServerDown { server, .. }) => assert_eq!(server, "io-test"),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_does_not_block_readers_and_reloads_tools() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let client = MCPClient::builder().with_name("http-test").with_http_transport(url).build().unwrap();
+        let client = Arc::new(RwLock::new(client));
+        let tools_changed = client.read().await.subscribe_tools_changed();
+
+        // Holds the initialize request until released
+        let (accepted_tx, accepted_rx) = tokio::sync::oneshot::channel();
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 4096];
+            let _ = socket.read(&mut request).await.unwrap();
+            accepted_tx.send(()).unwrap();
+            release_rx.await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}")
+                .await
+                .unwrap();
+        });
+
+        let reconnecting = tokio::spawn({
+            let client = client.clone();
+            async move { reconnect(&client, &HealthCheck::default().with_max_attempts(1), "lost".to_string()).await }
+        });
+        accepted_rx.await.unwrap();
+        {
+            let client = tokio::time::timeout(Duration::from_secs(1), client.write())
+                .await
+                .expect("client stayed locked while connecting");
+            assert!(matches!(client.status(), MCPStatus::Reconnecting { attempt: 1, .. }));
+        }
+        release_tx.send(()).unwrap();
+
+        assert!(reconnecting.await.unwrap());
+        server.await.unwrap();
+        let client = client.read().await;
+        assert_eq!(client.status(), MCPStatus::Connected);
+        assert!(client.is_connected());
+        assert!(tools_changed.has_changed().unwrap());
+    }
+}
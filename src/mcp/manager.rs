@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::tool::{DynTool, ToolDefinition, ToolRegistry};
+use super::adapter::{self, MCPToolAdapter};
+use super::client::{MCPClient, MCPError, MCPToolInfo};
+use super::config;
+use super::health::{self, HealthCheck};
+use super::manifest::ToolManifest;
+
+/// Owns several named MCP clients and exposes their tools together, each
+/// named `{server}__{tool}` so tools of different servers never collide.
+#[derive(Default)]
+pub struct MCPManager {
+    clients: BTreeMap<String, Arc<RwLock<MCPClient>>>,
+    registry: std::sync::Mutex<Option<Arc<Mutex<ToolRegistry>>>>,
+    watchers: std::sync::Mutex<BTreeMap<String, JoinHandle<()>>>,
+    health_check: Option<HealthCheck>,
+    health_tasks: std::sync::Mutex<BTreeMap<String, JoinHandle<()>>>,
+    manifest: Option<Arc<ToolManifest>>,
+    lazy: bool,
+}
+
+impl MCPManager {
+    /// Creates a manager without servers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a manager with the servers of a config file in the
+    /// `mcpServers` format used by desktop MCP hosts. Each server has a
+    /// `command` with `args` and `env`, or a remote `url` (Streamable HTTP,
+    /// or SSE with `"type": "sse"`) with `headers`. `${VAR}` in values is
+    /// replaced with environment variable `VAR`.
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, MCPError> {
+        let mut manager = Self::new();
+        for server in config::load(path.as_ref())? {
+            let mut builder = MCPClient::builder()
+                .with_name(server.name)
+                .with_transport(server.transport)
+                .with_timeout(server.timeout);
+            for (name, value) in server.headers {
+                builder = builder.with_header(name, value);
+            }
+            manager.add_client(builder.build()?);
+        }
+        Ok(manager)
+    }
+
+    /// Adds a server, keyed by the client's name; a server of the same name
+    /// is replaced.
+    pub fn with_client(mut self, client: MCPClient) -> Self {
+        self.add_client(client);
+        self
+    }
+
+    /// Pings connected servers and reconnects lost ones with backoff.
+    pub fn with_health_check(mut self, check: HealthCheck) -> Self {
+        self.health_check = Some(check);
+        self
+    }
+
+    /// Caches the tools of connected servers in a manifest file at `path`.
+    pub fn with_tool_manifest(mut self, path: impl AsRef<Path>) -> Self {
+        self.manifest = Some(Arc::new(ToolManifest::load(path)));
+        self
+    }
+
+    /// Leaves servers whose tools are in the tool manifest disconnected;
+    /// their tools are registered from the manifest and the server connects
+    /// when one is first called. Servers missing from the manifest connect
+    /// as usual. Tool list changes of lazily connected servers are picked up
+    /// on the next start.
+    pub fn with_lazy_connect(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    /// Returns the cached tools of a server that connects on first use.
+    fn deferred_tools(&self, name: &str) -> Option<Vec<MCPToolInfo>> {
+        self.manifest.as_ref().filter(|_| self.lazy)?.tools(name)
+    }
+
+    /// Adds a server, keyed by the client's name, and returns it.
+    pub fn add_client(&mut self, client: MCPClient) -> Arc<RwLock<MCPClient>> {
+        let name = client.name().to_string();
+        let client = Arc::new(RwLock::new(client));
+        self.clients.insert(name, client.clone());
+        client
+    }
+
+    /// Returns the names of the servers, sorted.
+    pub fn server_names(&self) -> Vec<&str> {
+        self.clients.keys().map(String::as_str).collect()
+    }
+
+    /// Returns the client of a server.
+    pub fn client(&self, name: &str) -> Option<Arc<RwLock<MCPClient>>> {
+        self.clients.get(name).cloned()
+    }
+
+    fn get(&self, name: &str) -> Result<&Arc<RwLock<MCPClient>>, MCPError> {
+        self.clients
+            .get(name)
+            .ok_or_else(|| MCPError::ConnectionError(format!("Unknown MCP server `{}`", name)))
+    }
+
+    /// Connects every server that is not connected yet, concurrently, and
+    /// starts their health checks if configured. A server failing to
+    /// connect does not stop the others; the failures are returned by
+    /// server name. In lazy mode, servers with cached tools are skipped.
+    pub async fn connect_all(&self) -> Vec<(String, MCPError)> {
+        let connects = self.clients.iter().map(|(name, client)| async move {
+            let mut client = client.write().await;
+            if client.is_connected() || self.deferred_tools(name).is_some() {
+                return None;
+            }
+            client.connect().await.err().map(|e| (name.clone(), e))
+        });
+        let failures: Vec<_> = futures::future::join_all(connects).await.into_iter().flatten().collect();
+        for (name, error) in &failures {
+            tracing::warn!(server = %name, error = %error, "Failed to connect to MCP server");
+        }
+        if let Some(check) = &self.health_check
+            && let Ok(mut tasks) = self.health_tasks.lock()
+        {
+            for (name, client) in &self.clients {
+                tasks
+                    .entry(name.clone())
+                    .or_insert_with(|| health::spawn_health_check(client.clone(), check.clone()));
+            }
+        }
+        failures
+    }
+
+    /// Reconnects one server, leaving the others untouched. When tools are
+    /// attached to a registry, the server's tools are synced again.
+    pub async fn reconnect(&self, name: &str) -> Result<(), MCPError> {
+        let client = self.get(name)?;
+        {
+            let mut client = client.write().await;
+            if let Err(e) = client.disconnect().await {
+                tracing::debug!(server = %name, error = %e, "Error closing MCP connection before reconnecting");
+            }
+            client.connect().await?;
+        }
+        let registry = self.registry.lock().ok().and_then(|registry| registry.clone());
+        if let Some(registry) = registry {
+            self.watch(name, client.clone(), registry).await?;
+        }
+        Ok(())
+    }
+
+    /// Disconnects every server.
+    pub async fn disconnect_all(&self) {
+        for (name, client) in &self.clients {
+            if let Err(e) = client.write().await.disconnect().await {
+                tracing::warn!(server = %name, error = %e, "Failed to disconnect from MCP server");
+            }
+        }
+    }
+
+    /// Returns the tools of every connected server, and in lazy mode of
+    /// the servers with cached tools, named `{server}__{tool}`.
+    pub async fn tools(&self) -> Result<Vec<DynTool>, MCPError> {
+        let mut tools = Vec::new();
+        for (name, client) in &self.clients {
+            let connection = client.read().await;
+            if connection.is_connected() {
+                for tool in connection.list_tools().await? {
+                    tools.push(Arc::new(self.adapter(name, client, tool, false)) as DynTool);
+                }
+            } else if let Some(cached) = self.deferred_tools(name) {
+                for tool in cached {
+                    tools.push(Arc::new(self.adapter(name, client, tool, true)) as DynTool);
+                }
+            }
+        }
+        Ok(tools)
+    }
+
+    fn adapter(&self, server: &str, client: &Arc<RwLock<MCPClient>>, tool: MCPToolInfo, lazy: bool) -> MCPToolAdapter {
+        let adapter = MCPToolAdapter::new(client.clone(), ToolDefinition::from(tool)).with_prefix(server);
+        match lazy {
+            true => adapter.with_lazy_connect(self.manifest.clone()),
+            false => adapter,
+        }
+    }
+
+    /// Registers the tools of every connected server in `registry`, in a
+    /// group per server, and keeps them in sync as servers report tool
+    /// list changes or are reconnected. In lazy mode, the cached tools of
+    /// servers not connected yet are registered too. Returns the number of
+    /// tools registered; servers failing to list their tools are skipped.
+    pub async fn attach(&self, registry: Arc<Mutex<ToolRegistry>>) -> usize {
+        if let Ok(mut attached) = self.registry.lock() {
+            *attached = Some(registry.clone());
+        }
+        let mut count = 0;
+        for (name, client) in &self.clients {
+            if !client.read().await.is_connected() {
+                if let Some(cached) = self.deferred_tools(name) {
+                    let mut registry = registry.lock().await;
+                    count += cached.len();
+                    for tool in cached {
+                        registry.register_in_group(name.clone(), Arc::new(self.adapter(name, client, tool, true)));
+                    }
+                }
+                continue;
+            }
+            match self.watch(name, client.clone(), registry.clone()).await {
+                Ok(()) => count += registry.lock().await.tools_in_group(name).len(),
+                Err(e) => tracing::warn!(server = %name, error = %e, "Failed to register MCP tools"),
+            }
+            if let Some(manifest) = &self.manifest {
+                let tools = client.read().await.list_tools().await;
+                if let Err(e) = tools.and_then(|tools| manifest.store(name, tools)) {
+                    tracing::warn!(server = %name, error = %e, "Failed to cache MCP tools");
+                }
+            }
+        }
+        count
+    }
+
+    /// Syncs a server's tools into `registry` and replaces its watcher.
+    async fn watch(
+        &self,
+        name: &str,
+        client: Arc<RwLock<MCPClient>>,
+        registry: Arc<Mutex<ToolRegistry>>,
+    ) -> Result<(), MCPError> {
+        let watcher = adapter::watch_tools(client, registry, true).await?;
+        if let Ok(mut watchers) = self.watchers.lock()
+            && let Some(previous) = watchers.insert(name.to_string(), watcher)
+        {
+            previous.abort();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MCPManager {
+    fn drop(&mut self) {
+        for tasks in [&mut self.watchers, &mut self.health_tasks] {
+            if let Ok(tasks) = tasks.get_mut() {
+                for task in tasks.values() {
+                    task.abort();
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for MCPManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MCPManager")
+            .field("servers", &self.server_names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    /// Serves a `search` tool that answers with the server's name.
+    fn serve(name: &'static str, server_io: tokio::io::DuplexStream) {
+        tokio::spawn(async move {
+            let (output, mut input) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(output).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let result = match request["method"].as_str() {
+                    Some("tools/list") => {
+                        serde_json::json!({"tools": [{"name": "search", "description": "Searches", "input_schema": {}}]})
+                    }
+                    _ => serde_json::json!({"content": [{"type": "text", "text": name}]}),
+                };
+                let response = serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                input.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_tools_are_prefixed_per_server() {
+        let mut manager = MCPManager::new();
+        for name in ["docs", "web"] {
+            let (client, server_io) = MCPClient::over_io(MCPClient::builder(), name);
+            serve(name, server_io);
+            manager.add_client(client);
+        }
+        assert!(manager.connect_all().await.is_empty());
+
+        let registry = Arc::new(Mutex::new(ToolRegistry::new()));
+        assert_eq!(manager.attach(registry.clone()).await, 2);
+        let tool = registry.lock().await.get("web__search").cloned().unwrap();
+        let output = tool.execute(serde_json::json!({})).await.unwrap().output;
+        assert_eq!(output, "web");
+        assert_eq!(registry.lock().await.tools_in_group("docs"), vec!["docs__search"]);
+    }
+
+    struct Upper;
+
+    #[async_trait::async_trait]
+    impl crate::tool::Tool for Upper {
+        fn name(&self) -> &str {
+            "upper"
+        }
+
+        fn description(&self) -> &str {
+            "Uppercases text"
+        }
+
+        fn parameters_schema(&self) -> Value {
+            serde_json::json!({"type": "object"})
+        }
+
+        async fn execute(&self, args: Value) -> Result<crate::tool::ToolResult, crate::tool::ToolError> {
+            Ok(args["text"].as_str().unwrap_or_default().to_uppercase().into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lazy_servers_connect_on_first_call() {
+        let mut served = ToolRegistry::new();
+        served.register(Arc::new(Upper));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let server = crate::mcp::MCPServer::new("text", Arc::new(Mutex::new(served)));
+        tokio::spawn(async move { server.serve_listener(listener).await });
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tools.json");
+        let cached = serde_json::json!({"text": [{"name": "upper", "description": "Stale description"}]});
+        std::fs::write(&path, cached.to_string()).unwrap();
+        let client = MCPClient::builder()
+            .with_name("text")
+            .with_streamable_http_transport(url)
+            .build()
+            .unwrap();
+        let manager = MCPManager::new()
+            .with_client(client)
+            .with_tool_manifest(&path)
+            .with_lazy_connect();
+
+        assert!(manager.connect_all().await.is_empty());
+        let registry = Arc::new(Mutex::new(ToolRegistry::new()));
+        assert_eq!(manager.attach(registry.clone()).await, 1);
+        let client = manager.client("text").unwrap();
+        assert!(!client.read().await.is_connected());
+
+        let tool = registry.lock().await.get("text__upper").cloned().unwrap();
+        let output = tool.execute(serde_json::json!({"text": "hi"})).await.unwrap().output;
+        assert_eq!(output, "HI");
+        assert!(client.read().await.is_connected());
+        // The manifest was refreshed from the server
+        let manifest = crate::mcp::ToolManifest::load(&path);
+        assert_eq!(manifest.tools("text").unwrap()[0].description, "Uppercases text");
+    }
+}
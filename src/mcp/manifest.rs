@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::client::{MCPError, MCPToolInfo};
+
+/// The tool lists of MCP servers, cached in a JSON file so their tools can
+/// be registered without connecting first.
+#[derive(Debug)]
+pub struct ToolManifest {
+    path: PathBuf,
+    servers: Mutex<BTreeMap<String, Vec<MCPToolInfo>>>,
+}
+
+impl ToolManifest {
+    /// Loads the manifest at `path`. A missing or unreadable file gives an
+    /// empty manifest, filled as servers connect.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let servers = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| match serde_json::from_str(&text) {
+                Ok(servers) => Some(servers),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Ignoring corrupt MCP tool manifest");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            servers: Mutex::new(servers),
+        }
+    }
+
+    /// Returns the cached tools of a server.
+    pub fn tools(&self, server: &str) -> Option<Vec<MCPToolInfo>> {
+        self.servers.lock().ok()?.get(server).cloned()
+    }
+
+    /// Caches the tools of a server and writes the manifest.
+    pub fn store(&self, server: &str, tools: Vec<MCPToolInfo>) -> Result<(), MCPError> {
+        let text = {
+            let mut servers = self
+                .servers
+                .lock()
+                .map_err(|_| MCPError::ConfigError("Tool manifest lock poisoned".to_string()))?;
+            servers.insert(server.to_string(), tools);
+            serde_json::to_string_pretty(&*servers).map_err(|e| MCPError::ConfigError(e.to_string()))?
+        };
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| MCPError::ConfigError(e.to_string()))?;
+        }
+        std::fs::write(&self.path, text)
+            .map_err(|e| MCPError::ConfigError(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
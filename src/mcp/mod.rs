@@ -1,5 +1,26 @@
 pub mod client;
+mod config;
 pub mod adapter;
+pub mod auth;
+pub mod framing;
+pub mod health;
+pub mod manager;
+pub mod manifest;
+mod rpc;
+pub mod sampling;
+pub mod server;
+mod sse;
+mod stdio;
+mod streamable;
 
-pub use client::{MCPClient, MCPConfig, MCPTransport, MCPError, MCPClientBuilder, MCToolInfo, ToolsListResponse};
-pub use adapter::{MCPToolAdapter, adapt_mcp_tools};
+pub use client::{MCPClient, MCPConfig, MCPTransport, MCPError, MCPClientBuilder, MCPToolInfo, MCPToolAnnotations, MCPToolResult, MCPContent, MCPResourceContents, ToolsListResponse, CompletionReference, CompletionResult, MCPLogLevel, MCPLogMessage, MCPProgress, MCPStatus, MCPServerInfo, ServerCapabilities};
+pub use framing::StdioFraming;
+pub use auth::{OAuthClient, OAuthConfig, OAuthToken, TokenStore, MemoryTokenStore, FileTokenStore, AuthorizationRequest};
+pub use manager::MCPManager;
+pub use manifest::ToolManifest;
+pub use server::MCPServer;
+pub use health::{HealthCheck, spawn_health_check};
+pub use sampling::{SamplingHandler, SamplingRequest, SamplingMessage, SamplingContent, SamplingResult, SamplingPolicy};
+pub use adapter::{MCPToolAdapter, adapt_mcp_tools, sync_mcp_tools, watch_mcp_tools};
+#[allow(deprecated)]
+pub use crate::compat::MCToolInfo;
@@ -0,0 +1,160 @@
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use super::client::MCPError;
+
+/// Called with every notification the server pushes.
+pub(crate) type NotificationHandler = Arc<dyn Fn(&Value) + Send + Sync>;
+
+/// Answers a request the server sends to the client, given its method and
+/// params.
+pub(crate) type RequestHandler = Arc<dyn Fn(String, Value) -> BoxFuture<'static, Result<Value, RpcError>> + Send + Sync>;
+
+/// Handles the messages a server initiates.
+#[derive(Clone)]
+pub(crate) struct ServerHandlers {
+    pub(crate) on_notification: NotificationHandler,
+    pub(crate) on_request: RequestHandler,
+}
+
+impl ServerHandlers {
+    /// Handlers ignoring notifications and rejecting every request.
+    #[cfg(test)]
+    pub(crate) fn none() -> Self {
+        Self {
+            on_notification: Arc::new(|_: &Value| {}),
+            on_request: Arc::new(|method: String, _| Box::pin(async move { Err(RpcError::method_not_found(&method)) })),
+        }
+    }
+
+    /// Answers server request `id`, returning the reply to send back.
+    pub(crate) async fn answer(&self, id: Value, method: String, params: Value) -> Value {
+        match (self.on_request)(method, params).await {
+            Ok(result) => serde_json::json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(error) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": error.code, "message": error.message}
+            }),
+        }
+    }
+}
+
+/// A JSON-RPC error sent in reply to a server request.
+#[derive(Debug, Clone)]
+pub(crate) struct RpcError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    /// The error for methods the client does not support.
+    pub(crate) fn method_not_found(method: &str) -> Self {
+        debug!(%method, "Rejecting MCP server request");
+        Self::new(-32601, format!("Method not found: {}", method))
+    }
+}
+
+/// A JSON-RPC message received from an MCP server.
+#[derive(Debug)]
+pub(crate) enum Incoming {
+    /// The response to one of our requests
+    Response(Value),
+    /// A notification
+    Notification(Value),
+    /// A request the server sends to the client
+    Request { id: Value, method: String, params: Value },
+    /// Anything else
+    Invalid,
+}
+
+impl Incoming {
+    pub(crate) fn classify(message: Value) -> Self {
+        match (message.get("id"), message.get("method")) {
+            (Some(_), None) => Incoming::Response(message),
+            (None, Some(_)) => Incoming::Notification(message),
+            (Some(id), Some(method)) => Incoming::Request {
+                id: id.clone(),
+                method: method.as_str().unwrap_or_default().to_string(),
+                params: message.get("params").cloned().unwrap_or(Value::Null),
+            },
+            (None, None) => Incoming::Invalid,
+        }
+    }
+}
+
+/// Requests waiting for their response, by JSON-RPC id, so responses can
+/// arrive in any order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingRequests(Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>);
+
+impl PendingRequests {
+    /// Registers a request before it is sent, returning its id and the
+    /// receiver of its response.
+    pub(crate) fn register(&self, request: &Value) -> Result<(u64, oneshot::Receiver<Value>), MCPError> {
+        let id = request
+            .get("id")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| MCPError::ProtocolError("Request without a numeric id".to_string()))?;
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut pending) = self.0.lock() {
+            pending.insert(id, tx);
+        }
+        Ok((id, rx))
+    }
+
+    /// Forgets a request that will not be answered.
+    pub(crate) fn cancel(&self, id: u64) {
+        if let Ok(mut pending) = self.0.lock() {
+            pending.remove(&id);
+        }
+    }
+
+    /// Hands a response to the request waiting for it.
+    pub(crate) fn resolve(&self, response: Value) {
+        let id = response.get("id").cloned().unwrap_or(Value::Null);
+        let sender = id.as_u64().and_then(|id| self.0.lock().ok().and_then(|mut p| p.remove(&id)));
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(response);
+            }
+            None => debug!(%id, "Ignoring response to unknown MCP request"),
+        }
+    }
+
+    /// Fails every waiting request, e.g. when the connection closes.
+    pub(crate) fn fail_all(&self) {
+        if let Ok(mut pending) = self.0.lock() {
+            pending.clear();
+        }
+    }
+
+    /// Waits for the response to request `id`, giving up after `timeout`.
+    pub(crate) async fn wait(
+        &self,
+        id: u64,
+        response: oneshot::Receiver<Value>,
+        timeout: Option<Duration>,
+    ) -> Result<Value, MCPError> {
+        let closed = || MCPError::ConnectionError("Connection closed before the response".to_string());
+        match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, response).await {
+                Ok(response) => response.map_err(|_| closed()),
+                Err(_) => {
+                    self.cancel(id);
+                    Err(MCPError::Timeout)
+                }
+            },
+            None => response.await.map_err(|_| closed()),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::llm::{FinishReason, LLMClient, LLMInput};
+use crate::session::{Message, MessageContent, MessageRole};
+use super::client::MCPError;
+
+/// Decides whether a server may sample, given its name and the request.
+pub type SamplingPolicy = Arc<dyn Fn(&str, &SamplingRequest) -> bool + Send + Sync>;
+
+/// A `sampling/createMessage` request: a server asking the client's model
+/// for a completion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingRequest {
+    /// The conversation to complete
+    pub messages: Vec<SamplingMessage>,
+    /// The system prompt the server asks for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Maximum tokens to generate
+    pub max_tokens: u32,
+    /// Optional temperature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// The server's model preferences, as sent; advisory only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<Value>,
+}
+
+/// A message of a sampling request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingMessage {
+    /// Who sent the message
+    pub role: MessageRole,
+    /// The content of the message
+    pub content: SamplingContent,
+}
+
+/// The content of a sampling message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SamplingContent {
+    /// Plain text
+    Text {
+        /// The text
+        text: String,
+    },
+    /// An image
+    Image {
+        /// The base64-encoded image bytes
+        data: String,
+        /// The MIME type, e.g. `image/png`
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+/// The completion returned to the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingResult {
+    /// Always the assistant
+    pub role: MessageRole,
+    /// The generated content
+    pub content: SamplingContent,
+    /// The model that generated it
+    pub model: String,
+    /// Why generation stopped: `endTurn` or `maxTokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+/// Answers servers' sampling requests with an `LLMClient`, typically the
+/// agent's own (see `Agent::sampling_handler`).
+#[derive(Clone)]
+pub struct SamplingHandler {
+    llm: Arc<dyn LLMClient>,
+    model: String,
+    max_tokens: Option<u32>,
+    policy: Option<SamplingPolicy>,
+}
+
+impl SamplingHandler {
+    /// Creates a handler completing with `model`. Every request is allowed
+    /// until a policy is set.
+    pub fn new(llm: Arc<dyn LLMClient>, model: impl Into<String>) -> Self {
+        Self {
+            llm,
+            model: model.into(),
+            max_tokens: None,
+            policy: None,
+        }
+    }
+
+    /// Caps the tokens a server may request per completion.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sets the policy deciding which requests are answered; rejected
+    /// requests get an error reply.
+    pub fn with_policy(mut self, policy: impl Fn(&str, &SamplingRequest) -> bool + Send + Sync + 'static) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Completes a request sent by `server`.
+    pub async fn create_message(&self, server: &str, request: SamplingRequest) -> Result<SamplingResult, MCPError> {
+        if let Some(policy) = &self.policy
+            && !policy(server, &request)
+        {
+            return Err(MCPError::SamplingRejected(format!("Sampling request from `{}` was rejected", server)));
+        }
+
+        let messages = request
+            .messages
+            .into_iter()
+            .map(|message| {
+                let content = match message.content {
+                    SamplingContent::Text { text } => MessageContent::Text { text },
+                    SamplingContent::Image { data, mime_type } => MessageContent::Image { media_type: mime_type, data },
+                };
+                let mut converted = Message::new_assistant(vec![content]);
+                if !matches!(message.role, MessageRole::Assistant) {
+                    converted.role = MessageRole::User;
+                }
+                converted
+            })
+            .collect();
+        let max_tokens = match self.max_tokens {
+            Some(cap) => request.max_tokens.min(cap),
+            None => request.max_tokens,
+        };
+        let input = LLMInput {
+            model: self.model.clone(),
+            messages,
+            system_prompt: request.system_prompt.unwrap_or_default(),
+            tools: Vec::new(),
+            max_tokens,
+            temperature: request.temperature,
+            request_options: Default::default(),
+            tool_generation: None,
+        };
+
+        let output = self
+            .llm
+            .complete(input)
+            .await
+            .map_err(|e| MCPError::ExecutionError(e.to_string()))?;
+        let text = output
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<String>();
+        let stop_reason = match output.finish_reason {
+            FinishReason::MaxTokens => "maxTokens",
+            _ => "endTurn",
+        };
+
+        Ok(SamplingResult {
+            role: MessageRole::Assistant,
+            content: SamplingContent::Text { text },
+            model: self.model.clone(),
+            stop_reason: Some(stop_reason.to_string()),
+        })
+    }
+}
+
+impl std::fmt::Debug for SamplingHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SamplingHandler")
+            .field("model", &self.model)
+            .field("max_tokens", &self.max_tokens)
+            .field("has_policy", &self.policy.is_some())
+            .finish()
+    }
+}
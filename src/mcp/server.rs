@@ -0,0 +1,429 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+use crate::session::{MessageContent, ToolResultBlock};
+use crate::tool::{ToolRegistry, ToolResult};
+use super::client::{MCPContent, MCPError, MCPToolResult, PROTOCOL_VERSIONS};
+use super::framing;
+use super::rpc::{Incoming, RpcError};
+
+/// Header carrying the Streamable HTTP session id.
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Serves the tools of a `ToolRegistry` as an MCP server, so tools written
+/// against the `Tool` trait can be used by any MCP client.
+///
+/// Only enabled tools are listed and callable; the registry is read on
+/// every request, so tools registered later are served too.
+#[derive(Clone)]
+pub struct MCPServer {
+    name: String,
+    version: String,
+    registry: Arc<Mutex<ToolRegistry>>,
+    sessions: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+impl MCPServer {
+    /// Creates a server named `name` serving the tools of `registry`.
+    pub fn new(name: impl Into<String>, registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        Self {
+            name: name.into(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            registry,
+            sessions: Default::default(),
+        }
+    }
+
+    /// Sets the version reported to clients; defaults to the crate version.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Handles one JSON-RPC message, returning the response to send back;
+    /// notifications and responses get none.
+    pub async fn handle(&self, message: Value) -> Option<Value> {
+        let Incoming::Request { id, method, params } = Incoming::classify(message) else {
+            return None;
+        };
+        let reply = match self.answer(&method, params).await {
+            Ok(result) => serde_json::json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(error) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": error.code, "message": error.message}
+            }),
+        };
+        Some(reply)
+    }
+
+    async fn answer(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str).unwrap_or_default();
+                let version = PROTOCOL_VERSIONS
+                    .into_iter()
+                    .find(|version| *version == requested)
+                    .unwrap_or(PROTOCOL_VERSIONS[0]);
+                Ok(serde_json::json!({
+                    "protocolVersion": version,
+                    "capabilities": {"tools": {"listChanged": false}},
+                    "serverInfo": {"name": self.name, "version": self.version}
+                }))
+            }
+            "ping" => Ok(serde_json::json!({})),
+            "tools/list" => {
+                let tools: Vec<_> = self
+                    .registry
+                    .lock()
+                    .await
+                    .to_tool_definitions()
+                    .into_iter()
+                    .map(|tool| {
+                        serde_json::json!({
+                            "name": tool.name,
+                            "description": tool.description,
+                            "inputSchema": tool.input_schema
+                        })
+                    })
+                    .collect();
+                Ok(serde_json::json!({"tools": tools}))
+            }
+            "tools/call" => {
+                let name = params
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| RpcError::new(-32602, "Missing tool name"))?;
+                let arguments = params.get("arguments").cloned().unwrap_or_else(|| serde_json::json!({}));
+                // Release the registry before running the tool
+                let tool = self.registry.lock().await.get(name).cloned();
+                let tool = tool.ok_or_else(|| RpcError::new(-32602, format!("Unknown tool: {}", name)))?;
+                let result = match tool.execute(arguments).await {
+                    Ok(result) => MCPToolResult::from(result),
+                    Err(error) => MCPToolResult {
+                        content: vec![MCPContent::Text { text: error.to_string() }],
+                        structured_content: None,
+                        is_error: true,
+                    },
+                };
+                serde_json::to_value(result).map_err(|e| RpcError::new(-32603, e.to_string()))
+            }
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    /// Serves one client over the process's stdin and stdout, until stdin
+    /// closes. This is how desktop MCP hosts run local servers.
+    pub async fn serve_stdio(&self) -> Result<(), MCPError> {
+        self.serve_io(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// Serves one client over a pair of streams until `input` closes.
+    /// Requests run concurrently; each reply uses the framing its request
+    /// arrived in.
+    pub async fn serve_io(
+        &self,
+        input: impl AsyncRead + Unpin,
+        output: impl AsyncWrite + Unpin + Send + 'static,
+    ) -> Result<(), MCPError> {
+        let mut reader = BufReader::new(input);
+        let output = Arc::new(Mutex::new(output));
+        loop {
+            let (body, framing) = match framing::read_message(&mut reader).await {
+                Ok(message) => message,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(MCPError::ConnectionError(e.to_string())),
+            };
+            let message = match serde_json::from_str(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::debug!(error = %e, "Ignoring malformed MCP message");
+                    continue;
+                }
+            };
+            let server = self.clone();
+            let output = output.clone();
+            tokio::spawn(async move {
+                if let Some(reply) = server.handle(message).await {
+                    let mut output = output.lock().await;
+                    if let Err(e) = framing::write_message(&mut *output, &reply.to_string(), framing).await {
+                        tracing::warn!(error = %e, "Failed to write MCP reply");
+                    }
+                }
+            });
+        }
+    }
+
+    /// Serves clients over Streamable HTTP at `addr`, answering POSTs to
+    /// any path. Runs until the listener fails.
+    pub async fn serve_http(&self, addr: impl tokio::net::ToSocketAddrs) -> Result<(), MCPError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| MCPError::ConnectionError(e.to_string()))?;
+        self.serve_listener(listener).await
+    }
+
+    /// Serves clients over Streamable HTTP on a bound listener.
+    pub async fn serve_listener(&self, listener: TcpListener) -> Result<(), MCPError> {
+        loop {
+            let (socket, peer) = listener
+                .accept()
+                .await
+                .map_err(|e| MCPError::ConnectionError(e.to_string()))?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.serve_connection(socket).await {
+                    tracing::debug!(%peer, error = %e, "MCP HTTP connection closed");
+                }
+            });
+        }
+    }
+
+    /// Answers the requests of one keep-alive connection.
+    async fn serve_connection(&self, socket: TcpStream) -> std::io::Result<()> {
+        let (reader, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(reader);
+        while let Some(request) = HttpRequest::read(&mut reader).await? {
+            let response = self.respond(request).await;
+            writer.write_all(&response.into_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    async fn respond(&self, request: HttpRequest) -> HttpResponse {
+        let session = request.headers.get(SESSION_HEADER).cloned();
+        let known = |session: &Option<String>| {
+            session
+                .as_ref()
+                .is_some_and(|id| self.sessions.lock().map(|sessions| sessions.contains(id)).unwrap_or(false))
+        };
+        match request.method.as_str() {
+            "POST" => {}
+            "DELETE" if known(&session) => {
+                if let (Ok(mut sessions), Some(id)) = (self.sessions.lock(), session) {
+                    sessions.remove(&id);
+                }
+                return HttpResponse::new("200 OK");
+            }
+            "DELETE" => return HttpResponse::new("404 Not Found"),
+            // There are no server-initiated messages to stream
+            _ => return HttpResponse::new("405 Method Not Allowed"),
+        }
+
+        let message: Value = match serde_json::from_slice(&request.body) {
+            Ok(message) => message,
+            Err(e) => return HttpResponse::new("400 Bad Request").with_json(&parse_error(e)),
+        };
+        let initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+        if !initialize && session.is_some() && !known(&session) {
+            return HttpResponse::new("404 Not Found");
+        }
+
+        match self.handle(message).await {
+            None => HttpResponse::new("202 Accepted"),
+            Some(reply) => {
+                let mut response = HttpResponse::new("200 OK").with_json(&reply);
+                if initialize && reply.get("result").is_some() {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    if let Ok(mut sessions) = self.sessions.lock() {
+                        sessions.insert(id.clone());
+                    }
+                    response.headers.push((SESSION_HEADER, id));
+                }
+                response
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for MCPServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MCPServer")
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl From<ToolResult> for MCPToolResult {
+    /// Sends the output as text, followed by the images and structured
+    /// blocks. Results with an error are flagged as failed.
+    fn from(result: ToolResult) -> Self {
+        let mut content = Vec::new();
+        let text = match (&result.error, result.output.is_empty()) {
+            (Some(error), true) => error.clone(),
+            _ => result.output,
+        };
+        if !text.is_empty() {
+            content.push(MCPContent::Text { text });
+        }
+        for image in result.images {
+            if let MessageContent::Image { media_type, data } = image {
+                content.push(MCPContent::Image { data, mime_type: media_type });
+            }
+        }
+        for block in result.blocks {
+            content.push(match block {
+                ToolResultBlock::Text { .. } | ToolResultBlock::Json { .. } => MCPContent::Text { text: block.render() },
+                ToolResultBlock::Image { media_type, data } => MCPContent::Image { data, mime_type: media_type },
+                ToolResultBlock::Resource { uri, name, mime_type } => MCPContent::ResourceLink { uri, name, mime_type },
+            });
+        }
+        Self {
+            content,
+            structured_content: None,
+            is_error: result.error.is_some(),
+        }
+    }
+}
+
+fn parse_error(error: serde_json::Error) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": {"code": -32700, "message": error.to_string()}
+    })
+}
+
+/// An HTTP/1.1 request, as much of it as the server needs.
+#[derive(Debug)]
+struct HttpRequest {
+    method: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Reads the next request, or `None` when the client closed the
+    /// connection.
+    async fn read(reader: &mut (impl AsyncBufRead + Unpin)) -> std::io::Result<Option<Self>> {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await? == 0 {
+            return Ok(None);
+        }
+        let method = request_line.split_whitespace().next().unwrap_or_default().to_string();
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(None);
+            }
+            let Some((name, value)) = line.trim_end().split_once(':') else {
+                break;
+            };
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+
+        let length = headers.get("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await?;
+        Ok(Some(Self { method, headers, body }))
+    }
+}
+
+/// An HTTP/1.1 response with an optional JSON body.
+struct HttpResponse {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl HttpResponse {
+    fn new(status: &'static str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    fn with_json(mut self, body: &Value) -> Self {
+        self.headers.push(("content-type", "application/json".to_string()));
+        self.body = body.to_string();
+        self
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\ncontent-length: {}\r\n", self.status, self.body.len());
+        for (name, value) in self.headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        response.push_str(&self.body);
+        response.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::{MCPClient, StdioFraming};
+    use crate::tool::{Tool, ToolError};
+
+    #[derive(Debug)]
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl Tool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echoes its input"
+        }
+
+        fn parameters_schema(&self) -> Value {
+            serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}}})
+        }
+
+        async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+            match args["text"].as_str() {
+                Some(text) => Ok(ToolResult::ok(text)),
+                None => Err(ToolError::InvalidArguments("Missing text".to_string())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_tools_are_served_over_http_and_stdio() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(Echo));
+        let server = MCPServer::new("local", Arc::new(Mutex::new(registry)));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let http = server.clone();
+        tokio::spawn(async move { http.serve_listener(listener).await });
+
+        let mut client = MCPClient::builder()
+            .with_name("local")
+            .with_streamable_http_transport(url)
+            .build()
+            .unwrap();
+        client.connect().await.unwrap();
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "echo");
+        let result = client.call_tool("echo", serde_json::json!({"text": "hi"})).await.unwrap();
+        assert_eq!(ToolResult::from(result).output, "hi");
+        let failed = client.call_tool("echo", serde_json::json!({})).await.unwrap();
+        assert!(failed.is_error);
+        client.disconnect().await.unwrap();
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (input, output) = tokio::io::split(server_io);
+        tokio::spawn(async move { server.serve_io(input, output).await });
+        let (reader, mut writer) = tokio::io::split(client_io);
+        let request = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "nope"}});
+        framing::write_message(&mut writer, &request.to_string(), StdioFraming::ContentLength).await.unwrap();
+        let (reply, framing) = framing::read_message(&mut BufReader::new(reader)).await.unwrap();
+        assert_eq!(framing, StdioFraming::ContentLength);
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["error"]["code"], -32602);
+    }
+}
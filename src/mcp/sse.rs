@@ -0,0 +1,246 @@
+use futures::StreamExt;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use super::auth::Credentials;
+use super::client::MCPError;
+use super::rpc::{Incoming, PendingRequests, ServerHandlers};
+
+/// An event of a server-sent event stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SseEvent {
+    /// The event name; `message` when the server sets none
+    pub event: String,
+    /// The data lines, joined with newlines
+    pub data: String,
+    /// The event ID, used to resume the stream after it
+    pub id: Option<String>,
+}
+
+/// Splits a byte stream into server-sent events. Chunks may end anywhere,
+/// including inside a line or a UTF-8 sequence.
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseDecoder {
+    /// Feeds a chunk, returning the events it completed.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let (event, id) = (self.event.take(), self.id.take());
+            if self.data.is_empty() {
+                return None;
+            }
+            return Some(SseEvent {
+                event: event.unwrap_or_else(|| "message".to_string()),
+                data: std::mem::take(&mut self.data).join("\n"),
+                id,
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// The MCP SSE transport: the server streams messages over a long-lived
+/// `GET`, and the client posts its messages to the endpoint announced in
+/// the stream's first `endpoint` event.
+pub(crate) struct SseTransport {
+    poster: Poster,
+    pending: PendingRequests,
+    reader: JoinHandle<()>,
+}
+
+/// Posts messages to the session's endpoint.
+#[derive(Clone)]
+struct Poster {
+    client: reqwest::Client,
+    endpoint: String,
+    credentials: Credentials,
+}
+
+impl Poster {
+    async fn post(&self, message: &Value) -> Result<(), MCPError> {
+        let response = self
+            .credentials
+            .send(|| self.client.post(&self.endpoint).json(message))
+            .await?;
+        if !response.status().is_success() {
+            return Err(MCPError::HttpError(format!(
+                "HTTP error: {} - {}",
+                response.status(),
+                response.text().await.unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl SseTransport {
+    /// Opens the event stream and waits for the server to announce where
+    /// messages are posted.
+    pub(crate) async fn connect(
+        client: reqwest::Client,
+        url: &str,
+        credentials: Credentials,
+        timeout: Duration,
+        handlers: ServerHandlers,
+    ) -> Result<Self, MCPError> {
+        let request = || client.get(url).header(reqwest::header::ACCEPT, "text/event-stream");
+        let response = tokio::time::timeout(timeout, credentials.send(request))
+            .await
+            .map_err(|_| MCPError::Timeout)??;
+        if !response.status().is_success() {
+            return Err(MCPError::HttpError(format!("HTTP error: {}", response.status())));
+        }
+
+        let base = response.url().clone();
+        let mut stream = response.bytes_stream();
+        let mut decoder = SseDecoder::default();
+        let mut backlog = Vec::new();
+        let endpoint = tokio::time::timeout(timeout, async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| MCPError::HttpError(e.to_string()))?;
+                let mut events = decoder.push(&chunk).into_iter();
+                if let Some(endpoint) = events.by_ref().find(|e| e.event == "endpoint") {
+                    backlog.extend(events);
+                    return base
+                        .join(endpoint.data.trim())
+                        .map_err(|e| MCPError::ProtocolError(format!("Invalid message endpoint: {}", e)));
+                }
+            }
+            Err(MCPError::ConnectionError("SSE stream closed before the endpoint event".to_string()))
+        })
+        .await
+        .map_err(|_| MCPError::Timeout)??;
+        debug!(%endpoint, "Discovered MCP message endpoint");
+
+        let poster = Poster {
+            client,
+            endpoint: endpoint.to_string(),
+            credentials,
+        };
+        let pending = PendingRequests::default();
+        let reader = tokio::spawn({
+            let poster = poster.clone();
+            let pending = pending.clone();
+            async move {
+                for event in backlog {
+                    Self::dispatch(&event, &pending, &poster, &handlers);
+                }
+                while let Some(Ok(chunk)) = stream.next().await {
+                    for event in decoder.push(&chunk) {
+                        Self::dispatch(&event, &pending, &poster, &handlers);
+                    }
+                }
+                debug!("MCP SSE stream closed");
+                // Waiting requests fail instead of hanging
+                pending.fail_all();
+            }
+        });
+
+        Ok(Self { poster, pending, reader })
+    }
+
+    /// Routes a message from the stream: responses to their request,
+    /// notifications and server requests to the handlers.
+    fn dispatch(event: &SseEvent, pending: &PendingRequests, poster: &Poster, handlers: &ServerHandlers) {
+        if event.event != "message" {
+            return;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(&event.data) else {
+            debug!(data = %event.data, "Ignoring malformed MCP message");
+            return;
+        };
+        match Incoming::classify(message) {
+            Incoming::Response(response) => pending.resolve(response),
+            Incoming::Notification(notification) => (handlers.on_notification)(&notification),
+            Incoming::Request { id, method, params } => {
+                let (poster, handlers) = (poster.clone(), handlers.clone());
+                tokio::spawn(async move {
+                    let reply = handlers.answer(id, method, params).await;
+                    let _ = poster.post(&reply).await;
+                });
+            }
+            Incoming::Invalid => debug!("Ignoring MCP message without id or method"),
+        }
+    }
+
+    /// Posts a request and waits for its response on the stream.
+    pub(crate) async fn request(&self, message: Value, timeout: Duration) -> Result<Value, MCPError> {
+        let (id, response) = self.pending.register(&message)?;
+        if let Err(e) = self.poster.post(&message).await {
+            self.pending.cancel(id);
+            return Err(e);
+        }
+        self.pending.wait(id, response, Some(timeout)).await
+    }
+
+    /// Posts a notification.
+    pub(crate) async fn notify(&self, message: Value) -> Result<(), MCPError> {
+        self.poster.post(&message).await
+    }
+}
+
+impl Drop for SseTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl std::fmt::Debug for SseTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SseTransport")
+            .field("endpoint", &self.poster.endpoint)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_handles_split_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b": keep-alive\r\nevent: endp").is_empty());
+        let events = decoder.push(b"oint\r\ndata: /messages?id=1\r\n\r\nid: 7\ndata: {\"a\":\ndata: 1}\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent { event: "endpoint".to_string(), data: "/messages?id=1".to_string(), id: None },
+                SseEvent { event: "message".to_string(), data: "{\"a\":\n1}".to_string(), id: Some("7".to_string()) },
+            ]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use super::client::MCPError;
+use super::framing::{self, StdioFraming};
+use super::rpc::{Incoming, PendingRequests, ServerHandlers};
+
+type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Lines of the server's stderr kept for error messages.
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Writes framed messages to the server's stdin.
+#[derive(Clone)]
+struct Writer {
+    inner: Arc<tokio::sync::Mutex<Option<BoxedWriter>>>,
+    framing: StdioFraming,
+    detected: Arc<Mutex<Option<StdioFraming>>>,
+}
+
+impl Writer {
+    /// Returns the framing used for outgoing messages: the configured one,
+    /// or in auto mode the one the server was seen using.
+    fn framing(&self) -> StdioFraming {
+        match self.framing {
+            StdioFraming::Auto => self
+                .detected
+                .lock()
+                .ok()
+                .and_then(|detected| *detected)
+                .unwrap_or(StdioFraming::Newline),
+            framing => framing,
+        }
+    }
+
+    async fn send(&self, message: &Value) -> Result<(), MCPError> {
+        let message = serde_json::to_string(message).map_err(|e| MCPError::ProtocolError(e.to_string()))?;
+        let framing = self.framing();
+        let mut inner = self.inner.lock().await;
+        let writer = inner
+            .as_mut()
+            .ok_or_else(|| MCPError::ConnectionError("Not connected".to_string()))?;
+        framing::write_message(writer, &message, framing)
+            .await
+            .map_err(|e| MCPError::ConnectionError(format!("Failed to write to stdin: {}", e)))
+    }
+
+    /// Closes stdin, which tells the server to exit.
+    async fn close(&self) {
+        self.inner.lock().await.take();
+    }
+}
+
+/// The last lines the server wrote to stderr, so connection errors can say
+/// why a server died.
+struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    // Becomes true once the server closed stderr, usually by exiting
+    closed: watch::Receiver<bool>,
+}
+
+impl StderrTail {
+    /// Reads the server's stderr in the background, emitting each line
+    /// through `tracing`.
+    fn capture(server: &str, stderr: ChildStderr) -> Self {
+        let lines = Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_TAIL_LINES)));
+        let (closed_tx, closed) = watch::channel(false);
+        let (server, tail) = (server.to_string(), lines.clone());
+        tokio::spawn(async move {
+            let mut stderr = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = stderr.next_line().await {
+                debug!(server = %server, "{}", line);
+                if let Ok(mut tail) = tail.lock() {
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+            }
+            let _ = closed_tx.send(true);
+        });
+        Self { lines, closed }
+    }
+
+    /// Appends the stderr tail to connection errors.
+    async fn annotate(&self, error: MCPError) -> MCPError {
+        let MCPError::ConnectionError(message) = error else {
+            return error;
+        };
+        // A crashing server's last lines may still be in flight
+        let mut closed = self.closed.clone();
+        let _ = tokio::time::timeout(Duration::from_millis(200), closed.wait_for(|closed| *closed)).await;
+        let tail = self.lines.lock().map(|lines| Vec::from(lines.clone())).unwrap_or_default();
+        if tail.is_empty() {
+            return MCPError::ConnectionError(message);
+        }
+        MCPError::ConnectionError(format!("{}\nServer stderr:\n{}", message, tail.join("\n")))
+    }
+}
+
+/// The MCP stdio transport: messages go to the server's stdin, and a
+/// background task reads its stdout, routing responses to their requests
+/// by id.
+pub(crate) struct StdioTransport {
+    child: Option<Child>,
+    writer: Writer,
+    pending: PendingRequests,
+    reader: JoinHandle<()>,
+    stderr: Option<StderrTail>,
+}
+
+impl StdioTransport {
+    /// Starts the server process; `server` names it in logs.
+    pub(crate) fn spawn(
+        server: &str,
+        command: &str,
+        args: &[String],
+        env: &Option<HashMap<String, String>>,
+        framing: StdioFraming,
+        handlers: ServerHandlers,
+    ) -> Result<Self, MCPError> {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(env_vars) = env {
+            cmd.envs(env_vars);
+        }
+
+        let mut child = cmd.spawn().map_err(|e| {
+            MCPError::ConnectionError(format!("Failed to start MCP server: {}", e))
+        })?;
+        let stdin = child.stdin.take().ok_or_else(|| {
+            MCPError::ConnectionError("Failed to get stdin".to_string())
+        })?;
+        let stdout = child.stdout.take().ok_or_else(|| {
+            MCPError::ConnectionError("Failed to get stdout".to_string())
+        })?;
+
+        let stderr = child.stderr.take().map(|stderr| StderrTail::capture(server, stderr));
+
+        let mut transport = Self::from_io(stdout, stdin, framing, handlers);
+        transport.child = Some(child);
+        transport.stderr = stderr;
+        Ok(transport)
+    }
+
+    /// Speaks to a server over the given streams.
+    pub(crate) fn from_io(
+        output: impl AsyncRead + Send + Unpin + 'static,
+        input: impl AsyncWrite + Send + Unpin + 'static,
+        framing: StdioFraming,
+        handlers: ServerHandlers,
+    ) -> Self {
+        let writer = Writer {
+            inner: Arc::new(tokio::sync::Mutex::new(Some(Box::new(input)))),
+            framing,
+            detected: Arc::default(),
+        };
+        let pending = PendingRequests::default();
+        let reader = tokio::spawn({
+            let (writer, pending) = (writer.clone(), pending.clone());
+            async move {
+                let mut output = BufReader::new(output);
+                loop {
+                    let (body, framing) = match framing::read_message(&mut output).await {
+                        Ok(message) => message,
+                        Err(e) => {
+                            debug!(error = %e, "MCP server output closed");
+                            break;
+                        }
+                    };
+                    if let Ok(mut detected) = writer.detected.lock()
+                        && detected.is_none()
+                    {
+                        debug!(?framing, "Detected MCP stdio framing");
+                        *detected = Some(framing);
+                    }
+                    let Ok(message) = serde_json::from_str::<Value>(&body) else {
+                        debug!(%body, "Ignoring malformed MCP message");
+                        continue;
+                    };
+                    match Incoming::classify(message) {
+                        Incoming::Response(response) => pending.resolve(response),
+                        Incoming::Notification(notification) => (handlers.on_notification)(&notification),
+                        Incoming::Request { id, method, params } => {
+                            // Answered concurrently, as answering may itself take requests
+                            let (writer, handlers) = (writer.clone(), handlers.clone());
+                            tokio::spawn(async move {
+                                let reply = handlers.answer(id, method, params).await;
+                                let _ = writer.send(&reply).await;
+                            });
+                        }
+                        Incoming::Invalid => debug!("Ignoring MCP message without id or method"),
+                    }
+                }
+                // Waiting requests fail instead of hanging
+                pending.fail_all();
+            }
+        });
+
+        Self {
+            child: None,
+            writer,
+            pending,
+            reader,
+            stderr: None,
+        }
+    }
+
+    /// Adds the server's last stderr lines to connection errors.
+    async fn diagnose(&self, error: MCPError) -> MCPError {
+        match &self.stderr {
+            Some(stderr) => stderr.annotate(error).await,
+            None => error,
+        }
+    }
+
+    /// Sends a request and waits for its response, giving up after
+    /// `timeout` and telling the server the request was cancelled.
+    pub(crate) async fn request(&self, message: Value, timeout: Duration) -> Result<Value, MCPError> {
+        let (id, response) = self.pending.register(&message)?;
+        if let Err(e) = self.writer.send(&message).await {
+            self.pending.cancel(id);
+            return Err(self.diagnose(e).await);
+        }
+        let result = self.pending.wait(id, response, Some(timeout)).await;
+        if matches!(result, Err(MCPError::Timeout)) {
+            let cancelled = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/cancelled",
+                "params": {"requestId": id, "reason": "Request timed out"}
+            });
+            let _ = self.writer.send(&cancelled).await;
+        }
+        match result {
+            Err(e) => Err(self.diagnose(e).await),
+            ok => ok,
+        }
+    }
+
+    /// Sends a notification.
+    pub(crate) async fn notify(&self, message: Value) -> Result<(), MCPError> {
+        match self.writer.send(&message).await {
+            Err(e) => Err(self.diagnose(e).await),
+            ok => ok,
+        }
+    }
+
+    /// Closes the server's stdin and waits for it to exit.
+    pub(crate) async fn shutdown(mut self) -> Result<(), MCPError> {
+        self.writer.close().await;
+        if let Some(child) = self.child.as_mut() {
+            child.wait().await.map_err(|e| {
+                MCPError::ConnectionError(format!("Failed to wait for process: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl std::fmt::Debug for StdioTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdioTransport")
+            .field("pid", &self.child.as_ref().and_then(Child::id))
+            .field("framing", &self.writer.framing())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_requests_get_their_own_responses() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (client_out, client_in) = tokio::io::split(client_io);
+        let transport = StdioTransport::from_io(client_out, client_in, StdioFraming::Auto, ServerHandlers::none());
+
+        // The server answers in reverse order, in Content-Length framing
+        let server = tokio::spawn(async move {
+            let (server_out, mut server_in) = tokio::io::split(server_io);
+            let mut server_out = BufReader::new(server_out);
+            let first: Value = serde_json::from_str(&framing::read_message(&mut server_out).await.unwrap().0).unwrap();
+            let second: Value = serde_json::from_str(&framing::read_message(&mut server_out).await.unwrap().0).unwrap();
+            for request in [&second, &first] {
+                let response = serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": request["method"]});
+                framing::write_message(&mut server_in, &response.to_string(), StdioFraming::ContentLength)
+                    .await
+                    .unwrap();
+            }
+            framing::read_message(&mut server_out).await.unwrap()
+        });
+
+        let request = |id: u64, method: &str| serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method});
+        let timeout = Duration::from_secs(5);
+        let (a, b) = tokio::join!(
+            transport.request(request(1, "a"), timeout),
+            transport.request(request(2, "b"), timeout)
+        );
+        assert_eq!(a.unwrap()["result"], "a");
+        assert_eq!(b.unwrap()["result"], "b");
+
+        transport.notify(serde_json::json!({"jsonrpc": "2.0", "method": "done"})).await.unwrap();
+        assert_eq!(server.await.unwrap().1, StdioFraming::ContentLength);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_request_times_out_and_is_cancelled() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (client_out, client_in) = tokio::io::split(client_io);
+        let transport = StdioTransport::from_io(client_out, client_in, StdioFraming::Newline, ServerHandlers::none());
+
+        let request = serde_json::json!({"jsonrpc": "2.0", "id": 7, "method": "tools/call"});
+        let result = transport.request(request, Duration::from_millis(20)).await;
+        assert!(matches!(result, Err(MCPError::Timeout)));
+
+        let mut server_out = BufReader::new(tokio::io::split(server_io).0);
+        let _request = framing::read_message(&mut server_out).await.unwrap();
+        let cancelled: Value = serde_json::from_str(&framing::read_message(&mut server_out).await.unwrap().0).unwrap();
+        assert_eq!(cancelled["method"], "notifications/cancelled");
+        assert_eq!(cancelled["params"]["requestId"], 7);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_crash_on_startup_reports_stderr() {
+        let script = "echo 'loading config' >&2; echo 'fatal: missing API key' >&2; exit 1".to_string();
+        let transport = StdioTransport::spawn(
+            "crashy",
+            "sh",
+            &["-c".to_string(), script],
+            &None,
+            StdioFraming::Newline,
+            ServerHandlers::none(),
+        )
+        .unwrap();
+
+        let request = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"});
+        match transport.request(request, Duration::from_secs(5)).await {
+            Err(MCPError::ConnectionError(message)) => {
+                assert!(message.ends_with("Server stderr:\nloading config\nfatal: missing API key"), "{}", message)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use futures::StreamExt;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::debug;
+
+use super::auth::Credentials;
+use super::client::MCPError;
+use super::rpc::{Incoming, ServerHandlers};
+use super::sse::SseDecoder;
+
+/// Header carrying the session the server assigned at initialization.
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// How often a broken response stream is resumed before giving up.
+const MAX_RESUMPTIONS: usize = 3;
+
+/// The MCP Streamable HTTP transport (protocol version 2025-03-26): every
+/// message is posted to one endpoint, which answers with either a JSON body
+/// or an event stream carrying the response.
+pub(crate) struct StreamableHttpTransport {
+    client: reqwest::Client,
+    url: String,
+    credentials: Credentials,
+    session_id: Mutex<Option<String>>,
+    handlers: ServerHandlers,
+}
+
+/// Where the response to a request is read from next.
+enum ResponseSource {
+    Response(reqwest::Response),
+    Resume(String),
+}
+
+impl StreamableHttpTransport {
+    pub(crate) fn new(
+        client: reqwest::Client,
+        url: impl Into<String>,
+        credentials: Credentials,
+        handlers: ServerHandlers,
+    ) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            credentials,
+            session_id: Mutex::new(None),
+            handlers,
+        }
+    }
+
+    /// Returns the session the server assigned, if any.
+    pub(crate) fn session_id(&self) -> Option<String> {
+        self.session_id.lock().ok().and_then(|id| id.clone())
+    }
+
+    fn with_session(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.session_id() {
+            Some(session_id) => request.header(SESSION_HEADER, session_id),
+            None => request,
+        }
+    }
+
+    /// Posts a message, remembering the session the server assigns.
+    async fn post(&self, message: &Value) -> Result<reqwest::Response, MCPError> {
+        let request = || {
+            self.with_session(self.client.post(&self.url))
+                .header(ACCEPT, "application/json, text/event-stream")
+                .json(message)
+        };
+        let response = self.credentials.send(request).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND && self.session_id().is_some() {
+            if let Ok(mut session_id) = self.session_id.lock() {
+                *session_id = None;
+            }
+            return Err(MCPError::ConnectionError("MCP session expired; reconnect to start a new one".to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(MCPError::HttpError(format!(
+                "HTTP error: {} - {}",
+                response.status(),
+                response.text().await.unwrap_or_default()
+            )));
+        }
+        if let Some(session_id) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok())
+            && let Ok(mut current) = self.session_id.lock()
+        {
+            *current = Some(session_id.to_string());
+        }
+        Ok(response)
+    }
+
+    /// Posts a request and returns its response, resuming the event stream
+    /// from the last event seen if it breaks before the response arrives.
+    pub(crate) async fn request(&self, message: Value, timeout: Duration) -> Result<Value, MCPError> {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        tokio::time::timeout(timeout, async {
+            let mut source = ResponseSource::Response(self.post(&message).await?);
+            for _ in 0..=MAX_RESUMPTIONS {
+                let response = match source {
+                    ResponseSource::Response(response) => response,
+                    ResponseSource::Resume(last_event_id) => self.resume(&last_event_id).await?,
+                };
+                let is_stream = response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.starts_with("text/event-stream"));
+                if !is_stream {
+                    let body: Value = response.json().await.map_err(|e| MCPError::ProtocolError(e.to_string()))?;
+                    return self.find_response(body, &id).await;
+                }
+                match self.read_stream(response, &id).await {
+                    Ok(response) => return Ok(response),
+                    Err(Some(last_event_id)) => {
+                        debug!(%last_event_id, "Resuming MCP response stream");
+                        source = ResponseSource::Resume(last_event_id);
+                    }
+                    Err(None) => break,
+                }
+            }
+            Err(MCPError::ConnectionError("Response stream closed before the response".to_string()))
+        })
+        .await
+        .map_err(|_| MCPError::Timeout)?
+    }
+
+    /// Reopens the event stream after `last_event_id`.
+    async fn resume(&self, last_event_id: &str) -> Result<reqwest::Response, MCPError> {
+        let request = || {
+            self.with_session(self.client.get(&self.url))
+                .header(ACCEPT, "text/event-stream")
+                .header("Last-Event-ID", last_event_id)
+        };
+        let response = self.credentials.send(request).await?;
+        if !response.status().is_success() {
+            return Err(MCPError::HttpError(format!("HTTP error: {}", response.status())));
+        }
+        Ok(response)
+    }
+
+    /// Reads an event stream until the response to `id`. When the stream
+    /// ends first, returns the ID of the last event to resume after.
+    async fn read_stream(&self, response: reqwest::Response, id: &Value) -> Result<Value, Option<String>> {
+        let mut stream = response.bytes_stream();
+        let mut decoder = SseDecoder::default();
+        let mut last_event_id = None;
+        while let Some(Ok(chunk)) = stream.next().await {
+            for event in decoder.push(&chunk) {
+                if event.id.is_some() {
+                    last_event_id = event.id.clone();
+                }
+                if event.event != "message" {
+                    continue;
+                }
+                let Ok(message) = serde_json::from_str::<Value>(&event.data) else {
+                    debug!(data = %event.data, "Ignoring malformed MCP message");
+                    continue;
+                };
+                if let Some(response) = self.route(message, id).await {
+                    return Ok(response);
+                }
+            }
+        }
+        Err(last_event_id)
+    }
+
+    /// Finds the response to `id` in a JSON body holding one message or a
+    /// batch, handling the other messages.
+    async fn find_response(&self, body: Value, id: &Value) -> Result<Value, MCPError> {
+        let messages = match body {
+            Value::Array(messages) => messages,
+            message => vec![message],
+        };
+        let mut found = None;
+        for message in messages {
+            if let Some(response) = self.route(message, id).await {
+                found = Some(response);
+            }
+        }
+        found.ok_or_else(|| MCPError::ProtocolError("No response to the request".to_string()))
+    }
+
+    /// Returns the message if it is the response to `id`; otherwise hands
+    /// notifications and server requests to the handlers.
+    async fn route(&self, message: Value, id: &Value) -> Option<Value> {
+        match Incoming::classify(message) {
+            Incoming::Response(response) if response.get("id") == Some(id) => Some(response),
+            Incoming::Notification(notification) => {
+                (self.handlers.on_notification)(&notification);
+                None
+            }
+            Incoming::Request { id, method, params } => {
+                // The server holds its response until this is answered
+                let reply = self.handlers.answer(id, method, params).await;
+                let _ = self.post(&reply).await;
+                None
+            }
+            _ => {
+                debug!("Ignoring unexpected MCP message");
+                None
+            }
+        }
+    }
+
+    /// Posts a notification.
+    pub(crate) async fn notify(&self, message: Value) -> Result<(), MCPError> {
+        self.post(&message).await.map(|_| ())
+    }
+
+    /// Ends the session on the server.
+    pub(crate) async fn close(&self) {
+        if self.session_id().is_none() {
+            return;
+        }
+        // Servers may not allow clients to end sessions; that is fine
+        let _ = self.credentials.send(|| self.with_session(self.client.delete(&self.url))).await;
+        if let Ok(mut session_id) = self.session_id.lock() {
+            *session_id = None;
+        }
+    }
+}
+
+impl std::fmt::Debug for StreamableHttpTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamableHttpTransport")
+            .field("url", &self.url)
+            .field("session_id", &self.session_id())
+            .finish()
+    }
+}
@@ -0,0 +1,102 @@
+//! Endpoint resolution overrides shared by the crate's HTTP clients.
+
+use reqwest::dns::Resolve;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Overrides how host names are resolved by the HTTP clients built for LLM
+/// providers and MCP servers.
+///
+/// Static entries take precedence over the custom resolver, which in turn
+/// replaces system DNS. This lets deployments behind service meshes or
+/// without public DNS direct traffic without editing `/etc/hosts`.
+#[derive(Clone, Default)]
+pub struct EndpointResolution {
+    static_map: HashMap<String, Vec<SocketAddr>>,
+    resolver: Option<ApplyResolver>,
+}
+
+/// Installs a custom resolver on a client builder.
+type ApplyResolver = Arc<dyn Fn(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send + Sync>;
+
+impl EndpointResolution {
+    /// Creates an empty override set that uses system DNS.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves `host` to the given addresses. The port of each address is
+    /// replaced by the port of the request URL.
+    pub fn with_static(mut self, host: impl Into<String>, addrs: Vec<SocketAddr>) -> Self {
+        self.static_map.insert(host.into().to_ascii_lowercase(), addrs);
+        self
+    }
+
+    /// Uses a custom resolver for hosts without a static entry.
+    pub fn with_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> Self {
+        self.resolver = Some(Arc::new(move |builder: reqwest::ClientBuilder| {
+            builder.dns_resolver(resolver.clone())
+        }));
+        self
+    }
+
+    /// Returns whether no overrides are configured.
+    pub fn is_empty(&self) -> bool {
+        self.static_map.is_empty() && self.resolver.is_none()
+    }
+
+    /// Applies the overrides to an HTTP client builder.
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        for (host, addrs) in &self.static_map {
+            builder = builder.resolve_to_addrs(host, addrs);
+        }
+        if let Some(apply_resolver) = &self.resolver {
+            builder = apply_resolver(builder);
+        }
+        builder
+    }
+}
+
+impl std::fmt::Debug for EndpointResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EndpointResolution")
+            .field("static_map", &self.static_map)
+            .field("custom_resolver", &self.resolver.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_static_entry_routes_unresolvable_host() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+        });
+
+        let resolution = EndpointResolution::new().with_static("llm.internal", vec![addr]);
+        let client = resolution.apply(reqwest::Client::builder()).build().unwrap();
+        let body = client
+            .get(format!("http://llm.internal:{}/", addr.port()))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert_eq!(body, "ok");
+    }
+}
@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use std::io::{BufRead, Write};
+use tokio::sync::{mpsc, oneshot};
+
+use super::manager::PermissionContext;
+
+/// An answer to an approval request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    /// Run this call
+    AllowOnce,
+    /// Run this call, and identical calls for the rest of the session
+    AllowForSession,
+    /// Run this call, and identical calls from now on, remembered by the
+    /// manager's `PermissionStore`
+    AlwaysAllow,
+    /// Refuse the call
+    Deny,
+}
+
+impl ApprovalDecision {
+    /// Returns whether the call may run.
+    pub fn is_allowed(self) -> bool {
+        self != ApprovalDecision::Deny
+    }
+}
+
+/// Decides tool calls whose permission rule says `Ask`, e.g. by prompting
+/// a human.
+#[async_trait]
+pub trait ApprovalHandler: Send + Sync {
+    /// Asks whether the call described by `ctx` may run.
+    async fn ask(&self, ctx: &PermissionContext) -> ApprovalDecision;
+}
+
+/// Prompts on the terminal: prints the call to stderr and reads the answer
+/// from stdin. Prompts of parallel calls are asked one at a time.
+#[derive(Debug, Default)]
+pub struct TerminalApprovalHandler {
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl TerminalApprovalHandler {
+    /// Creates a terminal prompter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prints the question to `output` and reads the answer from `input`:
+    /// `y` allows once, `s` for the session and `a` always; anything else
+    /// denies.
+    fn prompt(ctx: &PermissionContext, input: &mut impl BufRead, output: &mut impl Write) -> ApprovalDecision {
+        let asked = write!(
+            output,
+            "Allow tool `{}` with {}? [y]es / for this [s]ession / [a]lways / [N]o ",
+            ctx.tool, ctx.args
+        )
+        .and_then(|_| output.flush());
+        let mut answer = String::new();
+        if asked.is_err() || input.read_line(&mut answer).is_err() {
+            return ApprovalDecision::Deny;
+        }
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => ApprovalDecision::AllowOnce,
+            "s" | "session" => ApprovalDecision::AllowForSession,
+            "a" | "always" => ApprovalDecision::AlwaysAllow,
+            _ => ApprovalDecision::Deny,
+        }
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for TerminalApprovalHandler {
+    async fn ask(&self, ctx: &PermissionContext) -> ApprovalDecision {
+        let _turn = self.lock.lock().await;
+        let ctx = ctx.clone();
+        tokio::task::spawn_blocking(move || {
+            Self::prompt(&ctx, &mut std::io::stdin().lock(), &mut std::io::stderr())
+        })
+        .await
+        .unwrap_or(ApprovalDecision::Deny)
+    }
+}
+
+/// A call waiting for approval, sent by `ChannelApprovalHandler`. Dropping
+/// it unanswered denies the call.
+#[derive(Debug)]
+pub struct ApprovalRequest {
+    /// The call to decide
+    pub context: PermissionContext,
+    responder: oneshot::Sender<ApprovalDecision>,
+}
+
+impl ApprovalRequest {
+    /// Lets the call run, this once.
+    pub fn approve(self) {
+        self.respond(ApprovalDecision::AllowOnce);
+    }
+
+    /// Denies the call.
+    pub fn deny(self) {
+        self.respond(ApprovalDecision::Deny);
+    }
+
+    /// Answers with `decision`.
+    pub fn respond(self, decision: ApprovalDecision) {
+        let _ = self.responder.send(decision);
+    }
+}
+
+/// Sends approval requests over a channel, for GUIs and other hosts that
+/// answer on their own event loop.
+#[derive(Debug, Clone)]
+pub struct ChannelApprovalHandler {
+    requests: mpsc::UnboundedSender<ApprovalRequest>,
+}
+
+impl ChannelApprovalHandler {
+    /// Creates a handler and the receiver of its requests. Calls are denied
+    /// once the receiver is dropped.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ApprovalRequest>) {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        (Self { requests }, receiver)
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for ChannelApprovalHandler {
+    async fn ask(&self, ctx: &PermissionContext) -> ApprovalDecision {
+        let (responder, response) = oneshot::channel();
+        let request = ApprovalRequest {
+            context: ctx.clone(),
+            responder,
+        };
+        if self.requests.send(request).is_err() {
+            return ApprovalDecision::Deny;
+        }
+        response.await.unwrap_or(ApprovalDecision::Deny)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::{Permission, PermissionAction, PermissionManager, PermissionResult};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_ask_rules_are_decided_by_the_handler() {
+        let (handler, mut requests) = ChannelApprovalHandler::new();
+        let mut manager = PermissionManager::new().with_approval_handler(Arc::new(handler));
+        manager.add_rule(Permission {
+            tool: "bash".to_string(),
+            action: PermissionAction::Ask,
+            patterns: None,
+            args: None,
+            quota: None,
+        });
+        tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                match request.context.args["command"].as_str() {
+                    Some("ls") => request.approve(),
+                    _ => request.deny(),
+                }
+            }
+        });
+
+        let ctx = |command: &str| PermissionContext {
+            tool: "bash".to_string(),
+            args: serde_json::json!({"command": command}),
+            session_id: "s".to_string(),
+        };
+        assert_eq!(manager.check(&ctx("ls")).await, PermissionResult::Allow);
+        assert_eq!(manager.check(&ctx("rm -rf /")).await, PermissionResult::Deny);
+
+        let mut output = Vec::new();
+        let answer = TerminalApprovalHandler::prompt(&ctx("ls"), &mut "Y\n".as_bytes(), &mut output);
+        assert_eq!(answer, ApprovalDecision::AllowOnce);
+        assert!(String::from_utf8(output).unwrap().starts_with("Allow tool `bash` with"));
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use super::manager::{Permission, PermissionAction, PermissionResult};
+
+/// One permission decision in the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionAuditEntry {
+    /// When the decision was made
+    pub timestamp: DateTime<Utc>,
+    /// The session the call was made in
+    pub session_id: String,
+    /// The tool called
+    pub tool: String,
+    /// SHA-256 of the arguments, so the log holds no secrets
+    pub args_digest: String,
+    /// The rule that matched, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<Permission>,
+    /// The action applied: the rule's, or the default one
+    pub action: PermissionAction,
+    /// Whether the call was allowed
+    pub decision: PermissionResult,
+}
+
+impl PermissionAuditEntry {
+    /// Returns the digest recorded for a call's arguments.
+    pub fn digest_args(args: &Value) -> String {
+        Sha256::digest(args.to_string().as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Receives permission decisions as they are made.
+pub trait PermissionAuditSink: Send + Sync {
+    /// Records a decision.
+    fn record(&self, entry: &PermissionAuditEntry);
+}
+
+impl<F> PermissionAuditSink for F
+where
+    F: Fn(&PermissionAuditEntry) + Send + Sync,
+{
+    fn record(&self, entry: &PermissionAuditEntry) {
+        self(entry)
+    }
+}
+
+/// Appends decisions to a file, one JSON object per line.
+#[derive(Debug)]
+pub struct JsonlAuditSink {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JsonlAuditSink {
+    /// Creates a sink appending to the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+impl PermissionAuditSink for JsonlAuditSink {
+    fn record(&self, entry: &PermissionAuditEntry) {
+        let Ok(line) = serde_json::to_string(entry) else {
+            return;
+        };
+        let _guard = self.lock.lock();
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = written {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to write permission audit entry");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::{PermissionContext, PermissionManager};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_decisions_are_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let callback = {
+            let seen = seen.clone();
+            move |entry: &PermissionAuditEntry| seen.lock().unwrap().push(entry.tool.clone())
+        };
+        let mut manager = PermissionManager::new()
+            .with_audit_sink(Arc::new(JsonlAuditSink::new(&path)))
+            .with_audit_sink(Arc::new(callback));
+        manager.add_rule(Permission {
+            tool: "read".to_string(),
+            action: PermissionAction::Allow,
+            patterns: None,
+            args: None,
+            quota: None,
+        });
+
+        let ctx = |tool: &str| PermissionContext {
+            tool: tool.to_string(),
+            args: serde_json::json!({"path": "secret.txt"}),
+            session_id: "s".to_string(),
+        };
+        manager.check(&ctx("read")).await;
+        manager.check(&ctx("bash")).await;
+
+        let audit = manager.audit();
+        assert_eq!(audit.len(), 2);
+        assert_eq!(audit[0].rule.as_ref().unwrap().tool, "read");
+        assert_eq!(audit[0].decision, PermissionResult::Allow);
+        assert!(audit[1].rule.is_none());
+        assert_eq!(audit[1].decision, PermissionResult::Deny);
+        assert_eq!(audit[1].args_digest.len(), 64);
+        assert_eq!(*seen.lock().unwrap(), vec!["read", "bash"]);
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().count(), 2);
+        assert!(!lines.contains("secret.txt"));
+    }
+}
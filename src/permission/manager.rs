@@ -1,9 +1,19 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::approval::{ApprovalDecision, ApprovalHandler};
+use super::audit::{PermissionAuditEntry, PermissionAuditSink};
+use super::sandbox::{glob_regex, PathAccess, SandboxError, SandboxPolicy};
+use super::store::PermissionStore;
 
 /// Permission action types.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum PermissionAction {
     /// Allow the action
@@ -21,9 +31,154 @@ pub struct Permission {
     pub tool: String,
     /// The action for this permission
     pub action: PermissionAction,
-    /// Optional regex patterns for arguments
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Optional substrings, one of which some string argument must contain.
+    /// This is a plain substring test: `rm` also matches `format`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub patterns: Option<Vec<String>>,
+    /// Optional regexes by argument name, each of which the argument (as
+    /// text) must match. They are not anchored: use `^` and `$` to match the
+    /// whole argument
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<HashMap<String, String>>,
+    /// Optional limits on the calls this rule lets through
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<Quota>,
+}
+
+/// Limits on the calls a rule lets through, counted per session. Calls
+/// count once they are allowed, after any approval; calls over the quota
+/// are denied without asking.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quota {
+    /// Calls allowed in any 60 second window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_minute: Option<u32>,
+    /// Calls allowed in a session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_session: Option<u32>,
+    /// What one call costs, in whatever unit `max_cost` uses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_per_call: Option<f64>,
+    /// Total cost allowed in a session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+}
+
+/// Source of `CompiledRule::id`.
+static NEXT_RULE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A rule with its tool glob and argument regexes compiled.
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    /// Identifies the rule across clones and overlays, keying its quota
+    id: u64,
+    rule: Permission,
+    tool: Regex,
+    args: Vec<(String, Regex)>,
+}
+
+impl CompiledRule {
+    /// Compiles a rule, failing on an invalid tool glob or argument regex.
+    fn new(rule: Permission) -> Result<Self, PermissionError> {
+        let tool = Regex::new(&glob_regex(&rule.tool))
+            .map_err(|e| PermissionError::InvalidRule(format!("tool `{}`: {}", rule.tool, e)))?;
+        let args = rule
+            .args
+            .iter()
+            .flatten()
+            .map(|(arg, pattern)| {
+                Regex::new(pattern)
+                    .map(|re| (arg.clone(), re))
+                    .map_err(|e| PermissionError::InvalidRule(format!("argument `{}` of `{}`: {}", arg, rule.tool, e)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            id: NEXT_RULE_ID.fetch_add(1, Ordering::Relaxed),
+            rule,
+            tool,
+            args,
+        })
+    }
+
+    /// Checks if the rule matches the context.
+    fn matches(&self, ctx: &PermissionContext) -> bool {
+        // Check tool name match (supports wildcards)
+        if self.rule.tool != "*" && !self.tool.is_match(&ctx.tool) {
+            return false;
+        }
+
+        // Check argument patterns if specified
+        if let Some(patterns) = &self.rule.patterns
+            && !Self::args_match(patterns, &ctx.args)
+        {
+            return false;
+        }
+
+        self.args.iter().all(|(arg, re)| match ctx.args.get(arg) {
+            Some(Value::String(text)) => re.is_match(text),
+            Some(other) => re.is_match(&other.to_string()),
+            None => false,
+        })
+    }
+
+    /// Checks if arguments contain one of the given patterns.
+    fn args_match(patterns: &[String], args: &Value) -> bool {
+        // Simple pattern matching - check if arguments contain the pattern
+        for pattern in patterns {
+            if let Some(args_str) = args.as_str() {
+                if args_str.contains(pattern) {
+                    return true;
+                }
+            } else {
+                // For object args, check if any value contains the pattern
+                if let Some(obj) = args.as_object() {
+                    for value in obj.values() {
+                        if let Some(s) = value.as_str()
+                            && s.contains(pattern)
+                        {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        patterns.is_empty()
+    }
+}
+
+/// Calls counted against a quota in one session.
+#[derive(Debug, Default)]
+struct QuotaUsage {
+    recent: VecDeque<Instant>,
+    calls: u32,
+    cost: f64,
+}
+
+/// Errors loading permission rules.
+#[derive(Debug, thiserror::Error)]
+pub enum PermissionError {
+    /// The rule file could not be read
+    #[error("Failed to read rules: {0}")]
+    Read(String),
+    /// The rule file is malformed or in an unsupported format
+    #[error("Failed to parse rules: {0}")]
+    Parse(String),
+    /// A rule has an invalid tool glob or argument regex
+    #[error("Invalid rule: {0}")]
+    InvalidRule(String),
+    /// A call would exceed the quota of its rule
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+}
+
+/// A rule file: the rules, first match wins, and the action when none
+/// matches.
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    default: Option<PermissionAction>,
+    #[serde(default)]
+    rules: Vec<Permission>,
 }
 
 /// Context for permission checking.
@@ -38,7 +193,8 @@ pub struct PermissionContext {
 }
 
 /// Result of a permission check.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PermissionResult {
     /// Action is allowed
     Allow,
@@ -49,103 +205,327 @@ pub enum PermissionResult {
 }
 
 /// Manages permissions for tool execution.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct PermissionManager {
-    rules: Vec<Permission>,
+    rules: Vec<CompiledRule>,
+    default_action: Option<PermissionAction>,
+    approval: Option<Arc<dyn ApprovalHandler>>,
+    store: Option<Arc<dyn PermissionStore>>,
+    /// Calls allowed for their session, as (session, tool, arguments)
+    session_approvals: Arc<std::sync::Mutex<HashSet<(String, String, String)>>>,
+    audit: Arc<std::sync::Mutex<Vec<PermissionAuditEntry>>>,
+    audit_sinks: Vec<Arc<dyn PermissionAuditSink>>,
+    sandbox: Option<SandboxPolicy>,
+    /// Quota usage by rule id and session
+    quota_usage: Arc<std::sync::Mutex<HashMap<(u64, String), QuotaUsage>>>,
 }
 
 impl PermissionManager {
     /// Creates a new permission manager.
     pub fn new() -> Self {
-        Self { rules: Vec::new() }
+        Self {
+            rules: Vec::new(),
+            default_action: None,
+            approval: None,
+            store: None,
+            session_approvals: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            audit: Arc::new(std::sync::Mutex::new(Vec::new())),
+            audit_sinks: Vec::new(),
+            sandbox: None,
+            quota_usage: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Loads rules from a TOML (`.toml`) or JSON (`.json`) file with a
+    /// `rules` list, in the fields of `Permission`, and an optional
+    /// `default` action for calls no rule matches:
+    ///
+    /// ```toml
+    /// default = "ask"
+    ///
+    /// [[rules]]
+    /// tool = "bash"
+    /// action = "deny"
+    /// args = { command = "^\\s*rm\\s" }
+    /// ```
+    ///
+    /// Tool globs and argument regexes are checked when loading.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PermissionError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| PermissionError::Read(format!("{}: {}", path.display(), e)))?;
+        let file: RuleFile = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| PermissionError::Parse(e.to_string()))?,
+            Some("json") => serde_json::from_str(&text).map_err(|e| PermissionError::Parse(e.to_string()))?,
+            _ => {
+                return Err(PermissionError::Parse(format!(
+                    "{}: expected a .toml or .json file",
+                    path.display()
+                )));
+            }
+        };
+
+        let mut manager = Self::new();
+        for rule in file.rules {
+            manager.rules.push(CompiledRule::new(rule)?);
+        }
+        manager.default_action = file.default;
+        Ok(manager)
+    }
+
+    /// Sets the action for calls no rule matches; `check` denies them by
+    /// default.
+    pub fn with_default_action(mut self, action: PermissionAction) -> Self {
+        self.default_action = Some(action);
+        self
+    }
+
+    /// Returns the action for calls no rule matches, if set.
+    pub fn default_action(&self) -> Option<&PermissionAction> {
+        self.default_action.as_ref()
+    }
+
+    /// Sets who decides calls whose rule says `Ask`. Without a handler
+    /// they are denied by `check`, and paused for `Agent::approve` when run
+    /// by an agent.
+    pub fn with_approval_handler(mut self, handler: Arc<dyn ApprovalHandler>) -> Self {
+        self.approval = Some(handler);
+        self
+    }
+
+    /// Sets where "always allow" answers are kept. Without a store they
+    /// last for the session only.
+    pub fn with_permission_store(mut self, store: Arc<dyn PermissionStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Layers `overlay` over these rules: its rules are evaluated first,
+    /// and its default action, approval handler, store and sandbox replace
+    /// these ones when set. Decisions keep going to this manager's audit log.
+    ///
+    /// Layer per-agent rules over global defaults, and per-run rules (see
+    /// `RunOptions::with_permissions`) over both.
+    pub fn with_overlay(mut self, overlay: PermissionManager) -> Self {
+        let mut rules = overlay.rules;
+        rules.append(&mut self.rules);
+        self.rules = rules;
+        self.default_action = overlay.default_action.or(self.default_action);
+        self.approval = overlay.approval.or(self.approval);
+        self.store = overlay.store.or(self.store);
+        self.sandbox = overlay.sandbox.or(self.sandbox);
+        self.audit_sinks.extend(overlay.audit_sinks);
+        self
+    }
+
+    /// Sets where file tools may read and write, checked by the built-in
+    /// tools and by custom ones through `ToolContext::check_path`.
+    pub fn with_sandbox(mut self, sandbox: SandboxPolicy) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Returns the sandbox policy, if set.
+    pub fn sandbox(&self) -> Option<&SandboxPolicy> {
+        self.sandbox.as_ref()
+    }
+
+    /// Checks a path a call means to access against the sandbox, returning
+    /// it resolved. Refusals are recorded in the audit log as denials.
+    pub fn check_path(
+        &self,
+        ctx: &PermissionContext,
+        path: impl AsRef<Path>,
+        access: PathAccess,
+    ) -> Result<PathBuf, SandboxError> {
+        let Some(sandbox) = &self.sandbox else {
+            return Ok(path.as_ref().to_path_buf());
+        };
+        sandbox.check(path, access).inspect_err(|e| {
+            tracing::debug!(tool = %ctx.tool, error = %e, "Sandbox refused path");
+            self.record(ctx, PermissionAction::Deny, PermissionResult::Deny);
+        })
+    }
+
+    /// Adds a sink that receives every decision, besides the log kept for
+    /// `audit`.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn PermissionAuditSink>) -> Self {
+        self.audit_sinks.push(sink);
+        self
+    }
+
+    /// Returns every decision made so far, oldest first.
+    pub fn audit(&self) -> Vec<PermissionAuditEntry> {
+        self.audit.lock().map(|audit| audit.clone()).unwrap_or_default()
     }
 
-    /// Adds a permission rule.
+    /// Adds a permission rule. A rule with an invalid tool glob or argument
+    /// regex is skipped with a warning; `from_file` rejects it instead.
     pub fn add_rule(&mut self, rule: Permission) {
-        self.rules.push(rule);
+        match CompiledRule::new(rule) {
+            Ok(rule) => self.rules.push(rule),
+            Err(e) => tracing::warn!(error = %e, "Skipping permission rule"),
+        }
     }
 
     /// Checks if an action is permitted.
     pub async fn check(&self, ctx: &PermissionContext) -> PermissionResult {
-        for rule in &self.rules {
-            if self.matches(rule, ctx) {
-                return match rule.action {
-                    PermissionAction::Allow => PermissionResult::Allow,
-                    PermissionAction::Deny => PermissionResult::Deny,
-                    PermissionAction::Ask => self.ask_user(ctx).await,
-                };
-            }
-        }
-        PermissionResult::Deny // Default deny
+        // Default deny
+        let action = self
+            .evaluate(ctx)
+            .or_else(|| self.default_action.clone())
+            .unwrap_or(PermissionAction::Deny);
+        let result = match action {
+            PermissionAction::Deny => PermissionResult::Deny,
+            _ if self.check_quota(ctx).is_err() => PermissionResult::Deny,
+            PermissionAction::Allow => PermissionResult::Allow,
+            PermissionAction::Ask => self.ask(ctx).await.unwrap_or(PermissionResult::Deny),
+        };
+        // Only calls let through count against the quota
+        let result = match result {
+            PermissionResult::Allow if self.take_quota(ctx).is_err() => PermissionResult::Deny,
+            result => result,
+        };
+        self.record(ctx, action, result.clone());
+        result
     }
 
-    /// Checks if a rule matches the context.
-    fn matches(&self, rule: &Permission, ctx: &PermissionContext) -> bool {
-        // Check tool name match (supports wildcards)
-        if rule.tool != "*" && !self.tool_matches(&rule.tool, &ctx.tool) {
-            return false;
-        }
+    /// Returns the action of the first matching rule, or `None` when no
+    /// rule matches.
+    pub fn evaluate(&self, ctx: &PermissionContext) -> Option<PermissionAction> {
+        self.matching_rule(ctx).map(|rule| rule.action.clone())
+    }
 
-        // Check argument patterns if specified
-        if let Some(patterns) = &rule.patterns {
-            if !self.args_match(patterns, &ctx.args) {
-                return false;
-            }
-        }
+    /// Returns the first rule matching the context.
+    fn matching_rule(&self, ctx: &PermissionContext) -> Option<&Permission> {
+        self.rules.iter().find(|rule| rule.matches(ctx)).map(|rule| &rule.rule)
+    }
+
+    /// Checks that a call fits the quota of the rule matching it, without
+    /// counting it. Calls without a quota always pass.
+    pub fn check_quota(&self, ctx: &PermissionContext) -> Result<(), PermissionError> {
+        self.quota(ctx, false)
+    }
 
-        true
+    /// Counts a call against the quota of the rule matching it, failing
+    /// when that would exceed the quota. Calls without a quota always pass.
+    pub fn take_quota(&self, ctx: &PermissionContext) -> Result<(), PermissionError> {
+        self.quota(ctx, true)
     }
 
-    /// Checks if a tool name matches a pattern (supports * wildcards).
-    fn tool_matches(&self, pattern: &str, tool: &str) -> bool {
-        if pattern == "*" {
-            return true;
+    fn quota(&self, ctx: &PermissionContext, take: bool) -> Result<(), PermissionError> {
+        let Some(rule) = self.rules.iter().find(|rule| rule.matches(ctx)) else {
+            return Ok(());
+        };
+        let Some(quota) = &rule.rule.quota else {
+            return Ok(());
+        };
+        let key = (rule.id, ctx.session_id.clone());
+        let mut usage = self
+            .quota_usage
+            .lock()
+            .map_err(|_| PermissionError::QuotaExceeded("quota lock poisoned".to_string()))?;
+        let usage = usage.entry(key).or_default();
+
+        let now = Instant::now();
+        while usage
+            .recent
+            .front()
+            .is_some_and(|call| now.duration_since(*call) >= Duration::from_secs(60))
+        {
+            usage.recent.pop_front();
+        }
+        let cost = usage.cost + quota.cost_per_call.unwrap_or(0.0);
+        let exceeded = if quota.max_per_minute.is_some_and(|max| usage.recent.len() >= max as usize) {
+            Some(format!("at most {} calls per minute", quota.max_per_minute.unwrap_or_default()))
+        } else if quota.max_per_session.is_some_and(|max| usage.calls >= max) {
+            Some(format!("at most {} calls per session", quota.max_per_session.unwrap_or_default()))
+        } else if quota.max_cost.is_some_and(|max| cost > max) {
+            Some(format!("a cost of at most {} per session", quota.max_cost.unwrap_or_default()))
+        } else {
+            None
+        };
+        if let Some(limit) = exceeded {
+            return Err(PermissionError::QuotaExceeded(format!("tool `{}` is limited to {}", ctx.tool, limit)));
+        }
+        if !take {
+            return Ok(());
         }
 
-        // Convert glob pattern to regex
-        let regex_pattern = pattern
-            .replace("**", ".*")  // ** matches any characters including /
-            .replace("*", "[^/]*"); // * matches any characters except /
+        usage.recent.push_back(now);
+        usage.calls += 1;
+        usage.cost = cost;
+        Ok(())
+    }
 
-        Regex::new(&format!("^{}$", regex_pattern))
-            .ok()
-            .and_then(|re| Some(re.is_match(tool)))
-            .unwrap_or(false)
+    /// Adds a decision to the audit log and its sinks.
+    pub(crate) fn record(&self, ctx: &PermissionContext, action: PermissionAction, decision: PermissionResult) {
+        let entry = PermissionAuditEntry {
+            timestamp: chrono::Utc::now(),
+            session_id: ctx.session_id.clone(),
+            tool: ctx.tool.clone(),
+            args_digest: PermissionAuditEntry::digest_args(&ctx.args),
+            rule: self.matching_rule(ctx).cloned(),
+            action,
+            decision,
+        };
+        for sink in &self.audit_sinks {
+            sink.record(&entry);
+        }
+        if let Ok(mut audit) = self.audit.lock() {
+            audit.push(entry);
+        }
     }
 
-    /// Checks if arguments match the given patterns.
-    fn args_match(&self, patterns: &[String], args: &Value) -> bool {
-        // Simple pattern matching - check if arguments contain the pattern
-        for pattern in patterns {
-            if let Some(args_str) = args.as_str() {
-                if args_str.contains(pattern) {
-                    return true;
-                }
-            } else {
-                // For object args, check if any value contains the pattern
-                if let Some(obj) = args.as_object() {
-                    for value in obj.values() {
-                        if let Some(s) = value.as_str() {
-                            if s.contains(pattern) {
-                                return true;
-                            }
-                        }
-                    }
-                }
-            }
+    /// Asks the approval handler about a call, or returns `None` when
+    /// there is none. Calls identical to one allowed for the session or
+    /// always are allowed without asking.
+    pub async fn ask(&self, ctx: &PermissionContext) -> Option<PermissionResult> {
+        let handler = self.approval.as_ref()?;
+        let key = (ctx.session_id.clone(), ctx.tool.clone(), ctx.args.to_string());
+        if self.session_approvals.lock().is_ok_and(|approvals| approvals.contains(&key)) {
+            return Some(PermissionResult::Allow);
         }
-        patterns.is_empty()
+        if let Some(store) = &self.store
+            && store.is_allowed(&ctx.tool, &ctx.args).await
+        {
+            return Some(PermissionResult::Allow);
+        }
+
+        let decision = handler.ask(ctx).await;
+        if matches!(decision, ApprovalDecision::AllowForSession | ApprovalDecision::AlwaysAllow)
+            && let Ok(mut approvals) = self.session_approvals.lock()
+        {
+            approvals.insert(key);
+        }
+        if decision == ApprovalDecision::AlwaysAllow
+            && let Some(store) = &self.store
+        {
+            store.allow(&ctx.tool, &ctx.args).await;
+        }
+        Some(if decision.is_allowed() {
+            PermissionResult::Allow
+        } else {
+            PermissionResult::Deny
+        })
+    }
+}
+
+impl std::fmt::Debug for PermissionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PermissionManager")
+            .field("rules", &self.rules.iter().map(|rule| &rule.rule).collect::<Vec<_>>())
+            .field("default_action", &self.default_action)
+            .field("has_approval_handler", &self.approval.is_some())
+            .field("has_permission_store", &self.store.is_some())
+            .field("audit_sinks", &self.audit_sinks.len())
+            .field("sandbox", &self.sandbox)
+            .finish()
     }
+}
 
-    /// Asks the user for permission (placeholder).
-    async fn ask_user(&self, _ctx: &PermissionContext) -> PermissionResult {
-        // In a real implementation, this would:
-        // - Emit an event to ask the user
-        // - Wait for user response
-        // - Return the user's decision
-        //
-        // For now, we default to deny
-        PermissionResult::Deny
+impl Default for PermissionManager {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -153,24 +533,201 @@ impl PermissionManager {
 mod tests {
     use super::*;
 
+    fn tool_matches(pattern: &str, tool: &str) -> bool {
+        let rule = CompiledRule::new(Permission {
+            tool: pattern.to_string(),
+            action: PermissionAction::Allow,
+            patterns: None,
+            args: None,
+            quota: None,
+        })
+        .unwrap();
+        rule.matches(&PermissionContext {
+            tool: tool.to_string(),
+            args: Value::Null,
+            session_id: "s".to_string(),
+        })
+    }
+
     #[test]
     fn test_tool_matches() {
-        let manager = PermissionManager::new();
-
         // Exact match
-        assert!(manager.tool_matches("bash", "bash"));
-        assert!(!manager.tool_matches("bash", "read"));
+        assert!(tool_matches("bash", "bash"));
+        assert!(!tool_matches("bash", "read"));
 
         // Wildcard match
-        assert!(manager.tool_matches("*", "bash"));
-        assert!(manager.tool_matches("*", "read"));
+        assert!(tool_matches("*", "bash"));
+        assert!(tool_matches("*", "read"));
 
         // Glob patterns
-        assert!(manager.tool_matches("file_*", "file_read"));
-        assert!(!manager.tool_matches("file_*", "bash_read"));
+        assert!(tool_matches("file_*", "file_read"));
+        assert!(!tool_matches("file_*", "bash_read"));
 
         // Multiple wildcards
-        assert!(manager.tool_matches("file_*.write", "file_test.write"));
-        assert!(!manager.tool_matches("file_*.write", "other_test.write"));
+        assert!(tool_matches("file_*.write", "file_test.write"));
+        assert!(!tool_matches("file_*.write", "other_test.write"));
+
+        // Everything but wildcards matches literally
+        assert!(!tool_matches("file_*.write", "file_aXwrite"));
+        assert!(tool_matches("mcp_**", "mcp_server/fetch"));
+        assert!(!tool_matches("mcp_*", "mcp_server/fetch"));
+    }
+
+    #[tokio::test]
+    async fn test_rules_load_from_toml_and_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("rules.toml");
+        std::fs::write(
+            &toml_path,
+            r#"
+                default = "allow"
+
+                [[rules]]
+                tool = "bash"
+                action = "deny"
+                args = { command = "^\\s*rm\\s" }
+
+                [[rules]]
+                tool = "mcp_*"
+                action = "deny"
+            "#,
+        )
+        .unwrap();
+        let manager = PermissionManager::from_file(&toml_path).unwrap();
+        let ctx = |tool: &str, command: &str| PermissionContext {
+            tool: tool.to_string(),
+            args: serde_json::json!({"command": command}),
+            session_id: "s".to_string(),
+        };
+        assert_eq!(manager.check(&ctx("bash", "rm -rf /")).await, PermissionResult::Deny);
+        assert_eq!(manager.check(&ctx("bash", "ls")).await, PermissionResult::Allow);
+        assert_eq!(manager.check(&ctx("mcp_fetch", "")).await, PermissionResult::Deny);
+
+        let json_path = dir.path().join("rules.json");
+        std::fs::write(
+            &json_path,
+            r#"{
+                "default": "ask",
+                "rules": [
+                    {"tool": "file_*", "action": "allow", "args": {"path": "^src/"}},
+                    {"tool": "bash", "action": "deny"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let manager = PermissionManager::from_file(&json_path).unwrap();
+        let read = |path: &str| PermissionContext {
+            tool: "file_read".to_string(),
+            args: serde_json::json!({"path": path}),
+            session_id: "s".to_string(),
+        };
+        assert_eq!(manager.evaluate(&read("src/lib.rs")), Some(PermissionAction::Allow));
+        assert_eq!(manager.evaluate(&read("/etc/passwd")), None);
+        assert_eq!(manager.evaluate(&ctx("bash", "ls")), Some(PermissionAction::Deny));
+        assert_eq!(manager.default_action(), Some(&PermissionAction::Ask));
+
+        std::fs::write(&json_path, r#"{"rules": [{"tool": "bash", "action": "allow", "args": {"command": "("}}]}"#).unwrap();
+        assert!(matches!(PermissionManager::from_file(&json_path), Err(PermissionError::InvalidRule(_))));
+    }
+
+    #[tokio::test]
+    async fn test_overlays_take_priority() {
+        let rule = |tool: &str, action: PermissionAction, command: Option<&str>| Permission {
+            tool: tool.to_string(),
+            action,
+            patterns: None,
+            args: command.map(|command| HashMap::from([("command".to_string(), command.to_string())])),
+            quota: None,
+        };
+        let mut global = PermissionManager::new().with_default_action(PermissionAction::Ask);
+        global.add_rule(rule("bash", PermissionAction::Deny, None));
+        let mut agent = PermissionManager::new();
+        agent.add_rule(rule("read", PermissionAction::Allow, None));
+        let mut run = PermissionManager::new().with_default_action(PermissionAction::Deny);
+        run.add_rule(rule("bash", PermissionAction::Allow, Some("^ls")));
+        let layered = global.with_overlay(agent).with_overlay(run);
+
+        let ctx = |tool: &str, command: &str| PermissionContext {
+            tool: tool.to_string(),
+            args: serde_json::json!({"command": command}),
+            session_id: "s".to_string(),
+        };
+        assert_eq!(layered.check(&ctx("bash", "ls -l")).await, PermissionResult::Allow);
+        assert_eq!(layered.check(&ctx("bash", "rm x")).await, PermissionResult::Deny);
+        assert_eq!(layered.check(&ctx("read", "")).await, PermissionResult::Allow);
+        assert_eq!(layered.default_action(), Some(&PermissionAction::Deny));
+    }
+
+    #[tokio::test]
+    async fn test_quotas_limit_allowed_calls() {
+        let mut manager = PermissionManager::new();
+        for (tool, quota) in [
+            ("search", Quota { max_per_minute: Some(2), ..Default::default() }),
+            ("paid", Quota { cost_per_call: Some(0.4), max_cost: Some(1.0), ..Default::default() }),
+        ] {
+            manager.add_rule(Permission {
+                tool: tool.to_string(),
+                action: PermissionAction::Allow,
+                patterns: None,
+                args: None,
+                quota: Some(quota),
+            });
+        }
+        let ctx = |tool: &str, session: &str| PermissionContext {
+            tool: tool.to_string(),
+            args: serde_json::json!({}),
+            session_id: session.to_string(),
+        };
+
+        for tool in ["search", "paid"] {
+            assert_eq!(manager.check(&ctx(tool, "a")).await, PermissionResult::Allow);
+            assert_eq!(manager.check(&ctx(tool, "a")).await, PermissionResult::Allow);
+            assert_eq!(manager.check(&ctx(tool, "a")).await, PermissionResult::Deny);
+            assert_eq!(manager.check(&ctx(tool, "b")).await, PermissionResult::Allow);
+        }
+        assert!(matches!(
+            manager.take_quota(&ctx("search", "a")),
+            Err(PermissionError::QuotaExceeded(e)) if e.contains("2 calls per minute")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quotas_count_allowed_calls_per_rule() {
+        let rule = |action: PermissionAction| Permission {
+            tool: "search".to_string(),
+            action,
+            patterns: None,
+            args: None,
+            quota: Some(Quota { max_per_session: Some(1), ..Default::default() }),
+        };
+        let ctx = PermissionContext {
+            tool: "search".to_string(),
+            args: serde_json::json!({}),
+            session_id: "s".to_string(),
+        };
+
+        // An identical rule in an overlay keeps its own count
+        let mut global = PermissionManager::new();
+        global.add_rule(rule(PermissionAction::Allow));
+        let mut run = PermissionManager::new();
+        run.add_rule(rule(PermissionAction::Allow));
+        let layered = global.clone().with_overlay(run);
+        assert_eq!(global.check(&ctx).await, PermissionResult::Allow);
+        assert_eq!(layered.check(&ctx).await, PermissionResult::Allow);
+        assert_eq!(global.check(&ctx).await, PermissionResult::Deny);
+
+        // Denied approvals are not counted
+        let (handler, mut requests) = crate::permission::ChannelApprovalHandler::new();
+        tokio::spawn(async move {
+            for decision in [ApprovalDecision::Deny, ApprovalDecision::AllowOnce] {
+                requests.recv().await.unwrap().respond(decision);
+            }
+        });
+        let mut asking = PermissionManager::new().with_approval_handler(Arc::new(handler));
+        asking.add_rule(rule(PermissionAction::Ask));
+        assert_eq!(asking.check(&ctx).await, PermissionResult::Deny);
+        assert_eq!(asking.check(&ctx).await, PermissionResult::Allow);
+        // Over the quota, the handler is not asked again
+        assert_eq!(asking.check(&ctx).await, PermissionResult::Deny);
     }
 }
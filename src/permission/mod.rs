@@ -1,3 +1,11 @@
+pub mod approval;
+pub mod audit;
 pub mod manager;
+pub mod sandbox;
+pub mod store;
 
-pub use manager::{PermissionManager, Permission, PermissionAction, PermissionContext, PermissionResult};
+pub use approval::{ApprovalDecision, ApprovalHandler, ApprovalRequest, ChannelApprovalHandler, TerminalApprovalHandler};
+pub use audit::{JsonlAuditSink, PermissionAuditEntry, PermissionAuditSink};
+pub use manager::{PermissionManager, Permission, PermissionAction, PermissionContext, PermissionError, PermissionResult, Quota};
+pub use sandbox::{PathAccess, SandboxError, SandboxPolicy};
+pub use store::{FilePermissionStore, MemoryPermissionStore, PermissionStore, RememberedApproval};
@@ -0,0 +1,246 @@
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+
+/// How a tool means to use a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathAccess {
+    /// The tool reads the path
+    Read,
+    /// The tool creates, changes or deletes the path
+    Write,
+}
+
+/// Why a path is off limits.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SandboxError {
+    /// The path is outside every allowed root
+    #[error("`{0}` is outside the allowed directories")]
+    OutsideRoots(PathBuf),
+    /// The path may be read but not written
+    #[error("`{0}` is read-only")]
+    ReadOnly(PathBuf),
+    /// The path matches a denied glob
+    #[error("`{0}` is denied by the sandbox policy")]
+    Denied(PathBuf),
+    /// The file is larger than allowed
+    #[error("`{path}` is {size} bytes, over the limit of {max}")]
+    TooLarge { path: PathBuf, size: u64, max: u64 },
+    /// The path could not be resolved
+    #[error("`{0}`: {1}")]
+    Invalid(PathBuf, String),
+}
+
+/// Where file tools may read and write.
+///
+/// Paths are resolved, symlinks included, before they are checked. Reads
+/// are allowed below the roots and read-only paths, writes below the roots
+/// only. Denied globs match the whole path, or the file name when they hold
+/// no `/`. Without roots every path is allowed unless denied.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxPolicy {
+    roots: Vec<PathBuf>,
+    read_only: Vec<PathBuf>,
+    denied: Vec<(String, Regex)>,
+    max_file_size: Option<u64>,
+}
+
+impl SandboxPolicy {
+    /// Creates a policy allowing every path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows reading and writing below `root`. Relative paths are resolved
+    /// against the first root.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.roots.push(Self::absolute(root.into()));
+        self
+    }
+
+    /// Allows reading, but not writing, below `path`, even inside a root.
+    pub fn with_read_only(mut self, path: impl Into<PathBuf>) -> Self {
+        self.read_only.push(Self::absolute(path.into()));
+        self
+    }
+
+    /// Denies paths matching `glob`, e.g. `**/.env` or `*.pem`, where `*`
+    /// matches within a path segment and `**` across segments.
+    pub fn with_denied_glob(mut self, glob: impl Into<String>) -> Self {
+        let glob = glob.into();
+        let regex = Regex::new(&glob_regex(&glob)).expect("escaped glob is a valid regex");
+        self.denied.push((glob, regex));
+        self
+    }
+
+    /// Limits the size of files read or written.
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Returns the largest file tools may read or write, if limited.
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.max_file_size
+    }
+
+    /// Checks that `path` may be accessed, returning it resolved.
+    pub fn check(&self, path: impl AsRef<Path>, access: PathAccess) -> Result<PathBuf, SandboxError> {
+        let resolved = self.resolve(path.as_ref())?;
+
+        if self.denied.iter().any(|(glob, regex)| {
+            let subject = match glob.contains('/') {
+                true => resolved.to_string_lossy().into_owned(),
+                false => resolved
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            regex.is_match(&subject)
+        }) {
+            return Err(SandboxError::Denied(resolved));
+        }
+
+        let under = |dirs: &[PathBuf]| dirs.iter().any(|dir| resolved.starts_with(dir));
+        let read_only = under(&self.read_only);
+        if access == PathAccess::Write && read_only {
+            return Err(SandboxError::ReadOnly(resolved));
+        }
+        let readable = access == PathAccess::Read && read_only;
+        if !self.roots.is_empty() && !under(&self.roots) && !readable {
+            return Err(SandboxError::OutsideRoots(resolved));
+        }
+
+        if self.max_file_size.is_some()
+            && let Ok(metadata) = std::fs::metadata(&resolved)
+            && metadata.is_file()
+        {
+            self.check_size(&resolved, metadata.len())?;
+        }
+        Ok(resolved)
+    }
+
+    /// Checks that `size` bytes may be read from or written to `path`.
+    pub fn check_size(&self, path: impl AsRef<Path>, size: u64) -> Result<(), SandboxError> {
+        match self.max_file_size {
+            Some(max) if size > max => Err(SandboxError::TooLarge {
+                path: path.as_ref().to_path_buf(),
+                size,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Makes `path` absolute and resolves symlinks in the part of it that
+    /// exists; the rest may not contain `..`.
+    fn resolve(&self, path: &Path) -> Result<PathBuf, SandboxError> {
+        let path = match (path.is_absolute(), self.roots.first()) {
+            (true, _) => path.to_path_buf(),
+            (false, Some(root)) => root.join(path),
+            (false, None) => Self::absolute(path.to_path_buf()),
+        };
+
+        let mut existing = path.as_path();
+        let mut rest = Vec::new();
+        let resolved = loop {
+            match existing.canonicalize() {
+                Ok(resolved) => break resolved,
+                Err(e) => {
+                    let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                        return Err(SandboxError::Invalid(path.clone(), e.to_string()));
+                    };
+                    rest.push(name.to_os_string());
+                    existing = parent;
+                }
+            }
+        };
+        if Path::new(&rest.iter().rev().collect::<PathBuf>())
+            .components()
+            .any(|component| matches!(component, Component::ParentDir))
+        {
+            return Err(SandboxError::Invalid(path.clone(), "`..` below a missing directory".to_string()));
+        }
+        Ok(rest.into_iter().rev().fold(resolved, |resolved, name| resolved.join(name)))
+    }
+
+    /// Makes a path absolute against the current directory, resolving
+    /// symlinks when it exists.
+    fn absolute(path: PathBuf) -> PathBuf {
+        if let Ok(resolved) = path.canonicalize() {
+            return resolved;
+        }
+        match std::env::current_dir() {
+            Ok(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        }
+    }
+}
+
+/// Converts a path or tool name glob to an anchored regex: `*` and `?`
+/// stop at `/`, `**` does not, and everything else matches literally.
+pub(crate) fn glob_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::PermissionManager;
+    use crate::tool::ToolContext;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_policy_confines_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("work");
+        std::fs::create_dir_all(root.join("vendor")).unwrap();
+        std::fs::write(root.join("big.bin"), vec![0u8; 64]).unwrap();
+        let policy = SandboxPolicy::new()
+            .with_root(&root)
+            .with_read_only(root.join("vendor"))
+            .with_denied_glob("**/.env")
+            .with_denied_glob("*.pem")
+            .with_max_file_size(32);
+
+        let canonical = root.canonicalize().unwrap();
+        assert_eq!(policy.check("src/new.rs", PathAccess::Write).unwrap(), canonical.join("src/new.rs"));
+        assert!(policy.check("vendor/lib.rs", PathAccess::Read).is_ok());
+        assert!(matches!(policy.check("vendor/lib.rs", PathAccess::Write), Err(SandboxError::ReadOnly(_))));
+        assert!(matches!(policy.check("../outside", PathAccess::Read), Err(SandboxError::OutsideRoots(_))));
+        assert!(matches!(policy.check("missing/../../x", PathAccess::Read), Err(SandboxError::Invalid(..))));
+        assert!(matches!(policy.check(".env", PathAccess::Read), Err(SandboxError::Denied(_))));
+        assert!(matches!(policy.check("keys/id.pem", PathAccess::Read), Err(SandboxError::Denied(_))));
+        assert!(matches!(policy.check("big.bin", PathAccess::Read), Err(SandboxError::TooLarge { size: 64, .. })));
+
+        let permissions = PermissionManager::new().with_sandbox(policy);
+        let ctx = ToolContext {
+            tool_name: "write_file".to_string(),
+            permissions: Some(Arc::new(permissions)),
+            ..Default::default()
+        };
+        assert!(ctx.check_path(root.join(".env"), PathAccess::Write).is_err());
+        let audit = ctx.permissions.as_ref().unwrap().audit();
+        assert_eq!(audit[0].tool, "write_file");
+        assert_eq!(audit[0].decision, crate::permission::PermissionResult::Deny);
+    }
+}
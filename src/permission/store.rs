@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// A call the user chose to always allow: a tool and its exact arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RememberedApproval {
+    /// The tool called
+    pub tool: String,
+    /// The arguments it was called with
+    pub args: Value,
+}
+
+/// Persists "always allow" answers between runs.
+#[async_trait]
+pub trait PermissionStore: Send + Sync {
+    /// Returns whether a call with these arguments was always allowed.
+    async fn is_allowed(&self, tool: &str, args: &Value) -> bool;
+
+    /// Remembers that calls with these arguments are always allowed.
+    async fn allow(&self, tool: &str, args: &Value);
+}
+
+/// Keeps approvals in memory only.
+#[derive(Debug, Default)]
+pub struct MemoryPermissionStore(std::sync::Mutex<Vec<RememberedApproval>>);
+
+#[async_trait]
+impl PermissionStore for MemoryPermissionStore {
+    async fn is_allowed(&self, tool: &str, args: &Value) -> bool {
+        self.0
+            .lock()
+            .is_ok_and(|approvals| approvals.iter().any(|approval| approval.tool == tool && &approval.args == args))
+    }
+
+    async fn allow(&self, tool: &str, args: &Value) {
+        if let Ok(mut approvals) = self.0.lock() {
+            approvals.push(RememberedApproval {
+                tool: tool.to_string(),
+                args: args.clone(),
+            });
+        }
+    }
+}
+
+/// Keeps approvals in a JSON file holding a list of `RememberedApproval`.
+#[derive(Debug, Clone)]
+pub struct FilePermissionStore {
+    path: PathBuf,
+}
+
+impl FilePermissionStore {
+    /// Creates a store backed by the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    async fn read(&self) -> Vec<RememberedApproval> {
+        tokio::fs::read_to_string(&self.path)
+            .await
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl PermissionStore for FilePermissionStore {
+    async fn is_allowed(&self, tool: &str, args: &Value) -> bool {
+        self.read()
+            .await
+            .iter()
+            .any(|approval| approval.tool == tool && &approval.args == args)
+    }
+
+    async fn allow(&self, tool: &str, args: &Value) {
+        let mut approvals = self.read().await;
+        approvals.push(RememberedApproval {
+            tool: tool.to_string(),
+            args: args.clone(),
+        });
+        let Ok(text) = serde_json::to_string_pretty(&approvals) else {
+            return;
+        };
+        if let Some(parent) = self.path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        if let Err(e) = tokio::fs::write(&self.path, text).await {
+            tracing::warn!(path = %self.path.display(), error = %e, "Failed to save permission approval");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::{
+        ApprovalDecision, ChannelApprovalHandler, Permission, PermissionAction, PermissionContext,
+        PermissionManager, PermissionResult,
+    };
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_remembered_approvals_skip_the_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(FilePermissionStore::new(dir.path().join("approvals.json")));
+        let manager = |answer: ApprovalDecision, asked: Arc<AtomicUsize>| {
+            let (handler, mut requests) = ChannelApprovalHandler::new();
+            tokio::spawn(async move {
+                while let Some(request) = requests.recv().await {
+                    asked.fetch_add(1, Ordering::SeqCst);
+                    request.respond(answer);
+                }
+            });
+            let mut manager = PermissionManager::new()
+                .with_approval_handler(Arc::new(handler))
+                .with_permission_store(store.clone());
+            manager.add_rule(Permission {
+                tool: "bash".to_string(),
+                action: PermissionAction::Ask,
+                patterns: None,
+                args: None,
+                quota: None,
+            });
+            manager
+        };
+        let ctx = |session: &str, command: &str| PermissionContext {
+            tool: "bash".to_string(),
+            args: serde_json::json!({"command": command}),
+            session_id: session.to_string(),
+        };
+
+        let asked = Arc::new(AtomicUsize::new(0));
+        let session = manager(ApprovalDecision::AllowForSession, asked.clone());
+        assert_eq!(session.check(&ctx("a", "ls")).await, PermissionResult::Allow);
+        assert_eq!(session.check(&ctx("a", "ls")).await, PermissionResult::Allow);
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+        assert_eq!(session.check(&ctx("b", "ls")).await, PermissionResult::Allow);
+        assert_eq!(asked.load(Ordering::SeqCst), 2);
+
+        let always = manager(ApprovalDecision::AlwaysAllow, Arc::new(AtomicUsize::new(0)));
+        assert_eq!(always.check(&ctx("a", "pwd")).await, PermissionResult::Allow);
+
+        let asked = Arc::new(AtomicUsize::new(0));
+        let later = manager(ApprovalDecision::Deny, asked.clone());
+        assert_eq!(later.check(&ctx("c", "pwd")).await, PermissionResult::Allow);
+        assert_eq!(later.check(&ctx("c", "ls")).await, PermissionResult::Deny);
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,342 @@
+use serde_json::Value;
+
+use super::import::ImportError;
+use super::{Message, MessageContent, MessageRole, Session};
+
+/// A format sessions are exported to and imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// An OpenAI chat completion request: `model` and `messages`
+    OpenAIChat,
+    /// One `{"messages": [...]}` line in the OpenAI fine-tuning format;
+    /// lines of several sessions concatenate into a dataset
+    FineTuningJsonl,
+    /// A human-readable transcript
+    Markdown,
+}
+
+impl Session {
+    /// Writes the session in `format`.
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::OpenAIChat => {
+                let request = serde_json::json!({
+                    "model": self.model.name,
+                    "messages": self.openai_messages(true),
+                });
+                serde_json::to_string_pretty(&request).unwrap_or_default()
+            }
+            ExportFormat::FineTuningJsonl => {
+                let example = serde_json::json!({"messages": self.openai_messages(false)});
+                format!("{}\n", example)
+            }
+            ExportFormat::Markdown => self.markdown(),
+        }
+    }
+
+    /// Reads a session written in `format`. JSONL input must hold a single
+    /// example; use `import_jsonl` for datasets.
+    pub fn import(text: &str, format: ExportFormat) -> Result<Self, ImportError> {
+        match format {
+            ExportFormat::OpenAIChat => {
+                let json: Value =
+                    serde_json::from_str(text).map_err(|e| ImportError::InvalidFormat(e.to_string()))?;
+                Self::from_openai_messages(&json)
+            }
+            ExportFormat::FineTuningJsonl => {
+                let mut sessions = Self::import_jsonl(text)?;
+                match sessions.len() {
+                    1 => Ok(sessions.remove(0)),
+                    n => Err(ImportError::InvalidFormat(format!("expected one example, found {}", n))),
+                }
+            }
+            ExportFormat::Markdown => Self::from_markdown(text),
+        }
+    }
+
+    /// Reads every example of a fine-tuning JSONL dataset.
+    pub fn import_jsonl(text: &str) -> Result<Vec<Self>, ImportError> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let json: Value = serde_json::from_str(line)
+                    .map_err(|e| ImportError::InvalidFormat(format!("line {}: {}", i + 1, e)))?;
+                Self::from_openai_messages(&json)
+            })
+            .collect()
+    }
+
+    /// Renders the messages in the OpenAI chat format, with the model's
+    /// reasoning when `reasoning` is set.
+    fn openai_messages(&self, reasoning: bool) -> Vec<Value> {
+        let mut messages = Vec::new();
+        if !self.system_prompt.is_empty() {
+            messages.push(serde_json::json!({"role": "system", "content": self.system_prompt}));
+        }
+        for message in &self.messages {
+            match message.role {
+                MessageRole::User => {
+                    let images = message.content.iter().any(|c| matches!(c, MessageContent::Image { .. }));
+                    let content = if images {
+                        Value::Array(message.content.iter().filter_map(openai_part).collect())
+                    } else {
+                        Value::String(text_of(message))
+                    };
+                    messages.push(serde_json::json!({"role": "user", "content": content}));
+                }
+                MessageRole::Assistant => {
+                    let text = text_of(message);
+                    let mut entry = serde_json::json!({
+                        "role": "assistant",
+                        "content": if text.is_empty() { Value::Null } else { Value::String(text) },
+                    });
+                    let thinking: String = message
+                        .content
+                        .iter()
+                        .filter_map(|c| match c {
+                            MessageContent::Thinking { thinking } => Some(thinking.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    if reasoning && !thinking.is_empty() {
+                        entry["reasoning_content"] = Value::String(thinking);
+                    }
+                    let calls: Vec<Value> = message
+                        .content
+                        .iter()
+                        .filter_map(|c| match c {
+                            MessageContent::ToolCall { id, name, arguments } => Some(serde_json::json!({
+                                "id": id,
+                                "type": "function",
+                                "function": {"name": name, "arguments": arguments.to_string()},
+                            })),
+                            _ => None,
+                        })
+                        .collect();
+                    if !calls.is_empty() {
+                        entry["tool_calls"] = Value::Array(calls);
+                    }
+                    messages.push(entry);
+                }
+                MessageRole::Tool => {
+                    for content in &message.content {
+                        if let MessageContent::ToolResult { tool_call_id, result, .. } = content {
+                            messages.push(serde_json::json!({
+                                "role": "tool",
+                                "tool_call_id": tool_call_id,
+                                "content": result,
+                            }));
+                        }
+                    }
+                }
+            }
+        }
+        messages
+    }
+
+    /// Renders the session as a Markdown transcript.
+    fn markdown(&self) -> String {
+        let mut out = format!("# Session {}\n", self.id);
+        if !self.system_prompt.is_empty() {
+            out.push_str(&format!("\n## System\n\n{}\n", self.system_prompt));
+        }
+        for message in &self.messages {
+            match message.role {
+                MessageRole::User => out.push_str(&format!("\n## User\n\n{}\n", text_of(message))),
+                MessageRole::Assistant => {
+                    out.push_str("\n## Assistant\n");
+                    let text = text_of(message);
+                    if !text.is_empty() {
+                        out.push_str(&format!("\n{}\n", text));
+                    }
+                    for content in &message.content {
+                        if let MessageContent::ToolCall { id, name, arguments } = content {
+                            let arguments = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                            out.push_str(&format!("\n### Tool call `{}` `{}`\n\n{}\n", name, id, fenced(&arguments, "json")));
+                        }
+                    }
+                }
+                MessageRole::Tool => {
+                    for content in &message.content {
+                        if let MessageContent::ToolResult { tool_call_id, result, is_error, .. } = content {
+                            let heading = if *is_error == Some(true) { "Tool error" } else { "Tool result" };
+                            out.push_str(&format!("\n## {} `{}`\n\n{}\n", heading, tool_call_id, fenced(result, "")));
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Reads a transcript written by `export(ExportFormat::Markdown)`.
+    fn from_markdown(text: &str) -> Result<Self, ImportError> {
+        let mut session = Session::default();
+        let mut lines = text.lines().peekable();
+        let first = lines.next().unwrap_or_default();
+        if let Some(id) = first.strip_prefix("# Session ") {
+            session.id = id.trim().to_string();
+        } else {
+            return Err(ImportError::InvalidFormat("missing `# Session` heading".to_string()));
+        }
+
+        while let Some(line) = lines.next() {
+            let Some(heading) = line.strip_prefix("## ") else {
+                continue;
+            };
+            match heading {
+                "System" => session.system_prompt = section_text(&mut lines),
+                "User" => session.add_message(Message::new_user(section_text(&mut lines))),
+                "Assistant" => {
+                    let mut content = Vec::new();
+                    let text = section_text(&mut lines);
+                    if !text.is_empty() {
+                        content.push(MessageContent::Text { text });
+                    }
+                    while let Some(call) = lines.peek().and_then(|line| line.strip_prefix("### Tool call ")) {
+                        let [name, id] = code_spans(call)?;
+                        lines.next();
+                        let arguments = fenced_block(&mut lines)?;
+                        content.push(MessageContent::ToolCall {
+                            id,
+                            name,
+                            arguments: serde_json::from_str(&arguments)
+                                .map_err(|e| ImportError::InvalidFormat(format!("tool call arguments: {}", e)))?,
+                        });
+                    }
+                    session.add_message(Message::new_assistant(content));
+                }
+                heading if heading.starts_with("Tool result ") || heading.starts_with("Tool error ") => {
+                    let [tool_call_id] = code_spans(heading)?;
+                    let result = MessageContent::ToolResult {
+                        tool_call_id,
+                        result: fenced_block(&mut lines)?,
+                        is_error: heading.starts_with("Tool error ").then_some(true),
+                        blocks: Vec::new(),
+                    };
+                    match session.messages.last_mut() {
+                        Some(last) if last.role == MessageRole::Tool => last.content.push(result),
+                        _ => session.add_message(Message::new_tool_result(vec![result])),
+                    }
+                }
+                other => return Err(ImportError::UnsupportedRole(other.to_string())),
+            }
+        }
+        Ok(session)
+    }
+}
+
+/// Joins the text blocks of a message.
+fn text_of(message: &Message) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|c| match c {
+            MessageContent::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders a content block as an OpenAI content part.
+fn openai_part(content: &MessageContent) -> Option<Value> {
+    match content {
+        MessageContent::Text { text } => Some(serde_json::json!({"type": "text", "text": text})),
+        MessageContent::Image { media_type, data } => Some(serde_json::json!({
+            "type": "image_url",
+            "image_url": {"url": format!("data:{};base64,{}", media_type, data)},
+        })),
+        _ => None,
+    }
+}
+
+/// Wraps text in a code fence longer than any backtick run inside it.
+fn fenced(text: &str, lang: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, lang, text, fence)
+}
+
+/// Reads the text up to the next heading, trimmed.
+fn section_text<'a>(lines: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>) -> String {
+    let mut text = Vec::new();
+    while let Some(line) = lines.next_if(|line| !line.starts_with("## ") && !line.starts_with("### ")) {
+        text.push(line);
+    }
+    text.join("\n").trim().to_string()
+}
+
+/// Reads the next fenced code block, returning its contents.
+fn fenced_block<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Result<String, ImportError> {
+    let unterminated = || ImportError::InvalidFormat("unterminated code block".to_string());
+    let open = lines.find(|line| line.starts_with("```")).ok_or_else(unterminated)?;
+    let fence = &open[..open.len() - open.trim_start_matches('`').len()];
+    let mut text = Vec::new();
+    for line in lines {
+        if line == fence {
+            return Ok(text.join("\n"));
+        }
+        text.push(line);
+    }
+    Err(unterminated())
+}
+
+/// Returns the `N` code spans of a heading, e.g. the name and ID of a tool
+/// call.
+fn code_spans<const N: usize>(heading: &str) -> Result<[String; N], ImportError> {
+    let spans: Vec<String> = heading.split('`').skip(1).step_by(2).map(str::to_string).collect();
+    spans
+        .try_into()
+        .map_err(|_| ImportError::InvalidFormat(format!("malformed heading `{}`", heading)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_round_trip() {
+        let mut session = Session::with_default_model("Be brief.");
+        session.add_message(Message::new_user("Weather in Paris?"));
+        session.add_message(Message::new_assistant(vec![
+            MessageContent::Text { text: "Checking.".to_string() },
+            MessageContent::ToolCall {
+                id: "call_1".to_string(),
+                name: "weather".to_string(),
+                arguments: serde_json::json!({"city": "Paris"}),
+            },
+        ]));
+        session.add_message(Message::new_tool_result(vec![MessageContent::ToolResult {
+            tool_call_id: "call_1".to_string(),
+            result: "```\n18C\n```".to_string(),
+            is_error: Some(true),
+            blocks: Vec::new(),
+        }]));
+        session.add_message(Message::new_assistant(vec![MessageContent::Text {
+            text: "It is 18C.".to_string(),
+        }]));
+
+        for format in [ExportFormat::OpenAIChat, ExportFormat::FineTuningJsonl, ExportFormat::Markdown] {
+            let exported = session.export(format);
+            let imported = Session::import(&exported, format).unwrap();
+            assert_eq!(imported.system_prompt, "Be brief.", "{:?}", format);
+            assert_eq!(imported.messages.len(), 4, "{:?}", format);
+            assert!(matches!(
+                &imported.messages[1].content[..],
+                [MessageContent::Text { text }, MessageContent::ToolCall { arguments, .. }]
+                    if text == "Checking." && arguments["city"] == "Paris"
+            ));
+            assert!(matches!(
+                &imported.messages[2].content[0],
+                MessageContent::ToolResult { result, .. } if result == "```\n18C\n```"
+            ));
+        }
+
+        let jsonl = session.export(ExportFormat::FineTuningJsonl).repeat(2);
+        assert_eq!(jsonl.lines().count(), 2);
+        assert_eq!(Session::import_jsonl(&jsonl).unwrap().len(), 2);
+        assert!(session.export(ExportFormat::Markdown).contains("## Tool error `call_1`"));
+    }
+}
@@ -0,0 +1,271 @@
+use serde_json::Value;
+
+use super::{Message, MessageContent, MessageRole, Session};
+
+/// Errors from importing chat history written by other SDKs.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    /// The JSON does not have the expected shape
+    #[error("Invalid format: {0}")]
+    InvalidFormat(String),
+    /// A message has a role that has no equivalent here
+    #[error("Unsupported role: {0}")]
+    UnsupportedRole(String),
+}
+
+impl Session {
+    /// Creates a session from OpenAI chat completion messages.
+    ///
+    /// Accepts either a bare `messages` array or a request object with
+    /// `messages` (and optionally `model`). System and developer messages
+    /// become the system prompt; consecutive tool messages are grouped into
+    /// a single tool result message.
+    pub fn from_openai_messages(json: &Value) -> Result<Self, ImportError> {
+        let (messages, mut session) = split_request(json)?;
+        let mut system_parts = Vec::new();
+
+        for entry in messages {
+            let role = entry
+                .get("role")
+                .and_then(Value::as_str)
+                .ok_or_else(|| ImportError::InvalidFormat("message without a role".to_string()))?;
+
+            match role {
+                "system" | "developer" => system_parts.push(openai_text(entry.get("content"))),
+                "user" => session.add_message(Message::new_user(openai_text(entry.get("content")))),
+                "assistant" => {
+                    let mut content = Vec::new();
+                    if let Some(reasoning) = entry.get("reasoning_content").and_then(Value::as_str)
+                        && !reasoning.is_empty()
+                    {
+                        content.push(MessageContent::Thinking {
+                            thinking: reasoning.to_string(),
+                        });
+                    }
+                    let text = openai_text(entry.get("content"));
+                    if !text.is_empty() {
+                        content.push(MessageContent::Text { text });
+                    }
+                    for call in entry.get("tool_calls").and_then(Value::as_array).into_iter().flatten() {
+                        let function = call.get("function").unwrap_or(&Value::Null);
+                        let arguments = match function.get("arguments") {
+                            Some(Value::String(raw)) if !raw.is_empty() => {
+                                serde_json::from_str(raw).map_err(|e| {
+                                    ImportError::InvalidFormat(format!("tool call arguments: {}", e))
+                                })?
+                            }
+                            Some(Value::Object(map)) => Value::Object(map.clone()),
+                            _ => serde_json::json!({}),
+                        };
+                        content.push(MessageContent::ToolCall {
+                            id: string_field(call, "id")?,
+                            name: string_field(function, "name")?,
+                            arguments,
+                        });
+                    }
+                    session.add_message(Message::new_assistant(content));
+                }
+                "tool" => push_tool_result(
+                    &mut session,
+                    MessageContent::ToolResult {
+                        tool_call_id: string_field(entry, "tool_call_id")?,
+                        result: openai_text(entry.get("content")),
+                        is_error: None,
+                        blocks: Vec::new(),
+                    },
+                ),
+                other => return Err(ImportError::UnsupportedRole(other.to_string())),
+            }
+        }
+
+        session.system_prompt = system_parts.join("\n\n");
+        Ok(session)
+    }
+
+    /// Creates a session from Anthropic Messages API messages.
+    ///
+    /// Accepts either a bare `messages` array or a request object with
+    /// `messages` and optional `system` and `model`. `tool_result` blocks
+    /// are moved into tool result messages placed before any remaining
+    /// user text.
+    pub fn from_anthropic_messages(json: &Value) -> Result<Self, ImportError> {
+        let (messages, mut session) = split_request(json)?;
+        if let Some(system) = json.get("system") {
+            session.system_prompt = anthropic_text(system);
+        }
+
+        for entry in messages {
+            let role = entry
+                .get("role")
+                .and_then(Value::as_str)
+                .ok_or_else(|| ImportError::InvalidFormat("message without a role".to_string()))?;
+            let blocks = match entry.get("content") {
+                Some(Value::String(text)) => vec![serde_json::json!({"type": "text", "text": text})],
+                Some(Value::Array(blocks)) => blocks.clone(),
+                _ => return Err(ImportError::InvalidFormat("message without content".to_string())),
+            };
+
+            match role {
+                "user" => {
+                    let mut text = String::new();
+                    for block in &blocks {
+                        match block.get("type").and_then(Value::as_str) {
+                            Some("tool_result") => push_tool_result(
+                                &mut session,
+                                MessageContent::ToolResult {
+                                    tool_call_id: string_field(block, "tool_use_id")?,
+                                    result: block.get("content").map(anthropic_text).unwrap_or_default(),
+                                    is_error: block.get("is_error").and_then(Value::as_bool),
+                                    blocks: Vec::new(),
+                                },
+                            ),
+                            Some("text") => text.push_str(&anthropic_text(block)),
+                            _ => {}
+                        }
+                    }
+                    if !text.is_empty() {
+                        session.add_message(Message::new_user(text));
+                    }
+                }
+                "assistant" => {
+                    let mut content = Vec::new();
+                    for block in &blocks {
+                        match block.get("type").and_then(Value::as_str) {
+                            Some("text") => content.push(MessageContent::Text {
+                                text: anthropic_text(block),
+                            }),
+                            Some("thinking") => content.push(MessageContent::Thinking {
+                                thinking: string_field(block, "thinking")?,
+                            }),
+                            Some("tool_use") => content.push(MessageContent::ToolCall {
+                                id: string_field(block, "id")?,
+                                name: string_field(block, "name")?,
+                                arguments: block.get("input").cloned().unwrap_or(serde_json::json!({})),
+                            }),
+                            _ => {}
+                        }
+                    }
+                    session.add_message(Message::new_assistant(content));
+                }
+                other => return Err(ImportError::UnsupportedRole(other.to_string())),
+            }
+        }
+
+        Ok(session)
+    }
+}
+
+/// Returns the messages array and a session seeded with the request's model.
+fn split_request(json: &Value) -> Result<(&Vec<Value>, Session), ImportError> {
+    let messages = match json {
+        Value::Array(messages) => messages,
+        Value::Object(_) => json
+            .get("messages")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ImportError::InvalidFormat("missing `messages` array".to_string()))?,
+        _ => return Err(ImportError::InvalidFormat("expected an array or object".to_string())),
+    };
+
+    let mut session = Session::default();
+    if let Some(model) = json.get("model").and_then(Value::as_str) {
+        session.model.name = model.to_string();
+    }
+    Ok((messages, session))
+}
+
+/// Appends a tool result, grouping it with an immediately preceding tool message.
+fn push_tool_result(session: &mut Session, result: MessageContent) {
+    match session.messages.last_mut() {
+        Some(last) if last.role == MessageRole::Tool => last.content.push(result),
+        _ => session.add_message(Message::new_tool_result(vec![result])),
+    }
+}
+
+fn string_field(value: &Value, field: &str) -> Result<String, ImportError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| ImportError::InvalidFormat(format!("missing `{}`", field)))
+}
+
+/// Flattens OpenAI content (a string or an array of parts) to text.
+fn openai_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// Flattens Anthropic content (a string, a block or an array of blocks) to text.
+fn anthropic_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks.iter().map(anthropic_text).collect(),
+        Value::Object(_) => content
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_openai_and_anthropic_tool_turns() {
+        let openai = serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "weather", "arguments": "{\"city\":\"Paris\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "18C"},
+                {"role": "assistant", "content": "It is 18C."}
+            ]
+        });
+        let anthropic = serde_json::json!({
+            "system": "Be brief.",
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "call_1", "name": "weather", "input": {"city": "Paris"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "call_1", "content": [{"type": "text", "text": "18C"}]}
+                ]},
+                {"role": "assistant", "content": "It is 18C."}
+            ]
+        });
+
+        for session in [
+            Session::from_openai_messages(&openai).unwrap(),
+            Session::from_anthropic_messages(&anthropic).unwrap(),
+        ] {
+            assert_eq!(session.system_prompt, "Be brief.");
+            let roles: Vec<_> = session.messages.iter().map(|m| m.role.clone()).collect();
+            assert_eq!(
+                roles,
+                vec![MessageRole::User, MessageRole::Assistant, MessageRole::Tool, MessageRole::Assistant]
+            );
+            assert!(matches!(
+                &session.messages[1].content[0],
+                MessageContent::ToolCall { id, arguments, .. } if id == "call_1" && arguments["city"] == "Paris"
+            ));
+            assert!(matches!(
+                &session.messages[2].content[0],
+                MessageContent::ToolResult { tool_call_id, result, .. } if tool_call_id == "call_1" && result == "18C"
+            ));
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+use crate::agent::compaction::{self, CompactionConfig, SUMMARY_PREFIX};
+use crate::agent::Agent;
+use crate::llm::{LLMClient, LLMError, RequestOptions};
+use super::{MessageContent, MessageRole, Session, SessionStore, StoreError};
+
+/// Text that replaces purged tool outputs.
+pub const PURGED_TOOL_OUTPUT: &str = "[tool output purged]";
+
+/// Errors from store maintenance.
+#[derive(Debug, thiserror::Error)]
+pub enum MaintenanceError {
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("LLM error: {0}")]
+    LLM(#[from] LLMError),
+}
+
+/// What a maintenance pass changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Sessions inspected
+    pub sessions_scanned: usize,
+    /// Sessions that were modified and saved
+    pub sessions_changed: usize,
+    /// Messages replaced by summaries
+    pub messages_compacted: usize,
+    /// Tool results and tool images removed
+    pub tool_outputs_purged: usize,
+}
+
+/// Maintenance operations over every session in a store, keeping long-lived
+/// stores from growing unbounded.
+pub struct SessionMaintenance {
+    store: Arc<dyn SessionStore>,
+}
+
+impl SessionMaintenance {
+    /// Creates maintenance operations for the given store.
+    pub fn new(store: Arc<dyn SessionStore>) -> Self {
+        Self { store }
+    }
+
+    /// Replaces tool results older than `age` with a placeholder and drops
+    /// images returned by tools.
+    pub async fn purge_tool_outputs(&self, age: Duration) -> Result<MaintenanceReport, MaintenanceError> {
+        let cutoff = Utc::now() - age;
+        self.for_each_session(|session, report| {
+            let mut purged = 0;
+            for message in session.messages.iter_mut().filter(|m| m.created_at < cutoff) {
+                if message.role == MessageRole::Tool {
+                    let images = message.content.len();
+                    message.content.retain(|c| !matches!(c, MessageContent::Image { .. }));
+                    purged += images - message.content.len();
+                }
+                for content in &mut message.content {
+                    if let MessageContent::ToolResult { result, .. } = content
+                        && result != PURGED_TOOL_OUTPUT
+                    {
+                        *result = PURGED_TOOL_OUTPUT.to_string();
+                        purged += 1;
+                    }
+                }
+            }
+            report.tool_outputs_purged += purged;
+            purged > 0
+        })
+        .await
+    }
+
+    /// Summarizes older turns of every session, keeping the most recent
+    /// `config.keep_recent` messages verbatim.
+    pub async fn compact(
+        &self,
+        llm: &dyn LLMClient,
+        config: &CompactionConfig,
+        model: &str,
+    ) -> Result<MaintenanceReport, MaintenanceError> {
+        let model = config.model.as_deref().unwrap_or(model);
+        let mut report = MaintenanceReport::default();
+        for id in self.store.list().await? {
+            let mut session = self.store.load(&id).await?;
+            report.sessions_scanned += 1;
+
+            let Some(split) = compaction::split_point(&session.messages, config.keep_recent) else {
+                continue;
+            };
+            // A lone summary has nothing new to fold in
+            if split == 1 && is_summary(&session, 0) {
+                continue;
+            }
+
+            let transcript = compaction::render_transcript(&session.messages[..split]);
+            let summary = summarize(llm, config, model, transcript).await?;
+            session.messages.splice(..split, [compaction::summary_message(&summary)]);
+            self.store.save(&session).await?;
+            report.sessions_changed += 1;
+            report.messages_compacted += split;
+        }
+        Ok(report)
+    }
+
+    /// Summarizes existing summaries again, e.g. after the summarizer
+    /// prompt changed or to shrink summaries that grew over many compactions.
+    pub async fn resummarize(
+        &self,
+        llm: &dyn LLMClient,
+        config: &CompactionConfig,
+        model: &str,
+    ) -> Result<MaintenanceReport, MaintenanceError> {
+        let model = config.model.as_deref().unwrap_or(model);
+        let mut report = MaintenanceReport::default();
+        for id in self.store.list().await? {
+            let mut session = self.store.load(&id).await?;
+            report.sessions_scanned += 1;
+            if !is_summary(&session, 0) {
+                continue;
+            }
+
+            let transcript = compaction::render_transcript(&session.messages[..1]);
+            let summary = summarize(llm, config, model, transcript).await?;
+            session.messages[0] = compaction::summary_message(&summary);
+            self.store.save(&session).await?;
+            report.sessions_changed += 1;
+        }
+        Ok(report)
+    }
+
+    /// Loads every session, applies `f` and saves the sessions it changed.
+    async fn for_each_session<F>(&self, mut f: F) -> Result<MaintenanceReport, MaintenanceError>
+    where
+        F: FnMut(&mut Session, &mut MaintenanceReport) -> bool,
+    {
+        let mut report = MaintenanceReport::default();
+        for id in self.store.list().await? {
+            let mut session = self.store.load(&id).await?;
+            report.sessions_scanned += 1;
+            if f(&mut session, &mut report) {
+                self.store.save(&session).await?;
+                report.sessions_changed += 1;
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Returns whether the message at `index` is a compaction summary.
+fn is_summary(session: &Session, index: usize) -> bool {
+    session.messages.get(index).is_some_and(|m| {
+        m.role == MessageRole::User
+            && m.content.iter().any(|c| {
+                matches!(c, MessageContent::Text { text } if text.starts_with(SUMMARY_PREFIX))
+            })
+    })
+}
+
+async fn summarize(
+    llm: &dyn LLMClient,
+    config: &CompactionConfig,
+    model: &str,
+    transcript: String,
+) -> Result<String, LLMError> {
+    let output = compaction::summarize(llm, config, model, transcript, RequestOptions::default()).await?;
+    Ok(Agent::collect_text(&output.content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{FileSessionStore, Message};
+    use crate::testing::MockLLMClient;
+
+    #[tokio::test]
+    async fn test_purge_and_compact_stored_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(FileSessionStore::new(dir.path()));
+
+        let mut session = Session::default();
+        session.add_message(Message::new_user("look it up"));
+        let mut old_result = Message::new_tool_result(vec![MessageContent::ToolResult {
+            tool_call_id: "call_1".to_string(),
+            result: "a very long page".to_string(),
+            is_error: None,
+            blocks: Vec::new(),
+        }]);
+        old_result.created_at = Utc::now() - Duration::days(40);
+        session.add_message(old_result);
+        session.add_message(Message::new_user("thanks"));
+        session.add_message(Message::new_assistant(vec![MessageContent::Text { text: "ok".to_string() }]));
+        store.save(&session).await.unwrap();
+
+        let maintenance = SessionMaintenance::new(store.clone());
+        let report = maintenance.purge_tool_outputs(Duration::days(30)).await.unwrap();
+        assert_eq!(report.tool_outputs_purged, 1);
+        assert_eq!(report.sessions_changed, 1);
+
+        let llm = MockLLMClient::new().with_text_response("User asked for a lookup.");
+        let config = CompactionConfig {
+            keep_recent: 2,
+            ..Default::default()
+        };
+        let report = maintenance.compact(&llm, &config, "gpt-4o").await.unwrap();
+        assert_eq!(report.messages_compacted, 2);
+        assert!(llm.inputs()[0].messages[0].content.iter().any(|c| matches!(
+            c,
+            MessageContent::Text { text } if text.contains(PURGED_TOOL_OUTPUT)
+        )));
+
+        let stored = store.load(&session.id).await.unwrap();
+        assert_eq!(stored.messages.len(), 3);
+        assert!(is_summary(&stored, 0));
+    }
+}
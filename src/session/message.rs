@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
+use crate::llm::Usage;
+
 /// Represents a message in a conversation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -13,6 +16,14 @@ pub struct Message {
     pub content: Vec<MessageContent>,
     /// Timestamp when the message was created
     pub created_at: DateTime<Utc>,
+    /// Free-form data about the message; the agent records `model` and
+    /// `latency_ms` on assistant messages and `tool_durations_ms` (by call
+    /// ID) on tool result messages
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub metadata: Map<String, Value>,
+    /// Tokens used by the LLM request that produced the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 /// The role of the message sender.
@@ -36,6 +47,11 @@ pub enum MessageContent {
         /// The text content
         text: String,
     },
+    /// Reasoning ("thinking") produced by the model before its answer
+    Thinking {
+        /// The reasoning text
+        thinking: String,
+    },
     /// A tool call request
     ToolCall {
         /// Unique identifier for the tool call
@@ -54,9 +70,82 @@ pub enum MessageContent {
         /// Whether the tool execution resulted in an error
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
+        /// Structured content of the result, if the tool returned any;
+        /// `result` holds its text rendering
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        blocks: Vec<ToolResultBlock>,
+    },
+    /// An image, e.g. attached by the user or returned by a tool
+    Image {
+        /// The MIME type, e.g. `image/png`
+        media_type: String,
+        /// The base64-encoded image bytes
+        data: String,
+    },
+}
+
+/// A part of a structured tool result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolResultBlock {
+    /// Plain text
+    Text {
+        /// The text
+        text: String,
+    },
+    /// A JSON value
+    Json {
+        /// The value
+        value: serde_json::Value,
+    },
+    /// An image
+    Image {
+        /// The MIME type, e.g. `image/png`
+        media_type: String,
+        /// The base64-encoded image bytes
+        data: String,
+    },
+    /// A link to a resource the model can fetch or mention
+    Resource {
+        /// The resource URI
+        uri: String,
+        /// A human-readable name
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        /// The MIME type of the resource
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
     },
 }
 
+impl ToolResultBlock {
+    /// Renders the block as text, for providers that only accept a string.
+    pub fn render(&self) -> String {
+        match self {
+            ToolResultBlock::Text { text } => text.clone(),
+            ToolResultBlock::Json { value } => {
+                serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+            }
+            ToolResultBlock::Image { media_type, .. } => format!("[image: {}]", media_type),
+            ToolResultBlock::Resource { uri, name, .. } => match name {
+                Some(name) => format!("[resource: {}]({})", name, uri),
+                None => format!("[resource]({})", uri),
+            },
+        }
+    }
+}
+
+impl MessageContent {
+    /// Creates an image block from raw bytes.
+    pub fn image(media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        use base64::Engine;
+        MessageContent::Image {
+            media_type: media_type.into(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
 impl Message {
     /// Creates a new user message.
     pub fn new_user(text: impl Into<String>) -> Self {
@@ -67,6 +156,8 @@ impl Message {
                 text: text.into(),
             }],
             created_at: Utc::now(),
+            metadata: Map::new(),
+            usage: None,
         }
     }
 
@@ -77,6 +168,8 @@ impl Message {
             role: MessageRole::Assistant,
             content,
             created_at: Utc::now(),
+            metadata: Map::new(),
+            usage: None,
         }
     }
 
@@ -87,6 +180,20 @@ impl Message {
             role: MessageRole::Tool,
             content: results,
             created_at: Utc::now(),
+            metadata: Map::new(),
+            usage: None,
         }
     }
+
+    /// Sets a metadata entry.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the token usage of the request that produced the message.
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
 }
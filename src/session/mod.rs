@@ -1,5 +1,18 @@
+pub mod export;
+pub mod import;
+pub mod maintenance;
 pub mod message;
+#[allow(clippy::module_inception)]
 pub mod session;
+pub mod store;
+pub mod trimming;
+pub mod usage;
 
+pub use export::ExportFormat;
+pub use import::ImportError;
+pub use maintenance::{MaintenanceError, MaintenanceReport, SessionMaintenance};
 pub use message::*;
 pub use session::*;
+pub use store::{FileSessionStore, SessionStore, StoreError};
+pub use trimming::ContextStrategy;
+pub use usage::*;
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Represents a conversation session.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +15,26 @@ pub struct Session {
     pub model: ModelConfig,
     /// The current status of the session
     pub status: SessionStatus,
+    /// Accumulated token usage and cost per model
+    #[serde(default)]
+    pub usage: HashMap<String, super::ModelUsage>,
+    /// The model and tools the session last ran with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<SessionProvenance>,
+    /// Which messages are sent to the model; overrides
+    /// `AgentConfig::context_strategy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_strategy: Option<super::ContextStrategy>,
+}
+
+/// The model and tools a session last ran with, used to detect changes when
+/// it is resumed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionProvenance {
+    /// The model of the last LLM request
+    pub model: String,
+    /// Fingerprints of the available tools' input schemas, by tool name
+    pub tools: BTreeMap<String, String>,
 }
 
 /// The status of a session.
@@ -29,6 +49,8 @@ pub enum SessionStatus {
     Completed,
     /// Error occurred
     Error,
+    /// Stopped by the user before completing
+    Cancelled,
 }
 
 /// Configuration for the LLM model.
@@ -66,6 +88,9 @@ impl Session {
             system_prompt: system_prompt.into(),
             model,
             status: SessionStatus::Idle,
+            usage: HashMap::new(),
+            provenance: None,
+            context_strategy: None,
         }
     }
 
@@ -84,6 +109,19 @@ impl Session {
         self.messages.len()
     }
 
+    /// Records the usage of an LLM request against the given model.
+    pub fn record_usage(&mut self, model: &str, usage: &crate::llm::Usage, cost: Option<f64>) {
+        self.usage
+            .entry(model.to_string())
+            .or_default()
+            .record(usage, cost);
+    }
+
+    /// Returns a usage report with token counts and estimated cost per model.
+    pub fn usage_report(&self) -> super::UsageReport {
+        super::UsageReport::from_models(self.usage.clone())
+    }
+
     /// Clears all messages from the session.
     pub fn clear_messages(&mut self) {
         self.messages.clear();
@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use std::io;
+use std::path::PathBuf;
+
+use super::Session;
+
+/// Errors from a session store.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Session not found: {0}")]
+    NotFound(String),
+    #[error("Invalid session ID: {0}")]
+    InvalidId(String),
+}
+
+/// Persistent storage for sessions.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Saves the session, replacing any stored version.
+    async fn save(&self, session: &Session) -> Result<(), StoreError>;
+    /// Loads the session with the given ID.
+    async fn load(&self, id: &str) -> Result<Session, StoreError>;
+    /// Returns the IDs of all stored sessions.
+    async fn list(&self) -> Result<Vec<String>, StoreError>;
+    /// Deletes the session with the given ID.
+    async fn delete(&self, id: &str) -> Result<(), StoreError>;
+}
+
+/// Stores each session as `<id>.json` in a directory.
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    /// Creates a store in the given directory, which is created on first save.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, StoreError> {
+        if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+            return Err(StoreError::InvalidId(id.to_string()));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn save(&self, session: &Session) -> Result<(), StoreError> {
+        let path = self.path(&session.id)?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // Write to a temporary file first so a crash never leaves a torn session
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(session)?).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn load(&self, id: &str) -> Result<Session, StoreError> {
+        let json = match tokio::fs::read(self.path(id)?).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(StoreError::NotFound(id.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    async fn list(&self) -> Result<Vec<String>, StoreError> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut ids = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            if let Some(id) = name.to_str().and_then(|n| n.strip_suffix(".json")) {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), StoreError> {
+        match tokio::fs::remove_file(self.path(id)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(StoreError::NotFound(id.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Message, MessageContent, MessageRole};
+use crate::llm::TokenCounter;
+
+/// Text that replaces tool results dropped by `ContextStrategy`.
+pub const OMITTED_TOOL_RESULT: &str = "[tool result omitted to save context]";
+
+/// Which messages of a long conversation are sent to the model. The session
+/// keeps every message; only the request is trimmed.
+///
+/// Rules combine: the last `keep_last` messages are kept, then older tool
+/// results are dropped and older messages removed until the rest fits the
+/// token window. Kept history never starts with a tool result, so calls
+/// stay paired with their results.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextStrategy {
+    /// Keep at most this many recent messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<usize>,
+    /// Token budget of the messages; the agent uses what the model's context
+    /// window leaves after the system prompt, tools and output when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// Always send the first user message, which usually states the task
+    #[serde(default)]
+    pub pin_first_user: bool,
+    /// Replace old tool results with a placeholder before removing whole
+    /// messages
+    #[serde(default)]
+    pub drop_tool_results_first: bool,
+}
+
+impl ContextStrategy {
+    /// Keeps the last `n` messages.
+    pub fn keep_last(n: usize) -> Self {
+        Self {
+            keep_last: Some(n),
+            ..Self::default()
+        }
+    }
+
+    /// Keeps the most recent messages that fit in `max_tokens`.
+    pub fn token_window(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: Some(max_tokens),
+            ..Self::default()
+        }
+    }
+
+    /// Always sends the first user message.
+    pub fn with_pinned_first_user(mut self) -> Self {
+        self.pin_first_user = true;
+        self
+    }
+
+    /// Drops old tool results before removing whole messages.
+    pub fn with_tool_results_dropped_first(mut self) -> Self {
+        self.drop_tool_results_first = true;
+        self
+    }
+
+    /// Returns the messages to send, fitting `budget` tokens when the
+    /// strategy has no `max_tokens` of its own.
+    pub fn apply(&self, messages: &[Message], budget: Option<usize>, counter: &dyn TokenCounter) -> Vec<Message> {
+        let pinned = if self.pin_first_user {
+            messages.iter().position(|m| m.role == MessageRole::User)
+        } else {
+            None
+        };
+
+        let mut start = Self::boundary(messages, self.keep_last.map_or(0, |n| messages.len().saturating_sub(n)));
+        let mut kept: Vec<Message> = messages[start..].to_vec();
+        let pin = |start: usize| pinned.filter(|&i| i < start).map(|i| &messages[i]);
+
+        let Some(budget) = self.max_tokens.or(budget) else {
+            return pin(start).cloned().into_iter().chain(kept).collect();
+        };
+        let tokens = |kept: &[Message], start: usize| {
+            kept.iter().chain(pin(start)).map(|m| counter.count_message(m)).sum::<usize>()
+        };
+
+        // The latest message and its tool results are never trimmed: the
+        // model needs them to go on
+        let mut latest = kept.iter().rposition(|m| m.role != MessageRole::Tool).unwrap_or(0);
+        if self.drop_tool_results_first {
+            for i in 0..latest {
+                if tokens(&kept, start) <= budget {
+                    break;
+                }
+                for content in &mut kept[i].content {
+                    if let MessageContent::ToolResult { result, blocks, .. } = content {
+                        *result = OMITTED_TOOL_RESULT.to_string();
+                        blocks.clear();
+                    }
+                }
+            }
+        }
+        while tokens(&kept, start) > budget {
+            let removed = Self::boundary(messages, start + 1) - start;
+            if removed > latest {
+                break;
+            }
+            kept.drain(..removed);
+            start += removed;
+            latest -= removed;
+        }
+        pin(start).cloned().into_iter().chain(kept).collect()
+    }
+
+    /// Moves `start` forward past tool results, whose calls would be cut off.
+    fn boundary(messages: &[Message], mut start: usize) -> usize {
+        while start < messages.len() && messages[start].role == MessageRole::Tool {
+            start += 1;
+        }
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::HeuristicTokenCounter;
+
+    fn step(id: &str, output: &str) -> [Message; 2] {
+        [
+            Message::new_assistant(vec![MessageContent::ToolCall {
+                id: id.to_string(),
+                name: "read".to_string(),
+                arguments: serde_json::json!({}),
+            }]),
+            Message::new_tool_result(vec![MessageContent::ToolResult {
+                tool_call_id: id.to_string(),
+                result: output.to_string(),
+                is_error: None,
+                blocks: Vec::new(),
+            }]),
+        ]
+    }
+
+    fn result_of(message: &Message) -> &str {
+        match &message.content[0] {
+            MessageContent::ToolResult { result, .. } => result,
+            _ => "",
+        }
+    }
+
+    #[test]
+    fn test_strategies_trim_history() {
+        let big = "word ".repeat(400);
+        let mut messages = vec![Message::new_user("Summarize the repo")];
+        messages.extend(step("1", &big));
+        messages.extend(step("2", &big));
+        messages.extend(step("3", "small"));
+        let counter = HeuristicTokenCounter;
+
+        let kept = ContextStrategy::keep_last(3).with_pinned_first_user().apply(&messages, None, &counter);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].role, MessageRole::User);
+        assert_eq!(result_of(&kept[2]), "small");
+
+        let dropped = ContextStrategy::token_window(200)
+            .with_tool_results_dropped_first()
+            .apply(&messages, None, &counter);
+        assert_eq!(dropped.len(), messages.len());
+        assert_eq!(result_of(&dropped[2]), OMITTED_TOOL_RESULT);
+        assert_eq!(result_of(&dropped[4]), OMITTED_TOOL_RESULT);
+        assert_eq!(result_of(&dropped[6]), "small");
+
+        let windowed = ContextStrategy::default()
+            .with_pinned_first_user()
+            .apply(&messages, Some(200), &counter);
+        assert_eq!(windowed.len(), 3);
+        assert_eq!(windowed[1].role, MessageRole::Assistant);
+        assert!(windowed.iter().map(|m| counter.count_message(m)).sum::<usize>() <= 200);
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::llm::Usage;
+
+/// Accumulated token usage and estimated cost for a single model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    /// Number of LLM requests made
+    pub requests: u32,
+    /// Total input tokens
+    pub input_tokens: u64,
+    /// Total output tokens
+    pub output_tokens: u64,
+    /// Estimated cost in US dollars (unpriced requests contribute nothing)
+    pub cost: f64,
+}
+
+impl ModelUsage {
+    /// Adds the usage of a single request.
+    pub fn record(&mut self, usage: &Usage, cost: Option<f64>) {
+        self.requests += 1;
+        self.input_tokens += u64::from(usage.input_tokens);
+        self.output_tokens += u64::from(usage.output_tokens);
+        self.cost += cost.unwrap_or(0.0);
+    }
+}
+
+/// A summary of token usage and cost, broken down per model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Usage per model name
+    pub by_model: HashMap<String, ModelUsage>,
+    /// Total input tokens across all models
+    pub input_tokens: u64,
+    /// Total output tokens across all models
+    pub output_tokens: u64,
+    /// Total estimated cost in US dollars
+    pub cost: f64,
+}
+
+impl UsageReport {
+    /// Builds a report from per-model usage.
+    pub fn from_models(by_model: HashMap<String, ModelUsage>) -> Self {
+        let mut report = Self::default();
+        for usage in by_model.values() {
+            report.input_tokens += usage.input_tokens;
+            report.output_tokens += usage.output_tokens;
+            report.cost += usage.cost;
+        }
+        report.by_model = by_model;
+        report
+    }
+}
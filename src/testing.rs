@@ -0,0 +1,220 @@
+//! Test helpers for exercising agents without a real LLM provider.
+
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::llm::{FinishReason, LLMClient, LLMError, LLMEvent, LLMInput, LLMOutput, LLMStream, Usage};
+use crate::session::MessageContent;
+use crate::tool::{Tool, ToolError, ToolResult};
+
+/// An `LLMClient` that replays scripted responses and records every input
+/// it receives.
+///
+/// `complete` pops the next scripted output. `stream` pops the next scripted
+/// event list, or converts the next scripted output into events when no
+/// stream was scripted.
+#[derive(Debug, Default)]
+pub struct MockLLMClient {
+    responses: Mutex<VecDeque<Result<LLMOutput, LLMError>>>,
+    streams: Mutex<VecDeque<Vec<LLMEvent>>>,
+    inputs: Mutex<Vec<LLMInput>>,
+}
+
+impl MockLLMClient {
+    /// Creates a mock with no scripted responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scripts a complete response.
+    pub fn with_response(self, output: LLMOutput) -> Self {
+        self.push_response(output);
+        self
+    }
+
+    /// Scripts a plain text response.
+    pub fn with_text_response(self, text: impl Into<String>) -> Self {
+        self.with_response(Self::text_output(text))
+    }
+
+    /// Scripts a response containing a single tool call.
+    pub fn with_tool_call_response(
+        self,
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: serde_json::Value,
+    ) -> Self {
+        self.with_response(LLMOutput {
+            content: vec![MessageContent::ToolCall {
+                id: id.into(),
+                name: name.into(),
+                arguments,
+            }],
+            finish_reason: FinishReason::ToolCalls,
+            usage: Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+            },
+        })
+    }
+
+    /// Scripts an error returned in place of the next response.
+    pub fn with_error(self, error: LLMError) -> Self {
+        self.responses.lock().unwrap().push_back(Err(error));
+        self
+    }
+
+    /// Scripts a raw event stream.
+    pub fn with_stream(self, events: Vec<LLMEvent>) -> Self {
+        self.streams.lock().unwrap().push_back(events);
+        self
+    }
+
+    /// Appends a complete response to the script.
+    pub fn push_response(&self, output: LLMOutput) {
+        self.responses.lock().unwrap().push_back(Ok(output));
+    }
+
+    /// Returns every input received so far, in order.
+    pub fn inputs(&self) -> Vec<LLMInput> {
+        self.inputs.lock().unwrap().clone()
+    }
+
+    /// Returns the number of requests received.
+    pub fn call_count(&self) -> usize {
+        self.inputs.lock().unwrap().len()
+    }
+
+    /// Builds a text-only output.
+    pub fn text_output(text: impl Into<String>) -> LLMOutput {
+        LLMOutput {
+            content: vec![MessageContent::Text { text: text.into() }],
+            finish_reason: FinishReason::Stop,
+            usage: Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+            },
+        }
+    }
+
+    fn next_response(&self) -> Result<LLMOutput, LLMError> {
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| {
+                Err(LLMError::InvalidResponse(
+                    "MockLLMClient has no scripted responses left".to_string(),
+                ))
+            })
+    }
+}
+
+#[async_trait]
+impl LLMClient for MockLLMClient {
+    async fn stream(&self, input: LLMInput) -> Result<LLMStream, LLMError> {
+        self.inputs.lock().unwrap().push(input);
+
+        let scripted = self.streams.lock().unwrap().pop_front();
+        let events = match scripted {
+            Some(events) => events,
+            None => self.next_response()?.into_events(),
+        };
+
+        Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+    }
+
+    async fn complete(&self, input: LLMInput) -> Result<LLMOutput, LLMError> {
+        self.inputs.lock().unwrap().push(input);
+        self.next_response()
+    }
+}
+
+/// A tool named `echo` that returns its `text` argument, or the raw
+/// arguments as JSON when there is none.
+#[derive(Debug, Default)]
+pub struct EchoTool;
+
+#[async_trait]
+impl Tool for EchoTool {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn description(&self) -> &str {
+        "Echoes its input"
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({"type": "object"})
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        Ok(ToolResult::ok(match args["text"].as_str() {
+            Some(text) => text.to_string(),
+            None => args.to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, StopReason};
+    use crate::session::{MessageRole, Session};
+    use crate::tool::ToolRegistry;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    struct AddTool;
+
+    #[async_trait]
+    impl Tool for AddTool {
+        fn name(&self) -> &str {
+            "add"
+        }
+
+        fn description(&self) -> &str {
+            "Adds two numbers"
+        }
+
+        fn parameters_schema(&self) -> Value {
+            serde_json::json!({"type": "object"})
+        }
+
+        async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+            let sum = args["a"].as_i64().unwrap_or(0) + args["b"].as_i64().unwrap_or(0);
+            Ok(ToolResult::ok(sum.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_runs_scripted_tool_flow() {
+        let llm = Arc::new(
+            MockLLMClient::new()
+                .with_tool_call_response("call_1", "add", serde_json::json!({"a": 2, "b": 3}))
+                .with_text_response("The sum is 5."),
+        );
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(AddTool));
+        let registry = Arc::new(tokio::sync::Mutex::new(registry));
+
+        let agent = Agent::with_defaults(Session::default(), llm.clone(), registry);
+        let result = agent.run("What is 2 + 3?").await.unwrap();
+
+        let roles: Vec<_> = result.messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(roles, vec![MessageRole::Assistant, MessageRole::Tool, MessageRole::Assistant]);
+        assert!(matches!(
+            &result.messages[1].content[0],
+            MessageContent::ToolResult { result, .. } if result == "5"
+        ));
+        assert_eq!(result.text, "The sum is 5.");
+        assert_eq!(result.steps, 2);
+        assert_eq!(result.stop_reason, StopReason::Completed);
+
+        let inputs = llm.inputs();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[1].messages.len(), 3);
+    }
+}
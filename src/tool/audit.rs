@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Latency samples kept per tool for percentiles.
+const LATENCY_SAMPLES: usize = 1024;
+
+/// Aggregated statistics of one tool's calls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStats {
+    /// The name of the tool
+    pub name: String,
+    /// Number of calls
+    pub invocations: u64,
+    /// Number of calls that returned an error
+    pub errors: u64,
+    /// Median call duration
+    #[serde(with = "millis")]
+    pub latency_p50: Duration,
+    /// 95th percentile call duration
+    #[serde(with = "millis")]
+    pub latency_p95: Duration,
+    /// 99th percentile call duration
+    #[serde(with = "millis")]
+    pub latency_p99: Duration,
+    /// Total size of the results returned, in bytes
+    pub bytes_returned: u64,
+}
+
+impl ToolStats {
+    /// Returns the share of calls that failed, between 0 and 1.
+    pub fn error_rate(&self) -> f64 {
+        if self.invocations == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.invocations as f64
+    }
+}
+
+/// One tool call in the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// When the call finished
+    pub timestamp: DateTime<Utc>,
+    /// The session the call was made in
+    pub session_id: String,
+    /// The assistant message that requested the call
+    pub message_id: String,
+    /// The tool call ID
+    pub call_id: String,
+    /// The name of the tool
+    pub tool: String,
+    /// The arguments, with secrets redacted
+    pub args: Value,
+    /// FNV-1a hash of the result sent to the model
+    pub result_hash: String,
+    /// Whether the call failed
+    pub is_error: bool,
+    /// How long the call took
+    #[serde(with = "millis")]
+    pub duration: Duration,
+    /// Size of the result, in bytes
+    pub bytes: u64,
+}
+
+impl AuditEntry {
+    /// Returns the FNV-1a hash recorded for a result.
+    pub fn hash_result(result: &str) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in result.as_bytes() {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    invocations: u64,
+    errors: u64,
+    latencies: VecDeque<Duration>,
+    bytes: u64,
+}
+
+/// Collects statistics and the audit log of an executor's calls. Entries
+/// are only ever appended.
+#[derive(Debug, Default)]
+pub(crate) struct ToolMetrics {
+    samples: Mutex<HashMap<String, Samples>>,
+    audit: Mutex<Vec<AuditEntry>>,
+}
+
+impl ToolMetrics {
+    pub(crate) fn record(&self, entry: AuditEntry) {
+        if let Ok(mut samples) = self.samples.lock() {
+            let samples = samples.entry(entry.tool.clone()).or_default();
+            samples.invocations += 1;
+            samples.errors += u64::from(entry.is_error);
+            samples.bytes += entry.bytes;
+            if samples.latencies.len() == LATENCY_SAMPLES {
+                samples.latencies.pop_front();
+            }
+            samples.latencies.push_back(entry.duration);
+        }
+        if let Ok(mut audit) = self.audit.lock() {
+            audit.push(entry);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Vec<ToolStats> {
+        let Ok(samples) = self.samples.lock() else {
+            return Vec::new();
+        };
+        let mut stats: Vec<_> = samples
+            .iter()
+            .map(|(name, samples)| {
+                let mut latencies: Vec<_> = samples.latencies.iter().copied().collect();
+                latencies.sort_unstable();
+                ToolStats {
+                    name: name.clone(),
+                    invocations: samples.invocations,
+                    errors: samples.errors,
+                    latency_p50: percentile(&latencies, 50),
+                    latency_p95: percentile(&latencies, 95),
+                    latency_p99: percentile(&latencies, 99),
+                    bytes_returned: samples.bytes,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+
+    pub(crate) fn audit_log(&self) -> Vec<AuditEntry> {
+        self.audit.lock().map(|a| a.clone()).unwrap_or_default()
+    }
+}
+
+/// Nearest-rank percentile of sorted samples.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_aggregate_calls_per_tool() {
+        let metrics = ToolMetrics::default();
+        for (ms, is_error) in [(10, false), (20, false), (30, true), (400, false)] {
+            metrics.record(AuditEntry {
+                timestamp: Utc::now(),
+                session_id: "s".to_string(),
+                message_id: "m".to_string(),
+                call_id: format!("call_{}", ms),
+                tool: "search".to_string(),
+                args: serde_json::json!({"q": "rust"}),
+                result_hash: AuditEntry::hash_result("ok"),
+                is_error,
+                duration: Duration::from_millis(ms),
+                bytes: 2,
+            });
+        }
+
+        let stats = metrics.stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].invocations, 4);
+        assert_eq!(stats[0].error_rate(), 0.25);
+        assert_eq!(stats[0].latency_p50, Duration::from_millis(20));
+        assert_eq!(stats[0].latency_p99, Duration::from_millis(400));
+        assert_eq!(stats[0].bytes_returned, 8);
+
+        let json = serde_json::to_value(&metrics.audit_log()[2]).unwrap();
+        assert_eq!(json["duration"], 30);
+        assert_eq!(json["is_error"], true);
+    }
+}
@@ -0,0 +1,475 @@
+//! A shell tool running commands with `bash -c`.
+//!
+//! Commands run inside a working directory with a timeout and a cap on the
+//! output returned to the model. Allow and deny lists restrict which programs
+//! may be invoked. The tool asks for permission by default; `permission_rules`
+//! turns its deny list into rules for a `PermissionManager`.
+//!
+//! With a `SandboxPolicy` on the permission rules, the working directory and
+//! the paths spelled out in a command must be readable under it, and the
+//! paths a command writes, through redirects or programs such as `rm`, `cp`
+//! and `tee`, writable.
+//!
+//! The lists, working directory and sandbox keep a well-behaved model on
+//! track; they are not a security boundary against a hostile one.
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::permission::{PathAccess, Permission, PermissionAction};
+use crate::tool::{Tool, ToolContext, ToolError, ToolResult};
+
+/// Runs shell commands for the agent.
+///
+/// Requires the `bash` feature.
+#[derive(Debug, Clone)]
+pub struct BashTool {
+    root: PathBuf,
+    timeout: Duration,
+    max_output_bytes: usize,
+    allowed: Vec<String>,
+    denied: Vec<String>,
+}
+
+impl BashTool {
+    /// Creates a tool running commands in `root`; commands may only change
+    /// into directories below it.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            timeout: Duration::from_secs(60),
+            max_output_bytes: 32 * 1024,
+            allowed: Vec::new(),
+            denied: Vec::new(),
+        }
+    }
+
+    /// Sets how long a command may run before it is killed.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many bytes of output are returned to the model.
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Only allows commands that invoke these programs.
+    pub fn with_allowed_commands<I, S>(mut self, programs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed.extend(programs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Rejects commands that invoke any of these programs.
+    pub fn with_denied_commands<I, S>(mut self, programs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.denied.extend(programs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns permission rules denying calls that invoke a denied program,
+    /// e.g. `rm` in `ls && /bin/rm x` but not in `git rm x`. Other calls are
+    /// left to the manager's other rules and default action; the allow list
+    /// is enforced by the tool itself.
+    pub fn permission_rules(&self) -> Vec<Permission> {
+        self.denied
+            .iter()
+            .map(|program| Permission {
+                tool: "bash".to_string(),
+                action: PermissionAction::Deny,
+                patterns: None,
+                args: Some(HashMap::from([("command".to_string(), Self::program_regex(program))])),
+                quota: None,
+            })
+            .collect()
+    }
+
+    /// Returns a regex matching commands that invoke `program`, as split by
+    /// `programs`: at the start of a command, after variable assignments
+    /// and optionally by path.
+    fn program_regex(program: &str) -> String {
+        format!(
+            r"(^|[;&|\n()`$\{{}}])\s*(\S+=\S*\s+)*(\S*/)?{}(\s|[;&|\n()`\}}]|$)",
+            regex::escape(program)
+        )
+    }
+
+    /// Returns the programs a command invokes, e.g. `git` and `grep` for
+    /// `FOO=1 git log | grep fix`.
+    fn programs(command: &str) -> Vec<String> {
+        command
+            .split([';', '&', '|', '\n', '(', ')', '`', '$', '{', '}'])
+            .filter_map(|segment| {
+                segment
+                    .split_whitespace()
+                    .find(|word| !word.contains('='))
+                    .map(|word| word.rsplit('/').next().unwrap_or(word).to_string())
+            })
+            .collect()
+    }
+
+    /// Checks the command's programs against the allow and deny lists.
+    fn check_command(&self, command: &str) -> Result<(), ToolError> {
+        for program in Self::programs(command) {
+            if self.denied.contains(&program) {
+                return Err(ToolError::InvalidArguments(format!("`{}` is not allowed", program)));
+            }
+            if !self.allowed.is_empty() && !self.allowed.contains(&program) {
+                return Err(ToolError::InvalidArguments(format!(
+                    "`{}` is not allowed; allowed programs are {}",
+                    program,
+                    self.allowed.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the paths a command accesses. Redirect targets and the
+    /// operands of programs that change files are writes. Other words that
+    /// look like paths are reads: absolute ones, and relative ones starting
+    /// with `./` or climbing with `..`.
+    fn paths(command: &str) -> Vec<(String, PathAccess)> {
+        let looks_like_path =
+            |word: &str| word.starts_with('/') || word.starts_with("./") || word.split('/').any(|part| part == "..");
+        let mut paths = Vec::new();
+        for segment in command.split([';', '&', '|', '\n', '(', ')', '`', '$', '{', '}']) {
+            let mut words = Vec::new();
+            let mut tokens = Self::tokens(segment).into_iter();
+            while let Some(token) = tokens.next() {
+                let access = match token.as_str() {
+                    ">" | ">>" => PathAccess::Write,
+                    "<" => PathAccess::Read,
+                    _ => {
+                        words.push(token);
+                        continue;
+                    }
+                };
+                // The descriptor of `2>` is not an operand
+                if words.last().is_some_and(|word| word.chars().all(|c| c.is_ascii_digit())) {
+                    words.pop();
+                }
+                if let Some(target) = tokens.next()
+                    && !target.starts_with("/dev/")
+                {
+                    paths.push((target, access));
+                }
+            }
+
+            let words: Vec<&str> = words.iter().map(String::as_str).skip_while(|word| word.contains('=')).collect();
+            let Some((program, args)) = words.split_first() else {
+                continue;
+            };
+            let operands: Vec<&str> = args.iter().copied().filter(|arg| !arg.starts_with('-')).collect();
+            let program = program.rsplit('/').next().unwrap_or(program);
+            let written: Vec<&str> = match program {
+                "rm" | "rmdir" | "mkdir" | "touch" | "mv" | "tee" | "truncate" => operands,
+                "cp" | "ln" | "install" => operands.last().copied().into_iter().collect(),
+                "chmod" | "chown" => operands.into_iter().skip(1).collect(),
+                _ => Vec::new(),
+            };
+            for arg in args {
+                if written.contains(arg) {
+                    paths.push((arg.to_string(), PathAccess::Write));
+                } else if let Some(output) = arg.strip_prefix("of=").filter(|_| program == "dd") {
+                    paths.push((output.to_string(), PathAccess::Write));
+                } else {
+                    let path = arg.rsplit('=').next().unwrap_or(arg);
+                    if looks_like_path(path) {
+                        paths.push((path.to_string(), PathAccess::Read));
+                    }
+                }
+            }
+        }
+        paths
+    }
+
+    /// Splits a command into words, with quotes removed, and the redirect
+    /// operators `<`, `>` and `>>`.
+    fn tokens(segment: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        let mut quote = None;
+        let mut chars = segment.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, quote) {
+                (c, Some(q)) if c == q => quote = None,
+                (c, Some(_)) => word.push(c),
+                ('"' | '\'', None) => quote = Some(c),
+                (c, None) if c.is_whitespace() || c == '<' || c == '>' => {
+                    if !word.is_empty() {
+                        tokens.push(std::mem::take(&mut word));
+                    }
+                    if c == '>' && chars.next_if_eq(&'>').is_some() {
+                        tokens.push(">>".to_string());
+                    } else if !c.is_whitespace() {
+                        tokens.push(c.to_string());
+                    }
+                }
+                (c, None) => word.push(c),
+            }
+        }
+        if !word.is_empty() {
+            tokens.push(word);
+        }
+        tokens
+    }
+
+    /// Checks the working directory and the paths in a command against the
+    /// sandbox, if the permission rules have one.
+    fn check_sandbox(command: &str, dir: &Path, ctx: &ToolContext) -> Result<(), ToolError> {
+        let sandboxed = |path: &Path, access: PathAccess| {
+            ctx.check_path(path, access)
+                .map_err(|e| ToolError::InvalidArguments(e.to_string()))
+        };
+        sandboxed(dir, PathAccess::Read)?;
+        for (path, access) in Self::paths(command) {
+            sandboxed(&dir.join(path), access)?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the working directory of a call, which must stay below the
+    /// root.
+    fn working_dir(&self, cwd: Option<&str>) -> Result<PathBuf, ToolError> {
+        let root = self.root.canonicalize().map_err(|e| {
+            ToolError::ExecutionFailed(format!("working directory {}: {}", self.root.display(), e))
+        })?;
+        let Some(cwd) = cwd else {
+            return Ok(root);
+        };
+        let dir = root
+            .join(Path::new(cwd))
+            .canonicalize()
+            .map_err(|e| ToolError::InvalidArguments(format!("cwd `{}`: {}", cwd, e)))?;
+        if !dir.starts_with(&root) {
+            return Err(ToolError::InvalidArguments(format!(
+                "cwd `{}` is outside the working directory",
+                cwd
+            )));
+        }
+        Ok(dir)
+    }
+
+    /// Cuts the output down to the byte cap on a character boundary.
+    fn truncate(&self, mut output: String) -> String {
+        if output.len() <= self.max_output_bytes {
+            return output;
+        }
+        let total = output.len();
+        let mut end = self.max_output_bytes;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        output.truncate(end);
+        output.push_str(&format!("\n[output truncated: {} of {} bytes shown]", end, total));
+        output
+    }
+}
+
+#[async_trait]
+impl Tool for BashTool {
+    fn name(&self) -> &str {
+        "bash"
+    }
+
+    fn description(&self) -> &str {
+        "Runs a shell command with bash and returns its combined stdout and stderr"
+    }
+
+    fn parameters_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "The command to run"
+                },
+                "cwd": {
+                    "type": "string",
+                    "description": "Directory to run in, relative to the working directory"
+                }
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn default_permission(&self) -> Option<PermissionAction> {
+        Some(PermissionAction::Ask)
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_ctx(args, &ToolContext::default()).await
+    }
+
+    async fn execute_with_ctx(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let command = args["command"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidArguments("missing `command`".to_string()))?;
+        self.check_command(command)?;
+        let dir = self.working_dir(args["cwd"].as_str())?;
+        Self::check_sandbox(command, &dir, ctx)?;
+
+        let child = Command::new("bash")
+            .arg("-c")
+            .arg(command)
+            .current_dir(&dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // A timed-out command is killed when its future is dropped
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ToolError::ExecutionFailed(format!("failed to start bash: {}", e)))?;
+
+        let output = match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+            Ok(output) => output.map_err(|e| ToolError::ExecutionFailed(e.to_string()))?,
+            Err(_) => {
+                return Err(ToolError::ExecutionFailed(format!(
+                    "command timed out after {:?}",
+                    self.timeout
+                )));
+            }
+        };
+
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        let text = self.truncate(text);
+
+        if output.status.success() {
+            return Ok(ToolResult::ok(text));
+        }
+        let status = match output.status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "killed by a signal".to_string(),
+        };
+        Ok(ToolResult {
+            error: Some(status.clone()),
+            ..ToolResult::ok(format!("{}\n[{}]", text, status))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runs_confined_commands_within_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let tool = BashTool::new(dir.path())
+            .with_timeout(Duration::from_millis(200))
+            .with_max_output_bytes(8)
+            .with_denied_commands(["rm"]);
+
+        let result = tool.execute(serde_json::json!({"command": "pwd", "cwd": "sub"})).await.unwrap();
+        assert!(result.output.starts_with("/"));
+        assert!(result.output.contains("[output truncated"));
+
+        let failed = tool.execute(serde_json::json!({"command": "exit 3"})).await.unwrap();
+        assert_eq!(failed.error.as_deref(), Some("exit code 3"));
+
+        for args in [
+            serde_json::json!({"command": "ls && /bin/rm -r sub"}),
+            serde_json::json!({"command": "ls", "cwd": ".."}),
+        ] {
+            assert!(matches!(tool.execute(args).await, Err(ToolError::InvalidArguments(_))));
+        }
+        assert!(matches!(
+            tool.execute(serde_json::json!({"command": "sleep 5"})).await,
+            Err(ToolError::ExecutionFailed(e)) if e.contains("timed out")
+        ));
+        assert!(dir.path().join("sub").exists());
+    }
+
+    #[test]
+    fn test_permission_rules_deny_only_invoked_programs() {
+        let tool = BashTool::new(".").with_denied_commands(["rm"]).with_allowed_commands(["ls", "git", "rm"]);
+        let mut manager = crate::permission::PermissionManager::new().with_default_action(PermissionAction::Ask);
+        for rule in tool.permission_rules() {
+            manager.add_rule(rule);
+        }
+        let evaluate = |command: &str| {
+            manager.evaluate(&crate::permission::PermissionContext {
+                tool: "bash".to_string(),
+                args: serde_json::json!({"command": command}),
+                session_id: "s".to_string(),
+            })
+        };
+
+        for command in ["rm -rf x", "ls && /bin/rm x", "FOO=1 rm x", "ls | (rm x)"] {
+            assert_eq!(evaluate(command), Some(PermissionAction::Deny), "{}", command);
+        }
+        // Other calls keep the manager's default
+        for command in ["git rm --cached x", "format x", "npm run perform", "ls rm", "rmdir x"] {
+            assert_eq!(evaluate(command), None, "{}", command);
+        }
+        assert_eq!(manager.default_action(), Some(&PermissionAction::Ask));
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_confines_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = crate::permission::SandboxPolicy::new()
+            .with_root(dir.path())
+            .with_denied_glob("*.key");
+        let ctx = ToolContext {
+            permissions: Some(std::sync::Arc::new(crate::permission::PermissionManager::new().with_sandbox(sandbox))),
+            ..Default::default()
+        };
+        let tool = BashTool::new(dir.path());
+
+        let run = |command: &str| tool.execute_with_ctx(serde_json::json!({"command": command}), &ctx);
+        assert!(run("echo hi > ./note.txt").await.is_ok());
+        for command in ["cat /etc/hostname", "cat ../x", "cat ./secret.key"] {
+            assert!(matches!(run(command).await, Err(ToolError::InvalidArguments(_))), "{}", command);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_rejects_writes_to_read_only_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("ro")).unwrap();
+        std::fs::write(dir.path().join("ro/file"), "keep").unwrap();
+        let sandbox = crate::permission::SandboxPolicy::new()
+            .with_root(dir.path())
+            .with_read_only(dir.path().join("ro"));
+        let ctx = ToolContext {
+            permissions: Some(std::sync::Arc::new(crate::permission::PermissionManager::new().with_sandbox(sandbox))),
+            ..Default::default()
+        };
+        let tool = BashTool::new(dir.path());
+
+        let run = |command: &str| tool.execute_with_ctx(serde_json::json!({"command": command}), &ctx);
+        for command in [
+            "echo x > ro/file",
+            "echo x>>ro/file",
+            "echo x | tee ro/new",
+            "cp ./ro/file ro/copy",
+            "rm -f ro/file",
+            "FOO=1 /bin/touch 'ro/new'",
+            "dd if=/dev/zero of=ro/file count=1",
+        ] {
+            assert!(matches!(run(command).await, Err(ToolError::InvalidArguments(_))), "{}", command);
+        }
+        for command in ["cat ./ro/file", "cp ./ro/file copy", "ls ro 2>/dev/null > out.txt 2>&1"] {
+            assert!(run(command).await.is_ok(), "{}", command);
+        }
+        assert_eq!(std::fs::read_to_string(dir.path().join("ro/file")).unwrap(), "keep");
+    }
+}
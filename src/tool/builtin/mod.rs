@@ -0,0 +1,15 @@
+//! Ready-made tools, each behind its own cargo feature.
+
+#[cfg(feature = "bash")]
+pub mod bash;
+#[cfg(feature = "notify")]
+pub mod notify;
+#[cfg(feature = "screenshot")]
+pub mod screenshot;
+
+#[cfg(feature = "bash")]
+pub use bash::BashTool;
+#[cfg(feature = "notify")]
+pub use notify::{EmailConfig, EmailTool, NotifyTemplate, SlackConfig, SlackTool};
+#[cfg(feature = "screenshot")]
+pub use screenshot::ScreenshotTool;
@@ -0,0 +1,383 @@
+//! Outbound notification tools for alerting humans: SMTP email and Slack
+//! incoming webhooks.
+//!
+//! Recipients are restricted to allowlists from the configuration, and both
+//! tools ask for permission by default.
+
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::permission::PermissionAction;
+use crate::tool::{Tool, ToolError, ToolResult};
+
+/// A message template with `{{name}}` placeholders.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotifyTemplate {
+    /// Subject line (used by email only)
+    #[serde(default)]
+    pub subject: Option<String>,
+    /// Message body
+    pub body: String,
+}
+
+impl NotifyTemplate {
+    /// Creates a template with the given body.
+    pub fn new(body: impl Into<String>) -> Self {
+        Self {
+            subject: None,
+            body: body.into(),
+        }
+    }
+
+    /// Sets the subject line.
+    pub fn with_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+}
+
+/// Replaces `{{name}}` placeholders with values from `vars`.
+///
+/// Fails if a placeholder has no value, so half-filled alerts are never sent.
+pub fn render_template(template: &str, vars: &serde_json::Map<String, Value>) -> Result<String, ToolError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = match vars.get(name) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => {
+                return Err(ToolError::InvalidArguments(format!(
+                    "missing template variable `{}`",
+                    name
+                )));
+            }
+        };
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&value);
+        rest = &rest[start + 2 + len + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Resolves the subject and body of a notification from tool arguments:
+/// either a named template with `vars`, or a literal `subject`/`body`.
+fn compose(
+    args: &Value,
+    templates: &HashMap<String, NotifyTemplate>,
+    body_field: &str,
+) -> Result<(Option<String>, String), ToolError> {
+    if let Some(name) = args["template"].as_str() {
+        let template = templates
+            .get(name)
+            .ok_or_else(|| ToolError::InvalidArguments(format!("unknown template `{}`", name)))?;
+        let vars = args["vars"].as_object().cloned().unwrap_or_default();
+        let subject = template
+            .subject
+            .as_deref()
+            .map(|s| render_template(s, &vars))
+            .transpose()?;
+        return Ok((subject, render_template(&template.body, &vars)?));
+    }
+
+    let body = args[body_field].as_str().ok_or_else(|| {
+        ToolError::InvalidArguments(format!("either `template` or `{}` is required", body_field))
+    })?;
+    Ok((args["subject"].as_str().map(str::to_string), body.to_string()))
+}
+
+/// Configuration of the email tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// SMTP relay host
+    pub smtp_host: String,
+    /// SMTP port; 587 (STARTTLS) by default
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    /// SMTP user name
+    pub username: String,
+    /// SMTP password
+    pub password: String,
+    /// Sender address, e.g. `Agent <agent@example.com>`
+    pub from: String,
+    /// Allowed recipients: full addresses or `@domain` suffixes
+    pub allowed_recipients: Vec<String>,
+    /// Named templates the model can fill in
+    #[serde(default)]
+    pub templates: HashMap<String, NotifyTemplate>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+impl EmailConfig {
+    /// Returns whether the address is on the allowlist.
+    pub fn is_allowed(&self, address: &str) -> bool {
+        let address = address.trim().to_lowercase();
+        self.allowed_recipients.iter().any(|allowed| {
+            let allowed = allowed.trim().to_lowercase();
+            if allowed.starts_with('@') {
+                address.ends_with(&allowed)
+            } else {
+                address == allowed
+            }
+        })
+    }
+}
+
+/// Sends email over SMTP to allowlisted recipients.
+pub struct EmailTool {
+    config: EmailConfig,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl EmailTool {
+    /// Creates the tool, connecting to the relay with STARTTLS on first use.
+    pub fn new(config: EmailConfig) -> Result<Self, ToolError> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?
+            .port(config.smtp_port)
+            .credentials(Credentials::new(config.username.clone(), config.password.clone()))
+            .build();
+        Ok(Self { config, transport })
+    }
+
+    fn recipients(&self, args: &Value) -> Result<Vec<Mailbox>, ToolError> {
+        let to: Vec<&str> = match &args["to"] {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if to.is_empty() {
+            return Err(ToolError::InvalidArguments("`to` is required".to_string()));
+        }
+
+        to.into_iter()
+            .map(|address| {
+                if !self.config.is_allowed(address) {
+                    return Err(ToolError::InvalidArguments(format!(
+                        "recipient `{}` is not allowed",
+                        address
+                    )));
+                }
+                address
+                    .parse()
+                    .map_err(|e| ToolError::InvalidArguments(format!("invalid address `{}`: {}", address, e)))
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for EmailTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailTool")
+            .field("smtp_host", &self.config.smtp_host)
+            .field("from", &self.config.from)
+            .field("allowed_recipients", &self.config.allowed_recipients)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Tool for EmailTool {
+    fn name(&self) -> &str {
+        "send_email"
+    }
+
+    fn description(&self) -> &str {
+        "Sends an email to allowlisted recipients, either from a named template or with a literal subject and body"
+    }
+
+    fn parameters_schema(&self) -> Value {
+        let templates: Vec<&String> = self.config.templates.keys().collect();
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "to": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Recipient addresses"
+                },
+                "subject": { "type": "string" },
+                "body": { "type": "string" },
+                "template": {
+                    "type": "string",
+                    "enum": templates,
+                    "description": "Named template to use instead of subject/body"
+                },
+                "vars": {
+                    "type": "object",
+                    "description": "Values for the template placeholders"
+                }
+            },
+            "required": ["to"]
+        })
+    }
+
+    fn default_permission(&self) -> Option<PermissionAction> {
+        Some(PermissionAction::Ask)
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let recipients = self.recipients(&args)?;
+        let (subject, body) = compose(&args, &self.config.templates, "body")?;
+        let from: Mailbox = self
+            .config
+            .from
+            .parse()
+            .map_err(|e| ToolError::ExecutionFailed(format!("invalid sender address: {}", e)))?;
+
+        let mut builder = lettre::Message::builder()
+            .from(from)
+            .subject(subject.unwrap_or_default());
+        for recipient in &recipients {
+            builder = builder.to(recipient.clone());
+        }
+        let email = builder
+            .body(body)
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok(ToolResult::ok(format!("Email sent to {} recipient(s)", recipients.len())))
+    }
+}
+
+/// Configuration of the Slack tool.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SlackConfig {
+    /// Incoming webhook URL per channel name; only these channels can be posted to
+    pub webhooks: HashMap<String, String>,
+    /// Named templates the model can fill in
+    #[serde(default)]
+    pub templates: HashMap<String, NotifyTemplate>,
+}
+
+/// Posts messages to allowlisted Slack channels through incoming webhooks.
+#[derive(Debug, Clone)]
+pub struct SlackTool {
+    config: SlackConfig,
+    client: reqwest::Client,
+}
+
+impl SlackTool {
+    /// Creates the tool.
+    pub fn new(config: SlackConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for SlackTool {
+    fn name(&self) -> &str {
+        "send_slack_message"
+    }
+
+    fn description(&self) -> &str {
+        "Posts a message to an allowlisted Slack channel, either from a named template or with literal text"
+    }
+
+    fn parameters_schema(&self) -> Value {
+        let mut channels: Vec<&String> = self.config.webhooks.keys().collect();
+        channels.sort();
+        let templates: Vec<&String> = self.config.templates.keys().collect();
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "enum": channels },
+                "text": { "type": "string" },
+                "template": {
+                    "type": "string",
+                    "enum": templates,
+                    "description": "Named template to use instead of text"
+                },
+                "vars": {
+                    "type": "object",
+                    "description": "Values for the template placeholders"
+                }
+            },
+            "required": ["channel"]
+        })
+    }
+
+    fn default_permission(&self) -> Option<PermissionAction> {
+        Some(PermissionAction::Ask)
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let channel = args["channel"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidArguments("`channel` is required".to_string()))?;
+        let webhook = self.config.webhooks.get(channel).ok_or_else(|| {
+            ToolError::InvalidArguments(format!("channel `{}` is not allowed", channel))
+        })?;
+        let (_, text) = compose(&args, &self.config.templates, "text")?;
+
+        let response = self
+            .client
+            .post(webhook)
+            .json(&serde_json::json!({ "text": text }))
+            .send()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(ToolError::ExecutionFailed(format!(
+                "Slack returned {}",
+                response.status()
+            )));
+        }
+        Ok(ToolResult::ok(format!("Message posted to {}", channel)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_templates_and_allowlists() {
+        let vars = serde_json::json!({"host": "db-1", "load": 0.93});
+        assert_eq!(
+            render_template("{{ host }} at {{load}}", vars.as_object().unwrap()).unwrap(),
+            "db-1 at 0.93"
+        );
+        assert!(render_template("{{missing}}", vars.as_object().unwrap()).is_err());
+
+        let config = EmailConfig {
+            smtp_host: "smtp.example.com".to_string(),
+            smtp_port: 587,
+            username: "agent".to_string(),
+            password: "secret".to_string(),
+            from: "agent@example.com".to_string(),
+            allowed_recipients: vec!["@example.com".to_string(), "oncall@partner.io".to_string()],
+            templates: HashMap::new(),
+        };
+        assert!(config.is_allowed("Ops@Example.com"));
+        assert!(config.is_allowed("oncall@partner.io"));
+        assert!(!config.is_allowed("someone@partner.io"));
+
+        let slack = SlackTool::new(SlackConfig::default());
+        assert_eq!(slack.default_permission(), Some(PermissionAction::Ask));
+        let error = slack
+            .execute(serde_json::json!({"channel": "#general", "text": "hi"}))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("not allowed"));
+    }
+}
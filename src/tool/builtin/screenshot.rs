@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::io::Cursor;
+use xcap::Monitor;
+use xcap::image::ImageFormat;
+
+use crate::tool::{Tool, ToolError, ToolResult};
+
+/// Captures the screen and returns it as a PNG image, so vision-capable
+/// models can see what the user sees.
+///
+/// Requires the `screenshot` feature.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScreenshotTool;
+
+impl ScreenshotTool {
+    /// Creates the tool.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Captures the named monitor, or the primary one, as PNG bytes.
+    fn capture(monitor: Option<&str>) -> Result<(String, Vec<u8>), ToolError> {
+        let failed = |e: xcap::XCapError| ToolError::ExecutionFailed(e.to_string());
+        let monitors = Monitor::all().map_err(failed)?;
+
+        let mut selected = None;
+        for candidate in monitors {
+            let name = candidate.name().map_err(failed)?;
+            let matches = match monitor {
+                Some(wanted) => name == wanted,
+                None => candidate.is_primary().map_err(failed)?,
+            };
+            if matches {
+                selected = Some((name, candidate));
+                break;
+            }
+        }
+        let (name, selected) = selected.ok_or_else(|| match monitor {
+            Some(wanted) => ToolError::InvalidArguments(format!("no monitor named `{}`", wanted)),
+            None => ToolError::ExecutionFailed("no primary monitor found".to_string()),
+        })?;
+
+        let image = selected.capture_image().map_err(failed)?;
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        Ok((name, png))
+    }
+}
+
+#[async_trait]
+impl Tool for ScreenshotTool {
+    fn name(&self) -> &str {
+        "screenshot"
+    }
+
+    fn description(&self) -> &str {
+        "Takes a screenshot of the user's screen and returns it as an image"
+    }
+
+    fn parameters_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "monitor": {
+                    "type": "string",
+                    "description": "Name of the monitor to capture; defaults to the primary monitor"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let monitor = args["monitor"].as_str().map(str::to_string);
+        let (name, png) = tokio::task::spawn_blocking(move || Self::capture(monitor.as_deref()))
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))??;
+
+        Ok(ToolResult::ok(format!("Captured a screenshot of monitor `{}`", name))
+            .with_image("image/png", &png))
+    }
+}
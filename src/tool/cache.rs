@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::session::{MessageContent, ToolResultBlock};
+use super::ToolResult;
+
+/// Metadata key set to `true` on results served from the cache.
+pub const CACHE_HIT_KEY: &str = "cache_hit";
+
+/// A cached tool result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    output: String,
+    #[serde(default)]
+    metadata: Option<serde_json::Map<String, Value>>,
+    #[serde(default)]
+    images: Vec<MessageContent>,
+    #[serde(default)]
+    blocks: Vec<ToolResultBlock>,
+    expires_at: DateTime<Utc>,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self) -> bool {
+        self.expires_at > Utc::now()
+    }
+}
+
+/// Caches the results of deterministic tools, keyed by a hash of the tool
+/// name and its arguments.
+///
+/// Only tools given a TTL are cached, and only successful results are
+/// stored. With a disk directory, entries survive across runs and sessions.
+/// Clones share the same in-memory entries.
+#[derive(Debug, Clone, Default)]
+pub struct ToolCache {
+    ttls: HashMap<String, Duration>,
+    entries: Arc<Mutex<HashMap<String, CacheEntry>>>,
+    dir: Option<PathBuf>,
+}
+
+impl ToolCache {
+    /// Creates an in-memory cache that caches no tools yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caches results of the named tool for `ttl`.
+    pub fn with_ttl(mut self, tool: impl Into<String>, ttl: Duration) -> Self {
+        self.ttls.insert(tool.into(), ttl);
+        self
+    }
+
+    /// Also persists entries as JSON files in `dir`.
+    pub fn with_disk(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Returns the TTL of the named tool, if it is cached.
+    pub fn ttl(&self, tool: &str) -> Option<Duration> {
+        self.ttls.get(tool).copied()
+    }
+
+    /// Returns the cache key for a call: a stable FNV-1a hash of the tool
+    /// name and the canonical JSON of its arguments.
+    pub fn key(tool: &str, args: &Value) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let content = format!("{}\0{}", tool, args);
+        for byte in content.as_bytes() {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        format!("{:016x}", hash)
+    }
+
+    /// Returns the cached result of a call, flagged with `cache_hit` in its
+    /// metadata, if a fresh one exists.
+    pub fn get(&self, tool: &str, args: &Value) -> Option<ToolResult> {
+        self.ttl(tool)?;
+        let key = Self::key(tool, args);
+
+        let mut entries = self.entries.lock().ok()?;
+        let entry = match entries.get(&key) {
+            Some(entry) if entry.is_fresh() => entry.clone(),
+            _ => {
+                let entry = self.load(&key).filter(CacheEntry::is_fresh);
+                match entry {
+                    Some(entry) => {
+                        entries.insert(key, entry.clone());
+                        entry
+                    }
+                    None => {
+                        entries.remove(&key);
+                        return None;
+                    }
+                }
+            }
+        };
+        drop(entries);
+
+        let mut metadata = entry.metadata.unwrap_or_default();
+        metadata.insert(CACHE_HIT_KEY.to_string(), Value::Bool(true));
+        Some(ToolResult {
+            output: entry.output,
+            metadata: Some(metadata),
+            error: None,
+            images: entry.images,
+            blocks: entry.blocks,
+        })
+    }
+
+    /// Stores the result of a call if the tool is cached and the call
+    /// succeeded.
+    pub fn insert(&self, tool: &str, args: &Value, result: &ToolResult) {
+        let Some(ttl) = self.ttl(tool) else {
+            return;
+        };
+        if result.error.is_some() {
+            return;
+        }
+
+        let key = Self::key(tool, args);
+        let entry = CacheEntry {
+            output: result.output.clone(),
+            metadata: result.metadata.clone(),
+            images: result.images.clone(),
+            blocks: result.blocks.clone(),
+            expires_at: Utc::now() + chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX),
+        };
+        if let Some(dir) = &self.dir
+            && let Err(e) = Self::store(dir, &key, &entry)
+        {
+            tracing::warn!("Failed to persist cached result of `{}`: {}", tool, e);
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, entry);
+        }
+    }
+
+    /// Removes every entry, including those on disk.
+    pub fn clear(&self) -> std::io::Result<()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+        if let Some(dir) = &self.dir
+            && dir.exists()
+        {
+            for file in std::fs::read_dir(dir)? {
+                let path = file?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&self, key: &str) -> Option<CacheEntry> {
+        let path = self.dir.as_ref()?.join(format!("{}.json", key));
+        let json = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn store(dir: &Path, key: &str, entry: &CacheEntry) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(format!("{}.json", key)), serde_json::to_string(entry)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_are_served_from_disk_across_caches() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = serde_json::json!({"city": "Paris"});
+        let cache = ToolCache::new()
+            .with_ttl("geocode", Duration::from_secs(60))
+            .with_disk(dir.path());
+
+        assert!(cache.get("geocode", &args).is_none());
+        cache.insert("geocode", &args, &ToolResult::ok("48.85,2.35"));
+        cache.insert("search", &args, &ToolResult::ok("uncached"));
+
+        let fresh = ToolCache::new()
+            .with_ttl("geocode", Duration::from_secs(60))
+            .with_disk(dir.path());
+        let hit = fresh.get("geocode", &args).unwrap();
+        assert_eq!(hit.output, "48.85,2.35");
+        assert_eq!(hit.metadata.unwrap()[CACHE_HIT_KEY], Value::Bool(true));
+        assert!(fresh.get("geocode", &serde_json::json!({"city": "Rome"})).is_none());
+        assert!(fresh.get("search", &args).is_none());
+
+        let expired = ToolCache::new().with_ttl("geocode", Duration::ZERO);
+        expired.insert("geocode", &args, &ToolResult::ok("stale"));
+        assert!(expired.get("geocode", &args).is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::permission::{PathAccess, PermissionAction, PermissionContext, PermissionManager, SandboxError};
+use super::executor::ToolExecutionEvent;
+
+/// What a tool knows about the call it is executing.
+///
+/// Passed to `Tool::execute_with_ctx` by the `ToolExecutor`.
+#[derive(Debug, Clone, Default)]
+pub struct ToolContext {
+    /// The session the call belongs to
+    pub session_id: String,
+    /// The assistant message that made the call
+    pub message_id: String,
+    /// The tool call ID
+    pub call_id: String,
+    /// The name the tool was called by
+    pub tool_name: String,
+    /// Cancelled when the run is cancelled or the call times out; tools
+    /// that spawn work should stop it when this fires
+    pub cancellation: CancellationToken,
+    /// The executor's permission rules, if any
+    pub permissions: Option<Arc<PermissionManager>>,
+    pub(crate) progress: Option<mpsc::UnboundedSender<ToolExecutionEvent>>,
+}
+
+impl ToolContext {
+    /// Returns whether the call was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Reports progress of a long-running call, surfaced as
+    /// `AgentEvent::ToolProgress` on streams. `progress` is a fraction
+    /// between 0 and 1, when known.
+    pub fn report_progress(&self, progress: Option<f64>, message: impl Into<String>) {
+        if let Some(progress_tx) = &self.progress {
+            let _ = progress_tx.send(ToolExecutionEvent::Progress {
+                call_id: self.call_id.clone(),
+                name: self.tool_name.clone(),
+                progress,
+                message: message.into(),
+            });
+        }
+    }
+
+    /// Evaluates the permission rules for a call to another tool, e.g. one
+    /// the tool delegates to. `None` when no rule matches or there are no
+    /// rules.
+    pub fn permission_for(&self, tool: &str, args: &serde_json::Value) -> Option<PermissionAction> {
+        self.permissions.as_ref()?.evaluate(&PermissionContext {
+            tool: tool.to_string(),
+            args: args.clone(),
+            session_id: self.session_id.clone(),
+        })
+    }
+
+    /// Checks a path the call means to access against the sandbox policy
+    /// of the permission rules, returning it resolved. Without a policy the
+    /// path is returned as is.
+    pub fn check_path(&self, path: impl AsRef<Path>, access: PathAccess) -> Result<PathBuf, SandboxError> {
+        match &self.permissions {
+            Some(permissions) => permissions.check_path(
+                &PermissionContext {
+                    tool: self.tool_name.clone(),
+                    args: serde_json::json!({"path": path.as_ref()}),
+                    session_id: self.session_id.clone(),
+                },
+                path,
+                access,
+            ),
+            None => Ok(path.as_ref().to_path_buf()),
+        }
+    }
+}
@@ -1,7 +1,20 @@
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use crate::tool::{ToolRegistry, ToolDefinition};
-use crate::session::MessageContent;
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use futures::future::join_all;
+use tokio_util::sync::CancellationToken;
+use tokio::sync::{mpsc, oneshot, Mutex, OnceCell, Semaphore};
+use crate::permission::{PermissionAction, PermissionContext, PermissionManager, PermissionResult};
+use crate::tool::{DynTool, ToolRegistry, ToolDefinition};
+use crate::tool::registry::ToolFilter;
+use crate::session::{MessageContent, ToolResultBlock};
+use super::audit::{AuditEntry, ToolMetrics, ToolStats};
+use super::cache::ToolCache;
+use super::context::ToolContext;
+use super::limits::ToolLimiter;
+use super::schema;
+use super::truncation::TruncationPolicy;
+use super::vault::Vault;
 
 /// Context for tool execution.
 #[derive(Debug, Clone)]
@@ -23,30 +36,351 @@ pub struct ToolResult {
     pub error: Option<String>,
 }
 
+/// The state of a tool call that has not completed yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingState {
+    /// Waiting for an execution slot
+    Queued,
+    /// Currently executing
+    Running,
+    /// Waiting for a human to approve or deny the call
+    AwaitingApproval,
+}
+
+/// A tool call that has been queued but not yet completed.
+#[derive(Debug, Clone)]
+pub struct PendingToolCall {
+    /// The tool call ID
+    pub call_id: String,
+    /// The name of the tool
+    pub name: String,
+    /// Whether the call is queued or running
+    pub state: PendingState,
+    /// When the call was queued
+    pub queued_at: Instant,
+    /// When the call started executing
+    pub started_at: Option<Instant>,
+}
+
+/// Lifecycle events of the tool calls handled by the executor.
+#[derive(Debug, Clone)]
+pub enum ToolExecutionEvent {
+    /// A tool call was queued for execution
+    Queued {
+        call_id: String,
+        name: String,
+    },
+    /// A permission rule requires a human to approve the call
+    ApprovalRequired {
+        call_id: String,
+        name: String,
+        args: serde_json::Value,
+    },
+    /// A tool call started executing
+    Started {
+        call_id: String,
+        name: String,
+        /// How long the call waited in the queue
+        queue_wait: Duration,
+    },
+    /// A running tool call reported progress
+    Progress {
+        call_id: String,
+        name: String,
+        /// Fraction between 0 and 1, when known
+        progress: Option<f64>,
+        message: String,
+    },
+    /// A tool call finished executing
+    Completed {
+        call_id: String,
+        name: String,
+        /// How long the tool took to execute
+        duration: Duration,
+        is_error: bool,
+    },
+}
+
+/// Removes a batch's entries from the pending list when dropped.
+struct PendingCleanup<'a> {
+    executor: &'a ToolExecutor,
+    call_ids: Vec<String>,
+}
+
+impl Drop for PendingCleanup<'_> {
+    fn drop(&mut self) {
+        self.executor
+            .update_pending(|pending| pending.retain(|p| !self.call_ids.contains(&p.call_id)));
+        if let Ok(mut approvals) = self.executor.approvals.lock() {
+            approvals.retain(|id, _| !self.call_ids.contains(id));
+        }
+    }
+}
+
+/// Tool definitions and the registry generation they were built from.
+type CachedDefinitions = std::sync::Mutex<Option<(u64, Vec<ToolDefinition>)>>;
+
+/// A tool once its `initialize` succeeded.
+type InitCell = Arc<OnceCell<DynTool>>;
+
+/// Initialized tools, keyed by session ID and tool name. Each tool has its
+/// own cell, so a slow setup only holds up first calls of the same tool.
+#[derive(Default)]
+struct InitializedTools(Mutex<HashMap<(String, String), InitCell>>);
+
+impl std::fmt::Debug for InitializedTools {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.0.try_lock().map(|tools| tools.len()).ok();
+        f.debug_tuple("InitializedTools").field(&count).finish()
+    }
+}
+
 /// Executes tool calls from the agent.
 #[derive(Debug, Clone)]
 pub struct ToolExecutor {
     registry: Arc<Mutex<ToolRegistry>>,
+    pending: Arc<std::sync::Mutex<Vec<PendingToolCall>>>,
+    vault: Vault,
+    permissions: Option<Arc<PermissionManager>>,
+    approvals: Arc<std::sync::Mutex<HashMap<String, oneshot::Sender<bool>>>>,
+    approval_timeout: Option<Duration>,
+    cache: Option<ToolCache>,
+    definitions: Arc<CachedDefinitions>,
+    max_concurrency: Option<usize>,
+    default_timeout: Option<Duration>,
+    truncation: Option<Arc<TruncationPolicy>>,
+    filter: Option<ToolFilter>,
+    initialized: Arc<InitializedTools>,
+    limiters: Arc<std::sync::Mutex<HashMap<String, Arc<ToolLimiter>>>>,
+    metrics: Arc<ToolMetrics>,
 }
 
 impl ToolExecutor {
     /// Creates a new tool executor with the given registry.
     pub fn new(registry: Arc<Mutex<ToolRegistry>>) -> Self {
-        Self { registry }
+        Self {
+            registry,
+            pending: Arc::new(std::sync::Mutex::new(Vec::new())),
+            vault: Vault::new(),
+            permissions: None,
+            approvals: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            approval_timeout: None,
+            cache: None,
+            definitions: Arc::new(std::sync::Mutex::new(None)),
+            max_concurrency: None,
+            default_timeout: None,
+            truncation: None,
+            filter: None,
+            initialized: Arc::default(),
+            limiters: Arc::default(),
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Sets the permission rules consulted before every tool call.
+    ///
+    /// Without rules, every call is allowed unless the tool itself defaults
+    /// to asking. With rules, calls no rule matches fall back to the tool's
+    /// default permission, then to the rules' default action, and are
+    /// denied otherwise.
+    pub fn with_permissions(mut self, permissions: PermissionManager) -> Self {
+        self.permissions = Some(Arc::new(permissions));
+        self
+    }
+
+    /// Layers `overlay` over the current rules, which keep deciding the
+    /// calls it has no rule for. Without rules, those calls stay allowed.
+    pub fn with_permission_overlay(self, overlay: PermissionManager) -> Self {
+        let base = match &self.permissions {
+            Some(permissions) => (**permissions).clone(),
+            None => PermissionManager::new().with_default_action(PermissionAction::Allow),
+        };
+        self.with_permissions(base.with_overlay(overlay))
+    }
+
+    /// Returns the permission rules consulted before every tool call.
+    pub fn permissions(&self) -> Option<&PermissionManager> {
+        self.permissions.as_deref()
+    }
+
+    /// Approves a call that is awaiting approval. Returns `false` if no such
+    /// call is waiting.
+    pub fn approve(&self, call_id: &str) -> bool {
+        self.resolve_approval(call_id, true)
+    }
+
+    /// Denies a call that is awaiting approval. Returns `false` if no such
+    /// call is waiting.
+    pub fn deny(&self, call_id: &str) -> bool {
+        self.resolve_approval(call_id, false)
+    }
+
+    /// Denies calls left awaiting approval for longer than `timeout`. They
+    /// wait until answered by default.
+    pub fn with_approval_timeout(mut self, timeout: Duration) -> Self {
+        self.approval_timeout = Some(timeout);
+        self
+    }
+
+    fn resolve_approval(&self, call_id: &str, approved: bool) -> bool {
+        let sender = self.approvals.lock().ok().and_then(|mut a| a.remove(call_id));
+        sender.is_some_and(|sender| sender.send(approved).is_ok())
+    }
+
+    /// Sets the cache that serves repeated calls to deterministic tools.
+    pub fn with_cache(mut self, cache: ToolCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Limits how many calls of a batch run at the same time; by default
+    /// every call of a batch runs concurrently.
+    pub fn with_max_concurrency(mut self, limit: usize) -> Self {
+        self.max_concurrency = Some(limit.max(1));
+        self
+    }
+
+    /// Aborts calls that run longer than `timeout`, unless the tool sets its
+    /// own timeout. Calls are not timed out by default.
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Shortens oversized results before they reach the session. Results
+    /// are not limited by default.
+    pub fn with_truncation(mut self, policy: TruncationPolicy) -> Self {
+        self.truncation = Some(Arc::new(policy));
+        self
+    }
+
+    /// Only offers and runs the tools `filter` allows, so executors sharing
+    /// a registry can expose different subsets of it.
+    pub fn with_filter(mut self, filter: ToolFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Returns the filter restricting the tools of the executor.
+    pub fn filter(&self) -> Option<&ToolFilter> {
+        self.filter.as_ref()
+    }
+
+    /// Sets the vault used to resolve references in tool arguments.
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.vault = vault;
+        self
+    }
+
+    /// Returns the vault shared with tools.
+    pub fn vault(&self) -> &Vault {
+        &self.vault
+    }
+
+    /// Returns the tool calls that are queued or running.
+    pub fn pending_calls(&self) -> Vec<PendingToolCall> {
+        self.pending.lock().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    /// Returns per-tool statistics of the calls executed so far, sorted by
+    /// tool name.
+    pub fn stats(&self) -> Vec<ToolStats> {
+        self.metrics.stats()
+    }
+
+    /// Returns every call executed so far, oldest first.
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        self.metrics.audit_log()
+    }
+
+    /// Exports the statistics and the audit log as a JSON document.
+    pub fn export_metrics(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "tools": self.stats(),
+            "audit": self.audit_log(),
+        }))
     }
 
     /// Returns all tool definitions for passing to the LLM.
     pub async fn get_tool_definitions(&self) -> Vec<ToolDefinition> {
+        self.versioned_tool_definitions().await.0
+    }
+
+    /// Returns all tool definitions along with the registry generation they
+    /// were built from. Definitions are only rebuilt when the generation
+    /// changes.
+    pub async fn versioned_tool_definitions(&self) -> (Vec<ToolDefinition>, u64) {
         let registry = self.registry.lock().await;
-        registry.to_tool_definitions()
+        let generation = registry.generation();
+        let mut definitions = match self.definitions.lock() {
+            Ok(mut cached) => match &*cached {
+                Some((cached_generation, definitions)) if *cached_generation == generation => definitions.clone(),
+                _ => {
+                    let definitions = registry.to_tool_definitions();
+                    *cached = Some((generation, definitions.clone()));
+                    definitions
+                }
+            },
+            Err(_) => registry.to_tool_definitions(),
+        };
+        if let Some(filter) = &self.filter {
+            definitions.retain(|definition| filter.allows(&registry, &definition.name));
+        }
+        (definitions, generation)
     }
 
     /// Executes a single tool call.
+    ///
+    /// Calls that require approval wait until `approve` or `deny` is called.
     pub async fn execute(
         &self,
         call: &MessageContent,
-        _ctx: ExecutionContext,
+        ctx: ExecutionContext,
     ) -> MessageContent {
+        self.execute_with_images(call, ctx, None, CancellationToken::new()).await.0
+    }
+
+    /// Executes a single tool call, also returning any images the tool
+    /// attached to its result. Every call is recorded in the audit log.
+    async fn execute_with_images(
+        &self,
+        call: &MessageContent,
+        ctx: ExecutionContext,
+        events: Option<&mpsc::UnboundedSender<ToolExecutionEvent>>,
+        cancellation: CancellationToken,
+    ) -> (MessageContent, Vec<MessageContent>) {
+        let (session_id, message_id) = (ctx.session_id.clone(), ctx.message_id.clone());
+        let started = Instant::now();
+        let (content, images) = self.execute_call(call, ctx, events, cancellation).await;
+
+        if let (
+            MessageContent::ToolCall { id, name, arguments },
+            MessageContent::ToolResult { result, is_error, .. },
+        ) = (call, &content)
+        {
+            self.metrics.record(AuditEntry {
+                timestamp: chrono::Utc::now(),
+                session_id,
+                message_id,
+                call_id: id.clone(),
+                tool: name.clone(),
+                args: self.vault.redact_value(arguments),
+                result_hash: AuditEntry::hash_result(result),
+                is_error: is_error.unwrap_or(false),
+                duration: started.elapsed(),
+                bytes: result.len() as u64,
+            });
+        }
+        (content, images)
+    }
+
+    async fn execute_call(
+        &self,
+        call: &MessageContent,
+        ctx: ExecutionContext,
+        events: Option<&mpsc::UnboundedSender<ToolExecutionEvent>>,
+        cancellation: CancellationToken,
+    ) -> (MessageContent, Vec<MessageContent>) {
         let (id, name, arguments) = match call {
             MessageContent::ToolCall {
                 id,
@@ -54,54 +388,928 @@ impl ToolExecutor {
                 arguments,
             } => (id.clone(), name.clone(), arguments.clone()),
             _ => {
-                return MessageContent::ToolResult {
-                    tool_call_id: String::new(),
-                    result: "Invalid tool call content".to_string(),
-                    is_error: Some(true),
-                }
+                let result = Self::error_result(String::new(), "Invalid tool call content".to_string());
+                return (result, Vec::new());
             }
         };
 
         let registry = self.registry.lock().await;
-        let tool = match registry.get(&name) {
+        let allowed = self.filter.as_ref().is_none_or(|filter| filter.allows(&registry, &name));
+        let tool = match registry.get(&name).filter(|_| allowed) {
             Some(tool) => tool.clone(),
             None => {
-                return MessageContent::ToolResult {
-                    tool_call_id: id,
-                    result: format!("Tool not found: {}", name),
-                    is_error: Some(true),
-                }
+                let result = Self::error_result(id, format!("Tool not found: {}", name));
+                return (result, Vec::new());
             }
         };
         drop(registry);
 
-        match tool.execute(arguments).await {
-            Ok(result) => MessageContent::ToolResult {
+        // Arguments that do not match the schema go back to the model to fix
+        let violations = schema::validate(&tool.parameters_schema(), &arguments);
+        if !violations.is_empty() {
+            let mut message = format!("Invalid arguments for tool `{}`:", name);
+            for violation in &violations {
+                message.push_str(&format!("\n- {}", violation));
+            }
+            message.push_str("\nFix the arguments and call the tool again.");
+            let result = Self::error_result(id, message);
+            return (result, Vec::new());
+        }
+
+        if let Err(reason) = self.authorize(&id, &name, &tool, &arguments, &ctx, events).await {
+            let result = Self::error_result(id, reason);
+            return (result, Vec::new());
+        }
+
+        if let Some(cached) = self.cache.as_ref().and_then(|c| c.get(&name, &arguments)) {
+            tracing::debug!(tool = %name, "Serving tool result from cache");
+            let (text, blocks) = cached.content();
+            let content = MessageContent::ToolResult {
                 tool_call_id: id,
-                result: result.output,
-                is_error: result.error.as_ref().map(|_| true),
+                result: text,
+                is_error: None,
+                blocks,
+            };
+            return (content, cached.images);
+        }
+
+        // Secrets are only materialized for the tool itself
+        let resolved = self.vault.resolve_value(&arguments);
+        let tool_ctx = ToolContext {
+            session_id: ctx.session_id,
+            message_id: ctx.message_id,
+            call_id: id.clone(),
+            tool_name: name.clone(),
+            cancellation,
+            permissions: self.permissions.clone(),
+            progress: events.cloned(),
+        };
+
+        if let Err(e) = self.ensure_initialized(&tool, &tool_ctx).await {
+            tracing::warn!(tool = %name, error = %e, "Tool failed to initialize");
+            let result = Self::error_result(id, format!("Tool `{}` failed to initialize: {}", name, e));
+            return (result, Vec::new());
+        }
+
+        // Waiting for the tool's limits does not count against its timeout
+        let _slot = match self.limiter(&tool) {
+            Some(limiter) => limiter.acquire().await,
+            None => None,
+        };
+
+        // Dropping the timed-out future aborts the call
+        let outcome = match tool.timeout().or(self.default_timeout) {
+            Some(timeout) => match tokio::time::timeout(timeout, tool.execute_with_ctx(resolved, &tool_ctx)).await {
+                Ok(outcome) => outcome,
+                Err(_) => {
+                    tracing::warn!(tool = %name, ?timeout, "Tool call timed out");
+                    tool_ctx.cancellation.cancel();
+                    let result = Self::error_result(id, format!("Tool `{}` timed out after {:?}", name, timeout));
+                    return (result, Vec::new());
+                }
             },
-            Err(error) => MessageContent::ToolResult {
-                tool_call_id: id,
-                result: error.to_string(),
-                is_error: Some(true),
+            None => tool.execute_with_ctx(resolved, &tool_ctx).await,
+        };
+
+        match outcome {
+            Ok(mut result) => {
+                // Cached outputs are already redacted so secrets never hit the disk
+                if let Some(cache) = &self.cache {
+                    result.output = self.vault.redact(&result.output);
+                    result.blocks = result.blocks.iter().map(|b| self.redact_block(b)).collect();
+                    cache.insert(&name, &arguments, &result);
+                }
+                let (mut text, mut blocks) = result.content();
+                // Cut results only keep their text rendering
+                if let Some(shortened) = self.truncate(&name, &text).await {
+                    text = shortened;
+                    blocks.clear();
+                }
+                let content = MessageContent::ToolResult {
+                    tool_call_id: id,
+                    result: self.vault.redact(&text),
+                    is_error: result.error.as_ref().map(|_| true),
+                    blocks: blocks.iter().map(|b| self.redact_block(b)).collect(),
+                };
+                (content, result.images)
+            }
+            Err(error) => {
+                let mut message = error.to_string();
+                if let Some(shortened) = self.truncate(&name, &message).await {
+                    message = shortened;
+                }
+                let content = Self::error_result(id, self.vault.redact(&message));
+                (content, Vec::new())
+            }
+        }
+    }
+
+    /// Returns the limiter shared by the calls of a tool that declares
+    /// limits.
+    fn limiter(&self, tool: &DynTool) -> Option<Arc<ToolLimiter>> {
+        let (max_concurrency, rate_limit) = (tool.max_concurrency(), tool.rate_limit());
+        if max_concurrency.is_none() && rate_limit.is_none() {
+            return None;
+        }
+        let mut limiters = self.limiters.lock().ok()?;
+        let limiter = limiters
+            .entry(tool.name().to_string())
+            .or_insert_with(|| Arc::new(ToolLimiter::new(max_concurrency, rate_limit)));
+        Some(limiter.clone())
+    }
+
+    /// Initializes the tool for the call's session unless it already is.
+    async fn ensure_initialized(&self, tool: &DynTool, ctx: &ToolContext) -> Result<(), crate::tool::ToolError> {
+        let key = (ctx.session_id.clone(), ctx.tool_name.clone());
+        let cell = self.initialized.0.lock().await.entry(key).or_default().clone();
+        // Concurrent first calls wait for one setup; a failed one is retried
+        cell.get_or_try_init(|| async {
+            tool.initialize(ctx).await?;
+            Ok(tool.clone())
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Shuts down the tools initialized for `session_id`; they are
+    /// initialized again on their next call.
+    pub async fn shutdown_session(&self, session_id: &str) {
+        let cells: Vec<_> = {
+            let mut initialized = self.initialized.0.lock().await;
+            let keys: Vec<_> = initialized.keys().filter(|(id, _)| id == session_id).cloned().collect();
+            keys.into_iter().filter_map(|key| initialized.remove(&key)).collect()
+        };
+        for tool in cells.iter().filter_map(|cell| cell.get()) {
+            tool.shutdown().await;
+        }
+    }
+
+    /// Shuts down every initialized tool.
+    pub async fn shutdown(&self) {
+        let cells: Vec<_> = self.initialized.0.lock().await.drain().map(|(_, cell)| cell).collect();
+        for tool in cells.iter().filter_map(|cell| cell.get()) {
+            tool.shutdown().await;
+        }
+    }
+
+    /// Applies the truncation policy, returning `None` when the output fits.
+    async fn truncate(&self, tool: &str, output: &str) -> Option<String> {
+        let shortened = self.truncation.as_ref()?.apply(tool, output).await?;
+        tracing::debug!(tool = %tool, chars = output.len(), "Truncated tool output");
+        Some(shortened)
+    }
+
+    /// Builds an error result for a call.
+    fn error_result(tool_call_id: String, message: String) -> MessageContent {
+        MessageContent::ToolResult {
+            tool_call_id,
+            result: message,
+            is_error: Some(true),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Replaces secrets in the text of a result block.
+    fn redact_block(&self, block: &ToolResultBlock) -> ToolResultBlock {
+        match block {
+            ToolResultBlock::Text { text } => ToolResultBlock::Text { text: self.vault.redact(text) },
+            ToolResultBlock::Json { value } => ToolResultBlock::Json { value: self.vault.redact_value(value) },
+            other => other.clone(),
+        }
+    }
+
+    /// Resolves the permission for a call, waiting for a human decision when
+    /// it must be approved. Returns the reason when the call may not run.
+    async fn authorize(
+        &self,
+        call_id: &str,
+        name: &str,
+        tool: &DynTool,
+        arguments: &serde_json::Value,
+        ctx: &ExecutionContext,
+        events: Option<&mpsc::UnboundedSender<ToolExecutionEvent>>,
+    ) -> Result<(), String> {
+        let permission_ctx = PermissionContext {
+            tool: name.to_string(),
+            args: arguments.clone(),
+            session_id: ctx.session_id.clone(),
+        };
+        let rule = self.permissions.as_ref().and_then(|permissions| permissions.evaluate(&permission_ctx));
+        let fallback = match &self.permissions {
+            Some(permissions) => permissions.default_action().cloned().unwrap_or(PermissionAction::Deny),
+            None => PermissionAction::Allow,
+        };
+
+        let action = rule.or_else(|| tool.default_permission()).unwrap_or(fallback);
+        // Calls over the quota are refused before anyone is asked
+        if action != PermissionAction::Deny
+            && let Some(permissions) = &self.permissions
+            && let Err(e) = permissions.check_quota(&permission_ctx)
+        {
+            permissions.record(&permission_ctx, action, PermissionResult::Deny);
+            return Err(e.to_string());
+        }
+        let allowed = match action {
+            PermissionAction::Allow => true,
+            PermissionAction::Deny => false,
+            PermissionAction::Ask => match self.ask(call_id, name, arguments, &permission_ctx, events).await {
+                Ok(allowed) => allowed,
+                Err(reason) => {
+                    if let Some(permissions) = &self.permissions {
+                        permissions.record(&permission_ctx, action, PermissionResult::Deny);
+                    }
+                    return Err(reason);
+                }
             },
+        };
+        // Only calls let through count against the quota
+        if allowed
+            && let Some(permissions) = &self.permissions
+            && let Err(e) = permissions.take_quota(&permission_ctx)
+        {
+            permissions.record(&permission_ctx, action, PermissionResult::Deny);
+            return Err(e.to_string());
+        }
+        if let Some(permissions) = &self.permissions {
+            let decision = if allowed { PermissionResult::Allow } else { PermissionResult::Deny };
+            permissions.record(&permission_ctx, action.clone(), decision);
+        }
+
+        match (allowed, action) {
+            (true, _) => Ok(()),
+            (false, PermissionAction::Ask) => Err(format!("The user denied the call to tool `{}`", name)),
+            (false, _) => Err(format!("Permission denied for tool `{}`", name)),
+        }
+    }
+
+    /// Asks about a call whose permission says `Ask`: the approval handler
+    /// if there is one, else the host via `approve` and `deny` once told on
+    /// `events`. Returns the reason when nobody answers.
+    async fn ask(
+        &self,
+        call_id: &str,
+        name: &str,
+        arguments: &serde_json::Value,
+        permission_ctx: &PermissionContext,
+        events: Option<&mpsc::UnboundedSender<ToolExecutionEvent>>,
+    ) -> Result<bool, String> {
+        self.set_state(call_id, PendingState::AwaitingApproval);
+        let answer = match &self.permissions {
+            Some(permissions) => permissions.ask(permission_ctx).await,
+            None => None,
+        };
+        if let Some(answer) = answer {
+            self.set_state(call_id, PendingState::Running);
+            return Ok(answer == PermissionResult::Allow);
+        }
+
+        // Without a handler, the host answers via `approve`, if it hears of the call
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut approvals) = self.approvals.lock() {
+            approvals.insert(call_id.to_string(), tx);
+        }
+        let request = ToolExecutionEvent::ApprovalRequired {
+            call_id: call_id.to_string(),
+            name: name.to_string(),
+            args: arguments.clone(),
+        };
+        if events.is_none_or(|events| events.send(request).is_err()) {
+            self.resolve_approval(call_id, false);
+            return Err(format!(
+                "Tool `{}` requires approval, but there is no approval handler or event listener to ask",
+                name
+            ));
         }
+
+        let approved = match self.approval_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, rx).await {
+                Ok(answer) => answer.unwrap_or(false),
+                Err(_) => {
+                    self.resolve_approval(call_id, false);
+                    return Err(format!("The call to tool `{}` was not approved within {:?}", name, timeout));
+                }
+            },
+            None => rx.await.unwrap_or(false),
+        };
+        self.set_state(call_id, PendingState::Running);
+        Ok(approved)
     }
 
-    /// Executes multiple tool calls in parallel.
+    /// Executes multiple tool calls concurrently, up to the concurrency
+    /// limit. Results are returned in the order of the calls.
+    ///
+    /// Calls that need approval without an approval handler fail, since
+    /// nobody hears of them; `execute_all_with_events` reports them instead.
     pub async fn execute_all(
         &self,
         calls: Vec<MessageContent>,
         ctx: ExecutionContext,
     ) -> Vec<MessageContent> {
-        let mut results = Vec::new();
+        self.run_all(calls, ctx, None).await
+    }
 
-        for call in calls {
-            let result = self.execute(&call, ctx.clone()).await;
-            results.push(result);
+    /// Executes multiple tool calls, reporting their lifecycle on `events`.
+    pub async fn execute_all_with_events(
+        &self,
+        calls: Vec<MessageContent>,
+        ctx: ExecutionContext,
+        events: mpsc::UnboundedSender<ToolExecutionEvent>,
+    ) -> Vec<MessageContent> {
+        self.run_all(calls, ctx, Some(&events)).await
+    }
+
+    async fn run_all(
+        &self,
+        calls: Vec<MessageContent>,
+        ctx: ExecutionContext,
+        events: Option<&mpsc::UnboundedSender<ToolExecutionEvent>>,
+    ) -> Vec<MessageContent> {
+        let emit = |event: ToolExecutionEvent| {
+            if let Some(events) = events {
+                let _ = events.send(event);
+            }
+        };
+
+        // Queue every call up front so hosts can see the whole batch
+        let queued_at = Instant::now();
+        for call in &calls {
+            let (call_id, name) = Self::call_identity(call);
+            self.update_pending(|pending| {
+                pending.push(PendingToolCall {
+                    call_id: call_id.clone(),
+                    name: name.clone(),
+                    state: PendingState::Queued,
+                    queued_at,
+                    started_at: None,
+                })
+            });
+            emit(ToolExecutionEvent::Queued { call_id, name });
         }
 
+        // Drop any leftovers if the batch is cancelled midway
+        let _cleanup = PendingCleanup {
+            executor: self,
+            call_ids: calls.iter().map(|c| Self::call_identity(c).0).collect(),
+        };
+
+        // Cancels every call's token if the batch is dropped midway
+        let batch = CancellationToken::new();
+        let _cancel_on_drop = batch.clone().drop_guard();
+
+        let slots = Semaphore::new(self.max_concurrency.unwrap_or(Semaphore::MAX_PERMITS));
+        let executions = calls.iter().map(|call| async {
+            // Calls stay queued until a slot frees up
+            let _slot = slots.acquire().await.ok();
+            let (call_id, name) = Self::call_identity(call);
+            let started_at = Instant::now();
+            self.update_pending(|pending| {
+                if let Some(entry) = pending.iter_mut().find(|p| p.call_id == call_id) {
+                    entry.state = PendingState::Running;
+                    entry.started_at = Some(started_at);
+                }
+            });
+            emit(ToolExecutionEvent::Started {
+                call_id: call_id.clone(),
+                name: name.clone(),
+                queue_wait: started_at - queued_at,
+            });
+
+            let (result, images) = self
+                .execute_with_images(call, ctx.clone(), events, batch.child_token())
+                .await;
+
+            self.update_pending(|pending| pending.retain(|p| p.call_id != call_id));
+            emit(ToolExecutionEvent::Completed {
+                call_id,
+                name,
+                duration: started_at.elapsed(),
+                is_error: matches!(result, MessageContent::ToolResult { is_error: Some(true), .. }),
+            });
+
+            // Tools that return without waiting still give other tasks a turn
+            tokio::task::coop::consume_budget().await;
+            (result, images)
+        });
+
+        let mut results = Vec::new();
+        for (result, images) in join_all(executions).await {
+            results.push(result);
+            // Images follow the result of the call that produced them
+            results.extend(images);
+        }
         results
     }
+
+    /// Returns the call ID and tool name of a tool call.
+    fn call_identity(call: &MessageContent) -> (String, String) {
+        match call {
+            MessageContent::ToolCall { id, name, .. } => (id.clone(), name.clone()),
+            _ => (String::new(), String::new()),
+        }
+    }
+
+    fn set_state(&self, call_id: &str, state: PendingState) {
+        self.update_pending(|pending| {
+            if let Some(entry) = pending.iter_mut().find(|p| p.call_id == call_id) {
+                entry.state = state;
+            }
+        });
+    }
+
+    fn update_pending(&self, f: impl FnOnce(&mut Vec<PendingToolCall>)) {
+        if let Ok(mut pending) = self.pending.lock() {
+            f(&mut pending);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::{Tool, ToolError, ToolResult as ToolOutput};
+    use crate::testing::EchoTool;
+    use async_trait::async_trait;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn test_execute_all_reports_lifecycle_events() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoTool));
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry)));
+
+        let calls = vec![
+            MessageContent::ToolCall {
+                id: "a".to_string(),
+                name: "echo".to_string(),
+                arguments: serde_json::json!({"n": 1}),
+            },
+            MessageContent::ToolCall {
+                id: "b".to_string(),
+                name: "missing".to_string(),
+                arguments: serde_json::json!({}),
+            },
+        ];
+        let ctx = ExecutionContext {
+            session_id: "s".to_string(),
+            message_id: "m".to_string(),
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let results = executor.execute_all_with_events(calls, ctx, tx).await;
+
+        assert_eq!(results.len(), 2);
+        assert!(executor.pending_calls().is_empty());
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 6);
+        assert!(matches!(&events[0], ToolExecutionEvent::Queued { call_id, .. } if call_id == "a"));
+        assert!(matches!(&events[1], ToolExecutionEvent::Queued { call_id, .. } if call_id == "b"));
+        assert!(matches!(&events[2], ToolExecutionEvent::Started { call_id, .. } if call_id == "a"));
+        assert!(matches!(&events[3], ToolExecutionEvent::Completed { is_error: false, .. }));
+        assert!(matches!(&events[5], ToolExecutionEvent::Completed { is_error: true, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_definitions_follow_registry_generation() {
+        let registry = Arc::new(Mutex::new(ToolRegistry::new()));
+        let executor = ToolExecutor::new(registry.clone());
+
+        let (definitions, first) = executor.versioned_tool_definitions().await;
+        assert!(definitions.is_empty());
+        assert_eq!(executor.versioned_tool_definitions().await.1, first);
+
+        registry.lock().await.register(Arc::new(EchoTool));
+        let (definitions, second) = executor.versioned_tool_definitions().await;
+        assert_ne!(first, second);
+        assert_eq!(definitions[0].name, "echo");
+    }
+
+    #[tokio::test]
+    async fn test_calls_run_concurrently_up_to_the_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct NapTool {
+            running: AtomicUsize,
+            peak: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Tool for NapTool {
+            fn name(&self) -> &str {
+                "nap"
+            }
+
+            fn description(&self) -> &str {
+                "Sleeps for the given milliseconds"
+            }
+
+            fn parameters_schema(&self) -> Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            async fn execute(&self, args: Value) -> Result<ToolOutput, ToolError> {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(args["ms"].as_u64().unwrap_or(0))).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                Ok(ToolOutput::ok(args["ms"].to_string()))
+            }
+        }
+
+        let tool = Arc::new(NapTool::default());
+        let mut registry = ToolRegistry::new();
+        registry.register(tool.clone());
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry))).with_max_concurrency(2);
+
+        let calls = [40, 10, 30, 20]
+            .iter()
+            .enumerate()
+            .map(|(i, ms)| MessageContent::ToolCall {
+                id: format!("call_{}", i),
+                name: "nap".to_string(),
+                arguments: serde_json::json!({ "ms": ms }),
+            })
+            .collect();
+        let ctx = ExecutionContext {
+            session_id: "s".to_string(),
+            message_id: "m".to_string(),
+        };
+
+        let results = executor.execute_all(calls, ctx).await;
+
+        assert_eq!(tool.peak.load(Ordering::SeqCst), 2);
+        let ids: Vec<_> = results
+            .iter()
+            .map(|r| match r {
+                MessageContent::ToolResult { tool_call_id, .. } => tool_call_id.as_str(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(ids, ["call_0", "call_1", "call_2", "call_3"]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_calls_time_out() {
+        struct StuckTool(Option<Duration>);
+
+        #[async_trait]
+        impl Tool for StuckTool {
+            fn name(&self) -> &str {
+                if self.0.is_some() { "stuck_briefly" } else { "stuck" }
+            }
+
+            fn description(&self) -> &str {
+                "Never finishes"
+            }
+
+            fn parameters_schema(&self) -> Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            async fn execute(&self, _args: Value) -> Result<ToolOutput, ToolError> {
+                std::future::pending().await
+            }
+
+            fn timeout(&self) -> Option<Duration> {
+                self.0
+            }
+        }
+
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(StuckTool(None)));
+        registry.register(Arc::new(StuckTool(Some(Duration::from_millis(10)))));
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry))).with_default_timeout(Duration::from_millis(30));
+
+        let calls = ["stuck", "stuck_briefly"]
+            .iter()
+            .map(|name| MessageContent::ToolCall {
+                id: name.to_string(),
+                name: name.to_string(),
+                arguments: serde_json::json!({}),
+            })
+            .collect();
+        let ctx = ExecutionContext {
+            session_id: "s".to_string(),
+            message_id: "m".to_string(),
+        };
+
+        let results = executor.execute_all(calls, ctx).await;
+
+        let messages: Vec<_> = results
+            .iter()
+            .map(|r| match r {
+                MessageContent::ToolResult { result, is_error, .. } => (result.as_str(), *is_error),
+                _ => ("", None),
+            })
+            .collect();
+        assert_eq!(messages[0], ("Tool `stuck` timed out after 30ms", Some(true)));
+        assert_eq!(messages[1], ("Tool `stuck_briefly` timed out after 10ms", Some(true)));
+        assert!(executor.pending_calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tools_receive_context_with_progress_and_cancellation() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        #[derive(Default)]
+        struct WatchTool {
+            cancelled: Arc<AtomicBool>,
+        }
+
+        #[async_trait]
+        impl Tool for WatchTool {
+            fn name(&self) -> &str {
+                "watch"
+            }
+
+            fn description(&self) -> &str {
+                "Reports progress, then waits forever"
+            }
+
+            fn parameters_schema(&self) -> Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            async fn execute(&self, _args: Value) -> Result<ToolOutput, ToolError> {
+                unreachable!("the executor passes a context")
+            }
+
+            async fn execute_with_ctx(&self, _args: Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
+                ctx.report_progress(Some(0.5), format!("watching for {}", ctx.session_id));
+                let cancellation = ctx.cancellation.clone();
+                let cancelled = self.cancelled.clone();
+                tokio::spawn(async move {
+                    cancellation.cancelled().await;
+                    cancelled.store(true, Ordering::SeqCst);
+                });
+                std::future::pending().await
+            }
+
+            fn timeout(&self) -> Option<Duration> {
+                Some(Duration::from_millis(10))
+            }
+        }
+
+        let tool = Arc::new(WatchTool::default());
+        let mut registry = ToolRegistry::new();
+        registry.register(tool.clone());
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry)));
+        let calls = vec![MessageContent::ToolCall {
+            id: "a".to_string(),
+            name: "watch".to_string(),
+            arguments: serde_json::json!({}),
+        }];
+        let ctx = ExecutionContext {
+            session_id: "s".to_string(),
+            message_id: "m".to_string(),
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        executor.execute_all_with_events(calls, ctx, tx).await;
+        tokio::task::yield_now().await;
+
+        let progress = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|event| match event {
+            ToolExecutionEvent::Progress { progress, message, .. } => Some((progress, message)),
+            _ => None,
+        });
+        assert_eq!(progress, Some((Some(0.5), "watching for s".to_string())));
+        assert!(tool.cancelled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_tools_initialize_once_per_session_until_shutdown() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct PoolTool {
+            setups: AtomicUsize,
+            teardowns: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Tool for PoolTool {
+            fn name(&self) -> &str {
+                "query"
+            }
+
+            fn description(&self) -> &str {
+                "Queries a database"
+            }
+
+            fn parameters_schema(&self) -> Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            async fn initialize(&self, ctx: &ToolContext) -> Result<(), ToolError> {
+                if ctx.session_id == "broken" {
+                    return Err(ToolError::ExecutionFailed("no database".to_string()));
+                }
+                self.setups.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+
+            async fn shutdown(&self) {
+                self.teardowns.fetch_add(1, Ordering::SeqCst);
+            }
+
+            async fn execute(&self, _args: Value) -> Result<ToolOutput, ToolError> {
+                Ok(ToolOutput::ok("rows"))
+            }
+        }
+
+        let tool = Arc::new(PoolTool::default());
+        let mut registry = ToolRegistry::new();
+        registry.register(tool.clone());
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry)));
+        let call = |id: &str| MessageContent::ToolCall {
+            id: id.to_string(),
+            name: "query".to_string(),
+            arguments: serde_json::json!({}),
+        };
+        let ctx = |session: &str| ExecutionContext {
+            session_id: session.to_string(),
+            message_id: "m".to_string(),
+        };
+
+        executor.execute_all(vec![call("a"), call("b")], ctx("s1")).await;
+        executor.execute(&call("c"), ctx("s2")).await;
+        let failed = executor.execute(&call("d"), ctx("broken")).await;
+        assert!(matches!(
+            failed,
+            MessageContent::ToolResult { result, is_error: Some(true), .. }
+                if result == "Tool `query` failed to initialize: Execution failed: no database"
+        ));
+        assert_eq!(tool.setups.load(Ordering::SeqCst), 2);
+
+        executor.shutdown_session("s1").await;
+        assert_eq!(tool.teardowns.load(Ordering::SeqCst), 1);
+        executor.execute(&call("e"), ctx("s1")).await;
+        executor.shutdown().await;
+        assert_eq!(tool.setups.load(Ordering::SeqCst), 3);
+        assert_eq!(tool.teardowns.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_slow_tool_setup_does_not_block_other_tools() {
+        struct SetupTool {
+            name: &'static str,
+            ready: Option<Arc<tokio::sync::Notify>>,
+        }
+
+        #[async_trait]
+        impl Tool for SetupTool {
+            fn name(&self) -> &str {
+                self.name
+            }
+
+            fn description(&self) -> &str {
+                "Needs setting up"
+            }
+
+            fn parameters_schema(&self) -> Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            async fn initialize(&self, _ctx: &ToolContext) -> Result<(), ToolError> {
+                if let Some(ready) = &self.ready {
+                    ready.notified().await;
+                }
+                Ok(())
+            }
+
+            async fn execute(&self, _args: Value) -> Result<ToolOutput, ToolError> {
+                Ok(ToolOutput::ok(self.name))
+            }
+        }
+
+        let ready = Arc::new(tokio::sync::Notify::new());
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(SetupTool { name: "slow", ready: Some(ready.clone()) }));
+        registry.register(Arc::new(SetupTool { name: "fast", ready: None }));
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry)));
+        let call = |name: &str| MessageContent::ToolCall {
+            id: name.to_string(),
+            name: name.to_string(),
+            arguments: serde_json::json!({}),
+        };
+        let ctx = || ExecutionContext {
+            session_id: "s1".to_string(),
+            message_id: "m".to_string(),
+        };
+
+        let slow = tokio::spawn({
+            let executor = executor.clone();
+            let call = call("slow");
+            async move { executor.execute(&call, ctx()).await }
+        });
+        tokio::task::yield_now().await;
+
+        let fast = tokio::time::timeout(Duration::from_secs(5), executor.execute(&call("fast"), ctx())).await;
+        assert!(matches!(fast, Ok(MessageContent::ToolResult { result, .. }) if result == "fast"));
+
+        assert!(!slow.is_finished());
+        ready.notify_one();
+        assert!(matches!(slow.await.unwrap(), MessageContent::ToolResult { result, .. } if result == "slow"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_limits_throttle_parallel_calls() {
+        use crate::tool::RateLimit;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct SearchTool {
+            running: AtomicUsize,
+            peak: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Tool for SearchTool {
+            fn name(&self) -> &str {
+                "search"
+            }
+
+            fn description(&self) -> &str {
+                "Searches the web"
+            }
+
+            fn parameters_schema(&self) -> Value {
+                serde_json::json!({"type": "object"})
+            }
+
+            fn max_concurrency(&self) -> Option<usize> {
+                Some(2)
+            }
+
+            fn rate_limit(&self) -> Option<RateLimit> {
+                Some(RateLimit::new(3, Duration::from_millis(150)))
+            }
+
+            async fn execute(&self, _args: Value) -> Result<ToolOutput, ToolError> {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                Ok(ToolOutput::ok("results"))
+            }
+        }
+
+        let tool = Arc::new(SearchTool::default());
+        let mut registry = ToolRegistry::new();
+        registry.register(tool.clone());
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry)));
+        let calls = (0..5)
+            .map(|i| MessageContent::ToolCall {
+                id: format!("call_{}", i),
+                name: "search".to_string(),
+                arguments: serde_json::json!({}),
+            })
+            .collect();
+        let ctx = ExecutionContext {
+            session_id: "s".to_string(),
+            message_id: "m".to_string(),
+        };
+
+        let started = Instant::now();
+        let results = executor.execute_all(calls, ctx).await;
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(tool.peak.load(Ordering::SeqCst), 2);
+        // The burst covers three calls; the other two wait for refills
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_recorded_in_stats_and_audit_log() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(EchoTool));
+        let executor = ToolExecutor::new(Arc::new(Mutex::new(registry)));
+        let ctx = ExecutionContext {
+            session_id: "s".to_string(),
+            message_id: "m".to_string(),
+        };
+        let call = |id: &str, name: &str| MessageContent::ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments: serde_json::json!({"n": 1}),
+        };
+
+        executor.execute_all(vec![call("a", "echo"), call("b", "echo"), call("c", "missing")], ctx).await;
+
+        let stats = executor.stats();
+        assert_eq!(stats.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["echo", "missing"]);
+        assert_eq!(stats[0].invocations, 2);
+        assert_eq!(stats[0].bytes_returned, 14);
+        assert_eq!(stats[1].error_rate(), 1.0);
+
+        let log = executor.audit_log();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].result_hash, AuditEntry::hash_result("{\"n\":1}"));
+        let exported: Value = serde_json::from_str(&executor.export_metrics().unwrap()).unwrap();
+        assert_eq!(exported["audit"][2]["tool"], "missing");
+    }
 }
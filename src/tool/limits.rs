@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// How many calls of a tool may start within a period, e.g. 5 per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Calls allowed per period; also the size of a burst
+    pub calls: u32,
+    /// The period the calls are spread over
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// Allows `calls` calls every `per`.
+    pub fn new(calls: u32, per: Duration) -> Self {
+        Self { calls: calls.max(1), per }
+    }
+
+    /// Allows `calls` calls per second.
+    pub fn per_second(calls: u32) -> Self {
+        Self::new(calls, Duration::from_secs(1))
+    }
+
+    /// Allows `calls` calls per minute.
+    pub fn per_minute(calls: u32) -> Self {
+        Self::new(calls, Duration::from_secs(60))
+    }
+}
+
+/// A token bucket refilled continuously at the rate limit's pace.
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.calls),
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes a token, returning how long to wait when none is left.
+    fn try_take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let capacity = f64::from(self.limit.calls);
+        let rate = capacity / self.limit.per.as_secs_f64().max(f64::EPSILON);
+        self.tokens = (self.tokens + (now - self.refilled_at).as_secs_f64() * rate).min(capacity);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+/// Enforces a tool's concurrency limit and rate limit across calls.
+#[derive(Debug)]
+pub(crate) struct ToolLimiter {
+    slots: Option<Arc<Semaphore>>,
+    bucket: Option<Mutex<TokenBucket>>,
+}
+
+impl ToolLimiter {
+    pub(crate) fn new(max_concurrency: Option<usize>, rate_limit: Option<RateLimit>) -> Self {
+        Self {
+            slots: max_concurrency.map(|limit| Arc::new(Semaphore::new(limit.max(1)))),
+            bucket: rate_limit.map(|limit| Mutex::new(TokenBucket::new(limit))),
+        }
+    }
+
+    /// Waits until the call may start. The returned permit holds the call's
+    /// concurrency slot until dropped.
+    pub(crate) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permit = match &self.slots {
+            Some(slots) => slots.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        if let Some(bucket) = &self.bucket {
+            // Waiters queue on the lock, so tokens go out in arrival order
+            let mut bucket = bucket.lock().await;
+            while let Err(wait) = bucket.try_take() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        permit
+    }
+}
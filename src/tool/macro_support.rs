@@ -0,0 +1,85 @@
+//! Runtime support for code generated by the `#[tool]` macro.
+
+use schemars::JsonSchema;
+use schemars::r#gen::SchemaSettings;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt::Display;
+
+use super::{ToolError, ToolResult};
+
+/// Generates the input schema of a tool's arguments, with referenced types
+/// inlined since not every provider resolves `$ref`.
+pub fn schema_for<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings.meta_schema = None;
+        })
+        .into_generator();
+    let mut schema = serde_json::to_value(generator.into_root_schema_for::<T>()).unwrap_or_default();
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("title");
+    }
+    schema
+}
+
+/// Deserializes a call's arguments.
+pub fn parse_arguments<T: DeserializeOwned>(args: Value) -> Result<T, ToolError> {
+    serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))
+}
+
+/// Converts what a tool function returned into a tool result.
+pub fn into_result<T, E>(result: Result<T, E>) -> Result<ToolResult, ToolError>
+where
+    T: Into<ToolResult>,
+    E: Display,
+{
+    result
+        .map(Into::into)
+        .map_err(|e| ToolError::ExecutionFailed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tool::{Tool, ToolError};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Unit {
+        Celsius,
+        Fahrenheit,
+    }
+
+    /// Looks up the current weather.
+    #[crate::tool]
+    async fn get_weather(location: String, unit: Option<Unit>) -> Result<String, ToolError> {
+        match unit {
+            Some(Unit::Fahrenheit) => Ok(format!("{}: 72F", location)),
+            _ => Ok(format!("{}: 22C", location)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_macro_generates_schema_and_parses_arguments() {
+        assert_eq!(GetWeather.name(), "get_weather");
+        assert_eq!(GetWeather.description(), "Looks up the current weather.");
+
+        let schema = GetWeather.parameters_schema();
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["required"], serde_json::json!(["location"]));
+        assert_eq!(schema["properties"]["location"]["type"], "string");
+        assert!(schema.to_string().contains("fahrenheit"));
+        assert!(!schema.to_string().contains("$ref"));
+
+        let result = GetWeather
+            .execute(serde_json::json!({"location": "Paris", "unit": "fahrenheit"}))
+            .await
+            .unwrap();
+        assert_eq!(result.output, "Paris: 72F");
+        let error = GetWeather.execute(serde_json::json!({"unit": "kelvin"})).await.unwrap_err();
+        assert!(matches!(error, ToolError::InvalidArguments(_)));
+    }
+}
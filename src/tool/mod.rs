@@ -1,13 +1,33 @@
+pub mod audit;
+pub mod builtin;
+pub mod cache;
+pub mod context;
+pub mod limits;
 pub mod registry;
+pub mod schema;
+pub mod truncation;
+pub mod typed;
 pub mod executor;
+#[doc(hidden)]
+pub mod macro_support;
+pub mod vault;
 
-pub use registry::ToolRegistry;
-pub use executor::{ToolExecutor, ExecutionContext};
+pub use registry::{ConflictPolicy, RegistryError, ToolFilter, ToolRegistry};
+pub use executor::{ToolExecutor, ExecutionContext, PendingToolCall, PendingState, ToolExecutionEvent};
+pub use audit::{AuditEntry, ToolStats};
+pub use cache::ToolCache;
+pub use context::ToolContext;
+pub use limits::RateLimit;
+pub use schema::SchemaViolation;
+pub use vault::Vault;
 pub use tool_types::{ToolDefinition, ToolResult, ToolError};
 pub use tool_trait::Tool;
 pub use tool_trait::DynTool;
+pub use truncation::{LLMSummarizer, OutputSummarizer, TruncationPolicy, TruncationStrategy};
+pub use typed::TypedTool;
 
 mod tool_types {
+    use crate::session::{MessageContent, ToolResultBlock};
     use serde::{Deserialize, Serialize};
     use serde_json::Value;
 
@@ -22,6 +42,19 @@ mod tool_types {
         pub input_schema: Value,
     }
 
+    impl ToolDefinition {
+        /// Returns a stable FNV-1a hash of the input schema, which changes
+        /// whenever the schema does.
+        pub fn fingerprint(&self) -> String {
+            let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+            for byte in self.input_schema.to_string().as_bytes() {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+            format!("{:016x}", hash)
+        }
+    }
+
     /// The result of executing a tool.
     #[derive(Debug, Clone)]
     pub struct ToolResult {
@@ -33,6 +66,11 @@ mod tool_types {
         /// Optional error message if the tool execution failed
         #[allow(dead_code)]
         pub error: Option<String>,
+        /// Images returned alongside the output (`MessageContent::Image` blocks)
+        pub images: Vec<MessageContent>,
+        /// Structured content following the output, e.g. JSON values and
+        /// resource links
+        pub blocks: Vec<ToolResultBlock>,
     }
 
     impl ToolResult {
@@ -42,7 +80,51 @@ mod tool_types {
                 output: output.into(),
                 metadata: None,
                 error: None,
+                images: Vec::new(),
+                blocks: Vec::new(),
+            }
+        }
+
+        /// Attaches an image to the result.
+        pub fn with_image(mut self, media_type: impl Into<String>, bytes: &[u8]) -> Self {
+            self.images.push(MessageContent::image(media_type, bytes));
+            self
+        }
+
+        /// Appends a JSON value to the result.
+        pub fn with_json(self, value: Value) -> Self {
+            self.with_block(ToolResultBlock::Json { value })
+        }
+
+        /// Appends a link to a resource to the result.
+        pub fn with_resource(self, uri: impl Into<String>, name: Option<String>) -> Self {
+            self.with_block(ToolResultBlock::Resource {
+                uri: uri.into(),
+                name,
+                mime_type: None,
+            })
+        }
+
+        /// Appends a structured block to the result.
+        pub fn with_block(mut self, block: ToolResultBlock) -> Self {
+            self.blocks.push(block);
+            self
+        }
+
+        /// Returns the structured blocks of the result, starting with the
+        /// output as text, and the text rendering of those blocks. Results
+        /// without blocks have no structured content.
+        pub(crate) fn content(&self) -> (String, Vec<ToolResultBlock>) {
+            if self.blocks.is_empty() {
+                return (self.output.clone(), Vec::new());
+            }
+            let mut blocks = Vec::with_capacity(self.blocks.len() + 1);
+            if !self.output.is_empty() {
+                blocks.push(ToolResultBlock::Text { text: self.output.clone() });
             }
+            blocks.extend(self.blocks.iter().cloned());
+            let text = blocks.iter().map(ToolResultBlock::render).collect::<Vec<_>>().join("\n");
+            (text, blocks)
         }
 
         /// Creates a result with an error.
@@ -51,10 +133,24 @@ mod tool_types {
                 output: String::new(),
                 metadata: None,
                 error: Some(error.into()),
+                images: Vec::new(),
+                blocks: Vec::new(),
             }
         }
     }
 
+    impl From<String> for ToolResult {
+        fn from(output: String) -> Self {
+            Self::ok(output)
+        }
+    }
+
+    impl From<&str> for ToolResult {
+        fn from(output: &str) -> Self {
+            Self::ok(output)
+        }
+    }
+
     /// Errors that can occur when executing a tool.
     #[derive(Debug, thiserror::Error)]
     pub enum ToolError {
@@ -68,10 +164,14 @@ mod tool_types {
 }
 
 mod tool_trait {
+    use super::context::ToolContext;
+    use super::limits::RateLimit;
     use super::tool_types::{ToolDefinition, ToolResult, ToolError};
+    use crate::permission::PermissionAction;
     use async_trait::async_trait;
     use serde_json::Value;
     use std::sync::Arc;
+    use std::time::Duration;
 
     /// Trait representing a tool that can be called by the agent.
     #[async_trait]
@@ -86,6 +186,54 @@ mod tool_trait {
         /// Executes the tool with the given arguments.
         async fn execute(&self, args: Value) -> Result<ToolResult, ToolError>;
 
+        /// Executes the tool knowing who is calling it. The executor always
+        /// calls this method; by default it ignores the context and calls
+        /// `execute`.
+        async fn execute_with_ctx(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+            let _ = ctx;
+            self.execute(args).await
+        }
+
+        /// Sets up resources the tool holds, such as connection pools or
+        /// browsers. The executor calls this once per session, before the
+        /// tool's first call; a failure fails that call and the next call
+        /// tries again.
+        async fn initialize(&self, ctx: &ToolContext) -> Result<(), ToolError> {
+            let _ = ctx;
+            Ok(())
+        }
+
+        /// Releases the resources set up by `initialize`. Called by
+        /// `ToolExecutor::shutdown` for every session the tool was
+        /// initialized in.
+        async fn shutdown(&self) {}
+
+        /// The permission applied when no configured rule matches the tool.
+        ///
+        /// Tools with side effects outside the machine (e.g. sending messages)
+        /// return `Some(PermissionAction::Ask)`.
+        fn default_permission(&self) -> Option<PermissionAction> {
+            None
+        }
+
+        /// How long a call may run before it is aborted, overriding the
+        /// executor's default timeout.
+        fn timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        /// How many calls of the tool may run at the same time, across
+        /// batches. Unlimited by default.
+        fn max_concurrency(&self) -> Option<usize> {
+            None
+        }
+
+        /// How often calls of the tool may start, e.g. to stay within an API
+        /// quota. Calls over the limit wait for their turn.
+        fn rate_limit(&self) -> Option<RateLimit> {
+            None
+        }
+
         /// Converts the tool to its definition.
         fn to_definition(&self) -> ToolDefinition {
             ToolDefinition {
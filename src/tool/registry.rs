@@ -1,11 +1,48 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use crate::tool::DynTool;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use async_trait::async_trait;
+use serde_json::Value;
+use crate::permission::PermissionAction;
+use crate::tool::{DynTool, Tool, ToolContext, ToolError, ToolResult};
+
+/// Source of registry generations, shared by all registries so a generation
+/// identifies one set of tools.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// What `ToolRegistry` does when a tool is registered under a name that is
+/// already taken by another tool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Replace the registered tool
+    #[default]
+    Overwrite,
+    /// Keep the registered tool and reject the new one
+    Error,
+    /// Register the new tool under a prefixed name: its group, e.g.
+    /// `github_search`, or `tool2_search` when it has no group
+    Prefix,
+}
+
+/// Errors returned when registering tools.
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error("A tool named `{0}` is already registered")]
+    NameConflict(String),
+}
 
 /// A registry for managing tools available to the agent.
+///
+/// Tools can be tagged with groups (e.g. `"fs"`, `"web"`). Tools in a
+/// disabled group are hidden from the agent until the group is enabled again.
 #[derive(Clone)]
 pub struct ToolRegistry {
     tools: HashMap<String, DynTool>,
+    groups: HashMap<String, HashSet<String>>,
+    disabled_groups: HashSet<String>,
+    conflict_policy: ConflictPolicy,
+    generation: u64,
 }
 
 impl ToolRegistry {
@@ -13,31 +50,181 @@ impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            groups: HashMap::new(),
+            disabled_groups: HashSet::new(),
+            conflict_policy: ConflictPolicy::default(),
+            generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
         }
     }
 
-    /// Registers a tool with the registry.
+    /// Sets what happens when a tool is registered under a taken name.
+    /// Tools are overwritten by default.
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Returns the policy applied to name conflicts.
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.conflict_policy
+    }
+
+    /// Registers a tool with the registry, applying the conflict policy.
+    /// Rejected tools are logged and dropped; use `try_register` to handle
+    /// conflicts.
     pub fn register(&mut self, tool: DynTool) {
-        let name = tool.name().to_string();
-        self.tools.insert(name, tool);
+        if let Err(e) = self.try_register(tool) {
+            tracing::warn!(error = %e, "Tool not registered");
+        }
+    }
+
+    /// Registers a tool, returning the name it was registered under.
+    pub fn try_register(&mut self, tool: DynTool) -> Result<String, RegistryError> {
+        self.insert(None, tool)
+    }
+
+    /// Registers a tool and tags it with `group`. A tool can belong to
+    /// several groups by registering it in each of them.
+    pub fn register_in_group(&mut self, group: impl Into<String>, tool: DynTool) {
+        if let Err(e) = self.try_register_in_group(group, tool) {
+            tracing::warn!(error = %e, "Tool not registered");
+        }
+    }
+
+    /// Registers a tool tagged with `group`, returning the name it was
+    /// registered under.
+    pub fn try_register_in_group(&mut self, group: impl Into<String>, tool: DynTool) -> Result<String, RegistryError> {
+        self.insert(Some(group.into()), tool)
+    }
+
+    fn insert(&mut self, group: Option<String>, tool: DynTool) -> Result<String, RegistryError> {
+        let mut name = tool.name().to_string();
+        // Registering the same tool again only adds a group
+        let conflict = self.tools.get(&name).is_some_and(|existing| !Arc::ptr_eq(existing, &tool));
+        let tool = match self.conflict_policy {
+            ConflictPolicy::Error if conflict => return Err(RegistryError::NameConflict(name)),
+            ConflictPolicy::Prefix if conflict => {
+                name = self.prefixed_name(group.as_deref().unwrap_or("tool"), &name);
+                tracing::debug!(tool = %tool.name(), name = %name, "Registering conflicting tool under a prefixed name");
+                Arc::new(RenamedTool { name: name.clone(), inner: tool })
+            }
+            _ => tool,
+        };
+        if let Some(group) = group {
+            self.groups.entry(name.clone()).or_default().insert(group);
+        }
+        self.tools.insert(name.clone(), tool);
+        self.bump_generation();
+        Ok(name)
+    }
+
+    /// Returns the first free name of the form `{prefix}_{name}`,
+    /// `{prefix}2_{name}`, `{prefix}3_{name}`, ...
+    fn prefixed_name(&self, prefix: &str, name: &str) -> String {
+        let candidate = format!("{}_{}", prefix, name);
+        if !self.tools.contains_key(&candidate) {
+            return candidate;
+        }
+        (2..)
+            .map(|n| format!("{}{}_{}", prefix, n, name))
+            .find(|candidate| !self.tools.contains_key(candidate))
+            .unwrap_or_default()
     }
 
     /// Unregisters a tool from the registry.
     pub fn unregister(&mut self, name: &str) -> Option<DynTool> {
-        self.tools.remove(name)
+        let removed = self.tools.remove(name);
+        if removed.is_some() {
+            self.groups.remove(name);
+            self.bump_generation();
+        }
+        removed
+    }
+
+    /// Shows the tools of a disabled group again. Groups are enabled by
+    /// default.
+    pub fn enable_group(&mut self, group: &str) {
+        if self.disabled_groups.remove(group) {
+            self.bump_generation();
+        }
+    }
+
+    /// Hides the tools of `group`. A tool in several groups is hidden as
+    /// soon as one of them is disabled.
+    pub fn disable_group(&mut self, group: impl Into<String>) {
+        if self.disabled_groups.insert(group.into()) {
+            self.bump_generation();
+        }
+    }
+
+    /// Returns whether `group` is enabled.
+    pub fn is_group_enabled(&self, group: &str) -> bool {
+        !self.disabled_groups.contains(group)
+    }
+
+    /// Returns the groups a tool was registered in.
+    pub fn groups_of(&self, name: &str) -> Vec<&str> {
+        let mut groups: Vec<&str> = self
+            .groups
+            .get(name)
+            .map(|groups| groups.iter().map(String::as_str).collect())
+            .unwrap_or_default();
+        groups.sort_unstable();
+        groups
+    }
+
+    /// Returns the names of the tools registered in `group`.
+    pub fn tools_in_group(&self, group: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .groups
+            .iter()
+            .filter(|(_, groups)| groups.contains(group))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns whether the tool is registered and none of its groups is
+    /// disabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+            && self
+                .groups
+                .get(name)
+                .is_none_or(|groups| groups.is_disjoint(&self.disabled_groups))
+    }
+
+    /// Returns the generation of the registry, which changes whenever tools
+    /// are added or removed or a group is enabled or disabled. Registries with the same generation hold the
+    /// same tools, so derived data such as serialized definitions can be
+    /// cached per generation.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn bump_generation(&mut self) {
+        self.generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
     }
 
-    /// Gets a tool by name.
+    /// Gets an enabled tool by name.
     pub fn get(&self, name: &str) -> Option<&DynTool> {
-        self.tools.get(name)
+        self.tools.get(name).filter(|_| self.is_enabled(name))
     }
 
-    /// Returns a list of all registered tools.
+    /// Returns a list of all enabled tools.
     pub fn list(&self) -> Vec<&DynTool> {
-        self.tools.values().collect()
+        self.enabled().collect()
     }
 
-    /// Returns the number of registered tools.
+    fn enabled(&self) -> impl Iterator<Item = &DynTool> {
+        self.tools
+            .iter()
+            .filter(|(name, _)| self.is_enabled(name))
+            .map(|(_, tool)| tool)
+    }
+
+    /// Returns the number of registered tools, including disabled ones.
     pub fn len(&self) -> usize {
         self.tools.len()
     }
@@ -47,10 +234,9 @@ impl ToolRegistry {
         self.tools.is_empty()
     }
 
-    /// Converts all tools to their definitions.
+    /// Converts all enabled tools to their definitions.
     pub fn to_tool_definitions(&self) -> Vec<crate::tool::ToolDefinition> {
-        self.tools
-            .values()
+        self.enabled()
             .map(|tool| tool.to_definition())
             .collect()
     }
@@ -66,10 +252,101 @@ impl fmt::Debug for ToolRegistry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ToolRegistry")
             .field("tools_count", &self.tools.len())
+            .field("disabled_groups", &self.disabled_groups)
+            .field("generation", &self.generation)
             .finish()
     }
 }
 
+/// A tool registered under another name than its own.
+struct RenamedTool {
+    name: String,
+    inner: DynTool,
+}
+
+#[async_trait]
+impl Tool for RenamedTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.inner.parameters_schema()
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.inner.execute(args).await
+    }
+
+    async fn execute_with_ctx(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        self.inner.execute_with_ctx(args, ctx).await
+    }
+
+    async fn initialize(&self, ctx: &ToolContext) -> Result<(), ToolError> {
+        self.inner.initialize(ctx).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await
+    }
+
+    fn default_permission(&self) -> Option<PermissionAction> {
+        self.inner.default_permission()
+    }
+
+    fn timeout(&self) -> Option<std::time::Duration> {
+        self.inner.timeout()
+    }
+
+    fn max_concurrency(&self) -> Option<usize> {
+        self.inner.max_concurrency()
+    }
+
+    fn rate_limit(&self) -> Option<crate::tool::RateLimit> {
+        self.inner.rate_limit()
+    }
+}
+
+/// Restricts a run to a subset of the registered tools, selected by name or
+/// by group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolFilter {
+    tools: HashSet<String>,
+    groups: HashSet<String>,
+}
+
+impl ToolFilter {
+    /// Creates a filter that allows no tools.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the tool named `name`.
+    pub fn with_tool(mut self, name: impl Into<String>) -> Self {
+        self.tools.insert(name.into());
+        self
+    }
+
+    /// Allows every tool of `group`.
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.groups.insert(group.into());
+        self
+    }
+
+    /// Returns whether the filter lets the tool named `name` through.
+    pub fn allows(&self, registry: &ToolRegistry, name: &str) -> bool {
+        self.tools.contains(name)
+            || registry
+                .groups
+                .get(name)
+                .is_some_and(|groups| !groups.is_disjoint(&self.groups))
+    }
+}
+
 impl IntoIterator for ToolRegistry {
     type Item = (String, DynTool);
     type IntoIter = std::collections::hash_map::IntoIter<String, DynTool>;
@@ -87,3 +364,74 @@ impl<'a> IntoIterator for &'a ToolRegistry {
         self.tools.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl Tool for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn description(&self) -> &str {
+            "test tool"
+        }
+
+        fn parameters_schema(&self) -> Value {
+            serde_json::json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<ToolResult, ToolError> {
+            Ok(ToolResult::ok(""))
+        }
+    }
+
+    #[test]
+    fn test_disabled_groups_hide_their_tools() {
+        let mut registry = ToolRegistry::new();
+        registry.register_in_group("fs", Arc::new(Named("read_file")));
+        registry.register_in_group("fs", Arc::new(Named("write_file")));
+        registry.register_in_group("web", Arc::new(Named("write_file")));
+        registry.register(Arc::new(Named("calculator")));
+        assert_eq!(registry.tools_in_group("fs"), vec!["read_file", "write_file"]);
+
+        let generation = registry.generation();
+        registry.disable_group("web");
+        assert_ne!(registry.generation(), generation);
+        assert!(registry.get("write_file").is_none());
+        assert!(registry.get("read_file").is_some());
+        assert_eq!(registry.to_tool_definitions().len(), 2);
+        assert_eq!(registry.len(), 3);
+
+        registry.enable_group("web");
+        assert_eq!(registry.list().len(), 3);
+
+        let filter = ToolFilter::new().with_group("fs");
+        assert!(filter.allows(&registry, "write_file"));
+        assert!(!filter.allows(&registry, "calculator"));
+        assert!(filter.with_tool("calculator").allows(&registry, "calculator"));
+    }
+
+    #[test]
+    fn test_conflict_policies() {
+        let mut registry = ToolRegistry::new().with_conflict_policy(ConflictPolicy::Error);
+        let search: DynTool = Arc::new(Named("search"));
+        registry.try_register(search.clone()).unwrap();
+        assert_eq!(registry.try_register_in_group("web", search).unwrap(), "search");
+        let err = registry.try_register(Arc::new(Named("search"))).unwrap_err();
+        assert_eq!(err.to_string(), "A tool named `search` is already registered");
+
+        let mut registry = registry.with_conflict_policy(ConflictPolicy::Prefix);
+        let name = registry.try_register_in_group("github", Arc::new(Named("search"))).unwrap();
+        assert_eq!(name, "github_search");
+        assert_eq!(registry.get("github_search").unwrap().to_definition().name, "github_search");
+        assert_eq!(registry.groups_of("github_search"), vec!["github"]);
+        assert_eq!(registry.try_register(Arc::new(Named("search"))).unwrap(), "tool_search");
+        assert_eq!(registry.try_register(Arc::new(Named("search"))).unwrap(), "tool2_search");
+        assert_eq!(registry.len(), 4);
+    }
+}
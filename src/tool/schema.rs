@@ -0,0 +1,246 @@
+use serde_json::Value;
+
+/// An argument that does not match a tool's input schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Location of the argument, e.g. `items[0].name`; empty for the root
+    pub path: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "`{}`: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Checks a value against a JSON Schema, returning every violation found.
+///
+/// Covers the keywords tool schemas use in practice: `type`, `enum`,
+/// `const`, `required`, `properties`, `additionalProperties`, `items`,
+/// `anyOf`, `oneOf`, `allOf` and the numeric, length and size bounds.
+/// Other keywords, including `$ref`, are not checked.
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    check(schema, value, "", &mut violations);
+    violations
+}
+
+fn check(schema: &Value, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    let Some(schema) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            violate(out, path, "is not allowed");
+        }
+        return;
+    };
+    let mut report = |message: String| violate(out, path, message);
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            report(format!("expected {}, got {}", types.join(" or "), type_name(value)));
+            return;
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(value)
+    {
+        let options: Vec<String> = options.iter().map(Value::to_string).collect();
+        report(format!("expected one of {}", options.join(", ")));
+    }
+    if let Some(expected) = schema.get("const")
+        && expected != value
+    {
+        report(format!("expected {}", expected));
+    }
+
+    if let Some(n) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+            && n < min
+        {
+            report(format!("must be at least {}", min));
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+            && n > max
+        {
+            report(format!("must be at most {}", max));
+        }
+    }
+    if let Some(s) = value.as_str() {
+        let len = s.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+            && len < min
+        {
+            report(format!("must be at least {} characters long", min));
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+            && len > max
+        {
+            report(format!("must be at most {} characters long", max));
+        }
+    }
+
+    for (keyword, need) in [("anyOf", Need::Any), ("oneOf", Need::One), ("allOf", Need::All)] {
+        let Some(branches) = schema.get(keyword).and_then(Value::as_array) else {
+            continue;
+        };
+        let mut failures = Vec::new();
+        let mut matched = 0;
+        for branch in branches {
+            let mut branch_out = Vec::new();
+            check(branch, value, path, &mut branch_out);
+            if branch_out.is_empty() {
+                matched += 1;
+            } else {
+                failures.push(branch_out);
+            }
+        }
+        match need {
+            Need::All => out.extend(failures.into_iter().flatten()),
+            Need::Any | Need::One if matched == 0 => {
+                // A single alternative explains the mismatch best
+                if let [only] = failures.as_slice() {
+                    out.extend(only.iter().cloned());
+                } else {
+                    violate(out, path, "does not match any of the allowed schemas");
+                }
+            }
+            Need::One if matched > 1 => violate(out, path, "matches more than one of the allowed schemas"),
+            _ => {}
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for field in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(field) {
+                        violate(out, &join(path, field), "missing required field");
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (field, field_value) in object {
+                match properties.and_then(|p| p.get(field)) {
+                    Some(field_schema) => check(field_schema, field_value, &join(path, field), out),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => violate(out, &join(path, field), "unknown field"),
+                        Some(extra) => check(extra, field_value, &join(path, field), out),
+                        None => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+                && (items.len() as u64) < min
+            {
+                violate(out, path, format!("must have at least {} items", min));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+                && (items.len() as u64) > max
+            {
+                violate(out, path, format!("must have at most {} items", max));
+            }
+            if let Some(item_schema) = schema.get("items").filter(|s| s.is_object()) {
+                for (i, item) in items.iter().enumerate() {
+                    check(item_schema, item, &format!("{}[{}]", path, i), out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Need {
+    Any,
+    One,
+    All,
+}
+
+fn violate(out: &mut Vec<SchemaViolation>, path: &str, message: impl Into<String>) {
+    out.push(SchemaViolation {
+        path: path.to_string(),
+        message: message.into(),
+    });
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reports_missing_and_invalid_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "location": {"type": "string"},
+                "unit": {"anyOf": [{"enum": ["celsius", "fahrenheit"]}, {"type": "null"}]},
+                "days": {"type": "array", "items": {"type": "integer", "minimum": 1}}
+            },
+            "required": ["location"],
+            "additionalProperties": false
+        });
+
+        assert!(validate(&schema, &json!({"location": "Paris", "unit": null, "days": [1, 2]})).is_empty());
+
+        let violations: Vec<String> = validate(&schema, &json!({"unit": "kelvin", "days": [0, "2"], "hours": 3}))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                "`location`: missing required field",
+                "`days[0]`: must be at least 1",
+                "`days[1]`: expected integer, got string",
+                "`hours`: unknown field",
+                "`unit`: does not match any of the allowed schemas",
+            ]
+        );
+        assert_eq!(validate(&schema, &json!("Paris"))[0].to_string(), "expected object, got string");
+    }
+}
@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::llm::tokens::{HeuristicTokenCounter, TokenCounter};
+use crate::llm::{LLMClient, LLMInput};
+use crate::session::{Message, MessageContent};
+
+/// Which part of an oversized output is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Keep the beginning
+    Head,
+    /// Keep the end, e.g. for logs and build output
+    Tail,
+    /// Keep the beginning and the end
+    #[default]
+    HeadAndTail,
+}
+
+/// Shortens oversized tool outputs, e.g. with an LLM.
+#[async_trait]
+pub trait OutputSummarizer: Send + Sync {
+    /// Returns a summary of the output in at most `max_chars` characters,
+    /// or `None` to fall back to truncation.
+    async fn summarize(&self, tool: &str, output: &str, max_chars: usize) -> Option<String>;
+}
+
+/// Summarizes outputs with an LLM.
+pub struct LLMSummarizer {
+    llm: Arc<dyn LLMClient>,
+    model: String,
+    /// Longest input passed to the model; the rest is truncated first
+    max_input_chars: usize,
+}
+
+impl LLMSummarizer {
+    /// Creates a summarizer using the given client and model.
+    pub fn new(llm: Arc<dyn LLMClient>, model: impl Into<String>) -> Self {
+        Self {
+            llm,
+            model: model.into(),
+            max_input_chars: 100_000,
+        }
+    }
+
+    /// Sets the longest input passed to the model.
+    pub fn with_max_input_chars(mut self, max_input_chars: usize) -> Self {
+        self.max_input_chars = max_input_chars;
+        self
+    }
+}
+
+#[async_trait]
+impl OutputSummarizer for LLMSummarizer {
+    async fn summarize(&self, tool: &str, output: &str, max_chars: usize) -> Option<String> {
+        let output = truncate_chars(output, self.max_input_chars, TruncationStrategy::HeadAndTail, DEFAULT_MARKER);
+        let input = LLMInput {
+            model: self.model.clone(),
+            messages: vec![Message::new_user(format!(
+                "Summarize this output of the `{}` tool in at most {} characters. Keep identifiers, \
+                 numbers, paths and errors verbatim.\n\n{}",
+                tool, max_chars, output
+            ))],
+            system_prompt: String::new(),
+            tools: Vec::new(),
+            max_tokens: (max_chars / 3).max(64) as u32,
+            temperature: None,
+            request_options: Default::default(),
+            tool_generation: None,
+        };
+        match self.llm.complete(input).await {
+            Ok(response) => {
+                let summary: String = response
+                    .content
+                    .iter()
+                    .filter_map(|c| match c {
+                        MessageContent::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                (!summary.trim().is_empty()).then_some(summary)
+            }
+            Err(e) => {
+                tracing::warn!(tool = %tool, "Failed to summarize tool output: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for LLMSummarizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LLMSummarizer")
+            .field("model", &self.model)
+            .field("max_input_chars", &self.max_input_chars)
+            .finish()
+    }
+}
+
+/// Marker used in place of omitted output; `{omitted}` is replaced by the
+/// number of characters left out.
+pub const DEFAULT_MARKER: &str = "[... {omitted} characters truncated ...]";
+
+/// Limits the size of tool results before they are added to the session,
+/// so a single huge output does not overflow the next prompt.
+#[derive(Clone)]
+pub struct TruncationPolicy {
+    /// Maximum characters of a result
+    pub max_chars: usize,
+    /// Maximum tokens of a result, counted with `token_counter`
+    pub max_tokens: Option<usize>,
+    /// Which part of an oversized result is kept
+    pub strategy: TruncationStrategy,
+    /// Text inserted where output was cut; see `DEFAULT_MARKER`
+    pub marker: String,
+    /// Summarizes oversized results instead of cutting them, when set
+    pub summarizer: Option<Arc<dyn OutputSummarizer>>,
+    /// Counts tokens for `max_tokens`
+    pub token_counter: Arc<dyn TokenCounter>,
+    /// Per-tool character limits overriding `max_chars`
+    pub tool_limits: HashMap<String, usize>,
+}
+
+impl Default for TruncationPolicy {
+    fn default() -> Self {
+        Self {
+            max_chars: 20_000,
+            max_tokens: None,
+            strategy: TruncationStrategy::default(),
+            marker: DEFAULT_MARKER.to_string(),
+            summarizer: None,
+            token_counter: Arc::new(HeuristicTokenCounter),
+            tool_limits: HashMap::new(),
+        }
+    }
+}
+
+impl TruncationPolicy {
+    /// Creates a policy keeping at most `max_chars` characters per result.
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            ..Default::default()
+        }
+    }
+
+    /// Also limits results to `max_tokens` tokens.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sets which part of an oversized result is kept.
+    pub fn with_strategy(mut self, strategy: TruncationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the marker inserted where output was cut.
+    pub fn with_marker(mut self, marker: impl Into<String>) -> Self {
+        self.marker = marker.into();
+        self
+    }
+
+    /// Summarizes oversized results, falling back to truncation when the
+    /// summarizer gives up.
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn OutputSummarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// Sets the counter used for `max_tokens`.
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = counter;
+        self
+    }
+
+    /// Sets the character limit of one tool.
+    pub fn with_tool_limit(mut self, tool: impl Into<String>, max_chars: usize) -> Self {
+        self.tool_limits.insert(tool.into(), max_chars);
+        self
+    }
+
+    /// Returns the character limit of the output, taking the token limit
+    /// into account.
+    fn limit(&self, tool: &str, output: &str) -> usize {
+        let mut limit = self.tool_limits.get(tool).copied().unwrap_or(self.max_chars);
+        if let Some(max_tokens) = self.max_tokens {
+            let tokens = self.token_counter.count_text(output);
+            if tokens > max_tokens {
+                let chars = output.chars().count();
+                limit = limit.min(chars * max_tokens / tokens);
+            }
+        }
+        limit
+    }
+
+    /// Applies the policy to a tool's output, returning `None` when it fits.
+    pub async fn apply(&self, tool: &str, output: &str) -> Option<String> {
+        let limit = self.limit(tool, output);
+        if output.chars().count() <= limit {
+            return None;
+        }
+        if let Some(summarizer) = &self.summarizer
+            && let Some(summary) = summarizer.summarize(tool, output, limit).await
+            && summary.chars().count() <= limit
+        {
+            return Some(summary);
+        }
+        Some(truncate_chars(output, limit, self.strategy, &self.marker))
+    }
+}
+
+impl std::fmt::Debug for TruncationPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TruncationPolicy")
+            .field("max_chars", &self.max_chars)
+            .field("max_tokens", &self.max_tokens)
+            .field("strategy", &self.strategy)
+            .field("marker", &self.marker)
+            .field("summarizer", &self.summarizer.is_some())
+            .field("tool_limits", &self.tool_limits)
+            .finish()
+    }
+}
+
+/// Cuts text down to `limit` characters plus the marker.
+fn truncate_chars(text: &str, limit: usize, strategy: TruncationStrategy, marker: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= limit {
+        return text.to_string();
+    }
+    let marker = marker.replace("{omitted}", &(chars.len() - limit).to_string());
+    let (head, tail) = match strategy {
+        TruncationStrategy::Head => (limit, 0),
+        TruncationStrategy::Tail => (0, limit),
+        TruncationStrategy::HeadAndTail => (limit - limit / 2, limit / 2),
+    };
+
+    let mut out = String::with_capacity(limit * 4 + marker.len() + 2);
+    out.extend(&chars[..head]);
+    if head > 0 {
+        out.push('\n');
+    }
+    out.push_str(&marker);
+    if tail > 0 {
+        out.push('\n');
+    }
+    out.extend(&chars[chars.len() - tail..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedSummarizer;
+
+    #[async_trait]
+    impl OutputSummarizer for FixedSummarizer {
+        async fn summarize(&self, tool: &str, _output: &str, _max_chars: usize) -> Option<String> {
+            (tool == "search").then(|| "3 matches".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_outputs_are_cut_or_summarized() {
+        let output = "0123456789".repeat(3);
+        let policy = TruncationPolicy::new(10).with_tool_limit("logs", 4);
+
+        assert_eq!(policy.apply("read", "short").await, None);
+        assert_eq!(
+            policy.apply("read", &output).await.unwrap(),
+            "01234\n[... 20 characters truncated ...]\n56789"
+        );
+        let tail = policy.clone().with_strategy(TruncationStrategy::Tail).with_marker("[cut]");
+        assert_eq!(tail.apply("logs", &output).await.unwrap(), "[cut]\n6789");
+        let tokens = TruncationPolicy::new(100).with_max_tokens(2).with_strategy(TruncationStrategy::Head);
+        assert_eq!(tokens.apply("read", &output).await.unwrap(), "0123456\n[... 23 characters truncated ...]");
+
+        let summarized = policy.with_summarizer(Arc::new(FixedSummarizer));
+        assert_eq!(summarized.apply("search", &output).await.unwrap(), "3 matches");
+        assert!(summarized.apply("read", &output).await.unwrap().contains("truncated"));
+    }
+}
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::permission::PermissionAction;
+use super::macro_support::{parse_arguments, schema_for};
+use super::{RateLimit, Tool, ToolContext, ToolError, ToolResult};
+
+/// A tool whose arguments are a typed struct.
+///
+/// Every `TypedTool` is a `Tool`: its input schema is generated from `Args`
+/// and incoming arguments are deserialized into `Args` before `run` is
+/// called, so arguments that do not match the schema are rejected with
+/// `ToolError::InvalidArguments`.
+#[async_trait]
+pub trait TypedTool: Send + Sync {
+    /// The arguments of a call.
+    type Args: DeserializeOwned + JsonSchema + Send;
+
+    /// Returns the name of the tool.
+    fn name(&self) -> &str;
+    /// Returns a description of what the tool does.
+    fn description(&self) -> &str;
+
+    /// Executes the tool with the deserialized arguments.
+    async fn run(&self, args: Self::Args) -> Result<ToolResult, ToolError>;
+
+    /// Executes the tool knowing who is calling it; see
+    /// `Tool::execute_with_ctx`.
+    async fn run_with_ctx(&self, args: Self::Args, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let _ = ctx;
+        self.run(args).await
+    }
+
+    /// See `Tool::initialize`.
+    async fn initialize(&self, ctx: &ToolContext) -> Result<(), ToolError> {
+        let _ = ctx;
+        Ok(())
+    }
+
+    /// See `Tool::shutdown`.
+    async fn shutdown(&self) {}
+
+    /// See `Tool::default_permission`.
+    fn default_permission(&self) -> Option<PermissionAction> {
+        None
+    }
+
+    /// See `Tool::timeout`.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// See `Tool::max_concurrency`.
+    fn max_concurrency(&self) -> Option<usize> {
+        None
+    }
+
+    /// See `Tool::rate_limit`.
+    fn rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+}
+
+#[async_trait]
+impl<T: TypedTool> Tool for T {
+    fn name(&self) -> &str {
+        TypedTool::name(self)
+    }
+
+    fn description(&self) -> &str {
+        TypedTool::description(self)
+    }
+
+    fn parameters_schema(&self) -> Value {
+        schema_for::<T::Args>()
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.run(parse_arguments(args)?).await
+    }
+
+    async fn execute_with_ctx(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        self.run_with_ctx(parse_arguments(args)?, ctx).await
+    }
+
+    async fn initialize(&self, ctx: &ToolContext) -> Result<(), ToolError> {
+        TypedTool::initialize(self, ctx).await
+    }
+
+    async fn shutdown(&self) {
+        TypedTool::shutdown(self).await
+    }
+
+    fn default_permission(&self) -> Option<PermissionAction> {
+        TypedTool::default_permission(self)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        TypedTool::timeout(self)
+    }
+
+    fn max_concurrency(&self) -> Option<usize> {
+        TypedTool::max_concurrency(self)
+    }
+
+    fn rate_limit(&self) -> Option<RateLimit> {
+        TypedTool::rate_limit(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// Arguments of the add tool.
+    #[derive(Deserialize, JsonSchema)]
+    struct AddArgs {
+        /// The first summand
+        a: i64,
+        /// The second summand
+        b: i64,
+    }
+
+    struct AddTool;
+
+    #[async_trait]
+    impl TypedTool for AddTool {
+        type Args = AddArgs;
+
+        fn name(&self) -> &str {
+            "add"
+        }
+
+        fn description(&self) -> &str {
+            "Adds two integers"
+        }
+
+        async fn run(&self, args: AddArgs) -> Result<ToolResult, ToolError> {
+            Ok(ToolResult::ok((args.a + args.b).to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_tools_derive_schema_and_reject_bad_arguments() {
+        let tool: crate::tool::DynTool = std::sync::Arc::new(AddTool);
+
+        let schema = tool.parameters_schema();
+        assert_eq!(schema["properties"]["a"]["description"], "The first summand");
+        assert_eq!(schema["required"], serde_json::json!(["a", "b"]));
+
+        let result = tool.execute(serde_json::json!({"a": 2, "b": 3})).await.unwrap();
+        assert_eq!(result.output, "5");
+        let error = tool.execute(serde_json::json!({"a": "two", "b": 3})).await.unwrap_err();
+        assert!(matches!(error, ToolError::InvalidArguments(_)));
+    }
+}
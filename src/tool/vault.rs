@@ -0,0 +1,126 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+const REF_PREFIX: &str = "{{vault:";
+const REF_SUFFIX: &str = "}}";
+
+/// A store for sensitive values (tokens, passwords) that keeps them out of
+/// prompts.
+///
+/// Tools store a secret and return the opaque reference instead. The tool
+/// executor resolves references in tool arguments right before execution,
+/// and the agent replaces any stored value that shows up in messages with
+/// its reference before calling the LLM. Clones share the same storage.
+#[derive(Debug, Clone, Default)]
+pub struct Vault {
+    entries: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Vault {
+    /// Creates an empty vault.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a value and returns the reference that stands in for it.
+    pub fn store(&self, value: impl Into<String>) -> String {
+        let value = value.into();
+        let mut entries = self.entries.write().unwrap();
+        if let Some(id) = entries.iter().find(|(_, v)| **v == value).map(|(id, _)| id.clone()) {
+            return Self::reference(&id);
+        }
+        let id = Uuid::new_v4().simple().to_string()[..12].to_string();
+        entries.insert(id.clone(), value);
+        Self::reference(&id)
+    }
+
+    /// Returns the value behind a reference.
+    pub fn get(&self, reference: &str) -> Option<String> {
+        let id = reference.strip_prefix(REF_PREFIX)?.strip_suffix(REF_SUFFIX)?;
+        self.entries.read().unwrap().get(id).cloned()
+    }
+
+    /// Returns the number of stored values.
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// Returns whether the vault is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().unwrap().is_empty()
+    }
+
+    /// Replaces every known reference in the text with its value.
+    pub fn resolve(&self, text: &str) -> String {
+        let entries = self.entries.read().unwrap();
+        if entries.is_empty() || !text.contains(REF_PREFIX) {
+            return text.to_string();
+        }
+        let mut resolved = text.to_string();
+        for (id, value) in entries.iter() {
+            resolved = resolved.replace(&Self::reference(id), value);
+        }
+        resolved
+    }
+
+    /// Replaces every known reference in the JSON strings with its value.
+    pub fn resolve_value(&self, value: &Value) -> Value {
+        self.map_strings(value, &|s| self.resolve(s))
+    }
+
+    /// Replaces every stored value in the text with its reference.
+    pub fn redact(&self, text: &str) -> String {
+        let entries = self.entries.read().unwrap();
+        let mut redacted = text.to_string();
+        for (id, value) in entries.iter().filter(|(_, v)| !v.is_empty()) {
+            if redacted.contains(value.as_str()) {
+                redacted = redacted.replace(value.as_str(), &Self::reference(id));
+            }
+        }
+        redacted
+    }
+
+    /// Replaces every stored value in the JSON strings with its reference.
+    pub fn redact_value(&self, value: &Value) -> Value {
+        self.map_strings(value, &|s| self.redact(s))
+    }
+
+    fn map_strings(&self, value: &Value, f: &dyn Fn(&str) -> String) -> Value {
+        match value {
+            Value::String(s) => Value::String(f(s)),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.map_strings(v, f)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.map_strings(v, f)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn reference(id: &str) -> String {
+        format!("{}{}{}", REF_PREFIX, id, REF_SUFFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_resolve_and_redact() {
+        let vault = Vault::new();
+        let reference = vault.store("s3cr3t-token");
+        assert_eq!(vault.store("s3cr3t-token"), reference);
+        assert_eq!(vault.get(&reference).as_deref(), Some("s3cr3t-token"));
+
+        let args = serde_json::json!({"auth": format!("Bearer {}", reference), "n": 1});
+        assert_eq!(vault.resolve_value(&args)["auth"], "Bearer s3cr3t-token");
+
+        let leaked = "login ok, token=s3cr3t-token";
+        assert_eq!(vault.redact(leaked), format!("login ok, token={}", reference));
+        assert_eq!(vault.resolve("no references"), "no references");
+    }
+}
@@ -0,0 +1,285 @@
+//! Declarative multi-stage pipelines.
+//!
+//! A [`Workflow`] is a graph of named [`Node`]s sharing a typed state. After
+//! a node runs, its outgoing edge picks the next node: either a fixed node or
+//! a router computed from the state. Nodes without an outgoing edge, and
+//! edges to [`END`], finish the workflow.
+//!
+//! ```rust,no_run
+//! use simple_agent::workflow::{FnNode, Workflow, END};
+//!
+//! #[derive(Default)]
+//! struct Ticket { text: String, category: String, reply: String }
+//!
+//! # async fn example() -> Result<(), simple_agent::workflow::WorkflowError> {
+//! let workflow = Workflow::builder("classify")
+//!     .node("classify", FnNode::new(|t: &mut Ticket| {
+//!         t.category = if t.text.contains("invoice") { "billing" } else { "other" }.to_string();
+//!         Ok(())
+//!     }))
+//!     .node("billing", FnNode::new(|t: &mut Ticket| {
+//!         t.reply = "Forwarded to billing.".to_string();
+//!         Ok(())
+//!     }))
+//!     .route("classify", |t: &Ticket| if t.category == "billing" { "billing" } else { END })
+//!     .build()?;
+//!
+//! let ticket = workflow.run(Ticket { text: "Wrong invoice".into(), ..Default::default() }).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod node;
+
+pub use node::{AgentNode, FnNode, Node, ParallelNode, ToolNode};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::agent::AgentError;
+use crate::tool::ToolError;
+
+/// Name of the pseudo-node that finishes a workflow.
+pub const END: &str = "__end__";
+
+/// Errors that can occur when building or running a workflow.
+#[derive(Debug, thiserror::Error)]
+pub enum WorkflowError {
+    #[error("Invalid workflow: {0}")]
+    InvalidGraph(String),
+    #[error("Router of node '{from}' chose unknown node '{to}'")]
+    UnknownNode { from: String, to: String },
+    #[error("Workflow did not finish within {0} steps")]
+    MaxSteps(usize),
+    #[error("Agent error: {0}")]
+    Agent(#[from] AgentError),
+    #[error("Tool error: {0}")]
+    Tool(#[from] ToolError),
+    #[error("Node failed: {0}")]
+    Failed(String),
+}
+
+type Router<S> = Arc<dyn Fn(&S) -> String + Send + Sync>;
+
+/// Where the workflow goes after a node.
+enum Edge<S> {
+    /// Always the same node
+    To(String),
+    /// The node returned by a router
+    Route(Router<S>),
+}
+
+/// A validated graph of nodes sharing a state of type `S`.
+pub struct Workflow<S> {
+    start: String,
+    nodes: HashMap<String, Arc<dyn Node<S>>>,
+    edges: HashMap<String, Edge<S>>,
+    max_steps: usize,
+}
+
+impl<S: Send + 'static> Workflow<S> {
+    /// Starts building a workflow that begins at the `start` node.
+    pub fn builder(start: impl Into<String>) -> WorkflowBuilder<S> {
+        WorkflowBuilder {
+            start: start.into(),
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+            max_steps: 100,
+            problems: Vec::new(),
+        }
+    }
+
+    /// Runs the workflow from its start node and returns the final state.
+    pub async fn run(&self, mut state: S) -> Result<S, WorkflowError> {
+        let mut current = self.start.clone();
+        let mut steps = 0;
+
+        while current != END {
+            if steps == self.max_steps {
+                return Err(WorkflowError::MaxSteps(self.max_steps));
+            }
+            steps += 1;
+
+            let node = &self.nodes[&current];
+            tracing::debug!(node = %current, step = steps, "Running workflow node");
+            node.run(&mut state).await?;
+
+            current = match self.edges.get(&current) {
+                None => END.to_string(),
+                Some(Edge::To(next)) => next.clone(),
+                Some(Edge::Route(router)) => {
+                    let next = router(&state);
+                    if next != END && !self.nodes.contains_key(&next) {
+                        return Err(WorkflowError::UnknownNode { from: current, to: next });
+                    }
+                    next
+                }
+            };
+        }
+
+        Ok(state)
+    }
+}
+
+impl<S> std::fmt::Debug for Workflow<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut nodes: Vec<_> = self.nodes.keys().collect();
+        nodes.sort();
+        f.debug_struct("Workflow")
+            .field("start", &self.start)
+            .field("nodes", &nodes)
+            .field("max_steps", &self.max_steps)
+            .finish()
+    }
+}
+
+/// Builds a [`Workflow`], checking the graph in `build`.
+pub struct WorkflowBuilder<S> {
+    start: String,
+    nodes: HashMap<String, Arc<dyn Node<S>>>,
+    edges: HashMap<String, Edge<S>>,
+    max_steps: usize,
+    problems: Vec<String>,
+}
+
+impl<S: Send + 'static> WorkflowBuilder<S> {
+    /// Adds a node.
+    pub fn node(mut self, name: impl Into<String>, node: impl Node<S> + 'static) -> Self {
+        let name = name.into();
+        if name == END || self.nodes.insert(name.clone(), Arc::new(node)).is_some() {
+            self.problems.push(format!("node '{}' is defined twice or reserved", name));
+        }
+        self
+    }
+
+    /// Continues with `to` after `from`.
+    pub fn edge(self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.add_edge(from.into(), Edge::To(to.into()))
+    }
+
+    /// Continues with the node `router` picks from the state after `from`;
+    /// returning [`END`] finishes the workflow.
+    pub fn route<F, R>(self, from: impl Into<String>, router: F) -> Self
+    where
+        F: Fn(&S) -> R + Send + Sync + 'static,
+        R: Into<String>,
+    {
+        self.add_edge(from.into(), Edge::Route(Arc::new(move |state| router(state).into())))
+    }
+
+    /// Sets how many nodes may run before the workflow fails, guarding
+    /// against routers that loop forever. Defaults to 100.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    fn add_edge(mut self, from: String, edge: Edge<S>) -> Self {
+        if self.edges.insert(from.clone(), edge).is_some() {
+            self.problems.push(format!("node '{}' has more than one outgoing edge", from));
+        }
+        self
+    }
+
+    /// Checks that every referenced node exists and builds the workflow.
+    pub fn build(mut self) -> Result<Workflow<S>, WorkflowError> {
+        if !self.nodes.contains_key(&self.start) {
+            self.problems.push(format!("start node '{}' does not exist", self.start));
+        }
+        for (from, edge) in &self.edges {
+            if !self.nodes.contains_key(from) {
+                self.problems.push(format!("edge from unknown node '{}'", from));
+            }
+            if let Edge::To(to) = edge
+                && to != END
+                && !self.nodes.contains_key(to)
+            {
+                self.problems.push(format!("edge from '{}' to unknown node '{}'", from, to));
+            }
+        }
+        if !self.problems.is_empty() {
+            self.problems.sort();
+            return Err(WorkflowError::InvalidGraph(self.problems.join("; ")));
+        }
+
+        Ok(Workflow {
+            start: self.start,
+            nodes: self.nodes,
+            edges: self.edges,
+            max_steps: self.max_steps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentConfig};
+    use crate::session::Session;
+    use crate::testing::MockLLMClient;
+    use crate::tool::ToolRegistry;
+    use tokio::sync::Mutex;
+
+    #[derive(Debug, Clone, Default)]
+    struct Ticket {
+        text: String,
+        category: String,
+        reply: String,
+        checks: Vec<String>,
+    }
+
+    #[tokio::test]
+    async fn test_classify_route_and_verify_in_parallel() {
+        let classifier = Agent::new(
+            Session::default(),
+            Arc::new(MockLLMClient::new().with_text_response(" billing\n")),
+            Arc::new(Mutex::new(ToolRegistry::new())),
+            AgentConfig::default(),
+        );
+        let check = |name: &'static str| {
+            FnNode::new(move |t: &mut Ticket| {
+                t.checks.push(name.to_string());
+                Ok(())
+            })
+        };
+
+        let workflow = Workflow::builder("classify")
+            .node(
+                "classify",
+                AgentNode::new(
+                    classifier,
+                    |t: &Ticket| format!("Classify: {}", t.text),
+                    |t: &mut Ticket, result| t.category = result.text.trim().to_string(),
+                ),
+            )
+            .node("billing", FnNode::new(|t: &mut Ticket| {
+                t.reply = "Refund issued.".to_string();
+                Ok(())
+            }))
+            .node("other", FnNode::new(|_: &mut Ticket| Err(WorkflowError::Failed("wrong route".into()))))
+            .node(
+                "verify",
+                ParallelNode::new(|t: &mut Ticket, branches: Vec<Ticket>| {
+                    t.checks = branches.into_iter().flat_map(|b| b.checks).collect();
+                })
+                .with_branch(check("tone"))
+                .with_branch(check("policy")),
+            )
+            .route("classify", |t: &Ticket| if t.category == "billing" { "billing" } else { "other" })
+            .edge("billing", "verify")
+            .build()
+            .unwrap();
+
+        let ticket = workflow
+            .run(Ticket { text: "I was charged twice".to_string(), ..Default::default() })
+            .await
+            .unwrap();
+
+        assert_eq!(ticket.category, "billing");
+        assert_eq!(ticket.reply, "Refund issued.");
+        assert_eq!(ticket.checks, ["tone", "policy"]);
+
+        let invalid = Workflow::<Ticket>::builder("start").edge("start", "missing").build();
+        assert!(matches!(invalid, Err(WorkflowError::InvalidGraph(problems)) if problems.contains("'missing'")));
+    }
+}
@@ -0,0 +1,155 @@
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::agent::{Agent, AgentRunResult};
+use crate::tool::{DynTool, ToolError, ToolResult};
+use super::WorkflowError;
+
+/// A step of a workflow that reads and updates the shared state.
+#[async_trait]
+pub trait Node<S: Send>: Send + Sync {
+    /// Runs the step.
+    async fn run(&self, state: &mut S) -> Result<(), WorkflowError>;
+}
+
+type StateFn<S> = Arc<dyn Fn(&mut S) -> Result<(), WorkflowError> + Send + Sync>;
+type Input<S, T> = Arc<dyn Fn(&S) -> T + Send + Sync>;
+type Output<S, T> = Arc<dyn Fn(&mut S, T) + Send + Sync>;
+type Merge<S> = Arc<dyn Fn(&mut S, Vec<S>) + Send + Sync>;
+
+/// A node running a synchronous function on the state, e.g. to parse or
+/// reshape the output of a previous node.
+pub struct FnNode<S> {
+    f: StateFn<S>,
+}
+
+impl<S> FnNode<S> {
+    /// Creates a node from a function.
+    pub fn new(f: impl Fn(&mut S) -> Result<(), WorkflowError> + Send + Sync + 'static) -> Self {
+        Self { f: Arc::new(f) }
+    }
+}
+
+#[async_trait]
+impl<S: Send> Node<S> for FnNode<S> {
+    async fn run(&self, state: &mut S) -> Result<(), WorkflowError> {
+        (self.f)(state)
+    }
+}
+
+/// A node running an agent on a prompt built from the state.
+///
+/// The agent keeps its session across runs; give each node its own agent
+/// (e.g. with `Agent::fork_with`) unless they should share a conversation.
+pub struct AgentNode<S> {
+    agent: Agent,
+    input: Input<S, String>,
+    output: Output<S, AgentRunResult>,
+}
+
+impl<S> AgentNode<S> {
+    /// Creates a node sending `input(state)` to the agent and storing the
+    /// result with `output`.
+    pub fn new(
+        agent: Agent,
+        input: impl Fn(&S) -> String + Send + Sync + 'static,
+        output: impl Fn(&mut S, AgentRunResult) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            agent,
+            input: Arc::new(input),
+            output: Arc::new(output),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send> Node<S> for AgentNode<S> {
+    async fn run(&self, state: &mut S) -> Result<(), WorkflowError> {
+        let prompt = (self.input)(state);
+        let result = self.agent.run(&prompt).await?;
+        (self.output)(state, result);
+        Ok(())
+    }
+}
+
+/// A node calling a tool directly, with arguments built from the state.
+///
+/// The call bypasses the agent's permission checks. A result carrying an
+/// error fails the workflow.
+pub struct ToolNode<S> {
+    tool: DynTool,
+    args: Input<S, Value>,
+    output: Output<S, ToolResult>,
+}
+
+impl<S> ToolNode<S> {
+    /// Creates a node calling `tool` with `args(state)` and storing the
+    /// result with `output`.
+    pub fn new(
+        tool: DynTool,
+        args: impl Fn(&S) -> Value + Send + Sync + 'static,
+        output: impl Fn(&mut S, ToolResult) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            tool,
+            args: Arc::new(args),
+            output: Arc::new(output),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send> Node<S> for ToolNode<S> {
+    async fn run(&self, state: &mut S) -> Result<(), WorkflowError> {
+        let args = (self.args)(state);
+        let result = self.tool.execute(args).await?;
+        if let Some(error) = result.error {
+            return Err(ToolError::ExecutionFailed(error).into());
+        }
+        (self.output)(state, result);
+        Ok(())
+    }
+}
+
+/// A node running branches concurrently, each on its own copy of the state,
+/// then merging the branch states back.
+pub struct ParallelNode<S> {
+    branches: Vec<Arc<dyn Node<S>>>,
+    merge: Merge<S>,
+}
+
+impl<S: Clone + Send + Sync + 'static> ParallelNode<S> {
+    /// Creates a node without branches that merges with `merge`, which gets
+    /// the branch states in the order the branches were added.
+    pub fn new(merge: impl Fn(&mut S, Vec<S>) + Send + Sync + 'static) -> Self {
+        Self {
+            branches: Vec::new(),
+            merge: Arc::new(merge),
+        }
+    }
+
+    /// Adds a branch.
+    pub fn with_branch(mut self, branch: impl Node<S> + 'static) -> Self {
+        self.branches.push(Arc::new(branch));
+        self
+    }
+}
+
+#[async_trait]
+impl<S: Clone + Send + Sync> Node<S> for ParallelNode<S> {
+    async fn run(&self, state: &mut S) -> Result<(), WorkflowError> {
+        let branches = self.branches.iter().map(|branch| {
+            let mut copy = state.clone();
+            async move {
+                branch.run(&mut copy).await?;
+                Ok::<_, WorkflowError>(copy)
+            }
+        });
+        let results = try_join_all(branches).await?;
+        (self.merge)(state, results);
+        Ok(())
+    }
+}